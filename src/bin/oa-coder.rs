@@ -1,34 +1,482 @@
 //! oa-coder -- standalone MCP programming sub-agent.
 //!
-//! Usage: oa-coder --workspace <path> [--sandboxed]
+//! Usage: oa-coder [--config <path>] [--workspace <path>] [--allow-root <path>]...
+//!                 [--allow-tools <list>] [--deny-tools <list>]
+//!                 [--follow-symlinks] [--case-insensitive]
+//!                 [--skip <list>] [--no-skip <list>]
+//!                 [--require-ripgrep] [--tool-timeout <secs>]
+//!                 [--bash-timeout <secs>] [--bash-max-timeout <secs>]
+//!                 [--max-concurrent-calls <n>]
+//!                 [--sandboxed] [--redact-secrets] [--watch] [--http <addr>]
+//!
+//! `--http` (requires the `http` feature) serves MCP over HTTP + SSE instead
+//! of stdio; `<addr>` is `host:port` or a bare port bound to 127.0.0.1.
+//! `--watch` (requires the `watch` feature) pushes workspace file changes to
+//! the client as resource notifications.
+//!
+//! `--config` loads the same options from a TOML file (or JSON, for a `.json`
+//! path), keyed by the option names in snake case (`allow_tools`, `no_skip`,
+//! ...) plus `max_line_bytes`, `max_file_bytes`, `max_output_chars` (a cap
+//! on the size of each tool result), `redact_patterns` (extra regexes for
+//! `--redact-secrets`) and `eof_newline` (`preserve`, the default,
+//! `ensure_trailing_newline`, `single_trailing_newline` or
+//! `strip_trailing_newlines`, applied by the writing tools). Relative paths
+//! in the file resolve against its directory, and flags given on the command
+//! line override the file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+const USAGE: &str = "\
+oa-coder -- MCP programming sub-agent (stdio JSON-RPC server)
+
+Usage: oa-coder [OPTIONS]
+
+Options:
+  --config <path>       Read options from a TOML (or .json) file; flags given
+                        here override it
+  --workspace <path>    Workspace directory for file operations [default: .]
+  --allow-root <path>   Additional directory file tools may access by absolute
+                        path (repeatable)
+  --follow-symlinks     Let glob/grep follow symlinks that resolve inside the
+                        workspace
+  --case-insensitive    Compare paths to the workspace ignoring case [default:
+                        detected from the filesystem]
+  --skip <list>         Also skip these file/directory names when glob/grep walk
+                        the workspace (comma-separated, e.g. .venv,__pycache__)
+  --no-skip <list>      Stop skipping these names [defaults: .git, .hg, .svn,
+                        node_modules, target]
+  --require-ripgrep     Make grep/search fail if rg is not installed instead of
+                        using the built-in fallback search
+  --tool-timeout <secs> Abort any tool call that runs longer than this
+  --bash-timeout <secs> Timeout of bash commands that set none [default: 120]
+  --bash-max-timeout <secs>
+                        Clamp longer requested bash timeouts to this
+                        [default: 3600]
+  --max-concurrent-calls <n>
+                        Run tool calls on n worker threads; later calls queue
+                        until one is free [default: 1]
+  --allow-tools <list>  Expose only these tools (comma-separated)
+  --deny-tools <list>   Never expose these tools (comma-separated); overrides
+                        --allow-tools
+  --sandboxed           Run bash commands through oa-sandbox
+  --redact-secrets      Replace tokens, keys and passwords in bash output with ***
+  --watch               Notify the client when workspace files change; requires
+                        the `watch` feature (stdio only)
+  --http <addr>         Serve MCP over HTTP + SSE on <addr> (host:port, or a port
+                        bound to 127.0.0.1); requires the `http` feature
+  -h, --help            Print help
+  -V, --version         Print version";
+
+/// Parsed command-line options, also the shape of a `--config` file.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Args {
+    #[serde(skip)]
+    config: Option<PathBuf>,
+    workspace: Option<PathBuf>,
+    allow_roots: Vec<PathBuf>,
+    allow_tools: Option<Vec<String>>,
+    deny_tools: Vec<String>,
+    follow_symlinks: bool,
+    /// `None` detects it; a config file may set `false` to force it off.
+    case_insensitive: Option<bool>,
+    skip: Vec<String>,
+    no_skip: Vec<String>,
+    require_ripgrep: bool,
+    tool_timeout: Option<u64>,
+    bash_timeout: Option<u64>,
+    bash_max_timeout: Option<u64>,
+    max_concurrent_calls: Option<std::num::NonZeroUsize>,
+    sandboxed: bool,
+    redact_secrets: bool,
+    watch: bool,
+    http: Option<String>,
+    /// Only settable from a config file.
+    max_line_bytes: Option<usize>,
+    /// Only settable from a config file.
+    max_file_bytes: Option<u64>,
+    /// Only settable from a config file; implies `redact_secrets`.
+    redact_patterns: Vec<String>,
+    /// Only settable from a config file.
+    eof_newline: Option<oa_coder::util::eof_newline::EofNewline>,
+    /// Only settable from a config file.
+    max_output_chars: Option<usize>,
+}
+
+impl Args {
+    /// Fill in everything not given on the command line from `file`.
+    /// Switches are enabled by either; lists given on the command line
+    /// replace the file's.
+    fn or(self, file: Self) -> Self {
+        Self {
+            config: self.config,
+            workspace: self.workspace.or(file.workspace),
+            allow_roots: prefer(self.allow_roots, file.allow_roots),
+            allow_tools: self.allow_tools.or(file.allow_tools),
+            deny_tools: prefer(self.deny_tools, file.deny_tools),
+            follow_symlinks: self.follow_symlinks || file.follow_symlinks,
+            case_insensitive: self.case_insensitive.or(file.case_insensitive),
+            skip: prefer(self.skip, file.skip),
+            no_skip: prefer(self.no_skip, file.no_skip),
+            require_ripgrep: self.require_ripgrep || file.require_ripgrep,
+            tool_timeout: self.tool_timeout.or(file.tool_timeout),
+            bash_timeout: self.bash_timeout.or(file.bash_timeout),
+            bash_max_timeout: self.bash_max_timeout.or(file.bash_max_timeout),
+            max_concurrent_calls: self.max_concurrent_calls.or(file.max_concurrent_calls),
+            sandboxed: self.sandboxed || file.sandboxed,
+            redact_secrets: self.redact_secrets || file.redact_secrets,
+            watch: self.watch || file.watch,
+            http: self.http.or(file.http),
+            max_line_bytes: self.max_line_bytes.or(file.max_line_bytes),
+            max_file_bytes: self.max_file_bytes.or(file.max_file_bytes),
+            redact_patterns: prefer(self.redact_patterns, file.redact_patterns),
+            eof_newline: self.eof_newline.or(file.eof_newline),
+            max_output_chars: self.max_output_chars.or(file.max_output_chars),
+        }
+    }
+}
+
+/// `cli` unless it is empty, else `file`.
+fn prefer<T>(cli: Vec<T>, file: Vec<T>) -> Vec<T> {
+    if cli.is_empty() { file } else { cli }
+}
+
+/// Load a `--config` file: JSON if the path ends in `.json`, TOML otherwise.
+/// Relative paths inside it are made relative to the file's directory.
+fn load_config(path: &Path) -> anyhow::Result<Args> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config {}", path.display()))?;
+    let mut file: Args = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text)
+            .with_context(|| format!("invalid config {}", path.display()))?
+    } else {
+        toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?
+    };
+    let base = path.parent().unwrap_or_else(|| Path::new("."));
+    file.workspace = file.workspace.map(|p| base.join(p));
+    file.allow_roots = file.allow_roots.into_iter().map(|p| base.join(p)).collect();
+    Ok(file)
+}
+
+/// What the command line asks for.
+#[derive(Debug)]
+enum Invocation {
+    Run(Box<Args>),
+    Help,
+    Version,
+}
+
+/// Parse arguments (without the program name). Options taking a value accept
+/// both `--flag value` and `--flag=value`.
+fn parse_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Invocation> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .with_context(|| format!("{flag} requires a value\n\n{USAGE}"))
+        };
+
+        match flag {
+            "-h" | "--help" => return Ok(Invocation::Help),
+            "-V" | "--version" => return Ok(Invocation::Version),
+            "--config" => parsed.config = Some(value()?.into()),
+            "--workspace" => parsed.workspace = Some(value()?.into()),
+            "--allow-root" => parsed.allow_roots.push(value()?.into()),
+            "--allow-tools" => parsed
+                .allow_tools
+                .get_or_insert_with(Vec::new)
+                .extend(split_list(&value()?)),
+            "--deny-tools" => parsed.deny_tools.extend(split_list(&value()?)),
+            "--skip" => parsed.skip.extend(split_list(&value()?)),
+            "--no-skip" => parsed.no_skip.extend(split_list(&value()?)),
+            "--http" => parsed.http = Some(value()?),
+            "--max-concurrent-calls" => {
+                let n = value()?;
+                parsed.max_concurrent_calls = Some(
+                    n.parse()
+                        .with_context(|| format!("invalid {flag}: {n} (expected a positive integer)"))?,
+                );
+            }
+            "--tool-timeout" | "--bash-timeout" | "--bash-max-timeout" => {
+                let secs = value()?;
+                let secs = Some(
+                    secs.parse()
+                        .with_context(|| format!("invalid {flag}: {secs}"))?,
+                );
+                match flag {
+                    "--tool-timeout" => parsed.tool_timeout = secs,
+                    "--bash-timeout" => parsed.bash_timeout = secs,
+                    _ => parsed.bash_max_timeout = secs,
+                }
+            }
+            "--follow-symlinks" if inline.is_none() => parsed.follow_symlinks = true,
+            "--case-insensitive" if inline.is_none() => parsed.case_insensitive = Some(true),
+            "--require-ripgrep" if inline.is_none() => parsed.require_ripgrep = true,
+            "--sandboxed" if inline.is_none() => parsed.sandboxed = true,
+            "--redact-secrets" if inline.is_none() => parsed.redact_secrets = true,
+            "--watch" if inline.is_none() => parsed.watch = true,
+            "--follow-symlinks" | "--case-insensitive" | "--require-ripgrep" | "--sandboxed"
+            | "--redact-secrets" | "--watch" => {
+                bail!("{flag} does not take a value")
+            }
+            _ if flag.starts_with('-') => bail!("unrecognized option: {arg}\n\n{USAGE}"),
+            _ => bail!("unexpected argument: {arg}\n\n{USAGE}"),
+        }
+    }
+
+    Ok(Invocation::Run(Box::new(parsed)))
+}
+
+/// Split a comma-separated option value, dropping empty items.
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+}
 
 fn main() -> anyhow::Result<()> {
+    let args = match parse_args(std::env::args().skip(1))? {
+        Invocation::Run(args) => *args,
+        Invocation::Help => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Invocation::Version => {
+            println!("oa-coder {}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+    };
+
     // Initialize tracing to stderr so it does not interfere with MCP stdio.
-    tracing_subscriber::fmt()
-        .with_env_filter(
+    // The MCP layer additionally forwards events to the client once it
+    // requests a level via `logging/setLevel`.
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
-        )
-        .with_writer(std::io::stderr)
+        );
+    tracing_subscriber::registry()
+        .with(stderr_layer)
+        .with(oa_coder::logging::layer())
         .init();
 
-    let args: Vec<String> = std::env::args().collect();
+    let args = match &args.config {
+        Some(path) => {
+            let file = load_config(path)?;
+            args.or(file)
+        }
+        None => args,
+    };
+    let config = server_config(&args)?;
 
-    let workspace = args
-        .iter()
-        .position(|a| a == "--workspace")
-        .and_then(|i| args.get(i + 1))
-        .cloned()
-        .unwrap_or_else(|| ".".to_string());
+    #[cfg(feature = "http")]
+    if let Some(addr) = args.http {
+        if config.watch {
+            bail!("--watch is not supported with --http");
+        }
+        let addr = oa_coder::http::parse_listen_addr(&addr)?;
+        return oa_coder::http::run_http_server(&config, addr);
+    }
+    #[cfg(not(feature = "http"))]
+    if args.http.is_some() {
+        bail!("--http requires oa-coder to be built with the `http` feature");
+    }
 
-    let sandboxed = args.iter().any(|a| a == "--sandboxed");
+    oa_coder::run_mcp_server(&config)
+}
 
-    let workspace = std::path::Path::new(&workspace).canonicalize()?;
+/// Build the server configuration, resolving the workspace roots.
+fn server_config(args: &Args) -> anyhow::Result<oa_coder::server::McpServerConfig> {
+    let workspace = args.workspace.as_deref().unwrap_or_else(|| Path::new("."));
+    let workspace = workspace
+        .canonicalize()
+        .with_context(|| format!("invalid workspace: {}", workspace.display()))?;
+    let additional_roots = args
+        .allow_roots
+        .iter()
+        .map(|root| {
+            root.canonicalize()
+                .with_context(|| format!("invalid --allow-root: {}", root.display()))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let redactor = if args.redact_secrets || !args.redact_patterns.is_empty() {
+        Some(oa_coder::util::redact::Redactor::new(&args.redact_patterns)?)
+    } else {
+        None
+    };
 
-    let config = oa_coder::server::McpServerConfig {
+    Ok(oa_coder::server::McpServerConfig {
         workspace,
-        sandboxed,
-    };
+        sandboxed: args.sandboxed,
+        additional_roots,
+        follow_symlinks: args.follow_symlinks,
+        case_insensitive: args.case_insensitive,
+        skip_names: args.skip.clone(),
+        unskip_names: args.no_skip.clone(),
+        require_ripgrep: args.require_ripgrep,
+        tool_timeout: args.tool_timeout.map(std::time::Duration::from_secs),
+        bash_timeout: args
+            .bash_timeout
+            .map_or(oa_coder::tools::bash::DEFAULT_TIMEOUT, std::time::Duration::from_secs),
+        bash_max_timeout: args
+            .bash_max_timeout
+            .map_or(oa_coder::tools::bash::DEFAULT_MAX_TIMEOUT, std::time::Duration::from_secs),
+        tool_filter: oa_coder::tools::ToolFilter {
+            allow: args.allow_tools.clone(),
+            deny: args.deny_tools.clone(),
+        },
+        max_line_bytes: args
+            .max_line_bytes
+            .unwrap_or(oa_coder::server::DEFAULT_MAX_LINE_BYTES),
+        max_file_bytes: args
+            .max_file_bytes
+            .unwrap_or(oa_coder::tools::DEFAULT_MAX_FILE_BYTES),
+        eof_newline: args.eof_newline.unwrap_or_default(),
+        redactor,
+        watch: args.watch,
+        max_concurrent_calls: args
+            .max_concurrent_calls
+            .unwrap_or(oa_coder::server::DEFAULT_MAX_CONCURRENT_CALLS),
+        max_output_chars: args.max_output_chars,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> Args {
+        match parse_args(args.iter().map(|&a| a.to_owned())).expect("parse") {
+            Invocation::Run(args) => *args,
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_config_file_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::create_dir(root.join("project")).expect("mkdir");
+        std::fs::create_dir(root.join("deps")).expect("mkdir");
+        let toml_path = root.join("oa-coder.toml");
+        std::fs::write(
+            &toml_path,
+            "workspace = \"project\"\n\
+             allow_roots = [\"deps\"]\n\
+             sandboxed = true\n\
+             max_line_bytes = 4096\n\
+             max_file_bytes = 1048576\n\
+             eof_newline = \"ensure_trailing_newline\"\n\
+             max_output_chars = 50000\n\
+             tool_timeout = 30\n\
+             bash_timeout = 60\n\
+             skip = [\".venv\"]\n\
+             allow_tools = [\"read\", \"grep\"]\n",
+        )
+        .expect("write");
+
+        let config = server_config(&load_config(&toml_path).expect("load")).expect("config");
+        assert_eq!(config.workspace, root.join("project"));
+        assert_eq!(config.additional_roots, vec![root.join("deps")]);
+        assert!(config.sandboxed);
+        assert_eq!(config.max_line_bytes, 4096);
+        assert_eq!(config.max_file_bytes, 1_048_576);
+        assert_eq!(config.eof_newline, oa_coder::util::eof_newline::EofNewline::EnsureTrailingNewline);
+        assert_eq!(config.max_output_chars, Some(50_000));
+        assert_eq!(config.tool_timeout, Some(std::time::Duration::from_secs(30)));
+        assert_eq!(config.bash_timeout, std::time::Duration::from_secs(60));
+        assert_eq!(config.bash_max_timeout, oa_coder::tools::bash::DEFAULT_MAX_TIMEOUT);
+        assert_eq!(config.skip_names, vec![".venv".to_owned()]);
+        assert_eq!(config.tool_filter.allow, Some(vec!["read".to_owned(), "grep".to_owned()]));
+
+        // The same options as JSON load identically.
+        let json_path = root.join("oa-coder.json");
+        let json = serde_json::json!({
+            "workspace": "project",
+            "allow_roots": ["deps"],
+            "sandboxed": true,
+            "max_line_bytes": 4096,
+            "max_file_bytes": 1_048_576,
+            "eof_newline": "ensure_trailing_newline",
+            "max_output_chars": 50_000,
+            "tool_timeout": 30,
+            "bash_timeout": 60,
+            "skip": [".venv"],
+            "allow_tools": ["read", "grep"],
+        });
+        std::fs::write(&json_path, json.to_string()).expect("write");
+        assert_eq!(
+            load_config(&json_path).expect("load"),
+            load_config(&toml_path).expect("load")
+        );
+    }
+
+    #[test]
+    fn test_cli_overrides_config_file() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("oa-coder.toml");
+        std::fs::write(&path, "deny_tools = [\"bash\"]\nskip = [\".venv\"]\nhttp = \"8808\"\n")
+            .expect("write");
+
+        let cli = run_args(&["--config", path.to_str().expect("utf-8"), "--deny-tools", "write"]);
+        let args = cli.or(load_config(&path).expect("load"));
+        assert_eq!(args.deny_tools, vec!["write".to_owned()]);
+        assert_eq!(args.skip, vec![".venv".to_owned()]);
+        assert_eq!(args.http.as_deref(), Some("8808"));
+
+        std::fs::write(&path, "case_insensitive = false\n").expect("write");
+        assert_eq!(load_config(&path).expect("load").case_insensitive, Some(false));
+        let cli = run_args(&["--case-insensitive"]);
+        assert_eq!(cli.or(load_config(&path).expect("load")).case_insensitive, Some(true));
+
+        std::fs::write(&path, "bash_timeout = 60\nbash_max_timeout = 600\n").expect("write");
+        let cli = run_args(&["--bash-max-timeout=900"]);
+        let args = cli.or(load_config(&path).expect("load"));
+        assert_eq!((args.bash_timeout, args.bash_max_timeout), (Some(60), Some(900)));
+
+        std::fs::write(&path, "max_concurrent_calls = 4\n").expect("write");
+        let file = load_config(&path).expect("load");
+        assert_eq!(file.max_concurrent_calls, std::num::NonZeroUsize::new(4));
+        let cli = run_args(&["--max-concurrent-calls", "1"]);
+        assert_eq!(cli.or(file).max_concurrent_calls, std::num::NonZeroUsize::new(1));
+        assert!(parse_args(["--max-concurrent-calls=0".to_owned()]).is_err());
+        std::fs::write(&path, "max_concurrent_calls = 0\n").expect("write");
+        assert!(load_config(&path).is_err());
+
+        std::fs::write(&path, "unknown = 1\n").expect("write");
+        assert!(load_config(&path).is_err());
+    }
+
+    #[test]
+    fn test_redact_patterns_enable_redaction() {
+        assert!(server_config(&Args::default()).expect("config").redactor.is_none());
+        assert!(server_config(&run_args(&["--redact-secrets"])).expect("config").redactor.is_some());
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("oa-coder.toml");
+        std::fs::write(&path, "redact_patterns = [\"internal-[0-9]+\"]\n").expect("write");
+        let config = server_config(&load_config(&path).expect("load")).expect("config");
+        assert!(config.redactor.is_some());
 
-    oa_coder::run_mcp_server(config)
+        std::fs::write(&path, "redact_patterns = [\"(\"]\n").expect("write");
+        assert!(server_config(&load_config(&path).expect("load")).is_err());
+    }
 }
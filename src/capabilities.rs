@@ -0,0 +1,182 @@
+//! Per-tool readiness — the `oa-coder/capabilities` method.
+//!
+//! `oa-coder/health` answers "is the server usable"; capabilities answers
+//! "which of the enabled tools will actually work here". Both are built from
+//! the same [`Probes`]: external programs on PATH (`rg`, `git`, the built-in
+//! formatters), whether the sandbox is compiled in, and whether the primary
+//! workspace root is writable.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::tools::{ToolRouter, Workspace};
+
+/// Tools that never modify the workspace.
+const READ_ONLY_TOOLS: &[&str] =
+    &["read", "grep", "glob", "tree", "search", "stat", "git_diff", "symbols", "blame"];
+
+/// Tools that run their command through the `bash` execution path.
+const COMMAND_TOOLS: &[&str] = &["bash", "format", "git_diff", "blame"];
+
+/// Environment checks shared by `oa-coder/health` and `oa-coder/capabilities`.
+#[derive(Debug, Clone)]
+pub struct Probes {
+    /// Path of `rg`, if on PATH.
+    pub ripgrep: Option<PathBuf>,
+    /// Path of `git`, if on PATH.
+    pub git: Option<PathBuf>,
+    /// Built-in formatter programs and their paths, if on PATH.
+    pub formatters: Vec<(&'static str, Option<PathBuf>)>,
+    /// Whether the `sandbox` feature is compiled in.
+    pub sandbox: bool,
+    /// Whether a temp file could be created in the workspace root.
+    pub workspace_writable: bool,
+}
+
+impl Probes {
+    /// Run every probe. The only side effect is a temp file created in (and
+    /// removed from) the workspace root.
+    #[must_use]
+    pub fn run(workspace: &Workspace) -> Self {
+        Self {
+            ripgrep: which::which("rg").ok(),
+            git: which::which("git").ok(),
+            formatters: crate::tools::format::formatter_programs()
+                .map(|program| (program, which::which(program).ok()))
+                .collect(),
+            sandbox: cfg!(feature = "sandbox"),
+            workspace_writable: tempfile::Builder::new()
+                .prefix(".oa-coder-health")
+                .tempfile_in(workspace.root())
+                .is_ok(),
+        }
+    }
+}
+
+/// Result of `oa-coder/capabilities`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub workspace: String,
+    pub workspace_writable: bool,
+    /// Whether the `sandbox` feature is compiled in.
+    pub sandbox: bool,
+    /// One entry per enabled tool, in `tools/list` order.
+    pub tools: Vec<ToolCapability>,
+}
+
+/// Readiness of one tool.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCapability {
+    pub name: String,
+    /// Whether a call can be expected to work in this environment.
+    pub ready: bool,
+    pub read_only: bool,
+    /// For command-running tools: whether commands actually run sandboxed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandboxed: Option<bool>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<Dependency>,
+    /// Why the tool is not ready, or how it is degraded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// An external program a tool uses.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    pub name: &'static str,
+    pub present: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Whether the tool cannot work without it.
+    pub required: bool,
+}
+
+impl Dependency {
+    fn new(name: &'static str, path: Option<&PathBuf>, required: bool) -> Self {
+        Self {
+            name,
+            present: path.is_some(),
+            path: path.map(|path| path.display().to_string()),
+            required,
+        }
+    }
+}
+
+/// Readiness of every tool `router` exposes, given `probes`.
+#[must_use]
+pub fn capabilities(router: &ToolRouter, probes: &Probes) -> Capabilities {
+    let sandboxed = router.sandboxed() && probes.sandbox;
+    let tools = router
+        .list_tools()
+        .into_iter()
+        .map(|tool| {
+            let name = tool.name;
+            let read_only = READ_ONLY_TOOLS.contains(&name.as_str());
+            let mut ready = true;
+            let mut note = None;
+            let mut dependencies = Vec::new();
+
+            match name.as_str() {
+                "grep" | "search" => {
+                    let required = router.requires_ripgrep();
+                    dependencies.push(Dependency::new("rg", probes.ripgrep.as_ref(), required));
+                    if probes.ripgrep.is_none() {
+                        ready = !required;
+                        note = Some(if required {
+                            "rg not found and ripgrep is required".to_owned()
+                        } else {
+                            "rg not found; using the built-in fallback search".to_owned()
+                        });
+                    }
+                }
+                "git_diff" | "blame" => {
+                    dependencies.push(Dependency::new("git", probes.git.as_ref(), true));
+                    if probes.git.is_none() {
+                        ready = false;
+                        note = Some("git not found on PATH".to_owned());
+                    }
+                }
+                "format" => {
+                    dependencies.extend(
+                        probes
+                            .formatters
+                            .iter()
+                            .map(|(program, path)| Dependency::new(program, path.as_ref(), false)),
+                    );
+                }
+                _ => {}
+            }
+
+            if !read_only && !probes.workspace_writable {
+                ready = false;
+                note = Some("workspace is not writable".to_owned());
+            }
+            if COMMAND_TOOLS.contains(&name.as_str()) && router.sandboxed() && !probes.sandbox {
+                note.get_or_insert_with(|| {
+                    "sandbox requested but not compiled in; commands run unsandboxed".to_owned()
+                });
+            }
+
+            ToolCapability {
+                sandboxed: COMMAND_TOOLS.contains(&name.as_str()).then_some(sandboxed),
+                name,
+                ready,
+                read_only,
+                dependencies,
+                note,
+            }
+        })
+        .collect();
+
+    Capabilities {
+        workspace: router.workspace().root().display().to_string(),
+        workspace_writable: probes.workspace_writable,
+        sandbox: probes.sandbox,
+        tools,
+    }
+}
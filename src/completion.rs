@@ -0,0 +1,110 @@
+//! MCP completions — `completion/complete` for path-valued arguments.
+//!
+//! Arguments named like a path (`filePath`, `path`, ...) of tools and prompts
+//! are completed with workspace-relative entries found by the `glob` walker,
+//! so `.gitignore` and the skip list apply. A completion covers the next path
+//! component only: typing `src/to` offers `src/tools/` rather than every file
+//! below it. Other arguments get no suggestions.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::tools::Workspace;
+
+/// Most values returned per request (the MCP limit).
+pub const MAX_COMPLETIONS: usize = 100;
+
+/// Most files the walker collects per request.
+const MAX_SCANNED_FILES: usize = 5_000;
+
+/// Argument names completed as workspace paths.
+const PATH_ARGUMENTS: &[&str] = &["filePath", "path", "source", "destination", "cwd"];
+
+/// The `completion` object of a `completion/complete` result.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub values: Vec<String>,
+    pub total: usize,
+    pub has_more: bool,
+}
+
+/// Complete `value` for the argument `argument`.
+///
+/// # Errors
+///
+/// Returns an error if the workspace cannot be walked.
+pub fn complete(workspace: &Workspace, argument: &str, value: &str) -> Result<Completion> {
+    if !PATH_ARGUMENTS.contains(&argument) || std::path::Path::new(value).is_absolute() {
+        return Ok(Completion::default());
+    }
+    let (dir, partial) = value.rsplit_once('/').unwrap_or(("", value));
+    let dir_path = if dir.is_empty() { "." } else { dir };
+    let Ok(search_dir) = crate::tools::validate_dir_path(workspace, dir_path) else {
+        return Ok(Completion::default());
+    };
+    if !search_dir.is_dir() {
+        return Ok(Completion::default());
+    }
+
+    let pattern = format!("{}*", globset::escape(partial));
+    let files = crate::tools::glob::find_files(
+        workspace,
+        &search_dir,
+        &[pattern],
+        &[],
+        false,
+        true,
+        MAX_SCANNED_FILES,
+    )?;
+    let prefix = if dir.is_empty() { String::new() } else { format!("{dir}/") };
+    let candidates: BTreeSet<String> = files
+        .iter()
+        .map(|file| {
+            let file = file.replace('\\', "/");
+            // Keep the first component past `partial`, with its slash.
+            let next = file[partial.len()..]
+                .find('/')
+                .map_or(file.as_str(), |i| &file[..=partial.len() + i]);
+            format!("{prefix}{next}")
+        })
+        .collect();
+
+    let total = candidates.len();
+    Ok(Completion {
+        values: candidates.into_iter().take(MAX_COMPLETIONS).collect(),
+        total,
+        has_more: total > MAX_COMPLETIONS || files.len() >= MAX_SCANNED_FILES,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete_next_component() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/tools")).expect("mkdir");
+        std::fs::write(root.join("src/tools/mod.rs"), "").expect("write");
+        std::fs::write(root.join("src/tree.rs"), "").expect("write");
+        std::fs::write(root.join("src/lib.rs"), "").expect("write");
+        std::fs::write(root.join("README.md"), "").expect("write");
+        let workspace = Workspace::new(root.to_path_buf());
+
+        let completion = complete(&workspace, "filePath", "src/t").expect("complete");
+        assert_eq!(completion.values, ["src/tools/", "src/tree.rs"]);
+        assert_eq!(completion.total, 2);
+        assert!(!completion.has_more);
+
+        let completion = complete(&workspace, "path", "").expect("complete");
+        assert_eq!(completion.values, ["README.md", "src/"]);
+
+        assert!(complete(&workspace, "pattern", "src").expect("complete").values.is_empty());
+        assert!(complete(&workspace, "filePath", "missing/x").expect("complete").values.is_empty());
+        assert!(complete(&workspace, "filePath", "/etc/pa").expect("complete").values.is_empty());
+    }
+}
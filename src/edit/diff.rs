@@ -1,23 +1,155 @@
 //! Diff generation using the `similar` crate.
 //!
-//! Generates unified diffs for display after edit operations.
+//! Generates unified diffs for display after edit operations, and the
+//! `+N -M` line counts summarizing them.
 
-use similar::{Algorithm, TextDiff};
+use serde::Serialize;
+use similar::{Algorithm, ChangeTag, TextDiff};
+
+/// One hunk of a line diff, with 1-based line numbers as in a unified diff
+/// header (a zero-length side gives the line *before* which it applies).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+/// One line of a [`DiffHunk`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    /// `"equal"`, `"insert"` or `"delete"`.
+    pub tag: &'static str,
+    /// The line without its line terminator.
+    pub text: String,
+}
+
+/// Context lines around each hunk unless asked otherwise.
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// How diffs are computed and printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Line diff algorithm. Patience keeps source structure readable;
+    /// Myers finds the fewest changed lines, which can be a smaller diff
+    /// when many lines are repeated (e.g. moved blocks of braces).
+    pub algorithm: Algorithm,
+    /// Unchanged lines shown around each hunk (0 for a compact patch).
+    pub context_lines: usize,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::Patience,
+            context_lines: DEFAULT_CONTEXT_LINES,
+        }
+    }
+}
+
+/// Line diff of `old` against `new` with `algorithm`.
+fn line_diff<'a>(old: &'a str, new: &'a str, algorithm: Algorithm) -> TextDiff<'a, 'a, 'a, str> {
+    TextDiff::configure().algorithm(algorithm).diff_lines(old, new)
+}
 
 /// Generate a unified diff between old and new content.
 ///
 /// Uses the Patience diff algorithm which produces cleaner diffs
 /// for source code by preserving structure.
 pub fn unified_diff(file_name: &str, old: &str, new: &str) -> String {
-    let diff = TextDiff::configure()
-        .algorithm(Algorithm::Patience)
-        .diff_lines(old, new);
+    unified_diff_with_options(file_name, old, new, DiffOptions::default())
+}
 
-    diff.unified_diff()
+/// [`unified_diff`] with a chosen algorithm and context size.
+#[must_use]
+pub fn unified_diff_with_options(file_name: &str, old: &str, new: &str, options: DiffOptions) -> String {
+    line_diff(old, new, options.algorithm)
+        .unified_diff()
+        .context_radius(options.context_lines)
         .header(&format!("a/{file_name}"), &format!("b/{file_name}"))
         .to_string()
 }
 
+/// The hunks [`unified_diff_with_options`] would print, as structured data.
+#[must_use]
+pub fn diff_json(old: &str, new: &str, options: DiffOptions) -> Vec<DiffHunk> {
+    let diff = line_diff(old, new, options.algorithm);
+    let mut unified = diff.unified_diff();
+    unified.context_radius(options.context_lines);
+    unified
+        .iter_hunks()
+        .filter_map(|hunk| {
+            let (first, last) = (hunk.ops().first()?, hunk.ops().last()?);
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+            // Unified diffs number an empty side by the line before it.
+            let start = |range: &std::ops::Range<usize>| {
+                if range.is_empty() { range.start } else { range.start + 1 }
+            };
+            let lines = hunk
+                .iter_changes()
+                .map(|change| DiffLine {
+                    tag: match change.tag() {
+                        ChangeTag::Equal => "equal",
+                        ChangeTag::Insert => "insert",
+                        ChangeTag::Delete => "delete",
+                    },
+                    text: change
+                        .value()
+                        .trim_end_matches('\n')
+                        .trim_end_matches('\r')
+                        .to_owned(),
+                })
+                .collect();
+            Some(DiffHunk {
+                old_start: start(&old_range),
+                old_lines: old_range.len(),
+                new_start: start(&new_range),
+                new_lines: new_range.len(),
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Count the `(insertions, deletions, hunks)` of the change from `old` to
+/// `new`, as lines; hunks are those [`unified_diff_with_options`] would print.
+#[must_use]
+pub fn diff_stats(old: &str, new: &str, options: DiffOptions) -> (usize, usize, usize) {
+    let diff = line_diff(old, new, options.algorithm);
+    let (mut insertions, mut deletions) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => insertions += 1,
+            ChangeTag::Delete => deletions += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    let hunks = diff
+        .unified_diff()
+        .context_radius(options.context_lines)
+        .iter_hunks()
+        .count();
+    (insertions, deletions, hunks)
+}
+
+/// Summary of [`diff_stats`] with the default options, e.g. `(+3 -1, 2 hunks)`.
+#[must_use]
+pub fn diff_summary(old: &str, new: &str) -> String {
+    diff_summary_with_options(old, new, DiffOptions::default())
+}
+
+/// [`diff_summary`] with a chosen algorithm and context size.
+#[must_use]
+pub fn diff_summary_with_options(old: &str, new: &str, options: DiffOptions) -> String {
+    let (insertions, deletions, hunks) = diff_stats(old, new, options);
+    let plural = if hunks == 1 { "" } else { "s" };
+    format!("(+{insertions} -{deletions}, {hunks} hunk{plural})")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -37,4 +169,92 @@ mod tests {
         assert!(result.contains("-line2"));
         assert!(result.contains("+modified"));
     }
+
+    #[test]
+    fn test_diff_stats() {
+        use std::fmt::Write as _;
+
+        let mut old = String::new();
+        for i in 1..=20 {
+            let _ = writeln!(old, "line{i}");
+        }
+        // One line replaced near the top, two inserted near the bottom: far
+        // enough apart to print as separate hunks.
+        let new = old.replace("line2\n", "two\n").replace("line18\n", "line18\nx\ny\n");
+        assert_eq!(diff_stats(&old, &new, DiffOptions::default()), (3, 1, 2));
+        assert_eq!(diff_summary(&old, &new), "(+3 -1, 2 hunks)");
+        assert_eq!(diff_stats(&old, &old, DiffOptions::default()), (0, 0, 0));
+        assert_eq!(diff_summary("a\n", "b\n"), "(+1 -1, 1 hunk)");
+    }
+
+    #[test]
+    fn test_diff_json_matches_unified_header() {
+        let old = "a\nb\nc\n";
+        let new = "a\nB\nc\nd\n";
+        let hunks = diff_json(old, new, DiffOptions::default());
+        assert_eq!(hunks.len(), 1);
+        let hunk = &hunks[0];
+        assert!(unified_diff("f", old, new).contains(&format!(
+            "@@ -{},{} +{},{} @@",
+            hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+        )));
+        let tags: Vec<(&str, &str)> =
+            hunk.lines.iter().map(|l| (l.tag, l.text.as_str())).collect();
+        assert_eq!(
+            tags,
+            [("equal", "a"), ("delete", "b"), ("insert", "B"), ("equal", "c"), ("insert", "d")]
+        );
+
+        // Pure insertion into an empty file: the old side is empty at line 0.
+        let hunks = diff_json("", "x\n", DiffOptions::default());
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (0, 0));
+        assert_eq!((hunks[0].new_start, hunks[0].new_lines), (1, 1));
+        assert!(diff_json(old, old, DiffOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_algorithm_changes_diff_size() {
+        // Two unique lines swapped around a run of repeated ones. Patience
+        // anchors on one of the unique lines and rewrites the whole run;
+        // Myers keeps the run and swaps just the two lines.
+        let old = "U\nx\nx\nx\nx\nV\n";
+        let new = "V\nx\nx\nx\nx\nU\n";
+        let patience = DiffOptions::default();
+        let myers = DiffOptions { algorithm: Algorithm::Myers, ..patience };
+
+        let (insertions, deletions, _) = diff_stats(old, new, myers);
+        assert_eq!((insertions, deletions), (2, 2));
+        assert!(diff_stats(old, new, patience).0 > 2);
+        assert!(
+            unified_diff_with_options("f", old, new, myers).len()
+                < unified_diff_with_options("f", old, new, patience).len()
+        );
+        // Patience is the default.
+        assert_eq!(unified_diff("f", old, new), unified_diff_with_options("f", old, new, patience));
+    }
+
+    #[test]
+    fn test_context_lines() {
+        use std::fmt::Write as _;
+
+        let mut old = String::new();
+        for i in 1..=10 {
+            let _ = writeln!(old, "line{i}");
+        }
+        let new = old.replace("line5\n", "five\n");
+        let compact = DiffOptions { context_lines: 0, ..DiffOptions::default() };
+
+        let full = unified_diff("f", &old, &new);
+        let patch = unified_diff_with_options("f", &old, &new, compact);
+        assert!(full.starts_with("--- a/f\n+++ b/f\n"));
+        assert!(patch.starts_with("--- a/f\n+++ b/f\n"));
+        assert!(full.contains("@@ -2,7 +2,7 @@"));
+        assert!(patch.contains("@@ -5 +5 @@"));
+        assert!(!patch.contains(" line4"));
+        assert!(patch.len() < full.len());
+
+        let hunks = diff_json(&old, &new, compact);
+        assert_eq!((hunks[0].old_start, hunks[0].old_lines), (5, 1));
+        assert_eq!(hunks[0].lines.len(), 2);
+    }
 }
@@ -8,7 +8,7 @@
 /// Inputs longer than this are rejected with a pessimistic distance estimate
 /// to prevent O(m*n) allocation/computation DoS. VS Code uses 128; we use
 /// 10,000 which covers any reasonable code block.
-const MAX_LEVENSHTEIN_INPUT: usize = 10_000;
+pub(crate) const MAX_LEVENSHTEIN_INPUT: usize = 10_000;
 
 /// Compute the Levenshtein edit distance between two strings.
 ///
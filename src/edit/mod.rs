@@ -24,6 +24,10 @@
 //! 7. `TrimmedBoundaryReplacer` — trim boundary blank lines
 //! 8. `ContextAwareReplacer` — context-line anchoring + similarity
 //! 9. `MultiOccurrenceReplacer` — yields all exact matches for `replace_all`
+//!
+//! With [`MatchOptions::normalize_unicode`], `UnicodeNormalizedReplacer`
+//! runs right after `SimpleReplacer`: it matches with smart quotes, dashes
+//! and non-breaking spaces folded to ASCII on both sides.
 
 pub mod diff;
 pub mod levenshtein;
@@ -35,44 +39,164 @@ use tracing::debug;
 const SINGLE_CANDIDATE_SIMILARITY_THRESHOLD: f64 = 0.0;
 const MULTIPLE_CANDIDATES_SIMILARITY_THRESHOLD: f64 = 0.3;
 
-/// A replacer function signature: takes (content, find) and returns
+/// Minimum similarity for [`closest_match`] to suggest a block.
+const CLOSEST_MATCH_THRESHOLD: f64 = 0.6;
+
+/// Most line windows [`closest_match`] scores.
+const MAX_CLOSEST_MATCH_WINDOWS: usize = 1_000;
+
+/// Budget of Levenshtein matrix cells (window count × find length²) for
+/// [`closest_match`]; long find strings get fewer windows.
+const CLOSEST_MATCH_CELL_BUDGET: usize = 100_000_000;
+
+/// A replacer function signature: takes (content, find, options) and returns
 /// candidate strings found in content that match the search.
-type Replacer = fn(&str, &str) -> Vec<String>;
+type Replacer = fn(&str, &str, MatchOptions) -> Vec<String>;
+
+/// Name of the opt-in layer, skipped unless
+/// [`MatchOptions::normalize_unicode`] is set.
+const UNICODE_NORMALIZED_REPLACER: &str = "UnicodeNormalizedReplacer";
 
-/// The ordered chain of replacers, matching OpenAcosmi's exact order.
+/// The ordered chain of replacers, matching OpenAcosmi's exact order (plus
+/// the opt-in [`UNICODE_NORMALIZED_REPLACER`]).
 const REPLACER_CHAIN: &[(&str, Replacer)] = &[
-    ("SimpleReplacer", replacers::simple_replacer),
-    ("LineTrimmedReplacer", replacers::line_trimmed_replacer),
-    ("BlockAnchorReplacer", replacers::block_anchor_replacer),
+    ("SimpleReplacer", |c, f, _| replacers::simple_replacer(c, f)),
+    (
+        UNICODE_NORMALIZED_REPLACER,
+        |c, f, _| replacers::unicode_normalized_replacer(c, f),
+    ),
+    ("LineTrimmedReplacer", |c, f, _| replacers::line_trimmed_replacer(c, f)),
+    ("BlockAnchorReplacer", |c, f, _| replacers::block_anchor_replacer(c, f)),
     (
         "WhitespaceNormalizedReplacer",
-        replacers::whitespace_normalized_replacer,
+        |c, f, _| replacers::whitespace_normalized_replacer(c, f),
     ),
     (
         "IndentationFlexibleReplacer",
-        replacers::indentation_flexible_replacer,
+        |c, f, _| replacers::indentation_flexible_replacer(c, f),
     ),
     (
         "EscapeNormalizedReplacer",
-        replacers::escape_normalized_replacer,
+        |c, f, _| replacers::escape_normalized_replacer(c, f),
     ),
     (
         "TrimmedBoundaryReplacer",
-        replacers::trimmed_boundary_replacer,
+        |c, f, _| replacers::trimmed_boundary_replacer(c, f),
     ),
     (
         "ContextAwareReplacer",
-        replacers::context_aware_replacer,
+        |c, f, o| replacers::context_aware_replacer_with_ratio(c, f, o.context_match_ratio),
+    ),
+    (
+        "MultiOccurrenceReplacer",
+        |c, f, _| replacers::multi_occurrence_replacer(c, f),
     ),
+];
+
+/// The exact-match layers alone, for [`replace_exact`].
+const EXACT_CHAIN: &[(&str, Replacer)] = &[
+    ("SimpleReplacer", |c, f, _| replacers::simple_replacer(c, f)),
     (
         "MultiOccurrenceReplacer",
-        replacers::multi_occurrence_replacer,
+        |c, f, _| replacers::multi_occurrence_replacer(c, f),
     ),
 ];
 
+/// Result of a successful [`replace_with_ranges`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    /// The new file content.
+    pub content: String,
+    /// Byte ranges `(start, end)` in the **original** content that were
+    /// replaced, in ascending order (one per occurrence for `replace_all`).
+    pub ranges: Vec<(usize, usize)>,
+}
+
 /// Try to replace `old` with `new` in `content` using the 9-layer chain.
 ///
 /// Returns `Some(new_content)` if a match was found, `None` otherwise.
+/// See [`replace_with_ranges`] for the matched positions.
+#[must_use]
+pub fn replace(content: &str, old: &str, new: &str, replace_all: bool) -> Option<String> {
+    replace_with_ranges(content, old, new, replace_all).map(|r| r.content)
+}
+
+/// Like [`replace`], but only exact occurrences of `old` match
+/// (`SimpleReplacer` and `MultiOccurrenceReplacer`): no fuzzy layer runs.
+#[must_use]
+pub fn replace_exact(content: &str, old: &str, new: &str, replace_all: bool) -> Option<String> {
+    run_chain(EXACT_CHAIN, content, old, new, replace_all, MatchOptions::default(), None)
+        .map(|r| r.content)
+}
+
+/// Extra constraints on where an exact match of `old` may sit, and how
+/// loose the fuzzy layers may be.
+///
+/// `whole_word` and `whole_line` apply only to candidates identical to `old`
+/// (the exact-match path of `SimpleReplacer` and `MultiOccurrenceReplacer`);
+/// the fuzzy layers yield other text and are matched as usual.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MatchOptions {
+    /// `old` must not continue a word: a word character (alphanumeric or
+    /// `_`) at either end of it must not touch another one in `content`.
+    pub whole_word: bool,
+    /// `old` must start at a line start and end at a line end.
+    pub whole_line: bool,
+    /// Share of middle lines `ContextAwareReplacer` requires to match
+    /// exactly (see [`replacers::context_aware_replacer_with_ratio`]).
+    pub context_match_ratio: f64,
+    /// Also try matching with unicode lookalikes (smart quotes, dashes,
+    /// non-breaking spaces) folded to ASCII in both `content` and `old`; see
+    /// [`replacers::unicode_normalized_replacer`].
+    pub normalize_unicode: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            whole_word: false,
+            whole_line: false,
+            context_match_ratio: replacers::DEFAULT_CONTEXT_MATCH_RATIO,
+            normalize_unicode: false,
+        }
+    }
+}
+
+impl MatchOptions {
+    const fn is_unconstrained(self) -> bool {
+        !self.whole_word && !self.whole_line
+    }
+
+    /// Whether the occurrence of `found` at `start` satisfies the options.
+    fn accepts(self, content: &str, start: usize, found: &str) -> bool {
+        let end = start + found.len();
+        let before = content[..start].chars().next_back();
+        let after = content[end..].chars().next();
+        if self.whole_word {
+            let touches = |edge: Option<char>, outside: Option<char>| {
+                edge.is_some_and(is_word_char) && outside.is_some_and(is_word_char)
+            };
+            if touches(found.chars().next(), before) || touches(found.chars().next_back(), after) {
+                return false;
+            }
+        }
+        if self.whole_line {
+            let starts_line = before.is_none_or(|c| c == '\n') || found.starts_with('\n');
+            let ends_line =
+                after.is_none_or(|c| c == '\n' || c == '\r') || found.ends_with('\n');
+            if !starts_line || !ends_line {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Like [`replace`], but also reports where in `content` the match was.
 ///
 /// Matches OpenAcosmi's `replace()` function logic exactly:
 /// - For each replacer, for each yielded candidate:
@@ -80,38 +204,153 @@ const REPLACER_CHAIN: &[(&str, Replacer)] = &[
 ///   - If `replace_all` → replace all occurrences and return
 ///   - If not `replace_all` → check uniqueness; if unique, replace; if ambiguous, skip
 /// - If all replacers exhausted with no replacement → `None`
-pub fn replace(content: &str, old: &str, new: &str, replace_all: bool) -> Option<String> {
+#[must_use]
+pub fn replace_with_ranges(
+    content: &str,
+    old: &str,
+    new: &str,
+    replace_all: bool,
+) -> Option<Replacement> {
+    replace_with_options(content, old, new, replace_all, MatchOptions::default())
+}
+
+/// Like [`replace_with_ranges`], with exact matches of `old` restricted by
+/// `options`: occurrences that violate them are ignored, both for the
+/// uniqueness check and for `replace_all`.
+#[must_use]
+pub fn replace_with_options(
+    content: &str,
+    old: &str,
+    new: &str,
+    replace_all: bool,
+    options: MatchOptions,
+) -> Option<Replacement> {
+    run_chain(REPLACER_CHAIN, content, old, new, replace_all, options, None)
+}
+
+/// What one layer of the chain found, for explaining an edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayerReport {
+    /// The replacer's name, e.g. `LineTrimmedReplacer`.
+    pub layer: &'static str,
+    /// Candidates the layer yielded that occur in the content.
+    pub candidates: usize,
+    /// Of those, how many occur more than once (and were skipped).
+    pub ambiguous: usize,
+}
+
+/// Like [`replace_with_options`], also reporting each layer that ran.
+///
+/// On success the last report is the layer that matched; on failure every
+/// layer has one.
+#[must_use]
+pub fn replace_with_diagnostics(
+    content: &str,
+    old: &str,
+    new: &str,
+    replace_all: bool,
+    options: MatchOptions,
+) -> (Option<Replacement>, Vec<LayerReport>) {
+    let mut reports = Vec::with_capacity(REPLACER_CHAIN.len());
+    let replacement =
+        run_chain(REPLACER_CHAIN, content, old, new, replace_all, options, Some(&mut reports));
+    (replacement, reports)
+}
+
+/// One line summarising `reports`, e.g. `LineTrimmedReplacer found 3
+/// ambiguous candidates; none from SimpleReplacer, BlockAnchorReplacer`.
+#[must_use]
+pub fn layer_summary(reports: &[LayerReport]) -> String {
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let mut parts: Vec<String> = reports
+        .iter()
+        .filter(|r| r.candidates > 0)
+        .map(|r| {
+            if r.ambiguous == r.candidates {
+                format!("{} found {} ambiguous candidate{}", r.layer, r.ambiguous, plural(r.ambiguous))
+            } else {
+                format!(
+                    "{} found {} candidate{} ({} ambiguous)",
+                    r.layer,
+                    r.candidates,
+                    plural(r.candidates),
+                    r.ambiguous
+                )
+            }
+        })
+        .collect();
+    let empty: Vec<&str> = reports.iter().filter(|r| r.candidates == 0).map(|r| r.layer).collect();
+    if !empty.is_empty() {
+        parts.push(format!("none from {}", empty.join(", ")));
+    }
+    parts.join("; ")
+}
+
+/// Run the replacer `chain`, pushing a [`LayerReport`] per layer tried onto
+/// `reports` if given.
+fn run_chain(
+    chain: &[(&'static str, Replacer)],
+    content: &str,
+    old: &str,
+    new: &str,
+    replace_all: bool,
+    options: MatchOptions,
+    mut reports: Option<&mut Vec<LayerReport>>,
+) -> Option<Replacement> {
     let mut any_found = false;
 
-    for &(name, replacer) in REPLACER_CHAIN {
-        let candidates = replacer(content, old);
+    for &(name, replacer) in chain {
+        if name == UNICODE_NORMALIZED_REPLACER && !options.normalize_unicode {
+            continue;
+        }
+        let candidates = replacer(content, old, options);
+        let mut report = LayerReport { layer: name, candidates: 0, ambiguous: 0 };
+        let mut result = None;
 
         for search in &candidates {
-            let Some(index) = content.find(search.as_str()) else {
+            let constrained = search == old && !options.is_unconstrained();
+            let ranges: Vec<(usize, usize)> = content
+                .match_indices(search.as_str())
+                .filter(|&(start, m)| !constrained || options.accepts(content, start, m))
+                .map(|(start, m)| (start, start + m.len()))
+                .collect();
+            let Some(&first) = ranges.first() else {
                 continue;
             };
             any_found = true;
+            report.candidates += 1;
 
             if replace_all {
                 debug!(replacer = name, "replace_all match");
-                return Some(content.replace(search.as_str(), new));
+                result = Some(Replacement {
+                    content: splice(content, &ranges, new),
+                    ranges,
+                });
+                break;
             }
 
-            // Check uniqueness: last occurrence must equal first occurrence.
-            let last_index = content.rfind(search.as_str());
-            if last_index != Some(index) {
+            // Check uniqueness: the candidate must occur exactly once.
+            if ranges.len() > 1 {
                 // Multiple occurrences — skip this candidate, try next.
                 debug!(replacer = name, "ambiguous match, skipping");
+                report.ambiguous += 1;
                 continue;
             }
 
             // Unique match — perform replacement.
             debug!(replacer = name, "unique match found");
-            let mut result = String::with_capacity(content.len() + new.len());
-            result.push_str(&content[..index]);
-            result.push_str(new);
-            result.push_str(&content[index + search.len()..]);
-            return Some(result);
+            result = Some(Replacement {
+                content: splice(content, &ranges, new),
+                ranges: vec![first],
+            });
+            break;
+        }
+
+        if let Some(reports) = reports.as_deref_mut() {
+            reports.push(report);
+        }
+        if result.is_some() {
+            return result;
         }
     }
 
@@ -121,3 +360,143 @@ pub fn replace(content: &str, old: &str, new: &str, replace_all: bool) -> Option
 
     None
 }
+
+/// Replace each of the ascending, non-overlapping `ranges` of `content`
+/// with `new`.
+fn splice(content: &str, ranges: &[(usize, usize)], new: &str) -> String {
+    let mut result = String::with_capacity(content.len() + new.len() * ranges.len());
+    let mut last = 0;
+    for &(start, end) in ranges {
+        result.push_str(&content[last..start]);
+        result.push_str(new);
+        last = end;
+    }
+    result.push_str(&content[last..]);
+    result
+}
+
+/// The block of `content` most similar to `find`, for a "did you mean" hint
+/// after [`replace`] found nothing.
+///
+/// Scores each window of as many lines as `find` with
+/// [`levenshtein::similarity`], from the top of the file, until the window
+/// or cost limit is reached. Returns the 1-based first line of the best
+/// window, its text and its score, if the score is at least 0.6.
+#[must_use]
+pub fn closest_match(content: &str, find: &str) -> Option<(usize, String, f64)> {
+    let find = find.strip_suffix('\n').unwrap_or(find);
+    let find_len = find.chars().count();
+    if find.trim().is_empty() || find_len > levenshtein::MAX_LEVENSHTEIN_INPUT {
+        return None;
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let window = find.lines().count().max(1);
+    if window > lines.len() {
+        return None;
+    }
+    let max_windows =
+        (CLOSEST_MATCH_CELL_BUDGET / (find_len * find_len)).min(MAX_CLOSEST_MATCH_WINDOWS);
+
+    let mut best: Option<(usize, String, f64)> = None;
+    for (i, block) in lines.windows(window).take(max_windows).enumerate() {
+        let block = block.join("\n");
+        let score = levenshtein::similarity(&block, find);
+        if best.as_ref().is_none_or(|(_, _, s)| score > *s) {
+            best = Some((i + 1, block, score));
+        }
+    }
+    best.filter(|(_, _, score)| *score >= CLOSEST_MATCH_THRESHOLD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_unique_match() {
+        let r = replace_with_ranges("let a = 1;\nlet b = 2;\n", "let b = 2;", "let b = 3;", false)
+            .expect("match");
+        assert_eq!(r.ranges, vec![(11, 21)]);
+        assert_eq!(r.content, "let a = 1;\nlet b = 3;\n");
+    }
+
+    #[test]
+    fn test_closest_match() {
+        let content = "fn a() {\n    let total = 1;\n    total + 2\n}\n";
+        let (line, block, score) =
+            closest_match(content, "    let totl = 1;\n    totl + 2\n").expect("hint");
+        assert_eq!(line, 2);
+        assert_eq!(block, "    let total = 1;\n    total + 2");
+        assert!(score > 0.9);
+
+        assert!(closest_match(content, "something else entirely").is_none());
+        assert!(closest_match(content, "a\nb\nc\nd\ne\nf").is_none());
+    }
+
+    #[test]
+    fn test_ranges_replace_all() {
+        let r = replace_with_ranges("foo bar foo", "foo", "baz", true).expect("match");
+        assert_eq!(r.ranges, vec![(0, 3), (8, 11)]);
+        assert_eq!(r.content, "baz bar baz");
+    }
+
+    #[test]
+    fn test_whole_word_skips_matches_inside_words() {
+        let word = MatchOptions { whole_word: true, ..MatchOptions::default() };
+        let content = "let width = 1;\nlet id = 2;\n";
+
+        // Plain matching sees `id` twice (in `width` too) and gives up.
+        assert!(replace_with_ranges(content, "id", "key", false).is_none());
+        let r = replace_with_options(content, "id", "key", false, word).expect("match");
+        assert_eq!(r.content, "let width = 1;\nlet key = 2;\n");
+
+        assert!(replace_with_options("let width = 1;\n", "id", "key", false, word).is_none());
+        let r = replace_with_options("id(width, id_2, id)", "id", "k", true, word).expect("match");
+        assert_eq!(r.content, "k(width, id_2, k)");
+        // Punctuation at the edge of `old` needs no boundary.
+        let r = replace_with_options("a.b", "a.", "x.", false, word).expect("match");
+        assert_eq!(r.content, "x.b");
+    }
+
+    #[test]
+    fn test_whole_line() {
+        let line = MatchOptions { whole_line: true, ..MatchOptions::default() };
+        let content = "    foo();\nfoo();\r\n";
+        let r = replace_with_options(content, "foo();", "bar();", false, line).expect("match");
+        assert_eq!(r.content, "    foo();\nbar();\r\n");
+        assert!(replace_with_options("x foo();\n", "foo();", "bar();", false, line).is_none());
+    }
+
+    #[test]
+    fn test_normalize_unicode_is_opt_in() {
+        let content = "print(\"done\")\n";
+        let find = "print(\u{201C}done\u{201D})";
+        assert!(replace_with_ranges(content, find, "print('ok')", false).is_none());
+
+        let unicode = MatchOptions { normalize_unicode: true, ..MatchOptions::default() };
+        let (r, reports) = replace_with_diagnostics(content, find, "print('ok')", false, unicode);
+        let r = r.expect("match");
+        assert_eq!(r.content, "print('ok')\n");
+        assert_eq!(r.ranges, vec![(0, 13)]);
+        assert_eq!(reports.last().map(|r| r.layer), Some(UNICODE_NORMALIZED_REPLACER));
+    }
+
+    #[test]
+    fn test_layer_diagnostics() {
+        let content = "    foo();\n    foo();\n";
+        let (r, reports) =
+            replace_with_diagnostics(content, "foo();", "bar();", false, MatchOptions::default());
+        assert!(r.is_none());
+        // Every layer but the opt-in one ran.
+        assert_eq!(reports.len(), REPLACER_CHAIN.len() - 1);
+        assert_eq!(reports[0], LayerReport { layer: "SimpleReplacer", candidates: 1, ambiguous: 1 });
+        let summary = layer_summary(&reports);
+        assert!(summary.starts_with("SimpleReplacer found 1 ambiguous candidate;"), "got: {summary}");
+
+        let (r, reports) =
+            replace_with_diagnostics("a\n  b  \n", "b", "c", false, MatchOptions::default());
+        assert_eq!(r.expect("match").content, "a\n  c  \n");
+        assert_eq!(reports.last().map(|r| r.layer), Some("SimpleReplacer"));
+    }
+}
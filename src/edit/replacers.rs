@@ -4,6 +4,10 @@
 //! candidate strings found in `content` that match `find`. The orchestrator
 //! in `mod.rs` handles the actual replacement.
 //!
+//! [`unicode_normalized_replacer`] is not part of the TypeScript chain; it
+//! only runs when [`MatchOptions::normalize_unicode`](super::MatchOptions)
+//! is set.
+//!
 //! Ported from OpenAcosmi's `edit.ts` — each function corresponds to an
 //! exported `Replacer` generator in the TypeScript source.
 
@@ -33,13 +37,13 @@ pub fn line_trimmed_replacer(content: &str, find: &str) -> Vec<String> {
         search_lines.pop();
     }
 
-    if search_lines.is_empty() {
+    if search_lines.is_empty() || search_lines.len() > original_lines.len() {
         return Vec::new();
     }
 
     let mut results = Vec::new();
 
-    for i in 0..=original_lines.len().saturating_sub(search_lines.len()) {
+    for i in 0..=original_lines.len() - search_lines.len() {
         let mut matches = true;
 
         for j in 0..search_lines.len() {
@@ -209,6 +213,13 @@ fn extract_block(content: &str, lines: &[&str], start_line: usize, end_line: usi
 // Layer 4: WhitespaceNormalizedReplacer
 // ---------------------------------------------------------------------------
 
+/// Most words joined into the whitespace-flexible regex; longer find
+/// strings use the plain line scan instead.
+const MAX_WS_REGEX_WORDS: usize = 64;
+
+/// Compiled size limit for the whitespace-flexible regex.
+const WS_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
 /// Matches after normalizing whitespace. Yields the original text from content.
 pub fn whitespace_normalized_replacer(content: &str, find: &str) -> Vec<String> {
     let normalize = |s: &str| -> String {
@@ -219,19 +230,22 @@ pub fn whitespace_normalized_replacer(content: &str, find: &str) -> Vec<String>
     let lines: Vec<&str> = content.split('\n').collect();
     let mut results = Vec::new();
 
-    // Pre-compile the whitespace-flexible regex once, outside the loop.
-    let ws_regex = {
-        let words: Vec<&str> = find.split_whitespace().collect();
-        if words.is_empty() {
-            None
-        } else {
-            let pattern = words
-                .iter()
-                .map(|w| regex::escape(w))
-                .collect::<Vec<_>>()
-                .join(r"\s+");
-            regex::Regex::new(&pattern).ok()
-        }
+    // Pre-compile the whitespace-flexible regex once, outside the loop. Large
+    // find strings skip it (and a regex over the size limit is dropped) in
+    // favour of `find_words_flexible`, which cannot blow up.
+    let words: Vec<&str> = find.split_whitespace().collect();
+    let ws_regex = if words.is_empty() || words.len() > MAX_WS_REGEX_WORDS {
+        None
+    } else {
+        let pattern = words
+            .iter()
+            .map(|w| regex::escape(w))
+            .collect::<Vec<_>>()
+            .join(r"\s+");
+        regex::RegexBuilder::new(&pattern)
+            .size_limit(WS_REGEX_SIZE_LIMIT)
+            .build()
+            .ok()
     };
 
     // Single-line matches.
@@ -241,10 +255,12 @@ pub fn whitespace_normalized_replacer(content: &str, find: &str) -> Vec<String>
         } else {
             let normalized_line = normalize(line);
             if normalized_line.contains(&normalized_find) {
-                if let Some(ref re) = ws_regex {
-                    if let Some(m) = re.find(line) {
-                        results.push(m.as_str().to_owned());
-                    }
+                let found = ws_regex.as_ref().map_or_else(
+                    || find_words_flexible(line, &words),
+                    |re| re.find(line).map(|m| m.as_str()),
+                );
+                if let Some(found) = found {
+                    results.push(found.to_owned());
                 }
             }
         }
@@ -252,8 +268,8 @@ pub fn whitespace_normalized_replacer(content: &str, find: &str) -> Vec<String>
 
     // Multi-line matches.
     let find_lines: Vec<&str> = find.split('\n').collect();
-    if find_lines.len() > 1 {
-        for i in 0..=lines.len().saturating_sub(find_lines.len()) {
+    if find_lines.len() > 1 && find_lines.len() <= lines.len() {
+        for i in 0..=lines.len() - find_lines.len() {
             let block = lines[i..i + find_lines.len()].join("\n");
             if normalize(&block) == normalized_find {
                 results.push(block);
@@ -264,49 +280,67 @@ pub fn whitespace_normalized_replacer(content: &str, find: &str) -> Vec<String>
     results
 }
 
+/// Find `words` in `line` separated by runs of whitespace — what the regex
+/// `w1\s+w2\s+…` matches — by scanning from each occurrence of the first word.
+fn find_words_flexible<'a>(line: &'a str, words: &[&str]) -> Option<&'a str> {
+    let (first, rest) = words.split_first()?;
+    'start: for (start, _) in line.match_indices(first) {
+        let mut end = start + first.len();
+        for word in rest {
+            let after = &line[end..];
+            let trimmed = after.trim_start();
+            if trimmed.len() == after.len() || !trimmed.starts_with(word) {
+                continue 'start;
+            }
+            end += after.len() - trimmed.len() + word.len();
+        }
+        return Some(&line[start..end]);
+    }
+    None
+}
+
 // ---------------------------------------------------------------------------
 // Layer 5: IndentationFlexibleReplacer
 // ---------------------------------------------------------------------------
 
+/// Default tab width for [`indentation_flexible_replacer`].
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 /// Matches after removing common indentation. Yields the original text.
+///
+/// Uses [`DEFAULT_TAB_WIDTH`]; see [`indentation_flexible_replacer_with_tab_width`].
 pub fn indentation_flexible_replacer(content: &str, find: &str) -> Vec<String> {
-    let remove_indentation = |text: &str| -> String {
-        let lines: Vec<&str> = text.split('\n').collect();
-        let non_empty: Vec<&&str> = lines.iter().filter(|l| !l.trim().is_empty()).collect();
-
-        if non_empty.is_empty() {
-            return text.to_owned();
-        }
-
-        let min_indent = non_empty
-            .iter()
-            .map(|l| l.len() - l.trim_start().len())
-            .min()
-            .unwrap_or(0);
-
-        lines
-            .iter()
-            .map(|line| {
-                if line.trim().is_empty() {
-                    *line
-                } else if line.len() > min_indent && line.is_char_boundary(min_indent) {
-                    &line[min_indent..]
-                } else {
-                    line.trim_start()
-                }
-            })
-            .collect::<Vec<&str>>()
-            .join("\n")
-    };
+    indentation_flexible_replacer_with_tab_width(content, find, DEFAULT_TAB_WIDTH)
+}
 
-    let normalized_find = remove_indentation(find);
+/// Like [`indentation_flexible_replacer`], with an explicit tab width.
+///
+/// Indentation is measured in columns (a tab advances to the next multiple
+/// of `tab_width`), so tab- and space-indented blocks with the same layout
+/// match. Content blocks whose indentation has a space before a tab are
+/// skipped: their layout depends on the tab width, so any match is a guess.
+#[must_use]
+pub fn indentation_flexible_replacer_with_tab_width(
+    content: &str,
+    find: &str,
+    tab_width: usize,
+) -> Vec<String> {
+    let tab_width = tab_width.max(1);
+    let normalized_find = remove_indentation(find, tab_width);
     let content_lines: Vec<&str> = content.split('\n').collect();
     let find_lines: Vec<&str> = find.split('\n').collect();
     let mut results = Vec::new();
+    if find_lines.len() > content_lines.len() {
+        return results;
+    }
 
-    for i in 0..=content_lines.len().saturating_sub(find_lines.len()) {
-        let block = content_lines[i..i + find_lines.len()].join("\n");
-        if remove_indentation(&block) == normalized_find {
+    for i in 0..=content_lines.len() - find_lines.len() {
+        let lines = &content_lines[i..i + find_lines.len()];
+        if lines.iter().any(|line| has_ambiguous_indent(line)) {
+            continue;
+        }
+        let block = lines.join("\n");
+        if remove_indentation(&block, tab_width) == normalized_find {
             results.push(block);
         }
     }
@@ -314,11 +348,59 @@ pub fn indentation_flexible_replacer(content: &str, find: &str) -> Vec<String> {
     results
 }
 
+/// Remove the common indentation (in columns) from every non-blank line,
+/// re-rendering what remains of each line's indentation as spaces.
+fn remove_indentation(text: &str, tab_width: usize) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let min_indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| indent_columns(l, tab_width).0)
+        .min();
+    let Some(min_indent) = min_indent else {
+        return text.to_owned();
+    };
+
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                (*line).to_owned()
+            } else {
+                let (columns, rest) = indent_columns(line, tab_width);
+                format!("{}{rest}", " ".repeat(columns - min_indent))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Width of `line`'s leading spaces and tabs in columns, and the rest of it.
+fn indent_columns(line: &str, tab_width: usize) -> (usize, &str) {
+    let mut columns = 0;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            ' ' => columns += 1,
+            '\t' => columns += tab_width - columns % tab_width,
+            _ => return (columns, &line[i..]),
+        }
+    }
+    (columns, "")
+}
+
+/// Whether `line`'s indentation has a space followed by a tab.
+fn has_ambiguous_indent(line: &str) -> bool {
+    let indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+    indent.contains(" \t")
+}
+
 // ---------------------------------------------------------------------------
 // Layer 6: EscapeNormalizedReplacer
 // ---------------------------------------------------------------------------
 
-/// Matches after normalizing escape sequences in the find string.
+/// Matches after normalizing escape sequences, in both directions: the
+/// unescaped find string in content, and the find string's real control
+/// characters written as escapes (`\n`, `\t`, ...) in content.
 pub fn escape_normalized_replacer(content: &str, find: &str) -> Vec<String> {
     let unescape = |s: &str| -> String {
         let mut result = String::with_capacity(s.len());
@@ -395,9 +477,31 @@ pub fn escape_normalized_replacer(content: &str, find: &str) -> Vec<String> {
         }
     }
 
+    // Reverse direction: content holds escape sequences where the find
+    // string has real characters (e.g. `\n` inside a string literal).
+    let escaped_find = escape(find);
+    if escaped_find != find && content.contains(&escaped_find) && !results.contains(&escaped_find) {
+        results.push(escaped_find);
+    }
+
     results
 }
 
+/// Write backslashes and control characters as escape sequences.
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
 // ---------------------------------------------------------------------------
 // Layer 7: TrimmedBoundaryReplacer
 // ---------------------------------------------------------------------------
@@ -438,9 +542,25 @@ pub fn trimmed_boundary_replacer(content: &str, find: &str) -> Vec<String> {
 // Layer 8: ContextAwareReplacer
 // ---------------------------------------------------------------------------
 
+/// Default share of middle lines [`context_aware_replacer`] requires to
+/// match exactly.
+pub const DEFAULT_CONTEXT_MATCH_RATIO: f64 = 0.5;
+
 /// Matches using first/last line anchors with middle-line similarity scoring.
-/// Requires at least 3 lines and 50% middle-line exact match rate.
+///
+/// Uses [`DEFAULT_CONTEXT_MATCH_RATIO`]; see [`context_aware_replacer_with_ratio`].
 pub fn context_aware_replacer(content: &str, find: &str) -> Vec<String> {
+    context_aware_replacer_with_ratio(content, find, DEFAULT_CONTEXT_MATCH_RATIO)
+}
+
+/// Like [`context_aware_replacer`], with an explicit match ratio.
+///
+/// Requires at least 3 lines, and at least `ratio` of the non-blank middle
+/// lines to match exactly (after trimming). The ratio is not scaled by block
+/// size: a block with one middle line passes any ratio above 0 only if that
+/// line matches.
+#[must_use]
+pub fn context_aware_replacer_with_ratio(content: &str, find: &str, ratio: f64) -> Vec<String> {
     let mut find_lines: Vec<&str> = find.split('\n').collect();
 
     if find_lines.len() < 3 {
@@ -472,7 +592,7 @@ pub fn context_aware_replacer(content: &str, find: &str) -> Vec<String> {
                 break;
             }
 
-            // Score middle lines: 50% exact match threshold.
+            // Score middle lines against the exact-match ratio.
             let mut matching_lines = 0;
             let mut total_non_empty = 0;
 
@@ -489,7 +609,7 @@ pub fn context_aware_replacer(content: &str, find: &str) -> Vec<String> {
             }
 
             if total_non_empty == 0
-                || matching_lines as f64 / total_non_empty as f64 >= 0.5
+                || matching_lines as f64 / total_non_empty as f64 >= ratio
             {
                 let block = block_lines.join("\n");
                 return vec![block];
@@ -520,6 +640,66 @@ pub fn multi_occurrence_replacer(content: &str, find: &str) -> Vec<String> {
     results
 }
 
+// ---------------------------------------------------------------------------
+// Opt-in: UnicodeNormalizedReplacer
+// ---------------------------------------------------------------------------
+
+/// The ASCII character a typographic lookalike stands in for — smart quotes,
+/// primes, hyphens and dashes, non-breaking and other fixed-width spaces —
+/// or `c` itself.
+#[must_use]
+pub const fn fold_confusable(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+        '\u{2010}'..='\u{2015}' | '\u{2212}' => '-',
+        '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' | '\u{205F}' | '\u{3000}' => ' ',
+        _ => c,
+    }
+}
+
+/// Matches after folding unicode lookalikes (see [`fold_confusable`]).
+///
+/// Both content and find are folded, but the original text from content is
+/// yielded, so a curly-quoted find string replaces the straight-quoted
+/// source and vice versa.
+///
+/// Every lookalike folds to exactly one character, so a match in the
+/// folded content maps back to whole characters of the original.
+pub fn unicode_normalized_replacer(content: &str, find: &str) -> Vec<String> {
+    let folded_find: String = find.chars().map(fold_confusable).collect();
+    if folded_find.is_empty() {
+        return Vec::new();
+    }
+
+    // Folded content, with `(folded, original)` byte offsets per character.
+    let mut folded = String::with_capacity(content.len());
+    let mut offsets = Vec::with_capacity(content.len() + 1);
+    for (i, ch) in content.char_indices() {
+        offsets.push((folded.len(), i));
+        folded.push(fold_confusable(ch));
+    }
+    offsets.push((folded.len(), content.len()));
+    let original_at = |pos: usize| {
+        offsets
+            .binary_search_by_key(&pos, |&(f, _)| f)
+            .ok()
+            .map(|k| offsets[k].1)
+    };
+
+    let mut results: Vec<String> = Vec::new();
+    for (start, m) in folded.match_indices(folded_find.as_str()) {
+        let (Some(from), Some(to)) = (original_at(start), original_at(start + m.len())) else {
+            continue;
+        };
+        let text = &content[from..to];
+        if !results.iter().any(|r| r == text) {
+            results.push(text.to_owned());
+        }
+    }
+    results
+}
+
 // ===========================================================================
 // Tests
 // ===========================================================================
@@ -598,6 +778,26 @@ mod tests {
         assert!(!candidates.is_empty());
     }
 
+    #[test]
+    fn test_whitespace_normalized_large_find() {
+        // Thousands of short words: past the regex word cap, so the line scan
+        // finds the match, and quickly.
+        let words: Vec<String> = (0..3000).map(|i| format!("w{}", i % 7)).collect();
+        let find = words.join(" ");
+        let content = format!("prefix {} suffix", words.join(" \t "));
+        let started = std::time::Instant::now();
+        let candidates = whitespace_normalized_replacer(&content, &find);
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(candidates, vec![words.join(" \t ")]);
+    }
+
+    #[test]
+    fn test_find_words_flexible_requires_whitespace() {
+        assert_eq!(find_words_flexible("ab  cd", &["ab", "cd"]), Some("ab  cd"));
+        assert_eq!(find_words_flexible("abcd ab cd", &["ab", "cd"]), Some("ab cd"));
+        assert_eq!(find_words_flexible("abcd", &["ab", "cd"]), None);
+    }
+
     // -- Layer 5: IndentationFlexibleReplacer --
     #[test]
     fn test_indentation_flexible() {
@@ -608,6 +808,35 @@ mod tests {
         assert_eq!(candidates[0], content);
     }
 
+    #[test]
+    fn test_indentation_flexible_tabs_vs_spaces() {
+        let content = "impl A {\n\tfn a() {\n\t\treturn;\n\t}\n}";
+        let find = "fn a() {\n    return;\n}";
+        let candidates = indentation_flexible_replacer(content, find);
+        assert_eq!(candidates, vec!["\tfn a() {\n\t\treturn;\n\t}"]);
+
+        // With a tab width of 2 the tab-indented body is only 2 columns deep.
+        assert!(indentation_flexible_replacer_with_tab_width(content, find, 2).is_empty());
+        let find2 = "fn a() {\n  return;\n}";
+        assert_eq!(indentation_flexible_replacer_with_tab_width(content, find2, 2).len(), 1);
+    }
+
+    #[test]
+    fn test_indentation_flexible_mixed_block() {
+        // A block mixing tab- and space-indented lines normalizes consistently.
+        let content = "\tif x {\n        y();\n\t}";
+        let find = "if x {\n    y();\n}";
+        assert_eq!(indentation_flexible_replacer(content, find), vec![content]);
+    }
+
+    #[test]
+    fn test_indentation_flexible_skips_ambiguous_indent() {
+        // `  \t` is 4 columns at tab width 4 but 2 at tab width 2: ambiguous.
+        let content = "\tif x {\n  \t\ty();\n\t}";
+        let find = "if x {\n    y();\n}";
+        assert!(indentation_flexible_replacer(content, find).is_empty());
+    }
+
     // -- Layer 6: EscapeNormalizedReplacer --
     #[test]
     fn test_escape_normalized_direct() {
@@ -618,6 +847,33 @@ mod tests {
         assert!(!candidates.is_empty());
     }
 
+    #[test]
+    fn test_escape_normalized_reverse() {
+        // Content has the escape sequence \n; find has a real newline.
+        let content = "let msg = \"hello\\nworld\";";
+        let find = "\"hello\nworld\"";
+        let candidates = escape_normalized_replacer(content, find);
+        assert_eq!(candidates, vec!["\"hello\\nworld\"".to_owned()]);
+        assert_eq!(
+            crate::edit::replace(content, find, "\"bye\"", false).as_deref(),
+            Some("let msg = \"bye\";")
+        );
+    }
+
+    #[test]
+    fn test_find_longer_than_content() {
+        // More find lines than content lines must not slice out of bounds.
+        let content = "a";
+        let find = "a\nb\nc";
+        for replacer in [
+            line_trimmed_replacer,
+            whitespace_normalized_replacer,
+            indentation_flexible_replacer,
+        ] {
+            assert!(replacer(content, find).is_empty());
+        }
+    }
+
     #[test]
     fn test_escape_normalized_identical() {
         // Both content and find are identical — direct match via unescaping.
@@ -659,6 +915,26 @@ mod tests {
         assert_eq!(candidates.len(), 1);
     }
 
+    #[test]
+    fn test_context_aware_ratio_boundary() {
+        // Two of the four middle lines match: exactly 50%.
+        let content = "fn f() {\n    a();\n    b();\n    c();\n    d();\n}";
+        let find = "fn f() {\n    a();\n    b();\n    x();\n    y();\n}";
+        assert_eq!(context_aware_replacer(content, find), vec![content.to_owned()]);
+        assert_eq!(context_aware_replacer_with_ratio(content, find, 0.5).len(), 1);
+        assert_eq!(context_aware_replacer_with_ratio(content, find, 0.49).len(), 1);
+        assert!(context_aware_replacer_with_ratio(content, find, 0.51).is_empty());
+
+        // One of four (25%) is below the default.
+        let find = "fn f() {\n    a();\n    w();\n    x();\n    y();\n}";
+        assert!(context_aware_replacer(content, find).is_empty());
+        assert_eq!(context_aware_replacer_with_ratio(content, find, 0.25).len(), 1);
+        // Three of four (75%) is above it.
+        let find = "fn f() {\n    a();\n    b();\n    c();\n    y();\n}";
+        assert_eq!(context_aware_replacer(content, find).len(), 1);
+        assert!(context_aware_replacer_with_ratio(content, find, 0.8).is_empty());
+    }
+
     // -- Layer 9: MultiOccurrenceReplacer --
     #[test]
     fn test_multi_occurrence() {
@@ -668,6 +944,25 @@ mod tests {
         assert_eq!(candidates.len(), 3);
     }
 
+    // -- Opt-in: UnicodeNormalizedReplacer --
+    #[test]
+    fn test_unicode_normalized_curly_find() {
+        let content = "let s = \"it's\";\nlet t = x - y;\n";
+        let candidates = unicode_normalized_replacer(content, "let s = \u{201C}it\u{2019}s\u{201D};");
+        assert_eq!(candidates, vec!["let s = \"it's\";"]);
+        let candidates = unicode_normalized_replacer(content, "x\u{00A0}\u{2013} y");
+        assert_eq!(candidates, vec!["x - y"]);
+    }
+
+    #[test]
+    fn test_unicode_normalized_yields_original_text() {
+        // Multi-byte lookalikes in the content map back to whole characters.
+        let content = "say \u{201C}hi\u{201D} \u{2014} ok";
+        let candidates = unicode_normalized_replacer(content, "\"hi\" - ok");
+        assert_eq!(candidates, vec!["\u{201C}hi\u{201D} \u{2014} ok"]);
+        assert!(unicode_normalized_replacer(content, "\"bye\"").is_empty());
+    }
+
     // -- Integration: replace() orchestrator --
     #[test]
     fn test_replace_exact() {
@@ -2,6 +2,10 @@
 
 use std::path::PathBuf;
 
+use serde_json::json;
+
+use crate::server::JsonRpcError;
+
 /// Coder-specific error types.
 #[derive(Debug, thiserror::Error)]
 pub enum CoderError {
@@ -29,6 +33,18 @@ pub enum CoderError {
         actual: String,
     },
 
+    /// File content differs from the hash the caller last saw.
+    #[error("file content changed: {path} (expected sha256 {expected}, got {actual})")]
+    ContentChanged {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    /// File is larger than the server's file size limit.
+    #[error("file too large: {path} ({size} bytes, limit {limit})")]
+    FileTooLarge { path: PathBuf, size: u64, limit: u64 },
+
     /// ripgrep binary not found on PATH.
     #[error("ripgrep (rg) not found on PATH — install via: brew install ripgrep / apt install ripgrep")]
     RipgrepNotFound,
@@ -56,6 +72,86 @@ pub enum CoderError {
     /// Sandbox execution error.
     #[error("sandbox error: {0}")]
     Sandbox(String),
+
+    /// Tool exists but is disabled by the server's tool filter.
+    #[error("tool not available: {name}")]
+    ToolDisabled { name: String },
+}
+
+impl CoderError {
+    /// JSON-RPC error code for this error.
+    ///
+    /// Protocol and JSON errors use the standard codes, and a disabled tool
+    /// is reported as method-not-found; tool failures use application codes
+    /// in the implementation-defined range (-32001..).
+    #[must_use]
+    pub const fn rpc_code(&self) -> i64 {
+        match self {
+            Self::FileNotFound { .. } => -32001,
+            Self::BinaryFile { .. } => -32002,
+            Self::NoMatchFound { .. } => -32003,
+            Self::AmbiguousMatch { .. } => -32004,
+            Self::FileModified { .. } => -32005,
+            Self::RipgrepNotFound => -32006,
+            Self::SubprocessFailed { .. } => -32007,
+            Self::Sandbox(_) => -32008,
+            Self::FileTooLarge { .. } => -32009,
+            Self::ContentChanged { .. } => -32010,
+            Self::ToolDisabled { .. } => -32601,
+            Self::Protocol(_) => -32600,
+            Self::Json(_) => -32700,
+            Self::Io { .. } => -32603,
+        }
+    }
+
+    /// Structured error details: a `kind` tag plus the variant's fields.
+    #[must_use]
+    pub fn rpc_data(&self) -> serde_json::Value {
+        match self {
+            Self::FileNotFound { path } => json!({ "kind": "fileNotFound", "path": path }),
+            Self::BinaryFile { path } => json!({ "kind": "binaryFile", "path": path }),
+            Self::NoMatchFound { path } => json!({ "kind": "noMatchFound", "path": path }),
+            Self::AmbiguousMatch { path, count } => {
+                json!({ "kind": "ambiguousMatch", "path": path, "count": count })
+            }
+            Self::FileModified { path, expected, actual } => json!({
+                "kind": "fileModified",
+                "path": path,
+                "expected": expected,
+                "actual": actual,
+            }),
+            Self::ContentChanged { path, expected, actual } => json!({
+                "kind": "contentChanged",
+                "path": path,
+                "expected": expected,
+                "actual": actual,
+            }),
+            Self::FileTooLarge { path, size, limit } => {
+                json!({ "kind": "fileTooLarge", "path": path, "size": size, "limit": limit })
+            }
+            Self::RipgrepNotFound => json!({ "kind": "ripgrepNotFound" }),
+            Self::SubprocessFailed { command, reason } => {
+                json!({ "kind": "subprocessFailed", "command": command, "reason": reason })
+            }
+            Self::Protocol(_) => json!({ "kind": "protocol" }),
+            Self::Json(e) => json!({ "kind": "json", "line": e.line(), "column": e.column() }),
+            Self::Io { path, source } => {
+                json!({ "kind": "io", "path": path, "ioKind": source.kind().to_string() })
+            }
+            Self::Sandbox(_) => json!({ "kind": "sandbox" }),
+            Self::ToolDisabled { name } => json!({ "kind": "toolDisabled", "name": name }),
+        }
+    }
+}
+
+impl From<&CoderError> for JsonRpcError {
+    fn from(err: &CoderError) -> Self {
+        Self {
+            code: err.rpc_code(),
+            message: err.to_string(),
+            data: Some(err.rpc_data()),
+        }
+    }
 }
 
 /// Convenience result type for oa-coder operations.
@@ -0,0 +1,465 @@
+//! MCP HTTP + SSE transport (feature `http`).
+//!
+//! Implements the MCP "HTTP with SSE" transport on top of the same session
+//! core as stdio ([`crate::server`]):
+//!
+//! 1. Client opens `GET /sse` → server streams Server-Sent Events, starting
+//!    with an `endpoint` event naming the session's message URL
+//!    (`/message?sessionId=…`)
+//! 2. Client POSTs JSON-RPC messages (single or batch) to that URL → `202 Accepted`
+//! 3. Responses and notifications arrive as `message` events on the stream
+//! 4. Closing the stream ends the session and cancels its in-flight tool calls
+//!
+//! The HTTP server is deliberately minimal (std only, one thread per
+//! connection, `Connection: close` for POSTs) — enough for a local service.
+//! It has no authentication, so it binds to localhost unless told otherwise,
+//! and requests whose `Origin` is not a loopback host are refused to guard
+//! against DNS rebinding from web pages.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, anyhow};
+use tracing::{debug, info, warn};
+
+use crate::logging;
+use crate::server::{CallPool, McpServerConfig, Outbound, Session};
+use crate::tools::ToolRouter;
+
+/// Interval between SSE keep-alive comments (also how quickly a vanished
+/// client is noticed).
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Maximum size of the request line plus headers.
+const MAX_HEAD_BYTES: u64 = 64 * 1024;
+
+/// How long to wait for a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Parse a `--http` listen address.
+///
+/// Accepts a full socket address (`127.0.0.1:8080`, `[::1]:8080`,
+/// `0.0.0.0:8080`) or a bare port, which binds to `127.0.0.1`.
+pub fn parse_listen_addr(addr: &str) -> Result<SocketAddr> {
+    if let Ok(port) = addr.parse::<u16>() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port));
+    }
+    addr.parse()
+        .with_context(|| format!("invalid listen address: {addr} (expected host:port or port)"))
+}
+
+/// Run the MCP server over HTTP + SSE, listening on `addr`. Runs until the
+/// process is terminated.
+///
+/// # Errors
+///
+/// Returns an error if the address cannot be bound.
+pub fn run_http_server(config: &McpServerConfig, addr: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("failed to bind {addr}"))?;
+    if !addr.ip().is_loopback() {
+        warn!(%addr, "listening on a non-loopback address; the HTTP transport has no authentication");
+    }
+    serve(&listener, config)
+}
+
+/// Accept connections on an already-bound listener.
+pub fn serve(listener: &TcpListener, config: &McpServerConfig) -> Result<()> {
+    info!(
+        workspace = %config.workspace.display(),
+        sandboxed = config.sandboxed,
+        addr = %listener.local_addr().context("failed to read listen address")?,
+        "oa-coder MCP HTTP server starting"
+    );
+
+    let state = HttpState {
+        router: Arc::new(config.tool_router()),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        max_body_bytes: config.max_line_bytes,
+        pool: CallPool::new(config.max_concurrent_calls)?,
+        output_budget: config.max_output_chars,
+    };
+
+    // Log notifications go to every connected client.
+    let sessions = Arc::clone(&state.sessions);
+    logging::set_sink(Some(Arc::new(move |notification| {
+        let targets: Vec<Session> = sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect();
+        for session in targets {
+            let _ = session.send(&notification);
+        }
+    })));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = handle_connection(stream, &state) {
+                        debug!(error = %e, "HTTP connection ended with error");
+                    }
+                });
+            }
+            Err(e) => warn!(error = %e, "failed to accept HTTP connection"),
+        }
+    }
+
+    logging::set_sink(None);
+    Ok(())
+}
+
+/// State shared by all connection threads.
+#[derive(Clone)]
+struct HttpState {
+    router: Arc<ToolRouter>,
+    /// Live SSE sessions by session id.
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    max_body_bytes: usize,
+    /// Workers executing the tool calls of every session.
+    pool: CallPool,
+    /// See [`McpServerConfig::max_output_chars`].
+    output_budget: Option<usize>,
+}
+
+impl HttpState {
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, Session>> {
+        self.sessions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A parsed HTTP request.
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// An HTTP error status with a plain-text explanation.
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &HttpState) -> Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let request = match read_request(&stream, state.max_body_bytes) {
+        Ok(r) => r,
+        Err(e) => return respond(&stream, e.status, None, &e.message),
+    };
+    debug!(method = request.method, path = request.path, "HTTP request");
+
+    let origin = request.header("origin").map(str::to_owned);
+    if let Some(origin) = &origin {
+        if !is_loopback_origin(origin) {
+            warn!(origin, "refusing request from non-loopback origin");
+            return respond(&stream, 403, None, "forbidden origin");
+        }
+    }
+    let origin = origin.as_deref();
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/sse") => serve_sse(stream, origin, state),
+        ("POST", "/message") => {
+            let (status, body) = match handle_post(&request, state) {
+                Ok(()) => (202, "Accepted".to_owned()),
+                Err(e) => (e.status, e.message),
+            };
+            respond(&stream, status, origin, &body)
+        }
+        ("OPTIONS", "/sse" | "/message") => respond(&stream, 204, origin, ""),
+        (_, "/sse" | "/message") => respond(&stream, 405, origin, "method not allowed"),
+        _ => respond(&stream, 404, origin, "not found"),
+    }
+}
+
+/// Open an SSE stream for a new session and pump its messages until the
+/// client goes away.
+fn serve_sse(mut stream: TcpStream, origin: Option<&str>, state: &HttpState) -> Result<()> {
+    let (tx, rx) = mpsc::channel::<String>();
+    let out: Outbound = Arc::new(move |json| {
+        tx.send(json.to_owned())
+            .map_err(|_| anyhow!("SSE client disconnected"))
+    });
+    let session = Session::new(Arc::clone(&state.router), out, state.pool.clone())
+        .with_output_budget(state.output_budget);
+    let id = new_session_id();
+    state.sessions().insert(id.clone(), session.clone());
+    info!(session = id, "SSE client connected");
+
+    let result = stream_events(&mut stream, origin, &id, &rx);
+
+    state.sessions().remove(&id);
+    session.cancel_all();
+    info!(session = id, "SSE client disconnected");
+    result
+}
+
+fn stream_events(
+    stream: &mut TcpStream,
+    origin: Option<&str>,
+    id: &str,
+    rx: &mpsc::Receiver<String>,
+) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+         Connection: keep-alive\r\n{}\r\n",
+        cors_headers(origin)
+    )?;
+    write!(stream, "event: endpoint\ndata: /message?sessionId={id}\n\n")?;
+    stream.flush()?;
+
+    loop {
+        match rx.recv_timeout(KEEPALIVE_INTERVAL) {
+            // Serialized JSON never contains raw newlines, so one data line suffices.
+            Ok(json) => write!(stream, "event: message\ndata: {json}\n\n")?,
+            Err(RecvTimeoutError::Timeout) => stream.write_all(b": keepalive\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.flush()?;
+    }
+}
+
+/// Hand the JSON-RPC message in a POST body to its session.
+fn handle_post(request: &HttpRequest, state: &HttpState) -> Result<(), HttpError> {
+    let id = query_param(&request.query, "sessionId")
+        .ok_or_else(|| HttpError::new(400, "missing sessionId"))?;
+    let session = state
+        .sessions()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| HttpError::new(404, format!("unknown session: {id}")))?;
+
+    let text = std::str::from_utf8(&request.body)
+        .map_err(|_| HttpError::new(400, "request body is not valid UTF-8"))?
+        .trim();
+    if text.is_empty() {
+        return Err(HttpError::new(400, "empty request body"));
+    }
+
+    session
+        .handle_message(text)
+        .map_err(|e| HttpError::new(500, format!("failed to deliver response: {e}")))
+}
+
+/// Read one `HTTP/1.1` request (head plus `Content-Length` body).
+fn read_request(stream: &TcpStream, max_body_bytes: usize) -> Result<HttpRequest, HttpError> {
+    let mut reader = BufReader::new(stream);
+    let bad = |msg: &str| HttpError::new(400, msg);
+
+    let mut head = (&mut reader).take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    head.read_line(&mut line).map_err(|_| bad("failed to read request line"))?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(bad("malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let (method, path, query) = (method.to_owned(), path.to_owned(), query.to_owned());
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        let n = head.read_line(&mut line).map_err(|_| bad("failed to read headers"))?;
+        if n == 0 {
+            return Err(HttpError::new(431, "request head too large or incomplete"));
+        }
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            break;
+        }
+        let (name, value) = trimmed.split_once(':').ok_or_else(|| bad("malformed header"))?;
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+
+    let mut request = HttpRequest { method, path, query, headers, body: Vec::new() };
+    if let Some(len) = request.header("content-length") {
+        let len: usize = len.parse().map_err(|_| bad("invalid Content-Length"))?;
+        if len > max_body_bytes {
+            return Err(HttpError::new(
+                413,
+                format!("request body exceeds maximum size ({max_body_bytes} bytes)"),
+            ));
+        }
+        request.body.resize(len, 0);
+        reader
+            .read_exact(&mut request.body)
+            .map_err(|_| bad("failed to read request body"))?;
+    } else if request.method == "POST" {
+        return Err(HttpError::new(411, "Content-Length required"));
+    }
+
+    Ok(request)
+}
+
+/// Write a complete plain-text response and close the exchange.
+fn respond(mut stream: &TcpStream, status: u16, origin: Option<&str>, body: &str) -> Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n{}\r\n{body}",
+        reason_phrase(status),
+        body.len(),
+        cors_headers(origin)
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// CORS headers for browser clients served from a (loopback) origin.
+fn cors_headers(origin: Option<&str>) -> String {
+    origin.map_or_else(String::new, |origin| {
+        format!(
+            "Access-Control-Allow-Origin: {origin}\r\nAccess-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\nVary: Origin\r\n"
+        )
+    })
+}
+
+const fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        _ => "OK",
+    }
+}
+
+/// Whether an `Origin` header names a loopback host (any scheme and port).
+fn is_loopback_origin(origin: &str) -> bool {
+    let authority = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let authority = authority.split('/').next().unwrap_or_default();
+    let host = if authority.starts_with('[') {
+        authority.split_inclusive(']').next().unwrap_or_default()
+    } else {
+        authority.split(':').next().unwrap_or_default()
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Look up a query-string parameter (no percent-decoding; ids are hex).
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == name)
+        .map(|(_, v)| v)
+}
+
+/// Generate an unguessable session id.
+fn new_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let hasher = std::collections::hash_map::RandomState::new();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:016x}{:016x}", hasher.hash_one(n), hasher.hash_one(SystemTime::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_addr() {
+        let addr = parse_listen_addr("8080").expect("port");
+        assert_eq!(addr, "127.0.0.1:8080".parse().expect("addr"));
+        let addr = parse_listen_addr("0.0.0.0:9000").expect("addr");
+        assert_eq!(addr.port(), 9000);
+        assert!(parse_listen_addr("localhost").is_err());
+    }
+
+    #[test]
+    fn test_loopback_origin() {
+        assert!(is_loopback_origin("http://localhost:3000"));
+        assert!(is_loopback_origin("http://127.0.0.1"));
+        assert!(is_loopback_origin("http://[::1]:8080"));
+        assert!(!is_loopback_origin("https://evil.example"));
+        assert!(!is_loopback_origin("http://localhost.evil.example"));
+    }
+
+    #[test]
+    fn test_sse_round_trip() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let config = McpServerConfig {
+            workspace: dir.path().to_path_buf(),
+            ..McpServerConfig::default()
+        };
+        std::thread::spawn(move || serve(&listener, &config));
+
+        let mut sse = TcpStream::connect(addr).expect("connect");
+        sse.write_all(b"GET /sse HTTP/1.1\r\nHost: localhost\r\n\r\n").expect("write");
+        let mut events = BufReader::new(sse.try_clone().expect("clone"));
+        let endpoint = next_data(&mut events, "endpoint");
+        assert!(endpoint.starts_with("/message?sessionId="), "got: {endpoint}");
+
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        let mut post = TcpStream::connect(addr).expect("connect");
+        write!(
+            post,
+            "POST {endpoint} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .expect("write");
+        let mut status = String::new();
+        BufReader::new(post).read_line(&mut status).expect("status");
+        assert!(status.starts_with("HTTP/1.1 202"), "got: {status}");
+
+        let message: serde_json::Value =
+            serde_json::from_str(&next_data(&mut events, "message")).expect("json");
+        assert_eq!(message["id"], serde_json::json!(1));
+        assert_eq!(message["result"], serde_json::json!({}));
+    }
+
+    /// Read SSE lines until an event of `kind`, returning its data line.
+    fn next_data(events: &mut impl BufRead, kind: &str) -> String {
+        let mut line = String::new();
+        let mut current = String::new();
+        loop {
+            line.clear();
+            assert_ne!(events.read_line(&mut line).expect("read"), 0, "stream ended");
+            let line = line.trim_end();
+            if let Some(event) = line.strip_prefix("event: ") {
+                current = event.to_owned();
+            } else if let Some(data) = line.strip_prefix("data: ") {
+                if current == kind {
+                    return data.to_owned();
+                }
+            }
+        }
+    }
+}
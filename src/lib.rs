@@ -13,6 +13,19 @@
 //! - `grep` — ripgrep (`rg --json`) subprocess wrapper
 //! - `glob` — File discovery via globset patterns
 //! - `bash` — Sandboxed command execution via oa-sandbox
+//! - `tree` — ASCII directory tree with depth and entry limits
+//! - `search` — glob for files, then grep within them, in one call
+//! - `write_many` — all-or-nothing write of several files
+//! - `rename_symbol` — whole-word identifier rename across files
+//! - `stat` — file metadata (size, type, line count, mtime) without reading it
+//! - `format` — run a formatter on a file and return the diff
+//! - `copy` — duplicate a file (or directory tree) within the workspace
+//! - `mkdir` — create a directory (`parents` for `mkdir -p`)
+//! - `git_diff` — uncommitted or staged changes (`git diff`)
+//! - `replace_in_files` — one edit applied to every file matching a glob
+//! - `touch` — create an empty file or bump its mtime
+//! - `symbols` — top-level definitions of a file, found by per-language regexes
+//! - `blame` — last commit, author and date per line (`git blame`)
 //!
 //! # Architecture
 //!
@@ -22,12 +35,31 @@
 //!                                              oa-sandbox
 //! stdout (JSON-RPC) ←────────────────────────────────┘
 //! ```
+//!
+//! Workspace files are also exposed as MCP resources (see [`resources`]), and
+//! a few built-in coding prompt templates as MCP prompts (see [`prompts`]),
+//! and completes path-valued arguments via `completion/complete` (see
+//! [`completion`]).
+//! With the `watch` feature, file changes are pushed to the client as
+//! resource notifications (see `watch`).
+//!
+//! With the `http` feature, the same session core is also reachable over
+//! HTTP POST + Server-Sent Events (see [`http`]).
 
+pub mod capabilities;
+pub mod completion;
 pub mod edit;
 pub mod error;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod logging;
+pub mod prompts;
+pub mod resources;
 pub mod server;
 pub mod tools;
 pub mod util;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 pub use error::{CoderError, CoderResult};
-pub use server::run_mcp_server;
+pub use server::{run_mcp_server, run_mcp_server_with};
@@ -0,0 +1,197 @@
+//! MCP logging — forwards `tracing` events to the client.
+//!
+//! Implements the MCP logging capability: the client picks a minimum level
+//! via `logging/setLevel`, and [`McpLogLayer`] forwards every `tracing`
+//! event at or above it as a `notifications/message`. Forwarding is off
+//! until the client sets a level. stderr logging (configured by the binary)
+//! is unaffected. The fields of the spans an event occurs in (such as the
+//! request `id` and `tool` of a tool call) are merged into its data.
+//!
+//! The layer is installed once per process (tracing subscribers are global),
+//! so its level and notification sink are process-global as well; the MCP
+//! server connects the sink when it starts.
+
+use std::cell::Cell;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use serde::Deserialize;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::server::{JsonRpcNotification, NotificationSink};
+
+/// MCP log levels (RFC 5424 severities), lowest to highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug = 1,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl LogLevel {
+    const fn from_tracing(level: Level) -> Self {
+        match level {
+            Level::TRACE | Level::DEBUG => Self::Debug,
+            Level::INFO => Self::Info,
+            Level::WARN => Self::Warning,
+            Level::ERROR => Self::Error,
+        }
+    }
+
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Notice => "notice",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+            Self::Alert => "alert",
+            Self::Emergency => "emergency",
+        }
+    }
+}
+
+/// Minimum level to forward; 0 = forwarding disabled.
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Where forwarded log notifications go (set while a server is running).
+static SINK: Mutex<Option<NotificationSink>> = Mutex::new(None);
+
+thread_local! {
+    /// Set while this thread is forwarding an event, so log records emitted
+    /// while sending (e.g. "sending message") are not forwarded recursively.
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Set the minimum level forwarded to the client (`logging/setLevel`).
+pub fn set_level(level: LogLevel) {
+    MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Connect (or disconnect, with `None`) the notification sink.
+///
+/// Disconnecting also turns forwarding off until the next `logging/setLevel`.
+pub(crate) fn set_sink(sink: Option<NotificationSink>) {
+    if sink.is_none() {
+        MIN_LEVEL.store(0, Ordering::Relaxed);
+    }
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = sink;
+}
+
+/// Create the `tracing` layer that forwards events as MCP log notifications.
+#[must_use]
+pub const fn layer() -> McpLogLayer {
+    McpLogLayer
+}
+
+/// `tracing` layer forwarding events as MCP `notifications/message`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct McpLogLayer;
+
+/// A span's fields, stored in its extensions for [`McpLogLayer::on_event`].
+struct SpanFields(serde_json::Map<String, serde_json::Value>);
+
+impl<S> Layer<S> for McpLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = JsonVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanFields(visitor.fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            let mut visitor = JsonVisitor {
+                fields: std::mem::take(fields),
+            };
+            values.record(&mut visitor);
+            *fields = visitor.fields;
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let min = MIN_LEVEL.load(Ordering::Relaxed);
+        let level = LogLevel::from_tracing(*event.metadata().level());
+        if min == 0 || (level as u8) < min || FORWARDING.with(Cell::get) {
+            return;
+        }
+
+        let Some(sink) = SINK.lock().unwrap_or_else(PoisonError::into_inner).clone() else {
+            return;
+        };
+
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+        // Innermost span first; the event's own fields win over all of them.
+        for span in ctx.event_scope(event).into_iter().flatten() {
+            if let Some(SpanFields(fields)) = span.extensions().get::<SpanFields>() {
+                for (name, value) in fields {
+                    visitor.fields.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        let notification = JsonRpcNotification::new(
+            "notifications/message",
+            serde_json::json!({
+                "level": level.as_str(),
+                "logger": event.metadata().target(),
+                "data": serde_json::Value::Object(visitor.fields),
+            }),
+        );
+
+        FORWARDING.with(|f| f.set(true));
+        sink(notification);
+        FORWARDING.with(|f| f.set(false));
+    }
+}
+
+/// Collects event fields (including `message`) into a JSON object.
+#[derive(Default)]
+struct JsonVisitor {
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_owned(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        let mut text = String::new();
+        let _ = write!(text, "{value:?}");
+        self.fields.insert(field.name().to_owned(), text.into());
+    }
+}
@@ -0,0 +1,185 @@
+//! MCP prompts — built-in parameterized coding prompt templates.
+//!
+//! Implements `prompts/list` and `prompts/get`. Prompts only render text;
+//! they reference files by path and leave reading them to the model's tool
+//! calls.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+/// A prompt argument.
+#[derive(Debug, Serialize)]
+pub struct PromptArgument {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+}
+
+/// A prompt as advertised by `prompts/list`.
+#[derive(Debug, Serialize)]
+pub struct Prompt {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub arguments: Vec<PromptArgument>,
+}
+
+/// One message of a rendered prompt.
+#[derive(Debug, Serialize)]
+pub struct PromptMessage {
+    pub role: &'static str,
+    pub content: PromptContent,
+}
+
+/// Text content of a prompt message.
+#[derive(Debug, Serialize)]
+pub struct PromptContent {
+    #[serde(rename = "type")]
+    pub content_type: &'static str,
+    pub text: String,
+}
+
+/// Result of `prompts/get`.
+#[derive(Debug, Serialize)]
+pub struct GetPromptResult {
+    pub description: &'static str,
+    pub messages: Vec<PromptMessage>,
+}
+
+const fn arg(name: &'static str, description: &'static str, required: bool) -> PromptArgument {
+    PromptArgument {
+        name,
+        description,
+        required,
+    }
+}
+
+/// All built-in prompts.
+#[must_use]
+pub fn list() -> Vec<Prompt> {
+    vec![
+        Prompt {
+            name: "explain_file",
+            description: "Explain what a file does and how it fits into the project",
+            arguments: vec![
+                arg("path", "File to explain (relative to the workspace)", true),
+                arg("focus", "Aspect to concentrate on (e.g. error handling)", false),
+            ],
+        },
+        Prompt {
+            name: "write_tests",
+            description: "Write tests for the code in a file",
+            arguments: vec![
+                arg("path", "File whose code should be tested", true),
+                arg("framework", "Test framework or style to follow", false),
+            ],
+        },
+        Prompt {
+            name: "refactor_selection",
+            description: "Refactor a selected piece of code without changing its behavior",
+            arguments: vec![
+                arg("path", "File containing the selection", true),
+                arg("selection", "The code to refactor", true),
+                arg("goal", "What the refactoring should achieve", false),
+            ],
+        },
+    ]
+}
+
+/// Render the prompt `name` with `arguments`.
+///
+/// # Errors
+///
+/// Returns an error if the prompt is unknown or a required argument is missing.
+pub fn get<S: BuildHasher>(
+    name: &str,
+    arguments: &HashMap<String, String, S>,
+) -> Result<GetPromptResult> {
+    let Some(prompt) = list().into_iter().find(|p| p.name == name) else {
+        bail!("unknown prompt: {name}");
+    };
+    for argument in prompt.arguments.iter().filter(|a| a.required) {
+        if arguments.get(argument.name).is_none_or(|v| v.trim().is_empty()) {
+            bail!("missing required argument for {name}: {}", argument.name);
+        }
+    }
+    let get = |key: &str| arguments.get(key).map(String::as_str).unwrap_or_default();
+
+    // Each prompt's body, plus the optional argument appended after it.
+    let (mut text, (optional, lead_in)) = match name {
+        "explain_file" => (
+            format!(
+                "Read `{}` and explain what it does: its purpose, main types and functions, \
+                 and how it fits into the rest of the project.",
+                get("path")
+            ),
+            ("focus", "Focus in particular on: "),
+        ),
+        "write_tests" => (
+            format!(
+                "Read `{}` and write tests for its public behavior, covering edge cases and \
+                 error paths. Follow the existing test layout and conventions of the project.",
+                get("path")
+            ),
+            ("framework", "Use this test framework or style: "),
+        ),
+        _ => (
+            format!(
+                "Refactor the following code from `{}` without changing its behavior:\n\n```\n{}\n```",
+                get("path"),
+                get("selection")
+            ),
+            ("goal", "The goal of the refactoring: "),
+        ),
+    };
+    if !get(optional).is_empty() {
+        text.push_str("\n\n");
+        text.push_str(lead_in);
+        text.push_str(get(optional));
+    }
+
+    Ok(GetPromptResult {
+        description: prompt.description,
+        messages: vec![PromptMessage {
+            role: "user",
+            content: PromptContent {
+                content_type: "text",
+                text,
+            },
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| ((*k).to_owned(), (*v).to_owned())).collect()
+    }
+
+    #[test]
+    fn test_every_prompt_renders() {
+        let all = args(&[("path", "src/lib.rs"), ("selection", "let x = 1;")]);
+        for prompt in list() {
+            let result = get(prompt.name, &all).expect("render");
+            assert!(result.messages[0].content.text.contains("src/lib.rs"));
+        }
+    }
+
+    #[test]
+    fn test_optional_argument_appended() {
+        let result = get("write_tests", &args(&[("path", "a.rs"), ("framework", "proptest")]))
+            .expect("render");
+        assert!(result.messages[0].content.text.ends_with("style: proptest"));
+    }
+
+    #[test]
+    fn test_missing_required_argument() {
+        let err = get("refactor_selection", &args(&[("path", "a.rs")])).expect_err("missing");
+        assert!(err.to_string().contains("selection"));
+        assert!(get("nope", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! MCP resources — workspace files exposed as `file://` resources.
+//!
+//! `resources/list` pages through the files under the primary workspace root
+//! (gitignore-aware, via the glob walker); `resources/read` returns one
+//! file's contents, as text or as a base64 blob for binary files. Reads go
+//! through [`validate_path`], so the workspace boundary is enforced exactly
+//! as for the file tools.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use base64::Engine as _;
+use serde::Serialize;
+
+use crate::error::CoderError;
+use crate::tools::{Workspace, validate_path};
+
+/// Resources returned per `resources/list` page.
+const PAGE_SIZE: usize = 100;
+
+/// Maximum number of files listed in total.
+const MAX_RESOURCES: usize = 10_000;
+
+/// Largest file `resources/read` will return.
+const MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Max bytes checked for NUL when deciding between text and blob.
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// One entry in `resources/list`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Resource {
+    pub uri: String,
+    /// Path relative to the workspace root.
+    pub name: String,
+    pub mime_type: String,
+}
+
+/// Result of `resources/list`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourcesListResult {
+    pub resources: Vec<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Contents of one resource, as text or a base64 blob.
+#[derive(Debug, Serialize)]
+#[serde(untagged, rename_all_fields = "camelCase")]
+pub enum ResourceContents {
+    Text {
+        uri: String,
+        mime_type: String,
+        text: String,
+    },
+    Blob {
+        uri: String,
+        mime_type: String,
+        blob: String,
+    },
+}
+
+/// List one page of workspace files. `cursor` is the `nextCursor` of the
+/// previous page.
+///
+/// # Errors
+///
+/// Returns an error if the cursor is invalid or the workspace cannot be walked.
+pub fn list(workspace: &Workspace, cursor: Option<&str>) -> Result<ResourcesListResult> {
+    let offset = match cursor {
+        Some(c) => c.parse::<usize>().with_context(|| format!("invalid cursor: {c}"))?,
+        None => 0,
+    };
+
+    let root = workspace.root();
+    let mut files = crate::tools::glob::find_files(
+        workspace,
+        root,
+        &["**".to_owned()],
+        &[],
+        false,
+        true,
+        MAX_RESOURCES,
+    )?;
+    files.sort();
+
+    let resources = files
+        .iter()
+        .skip(offset)
+        .take(PAGE_SIZE)
+        .map(|name| {
+            let path = root.join(name);
+            Resource {
+                uri: file_uri(&path),
+                name: name.clone(),
+                mime_type: mime_type(&path).unwrap_or("text/plain").to_owned(),
+            }
+        })
+        .collect();
+    let next = offset.saturating_add(PAGE_SIZE);
+    Ok(ResourcesListResult {
+        resources,
+        next_cursor: (next < files.len()).then(|| next.to_string()),
+    })
+}
+
+/// Read the resource at a `file://` URI.
+///
+/// # Errors
+///
+/// Returns [`CoderError::FileNotFound`] if the file does not exist, or an
+/// error if the URI is invalid, escapes the workspace, or is too large.
+pub fn read(workspace: &Workspace, uri: &str) -> Result<ResourceContents> {
+    let Some(encoded) = uri.strip_prefix("file://") else {
+        bail!("unsupported resource URI (expected file://): {uri}");
+    };
+    let path = percent_decode(encoded).with_context(|| format!("invalid resource URI: {uri}"))?;
+    let path = validate_path(workspace, &path)?;
+
+    let meta = match std::fs::metadata(&path) {
+        Ok(meta) if meta.is_file() => meta,
+        Ok(_) => bail!("not a file: {}", path.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CoderError::FileNotFound { path }.into());
+        }
+        Err(source) => return Err(CoderError::Io { path, source }.into()),
+    };
+    if meta.len() > MAX_READ_BYTES {
+        bail!(
+            "resource too large: {} ({} bytes, limit {MAX_READ_BYTES})",
+            path.display(),
+            meta.len()
+        );
+    }
+
+    let bytes = std::fs::read(&path).map_err(|source| CoderError::Io {
+        path: path.clone(),
+        source,
+    })?;
+    let uri = file_uri(&path);
+    let binary = bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0);
+    match String::from_utf8(bytes) {
+        Ok(text) if !binary => Ok(ResourceContents::Text {
+            uri,
+            mime_type: mime_type(&path).unwrap_or("text/plain").to_owned(),
+            text,
+        }),
+        result => {
+            let bytes =
+                result.map_or_else(std::string::FromUtf8Error::into_bytes, String::into_bytes);
+            Ok(ResourceContents::Blob {
+                uri,
+                mime_type: mime_type(&path).unwrap_or("application/octet-stream").to_owned(),
+                blob: base64::engine::general_purpose::STANDARD.encode(bytes),
+            })
+        }
+    }
+}
+
+/// `file://` URI for an absolute path, percent-encoding everything but
+/// unreserved characters and `/`.
+pub(crate) fn file_uri(path: &Path) -> String {
+    let mut uri = "file://".to_owned();
+    for &b in path.to_string_lossy().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            uri.push(char::from(b));
+        } else {
+            let _ = write!(uri, "%{b:02X}");
+        }
+    }
+    uri
+}
+
+/// Decode `%XX` escapes in a URI path.
+fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3).context("truncated percent escape")?;
+            out.push(u8::from_str_radix(hex, 16).context("invalid percent escape")?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).context("URI path is not valid UTF-8")
+}
+
+/// MIME type from the file extension, if known.
+pub(crate) fn mime_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => "text/x-rust",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "js" | "mjs" | "cjs" => "text/javascript",
+        "ts" | "tsx" => "text/typescript",
+        "py" => "text/x-python",
+        "go" => "text/x-go",
+        "c" | "h" => "text/x-c",
+        "cpp" | "cc" | "hpp" => "text/x-c++",
+        "java" => "text/x-java",
+        "sh" => "text/x-shellscript",
+        "txt" | "log" => "text/plain",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "wasm" => "application/wasm",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_uri_round_trip() {
+        let path = Path::new("/tmp/a dir/ünï%code.rs");
+        let uri = file_uri(path);
+        assert_eq!(uri, "file:///tmp/a%20dir/%C3%BCn%C3%AF%25code.rs");
+        let decoded = percent_decode(uri.strip_prefix("file://").expect("prefix")).expect("decode");
+        assert_eq!(decoded, path.to_string_lossy());
+    }
+
+    #[test]
+    fn test_percent_decode_rejects_bad_escapes() {
+        assert!(percent_decode("/a%2").is_err());
+        assert!(percent_decode("/a%zz").is_err());
+    }
+}
@@ -10,18 +10,69 @@
 //! 3. Client sends `tools/list` → server returns tool definitions
 //! 4. Client sends `tools/call` → server executes tool and returns result
 //! 5. Client closes stdin → server exits
+//!
+//! JSON-RPC batches (an array of requests on one line) are answered with an
+//! array of the non-notification responses, in request order.
+//!
+//! `tools/call` requests run on a fixed pool of worker threads so the read
+//! loop stays responsive: tools may emit `notifications/progress` (e.g.
+//! streamed `bash` output) if the client passed `_meta.progressToken`, and a
+//! `notifications/cancelled` for the call's id aborts it with an error result.
+//! The pool has [`McpServerConfig::max_concurrent_calls`] workers (one by
+//! default, so calls run in arrival order); further calls queue until a
+//! worker is free. With more workers, responses may arrive out of order.
+//! Over HTTP the pool is shared by all sessions.
+//!
+//! With [`McpServerConfig::max_output_chars`], every tool result is cut to
+//! that many characters in total (text and base64 payloads alike), marker
+//! included, so no single response outgrows the client's context. JSON and
+//! binary items are dropped whole rather than cut.
+//!
+//! After `logging/setLevel`, `tracing` events at or above the requested level
+//! are also sent as `notifications/message` (see [`crate::logging`]).
+//!
+//! With the `watch` feature and [`McpServerConfig::watch`], the server
+//! advertises `resources.subscribe`: after `resources/subscribe` with a
+//! resource's URI, changes to that file are pushed as
+//! `notifications/resources/updated` until `resources/unsubscribe` (see
+//! `crate::watch`).
+//!
+//! `oa-coder/setWorkspace` (`{"path": ...}`) points the session at another
+//! directory inside the roots it started with, without a restart. Each
+//! request works on the router current when it arrived, so a tool call
+//! already in flight finishes against the old root while later calls use
+//! the new one. Only the session that sent it is affected (each HTTP client
+//! has its own). With [`McpServerConfig::watch`] it is refused, as the file
+//! watcher stays on the root the server started with.
 
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
 use std::io::{BufRead, Write};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, RwLock, mpsc};
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
-/// Maximum size of a single JSON-RPC line (10 MiB), matching oa-sandbox worker protocol.
-const MAX_LINE_BYTES: usize = 10 * 1024 * 1024;
+use crate::capabilities::{self, Probes};
+use crate::completion;
+use crate::error::CoderError;
+use crate::logging::{self, LogLevel};
+use crate::prompts;
+use crate::resources;
+use crate::tools::{ToolFilter, ToolRouter, Workspace};
+
+/// Default maximum size of a single JSON-RPC line (10 MiB), matching oa-sandbox worker protocol.
+pub const DEFAULT_MAX_LINE_BYTES: usize = 10 * 1024 * 1024;
 
-use crate::tools::ToolRouter;
+/// Default number of `tools/call` worker threads: calls run one at a time.
+pub const DEFAULT_MAX_CONCURRENT_CALLS: NonZeroUsize = NonZeroUsize::MIN;
+
+/// Tool definitions returned per `tools/list` page.
+const TOOLS_PAGE_SIZE: usize = 100;
 
 // ---------------------------------------------------------------------------
 // JSON-RPC 2.0 types
@@ -49,6 +100,26 @@ pub struct JsonRpcResponse {
     pub error: Option<JsonRpcError>,
 }
 
+/// JSON-RPC 2.0 notification (server → client, no id, no response expected).
+#[derive(Debug, Serialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: serde_json::Value,
+}
+
+impl JsonRpcNotification {
+    /// Create a notification for `method` with the given params.
+    #[must_use]
+    pub fn new(method: &str, params: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_owned(),
+            method: method.to_owned(),
+            params,
+        }
+    }
+}
+
 /// JSON-RPC 2.0 error object.
 #[derive(Debug, Serialize)]
 pub struct JsonRpcError {
@@ -74,6 +145,24 @@ struct ServerInfo {
 #[derive(Debug, Serialize)]
 struct ServerCapabilities {
     tools: ToolsCapability,
+    resources: ResourcesCapability,
+    prompts: PromptsCapability,
+    logging: LoggingCapability,
+    completions: CompletionsCapability,
+}
+
+/// Empty object: the server accepts `logging/setLevel`.
+#[derive(Debug, Serialize)]
+struct LoggingCapability {}
+
+/// Empty object: the server answers `completion/complete`.
+#[derive(Debug, Serialize)]
+struct CompletionsCapability {}
+
+/// Parameters for logging/setLevel.
+#[derive(Debug, Deserialize)]
+struct SetLevelParams {
+    level: LogLevel,
 }
 
 #[derive(Debug, Serialize)]
@@ -82,6 +171,81 @@ struct ToolsCapability {
     list_changed: bool,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourcesCapability {
+    subscribe: bool,
+    list_changed: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptsCapability {
+    list_changed: bool,
+}
+
+/// Parameters for prompts/get.
+#[derive(Debug, Deserialize)]
+struct GetPromptParams {
+    name: String,
+    #[serde(default)]
+    arguments: HashMap<String, String>,
+}
+
+/// Parameters for the paginated list methods (tools/list, resources/list).
+#[derive(Debug, Default, Deserialize)]
+struct ListParams {
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+/// Parameters for completion/complete. The `ref` (prompt or resource) is
+/// not needed: what gets completed depends only on the argument name.
+#[derive(Debug, Deserialize)]
+struct CompleteParams {
+    argument: CompleteArgument,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteArgument {
+    name: String,
+    value: String,
+}
+
+/// Parameters for oa-coder/setWorkspace.
+#[derive(Debug, Deserialize)]
+struct SetWorkspaceParams {
+    /// New primary root; relative paths resolve against the current one.
+    path: String,
+}
+
+/// Result of oa-coder/setWorkspace.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SetWorkspaceResult {
+    /// The new root, canonicalized.
+    workspace: String,
+    previous_workspace: String,
+}
+
+/// Parameters for resources/read, resources/subscribe and
+/// resources/unsubscribe.
+#[derive(Debug, Deserialize)]
+struct ResourcesReadParams {
+    uri: String,
+}
+
+/// Protocol versions this server speaks, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Parameters of the `initialize` request (only the fields we use).
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    #[serde(default)]
+    protocol_version: Option<String>,
+}
+
 /// MCP initialize result.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -91,6 +255,24 @@ struct InitializeResult {
     server_info: ServerInfo,
 }
 
+/// Result of the `oa-coder/health` self-test.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HealthResult {
+    server_version: &'static str,
+    /// Newest protocol version the server speaks.
+    protocol_version: &'static str,
+    /// Whether `rg` is on PATH (grep/search otherwise use the fallback).
+    ripgrep: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ripgrep_path: Option<String>,
+    /// Whether the `sandbox` feature is compiled in.
+    sandbox: bool,
+    workspace: String,
+    /// Whether a temp file could be created in the workspace root.
+    workspace_writable: bool,
+}
+
 /// MCP tool definition for tools/list.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -102,8 +284,11 @@ pub struct ToolDefinition {
 
 /// MCP tools/list result.
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct ToolsListResult {
     tools: Vec<ToolDefinition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_cursor: Option<String>,
 }
 
 /// MCP tools/call params.
@@ -112,15 +297,94 @@ struct ToolCallParams {
     name: String,
     #[serde(default)]
     arguments: serde_json::Value,
+    #[serde(rename = "_meta", default)]
+    meta: Option<RequestMeta>,
 }
 
-/// MCP content item in tools/call response.
-#[derive(Debug, Serialize)]
+/// MCP request `_meta` field.
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct RequestMeta {
+    /// Opaque token the client uses to correlate `notifications/progress`.
+    #[serde(default)]
+    progress_token: Option<serde_json::Value>,
+}
+
+/// MCP content item in tools/call response.
+///
+/// `text` items carry `text`; `image` items carry base64 `data` and a
+/// `mimeType`, and `resource` items an embedded `resource`. Fields other
+/// than `type` are serialized only when present (a non-text item's empty
+/// `text` is omitted).
+#[derive(Debug)]
 pub struct ContentItem {
-    #[serde(rename = "type")]
     pub content_type: String,
     pub text: String,
+    /// Base64-encoded payload of non-text items.
+    pub data: Option<String>,
+    pub mime_type: Option<String>,
+    /// Embedded resource of a `resource` item.
+    pub resource: Option<crate::resources::ResourceContents>,
+}
+
+impl ContentItem {
+    /// A `text` content item.
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content_type: "text".to_owned(),
+            text: text.into(),
+            data: None,
+            mime_type: None,
+            resource: None,
+        }
+    }
+
+    /// An `image` content item from base64 `data`.
+    #[must_use]
+    pub fn image(data: String, mime_type: &str) -> Self {
+        Self {
+            content_type: "image".to_owned(),
+            text: String::new(),
+            data: Some(data),
+            mime_type: Some(mime_type.to_owned()),
+            resource: None,
+        }
+    }
+
+    /// A `resource` content item embedding `resource`.
+    #[must_use]
+    pub fn resource(resource: crate::resources::ResourceContents) -> Self {
+        Self {
+            content_type: "resource".to_owned(),
+            text: String::new(),
+            data: None,
+            mime_type: None,
+            resource: Some(resource),
+        }
+    }
+}
+
+impl Serialize for ContentItem {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap as _;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", &self.content_type)?;
+        if self.content_type == "text" || !self.text.is_empty() {
+            map.serialize_entry("text", &self.text)?;
+        }
+        if let Some(data) = &self.data {
+            map.serialize_entry("data", data)?;
+        }
+        if let Some(mime_type) = &self.mime_type {
+            map.serialize_entry("mimeType", mime_type)?;
+        }
+        if let Some(resource) = &self.resource {
+            map.serialize_entry("resource", resource)?;
+        }
+        map.end()
+    }
 }
 
 /// MCP tools/call result.
@@ -132,17 +396,222 @@ pub struct ToolCallResult {
     pub is_error: bool,
 }
 
+impl ToolCallResult {
+    /// A successful result with a single text item.
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![ContentItem::text(text)],
+            is_error: false,
+        }
+    }
+
+    /// An `isError` result with a single text item (conventionally starting
+    /// with `Error: `).
+    #[must_use]
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![ContentItem::text(text)],
+            is_error: true,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Server-initiated notifications
+// ---------------------------------------------------------------------------
+
+/// Callback that delivers a notification to the connected client.
+pub type NotificationSink = Arc<dyn Fn(JsonRpcNotification) + Send + Sync>;
+
+/// Progress reporter handed to long-running tools.
+///
+/// Emits MCP `notifications/progress` carrying incremental output in
+/// `message`. Reporting is a no-op unless the client supplied a
+/// `_meta.progressToken` with its `tools/call` request.
+pub struct Progress {
+    token: Option<serde_json::Value>,
+    sink: Option<NotificationSink>,
+    value: u64,
+}
+
+impl Progress {
+    /// A reporter that discards all progress.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            token: None,
+            sink: None,
+            value: 0,
+        }
+    }
+
+    /// A reporter that sends progress for `token` through `sink`.
+    #[must_use]
+    pub const fn new(token: serde_json::Value, sink: NotificationSink) -> Self {
+        Self {
+            token: Some(token),
+            sink: Some(sink),
+            value: 0,
+        }
+    }
+
+    /// Whether reports will actually be delivered.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.token.is_some() && self.sink.is_some()
+    }
+
+    /// Report `amount` units of additional progress with a message.
+    pub fn report(&mut self, amount: u64, message: &str) {
+        let (Some(token), Some(sink)) = (&self.token, &self.sink) else {
+            return;
+        };
+        self.value = self.value.saturating_add(amount.max(1));
+        sink(JsonRpcNotification::new(
+            "notifications/progress",
+            serde_json::json!({
+                "progressToken": token,
+                "progress": self.value,
+                "message": message,
+            }),
+        ));
+    }
+}
+
+/// Cooperative cancellation flag shared between the server and a tool call.
+///
+/// Set when the client sends `notifications/cancelled` for the call's
+/// request id; tools poll [`is_cancelled`](Self::is_cancelled) and abort
+/// (killing any child process) as soon as they notice.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token that is not yet cancelled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Per-call context handed to tools: progress reporting, cancellation and
+/// the JSON-RPC request id (for log correlation).
+pub struct ToolContext {
+    pub progress: Progress,
+    pub cancel: CancelToken,
+    /// JSON text of the request id, if the call came from a request.
+    pub request_id: Option<String>,
+}
+
+impl ToolContext {
+    /// A context with no progress reporting that is never cancelled.
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            progress: Progress::none(),
+            cancel: CancelToken::new(),
+            request_id: None,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // MCP Server configuration
 // ---------------------------------------------------------------------------
 
 /// Configuration for the MCP server.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Clone)]
 pub struct McpServerConfig {
     /// Working directory for file operations.
     pub workspace: PathBuf,
     /// Whether to enable sandboxed execution for bash tool.
     pub sandboxed: bool,
+    /// Further directories file tools may access by absolute path (e.g. a
+    /// read-only dependency checkout). Relative paths still resolve against
+    /// `workspace`.
+    pub additional_roots: Vec<PathBuf>,
+    /// Whether glob/grep follow symlinks whose targets stay inside the
+    /// workspace roots (default: symlinks are skipped).
+    pub follow_symlinks: bool,
+    /// Whether paths are checked against the workspace roots ignoring case;
+    /// `None` detects it from the filesystem holding `workspace`.
+    pub case_insensitive: Option<bool>,
+    /// Names glob/grep skip while walking, on top of
+    /// [`DEFAULT_SKIP_NAMES`](crate::tools::DEFAULT_SKIP_NAMES).
+    pub skip_names: Vec<String>,
+    /// Names removed from the skip list (applied after `skip_names`).
+    pub unskip_names: Vec<String>,
+    /// Fail grep/search when ripgrep is missing instead of silently using
+    /// the slower built-in search (whose results can differ).
+    pub require_ripgrep: bool,
+    /// Longest a single tool call may run before it is abandoned with an
+    /// error result (see [`ToolRouter::with_call_timeout`]). No limit when
+    /// `None`.
+    pub tool_timeout: Option<std::time::Duration>,
+    /// Timeout of `bash` calls that give none.
+    pub bash_timeout: std::time::Duration,
+    /// Ceiling on the timeout a `bash` call may ask for; larger requests are
+    /// clamped to it.
+    pub bash_max_timeout: std::time::Duration,
+    /// Which tools are exposed via `tools/list` and `tools/call`.
+    pub tool_filter: ToolFilter,
+    /// Maximum size of a single incoming JSON-RPC line, in bytes.
+    pub max_line_bytes: usize,
+    /// Largest file `read` and `edit` load whole, in bytes.
+    pub max_file_bytes: u64,
+    /// Newline-at-end-of-file policy of `write`, `write_many` and `edit`.
+    pub eof_newline: crate::util::eof_newline::EofNewline,
+    /// Mask secrets in `bash` output (and the tools built on it); off when
+    /// `None`. See [`Redactor`](crate::util::redact::Redactor).
+    pub redactor: Option<crate::util::redact::Redactor>,
+    /// Watch the workspace and notify the client when files change
+    /// (requires the `watch` feature).
+    pub watch: bool,
+    /// Worker threads executing `tools/call` requests, i.e. the most calls
+    /// running at once. Further calls queue in arrival order and do not
+    /// count against [`tool_timeout`](Self::tool_timeout) until they start.
+    pub max_concurrent_calls: NonZeroUsize,
+    /// Most characters a tool result may return across all its content
+    /// items, counting text and base64 payloads; longer results are
+    /// truncated, marker included. No limit when `None`.
+    pub max_output_chars: Option<usize>,
+}
+
+impl McpServerConfig {
+    /// Build the tool router for this configuration.
+    pub(crate) fn tool_router(&self) -> ToolRouter {
+        let workspace = Workspace::with_roots(
+            self.workspace.clone(),
+            self.additional_roots.iter().cloned(),
+        )
+        .with_follow_symlinks(self.follow_symlinks)
+        .with_max_file_bytes(self.max_file_bytes)
+        .with_case_insensitive(self.case_insensitive.unwrap_or_else(|| {
+            crate::util::case_fold::is_case_insensitive(&self.workspace)
+        }))
+        .with_skip_names(self.skip_names.iter().cloned())
+        .without_skip_names(&self.unskip_names)
+        .with_eof_newline(self.eof_newline);
+        ToolRouter::with_workspace(workspace, self.sandboxed)
+            .with_filter(self.tool_filter.clone())
+            .with_require_ripgrep(self.require_ripgrep)
+            .with_call_timeout(self.tool_timeout)
+            .with_bash_timeouts(self.bash_timeout, self.bash_max_timeout)
+            .with_redactor(self.redactor.clone())
+    }
 }
 
 impl Default for McpServerConfig {
@@ -150,6 +619,23 @@ impl Default for McpServerConfig {
         Self {
             workspace: PathBuf::from("."),
             sandboxed: false,
+            additional_roots: Vec::new(),
+            follow_symlinks: false,
+            case_insensitive: None,
+            skip_names: Vec::new(),
+            unskip_names: Vec::new(),
+            require_ripgrep: false,
+            tool_timeout: None,
+            bash_timeout: crate::tools::bash::DEFAULT_TIMEOUT,
+            bash_max_timeout: crate::tools::bash::DEFAULT_MAX_TIMEOUT,
+            tool_filter: ToolFilter::default(),
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            max_file_bytes: crate::tools::DEFAULT_MAX_FILE_BYTES,
+            eof_newline: crate::util::eof_newline::EofNewline::Preserve,
+            redactor: None,
+            watch: false,
+            max_concurrent_calls: DEFAULT_MAX_CONCURRENT_CALLS,
+            max_output_chars: None,
         }
     }
 }
@@ -167,23 +653,80 @@ impl Default for McpServerConfig {
 /// # Errors
 ///
 /// Returns an error if stdin/stdout I/O fails fatally.
-pub fn run_mcp_server(config: McpServerConfig) -> Result<()> {
+pub fn run_mcp_server(config: &McpServerConfig) -> Result<()> {
+    run_mcp_server_with(config, |_| {})
+}
+
+/// Like [`run_mcp_server`], but first hands `on_start` a sink for pushing
+/// server-initiated notifications to the client, e.g. from a background
+/// thread of the embedding application.
+///
+/// # Errors
+///
+/// Returns an error if stdin/stdout I/O fails fatally, or if
+/// [`McpServerConfig::watch`] is set but the watcher cannot be started.
+pub fn run_mcp_server_with(
+    config: &McpServerConfig,
+    on_start: impl FnOnce(NotificationSink),
+) -> Result<()> {
     info!(
         workspace = %config.workspace.display(),
         sandboxed = config.sandboxed,
         "oa-coder MCP server starting"
     );
 
-    let router = ToolRouter::new(config.workspace.clone(), config.sandboxed);
-    let stdin = std::io::stdin();
-    let mut reader = std::io::BufReader::new(stdin.lock());
-    let mut stdout = std::io::stdout().lock();
+    let stdout = Mutex::new(std::io::stdout());
+    let out: Outbound = Arc::new(move |json| {
+        write_line(&mut *stdout.lock().unwrap_or_else(PoisonError::into_inner), json)
+    });
+    let router = Arc::new(config.tool_router());
+    let pool = CallPool::new(config.max_concurrent_calls)?;
+    let session = Session::new(Arc::clone(&router), out, pool)
+        .with_resource_updates(config.watch)
+        .with_output_budget(config.max_output_chars);
+    logging::set_sink(Some(session.notifier()));
+
+    #[cfg(feature = "watch")]
+    let _watcher = if config.watch {
+        Some(crate::watch::spawn(router.workspace(), session.notifier())?)
+    } else {
+        None
+    };
+    #[cfg(not(feature = "watch"))]
+    if config.watch {
+        anyhow::bail!("watching requires oa-coder to be built with the `watch` feature");
+    }
+
+    on_start(session.notifier());
+    serve_stdin(&session, config.max_line_bytes)?;
+
+    // Let in-flight tool calls finish and deliver their responses.
+    session.finish();
+
+    logging::set_sink(None);
+    info!("oa-coder MCP server stopped");
+    Ok(())
+}
+
+/// Handle requests read line by line from stdin until it is closed.
+fn serve_stdin(session: &Session, max_line_bytes: usize) -> Result<()> {
+    let mut reader = std::io::BufReader::new(std::io::stdin());
     let mut line_buf = String::new();
 
     loop {
         line_buf.clear();
-        let bytes_read = read_line_limited(&mut reader, &mut line_buf, MAX_LINE_BYTES)
-            .context("failed to read from stdin")?;
+        let bytes_read = match read_line_limited(&mut reader, &mut line_buf, max_line_bytes) {
+            Ok(n) => n,
+            Err(e) if e.is::<LineTooLong>() => {
+                // The oversized line was discarded; answer it instead of dying.
+                let id = recover_request_id(&line_buf);
+                warn!(error = %e, id = ?id, "oversized request rejected");
+                let resp = error_response(id, -32600, &format!("invalid request: {e}"));
+                session.send(&resp)?;
+                continue;
+            }
+            Err(e) => return Err(e.context("failed to read from stdin")),
+        };
 
         // EOF — client closed stdin, clean exit.
         if bytes_read == 0 {
@@ -196,63 +739,406 @@ pub fn run_mcp_server(config: McpServerConfig) -> Result<()> {
             continue;
         }
 
-        debug!(raw = trimmed, "received request");
+        session.handle_message(trimmed)?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Transport-independent session core
+// ---------------------------------------------------------------------------
+
+/// Delivers one serialized JSON-RPC message (no trailing newline) to the
+/// client over whatever transport the session is bound to.
+pub(crate) type Outbound = Arc<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// One client connection: handles incoming messages and sends responses and
+/// notifications through its [`Outbound`]. Cheap to clone.
+#[derive(Clone)]
+pub(crate) struct Session {
+    ctx: ServerContext,
+}
+
+impl Session {
+    /// A session running its tool calls on `pool`, which may be shared with
+    /// other sessions.
+    pub(crate) fn new(router: Arc<ToolRouter>, out: Outbound, pool: CallPool) -> Self {
+        let subscriptions = Arc::new(Mutex::new(HashSet::new()));
+        let notify: NotificationSink = {
+            let out = Arc::clone(&out);
+            let subscriptions = Arc::clone(&subscriptions);
+            Arc::new(move |notification| {
+                if !is_subscribed(&subscriptions, &notification) {
+                    return;
+                }
+                if let Err(e) = send_message(&out, &notification) {
+                    warn!(error = %e, method = notification.method, "failed to send notification");
+                }
+            })
+        };
+        Self {
+            ctx: ServerContext {
+                initial_workspace: router.workspace().clone(),
+                router: Arc::new(RwLock::new(router)),
+                resource_updates: false,
+                notify,
+                out,
+                in_flight: Arc::new(Mutex::new(HashMap::new())),
+                subscriptions,
+                pool,
+                pending: Arc::default(),
+                output_budget: None,
+            },
+        }
+    }
+
+    /// Advertise `resources.subscribe` and `resources.listChanged`: resource
+    /// change notifications will be sent to this session.
+    #[must_use]
+    pub(crate) const fn with_resource_updates(mut self, enabled: bool) -> Self {
+        self.ctx.resource_updates = enabled;
+        self
+    }
+
+    /// Truncate tool results to `budget` characters (see
+    /// [`McpServerConfig::max_output_chars`]).
+    #[must_use]
+    pub(crate) const fn with_output_budget(mut self, budget: Option<usize>) -> Self {
+        self.ctx.output_budget = budget;
+        self
+    }
 
-        let request: JsonRpcRequest = match serde_json::from_str(trimmed) {
+    /// Sink for server-initiated notifications to this session's client.
+    pub(crate) fn notifier(&self) -> NotificationSink {
+        Arc::clone(&self.ctx.notify)
+    }
+
+    /// Send a message (response, batch or notification) to the client.
+    pub(crate) fn send(&self, msg: &impl Serialize) -> Result<()> {
+        send_message(&self.ctx.out, msg)
+    }
+
+    /// Handle one incoming JSON-RPC message (a request, notification or
+    /// batch), sending any responses through the session's outbound.
+    ///
+    /// `tools/call` requests return immediately and respond from a pool
+    /// worker; everything else is answered before this returns.
+    pub(crate) fn handle_message(&self, text: &str) -> Result<()> {
+        debug!(raw = text, "received request");
+
+        // JSON-RPC 2.0 batch: an array of requests in one message.
+        if text.starts_with('[') {
+            let batch: Vec<serde_json::Value> = match serde_json::from_str(text) {
+                Ok(b) => b,
+                Err(e) => {
+                    warn!(error = %e, "invalid JSON-RPC batch");
+                    return self.send(&error_response(None, -32700, &format!("parse error: {e}")));
+                }
+            };
+            let responses = handle_batch(&self.ctx, batch);
+            // Empty and all-notification batches get no response at all.
+            if !responses.is_empty() {
+                self.send(&responses)?;
+            }
+            return Ok(());
+        }
+
+        let request: JsonRpcRequest = match serde_json::from_str(text) {
             Ok(r) => r,
             Err(e) => {
                 warn!(error = %e, "invalid JSON-RPC request");
-                let resp = error_response(None, -32700, &format!("parse error: {e}"));
-                write_response(&mut stdout, &resp)?;
-                continue;
+                return self.send(&error_response(None, -32700, &format!("parse error: {e}")));
             }
         };
 
-        // JSON-RPC 2.0 spec: "jsonrpc" MUST be exactly "2.0".
-        if request.jsonrpc != "2.0" {
-            warn!(
-                version = request.jsonrpc,
-                "invalid JSON-RPC version (expected \"2.0\")"
-            );
-            let resp = error_response(
-                request.id.clone(),
-                -32600,
-                &format!("invalid request: jsonrpc version must be \"2.0\", got \"{}\"", request.jsonrpc),
-            );
-            write_response(&mut stdout, &resp)?;
-            continue;
+        let span = request_span(&request);
+        let _entered = span.enter();
+
+        // Tool calls run off the read loop so `notifications/cancelled` can
+        // still be received while they execute.
+        if request.method == "tools/call" && request.id.is_some() && request.jsonrpc == "2.0" {
+            queue_tool_call(&self.ctx, request, span.clone());
+            return Ok(());
         }
 
-        // Notifications (no id) don't require a response.
-        let is_notification = request.id.is_none();
-        let response = dispatch(&router, &request);
+        handle_request(&self.ctx, &request).map_or(Ok(()), |resp| self.send(&resp))
+    }
 
-        if is_notification {
-            // Per JSON-RPC 2.0 spec, notifications MUST NOT receive a response.
-            debug!(method = request.method, "notification handled (no response)");
-            continue;
+    /// Wait for queued and in-flight tool calls to finish and deliver their
+    /// responses.
+    pub(crate) fn finish(&self) {
+        self.ctx.pending.wait_idle();
+    }
+
+    /// Cancel all in-flight tool calls (e.g. the client disconnected).
+    #[cfg(feature = "http")]
+    pub(crate) fn cancel_all(&self) {
+        for token in self.ctx.in_flight().values() {
+            token.cancel();
         }
+    }
+}
+
+/// Shared state for handling requests; cheap to clone into queued calls.
+#[derive(Clone)]
+struct ServerContext {
+    /// The session's current router, replaced by `oa-coder/setWorkspace`.
+    router: Arc<RwLock<Arc<ToolRouter>>>,
+    /// The workspace the session started with; `oa-coder/setWorkspace` only
+    /// moves to directories inside its roots.
+    initial_workspace: Workspace,
+    /// Whether resource change notifications are sent (see [`McpServerConfig::watch`]).
+    resource_updates: bool,
+    notify: NotificationSink,
+    out: Outbound,
+    /// Cancellation tokens of in-flight `tools/call` requests, keyed by the
+    /// JSON text of the request id.
+    in_flight: Arc<Mutex<HashMap<String, CancelToken>>>,
+    /// URIs passed to `resources/subscribe`; `notifications/resources/updated`
+    /// is only sent for these.
+    subscriptions: Arc<Mutex<HashSet<String>>>,
+    /// Workers executing `tools/call` requests.
+    pool: CallPool,
+    /// This session's calls not yet answered.
+    pending: Arc<PendingCalls>,
+    /// Most characters of text returned per tool result.
+    output_budget: Option<usize>,
+}
+
+impl ServerContext {
+    /// The router requests should use now. Callers keep the snapshot for the
+    /// whole request, so a concurrent `oa-coder/setWorkspace` cannot move the
+    /// workspace under a running tool call.
+    fn router(&self) -> Arc<ToolRouter> {
+        Arc::clone(&self.router.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn in_flight(&self) -> std::sync::MutexGuard<'_, HashMap<String, CancelToken>> {
+        self.in_flight.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Fixed pool of threads executing `tools/call` requests (see
+/// [`McpServerConfig::max_concurrent_calls`]). Calls wait in a channel until
+/// a worker is free. Clones share the pool; its threads exit once every clone
+/// is dropped.
+#[derive(Clone)]
+pub(crate) struct CallPool {
+    jobs: mpsc::Sender<CallJob>,
+}
 
-        if let Some(resp) = response {
-            write_response(&mut stdout, &resp)?;
+impl CallPool {
+    /// Start `size` worker threads.
+    pub(crate) fn new(size: NonZeroUsize) -> Result<Self> {
+        let (jobs, queue) = mpsc::channel::<CallJob>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..size.get() {
+            let queue = Arc::clone(&queue);
+            std::thread::Builder::new()
+                .name(format!("tool-call-{i}"))
+                .spawn(move || loop {
+                    // Hold the lock only while waiting, not while running the job.
+                    let job = queue.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                    // Every sender is gone and the queue is drained.
+                    let Ok(job) = job else { break };
+                    job.run();
+                })
+                .context("failed to spawn tool call worker thread")?;
         }
+        Ok(Self { jobs })
     }
+}
 
-    info!("oa-coder MCP server stopped");
-    Ok(())
+/// A `tools/call` waiting for (or running on) a [`CallPool`] worker.
+struct CallJob {
+    ctx: ServerContext,
+    /// Snapshot taken when the request arrived.
+    router: Arc<ToolRouter>,
+    request: JsonRpcRequest,
+    cancel: CancelToken,
+    /// The request's key in [`ServerContext::in_flight`].
+    key: String,
+    span: tracing::Span,
+}
+
+impl CallJob {
+    /// Execute the call (unless it was cancelled while queued) and send its
+    /// response.
+    fn run(self) {
+        let Self { ctx, router, request, cancel, key, span } = self;
+        let _entered = span.entered();
+        let resp = if cancel.is_cancelled() {
+            info!("tool call cancelled while queued");
+            let result = ToolCallResult::error("Error: tool call cancelled before it started");
+            success_response(request.id, &result)
+        } else {
+            // A panicking tool must not take the worker down with it.
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                handle_tools_call(&ctx, &router, &request, cancel)
+            }))
+            .unwrap_or_else(|_| {
+                error!("tool call panicked");
+                error_response(request.id.clone(), -32603, "internal error: tool call panicked")
+            })
+        };
+        ctx.in_flight().remove(&key);
+        if let Err(e) = send_message(&ctx.out, &resp) {
+            error!(error = %e, "failed to write tool call response");
+        }
+        ctx.pending.done();
+    }
+}
+
+/// Count of a session's `tools/call` requests queued or running.
+#[derive(Default)]
+struct PendingCalls {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl PendingCalls {
+    fn add(&self) {
+        *self.count.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+    }
+
+    fn done(&self) {
+        let mut count = self.count.lock().unwrap_or_else(PoisonError::into_inner);
+        *count -= 1;
+        let idle = *count == 0;
+        drop(count);
+        if idle {
+            self.idle.notify_all();
+        }
+    }
+
+    /// Block until no call is pending.
+    fn wait_idle(&self) {
+        let count = self.count.lock().unwrap_or_else(PoisonError::into_inner);
+        drop(
+            self.idle
+                .wait_while(count, |count| *count > 0)
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+    }
+}
+
+/// Span correlating every log record of one request: its method and, unless
+/// it is a notification, the JSON text of its id.
+fn request_span(request: &JsonRpcRequest) -> tracing::Span {
+    let span = tracing::info_span!("request", method = %request.method, id = tracing::field::Empty);
+    if let Some(id) = &request.id {
+        span.record("id", tracing::field::display(id));
+    }
+    span
+}
+
+/// Queue a `tools/call` on the session's [`CallPool`] (to run inside
+/// `span`), registering it for cancellation; the worker sends its response.
+///
+/// The router is taken here, on the reading thread, so the call runs against
+/// the workspace current when it arrived even if it is still queued when a
+/// later `oa-coder/setWorkspace` is handled.
+fn queue_tool_call(ctx: &ServerContext, request: JsonRpcRequest, span: tracing::Span) {
+    let key = request.id.as_ref().map(ToString::to_string).unwrap_or_default();
+    let cancel = CancelToken::new();
+    ctx.in_flight().insert(key.clone(), cancel.clone());
+    ctx.pending.add();
+
+    let job = CallJob {
+        ctx: ctx.clone(),
+        router: ctx.router(),
+        request,
+        cancel,
+        key,
+        span,
+    };
+    if let Err(mpsc::SendError(job)) = ctx.pool.jobs.send(job) {
+        // Only possible if every worker died; answer rather than hang.
+        error!("no tool call workers left");
+        let resp = error_response(job.request.id.clone(), -32603, "internal error: no tool call workers");
+        ctx.in_flight().remove(&job.key);
+        if let Err(e) = send_message(&ctx.out, &resp) {
+            error!(error = %e, "failed to write tool call response");
+        }
+        ctx.pending.done();
+    }
+}
+
+/// Validate and dispatch a single request, returning the response to send
+/// (`None` for notifications).
+fn handle_request(ctx: &ServerContext, request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+    // JSON-RPC 2.0 spec: "jsonrpc" MUST be exactly "2.0".
+    if request.jsonrpc != "2.0" {
+        warn!(
+            version = request.jsonrpc,
+            "invalid JSON-RPC version (expected \"2.0\")"
+        );
+        return Some(error_response(
+            request.id.clone(),
+            -32600,
+            &format!("invalid request: jsonrpc version must be \"2.0\", got \"{}\"", request.jsonrpc),
+        ));
+    }
+
+    // Notifications (no id) don't require a response.
+    let is_notification = request.id.is_none();
+    let response = dispatch(ctx, request);
+
+    if is_notification {
+        // Per JSON-RPC 2.0 spec, notifications MUST NOT receive a response.
+        debug!(method = request.method, "notification handled (no response)");
+        return None;
+    }
+
+    response
+}
+
+/// Handle a JSON-RPC batch: dispatch each element in order and collect the
+/// responses for non-notification requests.
+///
+/// Batched tool calls run inline, so they cannot be cancelled.
+fn handle_batch(ctx: &ServerContext, batch: Vec<serde_json::Value>) -> Vec<JsonRpcResponse> {
+    batch
+        .into_iter()
+        .filter_map(|value| {
+            let id = value.get("id").cloned();
+            match serde_json::from_value::<JsonRpcRequest>(value) {
+                Ok(request) => request_span(&request).in_scope(|| handle_request(ctx, &request)),
+                Err(e) => {
+                    warn!(error = %e, "invalid request in batch");
+                    Some(error_response(id, -32600, &format!("invalid request: {e}")))
+                }
+            }
+        })
+        .collect()
 }
 
 /// Dispatch a JSON-RPC request to the appropriate handler.
-fn dispatch(router: &ToolRouter, req: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+fn dispatch(ctx: &ServerContext, req: &JsonRpcRequest) -> Option<JsonRpcResponse> {
     match req.method.as_str() {
-        "initialize" => Some(handle_initialize(req)),
+        "initialize" => Some(handle_initialize(req, ctx.resource_updates)),
         "notifications/initialized" => {
             info!("client initialized");
             None // notification, no response
         }
-        "tools/list" => Some(handle_tools_list(router, req)),
-        "tools/call" => Some(handle_tools_call(router, req)),
+        "notifications/cancelled" => {
+            handle_cancelled(ctx, req);
+            None // notification, no response
+        }
+        "tools/list" => Some(handle_tools_list(&ctx.router(), req)),
+        "tools/call" => Some(handle_tools_call(ctx, &ctx.router(), req, CancelToken::new())),
+        "resources/list" => Some(handle_resources_list(&ctx.router(), req)),
+        "resources/read" => Some(handle_resources_read(&ctx.router(), req)),
+        "resources/subscribe" => Some(handle_resources_subscribe(ctx, req, true)),
+        "resources/unsubscribe" => Some(handle_resources_subscribe(ctx, req, false)),
+        "prompts/list" => Some(handle_prompts_list(req)),
+        "prompts/get" => Some(handle_prompts_get(req)),
+        "completion/complete" => Some(handle_complete(&ctx.router(), req)),
+        "logging/setLevel" => Some(handle_set_level(req)),
         "ping" => Some(handle_ping(req)),
+        "oa-coder/health" => Some(handle_health(&ctx.router(), req)),
+        "oa-coder/capabilities" => Some(handle_capabilities(&ctx.router(), req)),
+        "oa-coder/setWorkspace" => Some(handle_set_workspace(ctx, req)),
         _ => {
             warn!(method = req.method, "unknown method");
             Some(error_response(
@@ -268,13 +1154,43 @@ fn dispatch(router: &ToolRouter, req: &JsonRpcRequest) -> Option<JsonRpcResponse
 // Handlers
 // ---------------------------------------------------------------------------
 
-fn handle_initialize(req: &JsonRpcRequest) -> JsonRpcResponse {
+fn handle_initialize(req: &JsonRpcRequest, resource_updates: bool) -> JsonRpcResponse {
+    let params: InitializeParams = serde_json::from_value(req.params.clone()).unwrap_or_default();
+    let Some(protocol_version) = negotiate_protocol_version(params.protocol_version.as_deref()) else {
+        let requested = params.protocol_version.unwrap_or_default();
+        warn!(requested, "unsupported protocol version");
+        let mut resp = error_response(
+            req.id.clone(),
+            -32602,
+            &format!(
+                "unsupported protocol version {requested}; supported: {}",
+                SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+            ),
+        );
+        if let Some(err) = resp.error.as_mut() {
+            err.data = Some(serde_json::json!({
+                "requested": requested,
+                "supported": SUPPORTED_PROTOCOL_VERSIONS,
+            }));
+        }
+        return resp;
+    };
+
     let result = InitializeResult {
-        protocol_version: "2025-06-18".to_owned(),
+        protocol_version: protocol_version.to_owned(),
         capabilities: ServerCapabilities {
             tools: ToolsCapability {
                 list_changed: false,
             },
+            resources: ResourcesCapability {
+                subscribe: resource_updates,
+                list_changed: resource_updates,
+            },
+            prompts: PromptsCapability {
+                list_changed: false,
+            },
+            logging: LoggingCapability {},
+            completions: CompletionsCapability {},
         },
         server_info: ServerInfo {
             name: "oa-coder".to_owned(),
@@ -285,13 +1201,41 @@ fn handle_initialize(req: &JsonRpcRequest) -> JsonRpcResponse {
     success_response(req.id.clone(), &result)
 }
 
+/// Pick the protocol version to answer `initialize` with.
+///
+/// A supported version is echoed back. A version newer than any we support
+/// (versions are ISO dates, so they order lexicographically) is answered with
+/// our newest, which the client may accept or reject. Older unsupported
+/// versions have no overlap and yield `None`. A missing version gets our newest.
+fn negotiate_protocol_version(requested: Option<&str>) -> Option<&'static str> {
+    let newest = SUPPORTED_PROTOCOL_VERSIONS[0];
+    let Some(requested) = requested else {
+        return Some(newest);
+    };
+    if let Some(v) = SUPPORTED_PROTOCOL_VERSIONS.iter().find(|v| **v == requested) {
+        return Some(v);
+    }
+    (requested > newest).then_some(newest)
+}
+
 fn handle_tools_list(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
-    let tools = router.list_tools();
-    let result = ToolsListResult { tools };
-    success_response(req.id.clone(), &result)
+    let params: ListParams = serde_json::from_value(req.params.clone()).unwrap_or_default();
+    match router.list_tools_page(params.cursor.as_deref(), TOOLS_PAGE_SIZE) {
+        Ok((tools, next_cursor)) => {
+            success_response(req.id.clone(), &ToolsListResult { tools, next_cursor })
+        }
+        Err(e) => error_response(req.id.clone(), -32602, &format!("{e:#}")),
+    }
 }
 
-fn handle_tools_call(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+/// Run a `tools/call` with `router`, the snapshot taken when the request
+/// arrived.
+fn handle_tools_call(
+    ctx: &ServerContext,
+    router: &ToolRouter,
+    req: &JsonRpcRequest,
+    cancel: CancelToken,
+) -> JsonRpcResponse {
     let params: ToolCallParams = match serde_json::from_value(req.params.clone()) {
         Ok(p) => p,
         Err(e) => {
@@ -303,26 +1247,353 @@ fn handle_tools_call(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcRespon
         }
     };
 
-    match router.call_tool(&params.name, params.arguments) {
-        Ok(result) => success_response(req.id.clone(), &result),
+    let progress = params
+        .meta
+        .and_then(|m| m.progress_token)
+        .map_or_else(Progress::none, |token| Progress::new(token, Arc::clone(&ctx.notify)));
+    let mut tool_ctx = ToolContext {
+        progress,
+        cancel,
+        request_id: req.id.as_ref().map(ToString::to_string),
+    };
+
+    match router.call_tool_with_context(&params.name, params.arguments, &mut tool_ctx) {
+        Ok(mut result) => {
+            debug!(tool = params.name, is_error = result.is_error, "tool call finished");
+            if let Some(budget) = ctx.output_budget {
+                apply_output_budget(&mut result, budget);
+            }
+            success_response(req.id.clone(), &result)
+        }
         Err(e) => {
             error!(tool = params.name, error = %e, "tool call failed");
-            let result = ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!("Error: {e}"),
-                }],
-                is_error: true,
-            };
+            // Typed failures become JSON-RPC errors clients can branch on.
+            if let Some(coder_err) = e.downcast_ref::<CoderError>() {
+                return JsonRpcResponse {
+                    jsonrpc: "2.0".to_owned(),
+                    id: req.id.clone(),
+                    result: None,
+                    error: Some(coder_err.into()),
+                };
+            }
+            let result = ToolCallResult::error(format!("Error: {e}"));
             success_response(req.id.clone(), &result)
         }
     }
 }
 
+/// Cut `result` to `budget` characters in total, counting the text of every
+/// item and the base64 payload of images and embedded resources. Items are
+/// kept whole while they fit; the first that does not is cut if it is plain
+/// text, or dropped if it is JSON or binary (which a cut would leave
+/// unparseable), and later items are dropped. A marker giving the original
+/// size, itself within the budget, ends the cut item or follows as a text
+/// item of its own. Returns whether anything was cut.
+fn apply_output_budget(result: &mut ToolCallResult, budget: usize) -> bool {
+    let sizes: Vec<usize> = result.content.iter().map(content_chars).collect();
+    let total: usize = sizes.iter().sum();
+    if total <= budget {
+        return false;
+    }
+
+    let marker = |shown: usize, omitted: usize| {
+        let omitted = match omitted {
+            0 => String::new(),
+            1 => "; 1 further content item omitted".to_owned(),
+            n => format!("; {n} further content items omitted"),
+        };
+        format!("[output truncated: {shown} of {total} characters shown{omitted}]")
+    };
+    // Room for the longest marker: every number at its widest, plus the
+    // blank line separating it from cut text.
+    let reserve = marker(budget, result.content.len()).chars().count() + 2;
+    let mut remaining = budget.saturating_sub(reserve);
+    let kept = sizes
+        .iter()
+        .take_while(|&&size| {
+            let fits = size <= remaining;
+            if fits {
+                remaining -= size;
+            }
+            fits
+        })
+        .count();
+
+    let mut rest = result.content.split_off(kept).into_iter();
+    let mut omitted = rest.len();
+    let cut = rest.next().filter(|item| remaining > 0 && is_plain_text(item));
+    let mut shown = budget.saturating_sub(reserve) - remaining;
+    if let Some(mut item) = cut {
+        let end = item.text.char_indices().nth(remaining).map_or(item.text.len(), |(i, _)| i);
+        item.text.truncate(end);
+        shown += remaining;
+        omitted -= 1;
+        let _ = write!(item.text, "\n\n{}", marker(shown, omitted));
+        result.content.push(item);
+    } else {
+        result.content.push(ContentItem::text(marker(shown, omitted)));
+    }
+    info!(total, budget, "tool output truncated");
+    true
+}
+
+/// Characters an item counts against the output budget: its text plus any
+/// base64 or embedded payload.
+fn content_chars(item: &ContentItem) -> usize {
+    let resource = item.resource.as_ref().map_or(0, |resource| match resource {
+        crate::resources::ResourceContents::Text { text, .. } => text.chars().count(),
+        crate::resources::ResourceContents::Blob { blob, .. } => blob.len(),
+    });
+    item.text.chars().count() + item.data.as_ref().map_or(0, String::len) + resource
+}
+
+/// Whether `item` is text that stays meaningful when cut short: not a
+/// binary payload, and not a JSON document.
+fn is_plain_text(item: &ContentItem) -> bool {
+    item.data.is_none()
+        && item.resource.is_none()
+        && !(item.text.trim_start().starts_with(['{', '['])
+            && serde_json::from_str::<serde_json::Value>(&item.text).is_ok())
+}
+
+/// MCP `notifications/cancelled`: flag the matching in-flight tool call.
+fn handle_cancelled(ctx: &ServerContext, req: &JsonRpcRequest) {
+    let Some(request_id) = req.params.get("requestId") else {
+        warn!("notifications/cancelled without requestId");
+        return;
+    };
+    let reason = req.params.get("reason").and_then(|r| r.as_str()).unwrap_or("");
+
+    let token = ctx.in_flight().get(&request_id.to_string()).cloned();
+    if let Some(token) = token {
+        info!(request_id = %request_id, reason, "cancelling tool call");
+        token.cancel();
+    } else {
+        // Already finished (or unknown): nothing to do, per MCP spec.
+        debug!(request_id = %request_id, "cancellation for unknown request");
+    }
+}
+
+fn handle_resources_list(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+    let params: ListParams = serde_json::from_value(req.params.clone()).unwrap_or_default();
+    match resources::list(router.workspace(), params.cursor.as_deref()) {
+        Ok(result) => success_response(req.id.clone(), &result),
+        Err(e) => error_response(req.id.clone(), -32602, &format!("{e:#}")),
+    }
+}
+
+fn handle_resources_read(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+    let params: ResourcesReadParams = match serde_json::from_value(req.params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("invalid resources/read params: {e}"),
+            );
+        }
+    };
+
+    match resources::read(router.workspace(), &params.uri) {
+        Ok(contents) => success_response(
+            req.id.clone(),
+            &serde_json::json!({ "contents": [contents] }),
+        ),
+        Err(e) => {
+            // MCP reserves -32002 for "resource not found".
+            let code = match e.downcast_ref::<CoderError>() {
+                Some(CoderError::FileNotFound { .. }) => -32002,
+                _ => -32602,
+            };
+            error_response(req.id.clone(), code, &format!("{e:#}"))
+        }
+    }
+}
+
+/// `resources/subscribe` (or, with `subscribe` false, `resources/unsubscribe`):
+/// start or stop sending `notifications/resources/updated` for one URI.
+fn handle_resources_subscribe(
+    ctx: &ServerContext,
+    req: &JsonRpcRequest,
+    subscribe: bool,
+) -> JsonRpcResponse {
+    if !ctx.resource_updates {
+        return error_response(
+            req.id.clone(),
+            -32601,
+            &format!("method not found: {} (requires --watch)", req.method),
+        );
+    }
+    let params: ResourcesReadParams = match serde_json::from_value(req.params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("invalid {} params: {e}", req.method),
+            );
+        }
+    };
+    let mut subscriptions = ctx.subscriptions.lock().unwrap_or_else(PoisonError::into_inner);
+    if subscribe {
+        subscriptions.insert(params.uri);
+    } else {
+        subscriptions.remove(&params.uri);
+    }
+    drop(subscriptions);
+    success_response(req.id.clone(), &serde_json::json!({}))
+}
+
+/// Whether `notification` should reach the client: resource updates only
+/// for subscribed URIs, everything else always.
+fn is_subscribed(subscriptions: &Mutex<HashSet<String>>, notification: &JsonRpcNotification) -> bool {
+    if notification.method != "notifications/resources/updated" {
+        return true;
+    }
+    notification.params["uri"]
+        .as_str()
+        .is_some_and(|uri| subscriptions.lock().unwrap_or_else(PoisonError::into_inner).contains(uri))
+}
+
+fn handle_prompts_list(req: &JsonRpcRequest) -> JsonRpcResponse {
+    success_response(req.id.clone(), &serde_json::json!({ "prompts": prompts::list() }))
+}
+
+fn handle_prompts_get(req: &JsonRpcRequest) -> JsonRpcResponse {
+    let params: GetPromptParams = match serde_json::from_value(req.params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(req.id.clone(), -32602, &format!("invalid prompts/get params: {e}"));
+        }
+    };
+    match prompts::get(&params.name, &params.arguments) {
+        Ok(result) => success_response(req.id.clone(), &result),
+        Err(e) => error_response(req.id.clone(), -32602, &e.to_string()),
+    }
+}
+
+fn handle_complete(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+    let params: CompleteParams = match serde_json::from_value(req.params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("invalid completion/complete params: {e}"),
+            );
+        }
+    };
+    let argument = &params.argument;
+    match completion::complete(router.workspace(), &argument.name, &argument.value) {
+        Ok(completion) => {
+            success_response(req.id.clone(), &serde_json::json!({ "completion": completion }))
+        }
+        Err(e) => error_response(req.id.clone(), -32603, &format!("{e:#}")),
+    }
+}
+
+fn handle_set_level(req: &JsonRpcRequest) -> JsonRpcResponse {
+    match serde_json::from_value::<SetLevelParams>(req.params.clone()) {
+        Ok(params) => {
+            logging::set_level(params.level);
+            info!(level = ?params.level, "client log level set");
+            success_response(req.id.clone(), &serde_json::json!({}))
+        }
+        Err(e) => error_response(
+            req.id.clone(),
+            -32602,
+            &format!("invalid logging/setLevel params: {e}"),
+        ),
+    }
+}
+
 fn handle_ping(req: &JsonRpcRequest) -> JsonRpcResponse {
     success_response(req.id.clone(), &serde_json::json!({}))
 }
 
+/// Report whether the server's dependencies are usable. The only side effect
+/// is a temp file created in (and removed from) the workspace root.
+fn handle_health(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+    let probes = Probes::run(router.workspace());
+    let result = HealthResult {
+        server_version: env!("CARGO_PKG_VERSION"),
+        protocol_version: SUPPORTED_PROTOCOL_VERSIONS[0],
+        ripgrep: probes.ripgrep.is_some(),
+        ripgrep_path: probes.ripgrep.map(|path| path.display().to_string()),
+        sandbox: probes.sandbox,
+        workspace: router.workspace().root().display().to_string(),
+        workspace_writable: probes.workspace_writable,
+    };
+    success_response(req.id.clone(), &result)
+}
+
+/// Report which enabled tools are ready, from the same probes as
+/// `oa-coder/health`.
+fn handle_capabilities(router: &ToolRouter, req: &JsonRpcRequest) -> JsonRpcResponse {
+    let probes = Probes::run(router.workspace());
+    success_response(req.id.clone(), &capabilities::capabilities(router, &probes))
+}
+
+/// Switch the session to another primary workspace root. The path must be an
+/// existing directory inside one of the roots the session started with;
+/// later requests see the new root, in-flight tool calls finish on the old
+/// one. Refused while watching, since the watcher cannot follow the switch.
+fn handle_set_workspace(ctx: &ServerContext, req: &JsonRpcRequest) -> JsonRpcResponse {
+    if ctx.resource_updates {
+        return error_response(
+            req.id.clone(),
+            -32601,
+            &format!("method not available with --watch: {}", req.method),
+        );
+    }
+    let params: SetWorkspaceParams = match serde_json::from_value(req.params.clone()) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("invalid oa-coder/setWorkspace params: {e}"),
+            );
+        }
+    };
+
+    let mut router = ctx.router.write().unwrap_or_else(PoisonError::into_inner);
+    let previous = router.workspace().root().to_path_buf();
+    let root = match previous.join(&params.path).canonicalize() {
+        Ok(root) if !root.is_dir() => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("not a directory: {}", root.display()),
+            );
+        }
+        Ok(root) if !ctx.initial_workspace.contains(&root) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("outside the workspace roots: {}", root.display()),
+            );
+        }
+        Ok(root) => root,
+        Err(e) => {
+            return error_response(
+                req.id.clone(),
+                -32602,
+                &format!("invalid workspace {}: {e}", params.path),
+            );
+        }
+    };
+
+    *router = Arc::new(router.with_workspace_root(root.clone()));
+    drop(router);
+    info!(workspace = %root.display(), previous = %previous.display(), "workspace changed");
+    let result = SetWorkspaceResult {
+        workspace: root.display().to_string(),
+        previous_workspace: previous.display().to_string(),
+    };
+    success_response(req.id.clone(), &result)
+}
+
 // ---------------------------------------------------------------------------
 // Response helpers
 // ---------------------------------------------------------------------------
@@ -364,10 +1635,19 @@ fn error_response(id: Option<serde_json::Value>, code: i64, message: &str) -> Js
     }
 }
 
-/// Write a JSON-RPC response as a single line to stdout.
-fn write_response(out: &mut impl Write, resp: &JsonRpcResponse) -> Result<()> {
-    let json = serde_json::to_string(resp).context("failed to serialize response")?;
-    debug!(response = json, "sending response");
+/// Serialize a JSON-RPC message (response, batch of responses or
+/// notification) and hand it to the transport.
+///
+/// Logs before sending: the MCP log layer writes through the same transport,
+/// so no tracing event may be emitted while it holds its lock.
+fn send_message(out: &Outbound, msg: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string(msg).context("failed to serialize message")?;
+    debug!(message = json, "sending message");
+    out(&json)
+}
+
+/// Write one serialized message followed by a newline, then flush.
+fn write_line(out: &mut impl Write, json: &str) -> Result<()> {
     out.write_all(json.as_bytes())
         .context("failed to write to stdout")?;
     out.write_all(b"\n")
@@ -376,10 +1656,28 @@ fn write_response(out: &mut impl Write, resp: &JsonRpcResponse) -> Result<()> {
     Ok(())
 }
 
+/// Error returned by [`read_line_limited`] when a line exceeds the size limit.
+#[derive(Debug, thiserror::Error)]
+#[error("line exceeds maximum size ({max_bytes} bytes)")]
+struct LineTooLong {
+    max_bytes: usize,
+}
+
+/// Best-effort extraction of the top-level `"id"` from a truncated request.
+///
+/// Only the retained prefix of the line is available, so this succeeds when
+/// the id precedes the oversized payload (the usual serialization order).
+fn recover_request_id(prefix: &str) -> Option<serde_json::Value> {
+    let re = regex::Regex::new(r#""id"\s*:\s*(-?\d+|"(?:[^"\\]|\\.)*")"#).ok()?;
+    let raw = re.captures(prefix)?.get(1)?.as_str();
+    serde_json::from_str(raw).ok()
+}
+
 /// Read a line from `reader` into `buf`, stopping at newline or `max_bytes`.
 ///
 /// Returns the number of bytes read (0 = EOF). If the line exceeds `max_bytes`,
-/// the excess is consumed and discarded, and an error is returned.
+/// the excess is consumed and discarded and a [`LineTooLong`] error is
+/// returned; `buf` then holds the (lossily decoded) prefix that fit.
 fn read_line_limited(reader: &mut impl BufRead, buf: &mut String, max_bytes: usize) -> Result<usize> {
     let mut total = 0usize;
     loop {
@@ -388,11 +1686,14 @@ fn read_line_limited(reader: &mut impl BufRead, buf: &mut String, max_bytes: usi
             return Ok(total); // EOF
         }
         // Find newline position in available data.
-        let (consumed, found_newline) = match available.iter().position(|&b| b == b'\n') {
-            Some(pos) => (pos + 1, true),
-            None => (available.len(), false),
-        };
+        let (consumed, found_newline) = available
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or((available.len(), false), |pos| (pos + 1, true));
         if total + consumed > max_bytes {
+            // Keep what fits so the caller can try to recover the request id.
+            let keep = max_bytes.saturating_sub(total).min(consumed);
+            buf.push_str(&String::from_utf8_lossy(&available[..keep]));
             // Consume everything up to the newline (or buffer end) and error out.
             reader.consume(consumed);
             // Keep consuming until we find a newline or EOF.
@@ -409,7 +1710,7 @@ fn read_line_limited(reader: &mut impl BufRead, buf: &mut String, max_bytes: usi
                     reader.consume(eat);
                 }
             }
-            anyhow::bail!("line exceeds maximum size ({max_bytes} bytes)");
+            return Err(LineTooLong { max_bytes }.into());
         }
         // Safe: we're reading from stdin which should be valid UTF-8 JSON.
         let chunk = std::str::from_utf8(&available[..consumed])
@@ -422,3 +1723,180 @@ fn read_line_limited(reader: &mut impl BufRead, buf: &mut String, max_bytes: usi
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_line_limited_too_long_recovers_id() {
+        let input = format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":42,\"method\":\"tools/call\",\"params\":\"{}\"}}\n{{\"next\":1}}\n",
+            "x".repeat(100)
+        );
+        let mut reader = std::io::BufReader::with_capacity(16, input.as_bytes());
+        let mut buf = String::new();
+
+        let err = read_line_limited(&mut reader, &mut buf, 64).expect_err("should exceed limit");
+        assert!(err.is::<LineTooLong>());
+        assert_eq!(recover_request_id(&buf), Some(serde_json::json!(42)));
+
+        // The rest of the oversized line was discarded; the next line is intact.
+        buf.clear();
+        let n = read_line_limited(&mut reader, &mut buf, 64).expect("next line");
+        assert!(n > 0);
+        assert_eq!(buf.trim(), "{\"next\":1}");
+    }
+
+    #[test]
+    fn test_recover_request_id_string_and_missing() {
+        assert_eq!(
+            recover_request_id(r#"{"jsonrpc":"2.0","id":"abc-1","method":"x","par"#),
+            Some(serde_json::json!("abc-1"))
+        );
+        assert_eq!(recover_request_id(r#"{"jsonrpc":"2.0","method":"x","#), None);
+    }
+
+    #[test]
+    fn test_negotiate_matching_version() {
+        assert_eq!(negotiate_protocol_version(Some("2025-06-18")), Some("2025-06-18"));
+        assert_eq!(negotiate_protocol_version(Some("2024-11-05")), Some("2024-11-05"));
+        assert_eq!(negotiate_protocol_version(None), Some("2025-06-18"));
+    }
+
+    #[test]
+    fn test_negotiate_newer_client_downgrades() {
+        assert_eq!(negotiate_protocol_version(Some("2099-01-01")), Some("2025-06-18"));
+    }
+
+    #[test]
+    fn test_negotiate_unsupported_version() {
+        assert_eq!(negotiate_protocol_version(Some("2024-01-01")), None);
+        assert_eq!(negotiate_protocol_version(Some("2025-01-01")), None);
+
+        let req: JsonRpcRequest = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2024-01-01" }
+        }))
+        .expect("request");
+        let resp = handle_initialize(&req, false);
+        let err = resp.error.expect("error");
+        assert_eq!(err.code, -32602);
+        assert!(err.message.contains("2025-06-18"), "got: {}", err.message);
+    }
+
+    #[test]
+    fn test_call_pool_queue_and_cancel() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let router = Arc::new(ToolRouter::new(dir.path().to_path_buf(), false));
+        let (tx, rx) = mpsc::channel::<String>();
+        let out: Outbound = Arc::new(move |json| Ok(tx.send(json.to_owned())?));
+        let pool = CallPool::new(DEFAULT_MAX_CONCURRENT_CALLS).expect("pool");
+        let session = Session::new(router, out, pool);
+
+        let call = |id: u32, command: &str| {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": "bash", "arguments": { "command": command } },
+            })
+            .to_string()
+        };
+        // With one worker, call 2 is still queued when it is cancelled.
+        session.handle_message(&call(1, "sleep 0.3 && echo one")).expect("call 1");
+        session.handle_message(&call(2, "echo two")).expect("call 2");
+        session.handle_message(&call(3, "echo three")).expect("call 3");
+        session
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":2}}"#)
+            .expect("cancel");
+        session.finish();
+
+        let texts: Vec<(u64, String)> = rx
+            .try_iter()
+            .map(|line| {
+                let resp: serde_json::Value = serde_json::from_str(&line).expect("json");
+                let text = resp["result"]["content"][0]["text"].as_str().unwrap_or_default().to_owned();
+                (resp["id"].as_u64().expect("id"), text)
+            })
+            .collect();
+        let ids: Vec<u64> = texts.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![1, 2, 3], "answered in arrival order");
+        assert!(texts[0].1.contains("one"), "{texts:?}");
+        assert!(texts[1].1.contains("cancelled before it started"), "{texts:?}");
+        assert!(texts[2].1.contains("three"), "{texts:?}");
+    }
+
+    #[test]
+    fn test_output_budget() {
+        let result = |texts: &[&str]| ToolCallResult {
+            content: texts.iter().map(|&t| ContentItem::text(t)).collect(),
+            is_error: false,
+        };
+
+        let mut small = result(&["hello", "world"]);
+        assert!(!apply_output_budget(&mut small, 10));
+        assert_eq!(small.content[1].text, "world");
+
+        // The budget counts characters, not bytes, across all items, images
+        // included, and the marker stays within it.
+        let wide = "wörld".repeat(20);
+        let mut big = result(&["héllo", &wide, "{\"ranges\":[]}"]);
+        big.content.insert(1, ContentItem::image("AAAA".to_owned(), "image/png"));
+        assert!(apply_output_budget(&mut big, 100));
+        assert_eq!(big.content.len(), 3);
+        assert!(big.content.iter().map(content_chars).sum::<usize>() <= 100);
+        assert_eq!(big.content[0].text, "héllo");
+        assert_eq!(big.content[1].content_type, "image");
+        let (shown, marker) = big.content[2].text.split_once("\n\n").expect("marker");
+        assert!(wide.starts_with(shown) && !shown.is_empty(), "{shown}");
+        let shown = 9 + shown.chars().count();
+        assert_eq!(
+            marker,
+            format!("[output truncated: {shown} of 122 characters shown; 1 further content item omitted]")
+        );
+
+        // JSON and images are dropped whole, never cut.
+        let json = format!("{{\"ranges\":[{}]}}", vec!["[1,2]"; 40].join(","));
+        for item in [ContentItem::text(json), ContentItem::image("A".repeat(200), "image/png")] {
+            let mut cut = result(&["ok"]);
+            cut.content.push(item);
+            assert!(apply_output_budget(&mut cut, 100));
+            assert_eq!(cut.content.len(), 2);
+            assert_eq!(cut.content[0].text, "ok");
+            assert!(cut.content[1].text.starts_with("[output truncated: 2 of "), "{}", cut.content[1].text);
+            assert!(cut.content[1].text.ends_with("; 1 further content item omitted]"));
+        }
+    }
+
+    #[test]
+    fn test_content_item_serializes_only_present_fields() {
+        let text = serde_json::to_value(ContentItem::text("")).expect("serialize");
+        assert_eq!(text, serde_json::json!({ "type": "text", "text": "" }));
+
+        let image = serde_json::to_value(ContentItem::image("AAAA".to_owned(), "image/png"))
+            .expect("serialize");
+        assert_eq!(
+            image,
+            serde_json::json!({ "type": "image", "data": "AAAA", "mimeType": "image/png" })
+        );
+
+        let resource = serde_json::to_value(ContentItem::resource(
+            crate::resources::ResourceContents::Text {
+                uri: "file:///w/a.rs".to_owned(),
+                mime_type: "text/x-rust".to_owned(),
+                text: "fn main() {}".to_owned(),
+            },
+        ))
+        .expect("serialize");
+        assert_eq!(
+            resource,
+            serde_json::json!({
+                "type": "resource",
+                "resource": { "uri": "file:///w/a.rs", "mimeType": "text/x-rust", "text": "fn main() {}" }
+            })
+        );
+    }
+}
@@ -4,28 +4,112 @@
 //! inside an [`oa_sandbox`] isolation boundary (macOS Seatbelt / Linux
 //! Landlock+Seccomp / Windows `AppContainer`). Otherwise falls back to direct
 //! `sh -c` execution.
+//!
+//! With a [`Redactor`] configured (`--redact-secrets`), secrets are replaced
+//! with `***` in the final result and in the command text echoed in logs and
+//! error messages. Progress then reports only byte counts: a secret split
+//! across two chunks could not be recognized in either.
+//!
+//! A call without `timeout` gets the server's default; requested timeouts
+//! above the server's maximum are clamped to it (see [`BashOptions`]).
 
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Write as _;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 
-use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
+use super::Workspace;
+use crate::server::{CancelToken, ContentItem, Progress, ToolCallResult, ToolContext, ToolDefinition};
+use crate::util::redact::Redactor;
 
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct BashParams {
     /// The bash command to execute.
     pub command: String,
-    /// Execution timeout in seconds (default: 120).
-    #[serde(default = "default_timeout")]
-    pub timeout: u64,
+    /// Execution timeout in seconds. Default: the server's
+    /// [`BashOptions::default_timeout`]; capped at its `max_timeout`.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    /// Data written to the command's stdin (then closed). When absent,
+    /// stdin is empty (`/dev/null`).
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Extra environment variables. These augment the inherited environment
+    /// unless `clear_env` is set.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Start from an empty environment (only `env` is visible to the command).
+    #[serde(default)]
+    pub clear_env: bool,
+    /// Working directory (relative to workspace). Default: workspace root.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Maximum bytes captured per stream (stdout, stderr). Default: 1 MiB.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+const fn default_max_output_bytes() -> usize {
+    1024 * 1024
+}
+
+/// Timeout of a call that does not ask for one, unless configured.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Longest timeout a call may ask for, unless configured.
+pub const DEFAULT_MAX_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Server-level settings for running commands, shared by `bash` and the
+/// tools built on it (`format`, `git_diff`).
+#[derive(Debug, Clone, Copy)]
+pub struct BashOptions<'a> {
+    /// Run commands inside the OS sandbox (when compiled in).
+    pub sandboxed: bool,
+    /// Mask secrets in command output.
+    pub redactor: Option<&'a Redactor>,
+    /// Timeout of calls without `timeout`.
+    pub default_timeout: Duration,
+    /// Ceiling on `timeout`; larger requests are clamped to it.
+    pub max_timeout: Duration,
 }
 
-const fn default_timeout() -> u64 {
-    120
+impl Default for BashOptions<'_> {
+    fn default() -> Self {
+        Self {
+            sandboxed: false,
+            redactor: None,
+            default_timeout: DEFAULT_TIMEOUT,
+            max_timeout: DEFAULT_MAX_TIMEOUT,
+        }
+    }
+}
+
+impl BashOptions<'_> {
+    /// The timeout for a call asking for `requested` seconds, clamped to
+    /// `max_timeout` (with a warning).
+    fn timeout(&self, requested: Option<u64>) -> Duration {
+        let Some(secs) = requested else {
+            return self.default_timeout.min(self.max_timeout);
+        };
+        let requested = Duration::from_secs(secs);
+        if requested > self.max_timeout {
+            tracing::warn!(
+                requested = secs,
+                max = self.max_timeout.as_secs(),
+                "bash timeout clamped to the configured maximum"
+            );
+            return self.max_timeout;
+        }
+        requested
+    }
 }
 
 #[must_use]
@@ -42,32 +126,70 @@ pub fn tool_definition() -> ToolDefinition {
                 },
                 "timeout": {
                     "type": "integer",
-                    "description": "Timeout in seconds (default: 120)",
-                    "default": 120
+                    "description": "Timeout in seconds (default: 120 unless the server configures another; \
+                        values above the server's maximum, 1 hour by default, are clamped)"
+                },
+                "stdin": {
+                    "type": "string",
+                    "description": "Data to pipe to the command's stdin"
+                },
+                "env": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Environment variables to set (added to the inherited environment)"
+                },
+                "clearEnv": {
+                    "type": "boolean",
+                    "description": "Start with an empty environment so only `env` is set (default: false)",
+                    "default": false
+                },
+                "cwd": {
+                    "type": "string",
+                    "description": "Working directory inside the workspace (default: workspace root)"
+                },
+                "maxOutputBytes": {
+                    "type": "integer",
+                    "description": "Maximum bytes captured per output stream; the command is killed once exceeded (default: 1048576)",
+                    "default": 1_048_576,
+                    "minimum": 1
                 }
             },
-            "required": ["command"]
+            "required": ["command"],
+            "additionalProperties": false
         }),
     }
 }
 
 /// Execute the bash tool.
 ///
-/// When `sandboxed` is `true` and the `sandbox` feature is compiled in,
-/// the command runs inside an OS-native sandbox via [`oa_sandbox`].
-/// Otherwise falls back to direct `sh -c` execution.
+/// When `options.sandboxed` is `true` and the `sandbox` feature is compiled
+/// in, the command runs inside an OS-native sandbox via [`oa_sandbox`].
+/// Otherwise falls back to direct `sh -c` execution, streaming output
+/// through `ctx.progress` as it arrives and killing the command if
+/// `ctx.cancel` fires (the sandbox only reports on exit). With a
+/// `redactor`, secrets are masked in all of the command's output.
 pub fn execute(
-    workspace: &Path,
-    sandboxed: bool,
+    workspace: &Workspace,
+    options: &BashOptions<'_>,
     arguments: serde_json::Value,
+    ctx: &mut ToolContext,
 ) -> Result<ToolCallResult> {
-    let params: BashParams =
-        serde_json::from_value(arguments).context("invalid bash parameters")?;
+    let params: BashParams = super::parse_params(arguments, tool_definition)?;
+    let redaction = Redaction::new(options.redactor, &params);
+    let timeout = options.timeout(params.timeout);
 
-    if sandboxed {
+    let cwd = match &params.cwd {
+        Some(dir) => match resolve_cwd(workspace, dir) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+
+    if options.sandboxed {
         #[cfg(feature = "sandbox")]
         {
-            return execute_sandboxed(workspace, &params);
+            return execute_sandboxed(&cwd, &params, timeout, &redaction);
         }
 
         #[cfg(not(feature = "sandbox"))]
@@ -76,7 +198,63 @@ pub fn execute(
         }
     }
 
-    execute_direct(workspace, &params)
+    execute_direct(&cwd, &params, timeout, &redaction, &mut ctx.progress, &ctx.cancel)
+}
+
+/// Secret masking for one command: the configured patterns plus the values
+/// of secret-named variables in the command's environment.
+struct Redaction<'a> {
+    redactor: Option<&'a Redactor>,
+    secrets: Vec<String>,
+}
+
+impl<'a> Redaction<'a> {
+    fn new(redactor: Option<&'a Redactor>, params: &BashParams) -> Self {
+        let secrets = match redactor {
+            Some(_) => {
+                let inherited: Vec<(String, String)> = if params.clear_env {
+                    Vec::new()
+                } else {
+                    std::env::vars().collect()
+                };
+                Redactor::secret_values(
+                    inherited
+                        .iter()
+                        .map(|(name, value)| (name, value))
+                        .chain(&params.env)
+                        .map(|(name, value)| (name.as_str(), value.as_str())),
+                )
+            }
+            None => Vec::new(),
+        };
+        Self { redactor, secrets }
+    }
+
+    const fn is_active(&self) -> bool {
+        self.redactor.is_some()
+    }
+
+    fn apply<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        self.redactor
+            .map_or(Cow::Borrowed(text), |redactor| redactor.redact(text, &self.secrets))
+    }
+}
+
+/// Validate a `cwd` override like any tool path (so the workspace's symlink
+/// and case policies apply): it must stay inside the primary workspace root
+/// (additional roots are not exposed to commands) and be an existing directory.
+fn resolve_cwd(workspace: &Workspace, dir: &str) -> Result<PathBuf> {
+    let path = super::validate_dir_path(workspace, dir)?;
+    if !workspace.in_primary_root(&path) {
+        anyhow::bail!("cwd is outside the primary workspace root: {dir}");
+    }
+    if !path.exists() {
+        anyhow::bail!("cwd does not exist: {}", path.display());
+    }
+    if !path.is_dir() {
+        anyhow::bail!("cwd is not a directory: {}", path.display());
+    }
+    Ok(path)
 }
 
 // ---------------------------------------------------------------------------
@@ -85,25 +263,56 @@ pub fn execute(
 
 /// Execute a command inside the OS-native sandbox.
 #[cfg(feature = "sandbox")]
-fn execute_sandboxed(workspace: &Path, params: &BashParams) -> Result<ToolCallResult> {
+fn execute_sandboxed(
+    workspace: &Path,
+    params: &BashParams,
+    timeout: Duration,
+    redaction: &Redaction<'_>,
+) -> Result<ToolCallResult> {
     use oa_sandbox::config::{
         BackendPreference, OutputFormat, ResourceLimits, SandboxConfig, SecurityLevel,
     };
 
-    tracing::info!(command = %params.command, "executing in sandbox");
+    tracing::info!(command = %redaction.apply(&params.command), "executing in sandbox");
+
+    // The sandbox builds its own minimal environment, so `clear_env` has no
+    // additional effect here; `env` is passed through as-is.
+    let mut env_vars = params.env.clone();
+    let mut script = params.command.clone();
+
+    // The sandbox has no stdin channel: spill the data to a temp file inside
+    // the workspace (readable under L1) and redirect from it. The file is
+    // removed when `_stdin_file` drops after the run.
+    let _stdin_file = match &params.stdin {
+        Some(data) => {
+            let mut file = tempfile::Builder::new()
+                .prefix(".oa-coder-stdin-")
+                .tempfile_in(workspace)
+                .context("failed to create stdin file for sandbox")?;
+            std::io::Write::write_all(&mut file, data.as_bytes())
+                .context("failed to write stdin file for sandbox")?;
+            env_vars.insert(
+                "OA_CODER_STDIN".to_owned(),
+                file.path().display().to_string(),
+            );
+            script = format!("{{\n{script}\n}} < \"$OA_CODER_STDIN\"");
+            Some(file)
+        }
+        None => None,
+    };
 
     let config = SandboxConfig {
         security_level: SecurityLevel::L1Sandbox,
         command: "sh".to_owned(),
-        args: vec!["-c".to_owned(), params.command.clone()],
+        args: vec!["-c".to_owned(), script],
         workspace: workspace.to_path_buf(),
         mounts: vec![],
         resource_limits: ResourceLimits {
-            timeout_secs: Some(params.timeout),
+            timeout_secs: Some(timeout.as_secs()),
             ..ResourceLimits::default()
         },
         network_policy: None, // use L1 default (Restricted)
-        env_vars: std::collections::HashMap::new(),
+        env_vars,
         format: OutputFormat::Json,
         backend: BackendPreference::Auto,
     };
@@ -118,17 +327,19 @@ fn execute_sandboxed(workspace: &Path, params: &BashParams) -> Result<ToolCallRe
         .run(&config)
         .map_err(|e| anyhow::anyhow!("sandbox execution failed: {e}"))?;
 
-    // Convert SandboxOutput → ToolCallResult.
+    // Convert SandboxOutput → ToolCallResult, applying the same per-stream cap.
+    let stdout = truncate_output(&output.stdout, params.max_output_bytes);
+    let stderr = truncate_output(&output.stderr, params.max_output_bytes);
     let mut text = String::new();
-    if !output.stdout.is_empty() {
-        text.push_str(&output.stdout);
+    if !stdout.is_empty() {
+        text.push_str(&stdout);
     }
-    if !output.stderr.is_empty() {
+    if !stderr.is_empty() {
         if !text.is_empty() {
             text.push('\n');
         }
         text.push_str("STDERR:\n");
-        text.push_str(&output.stderr);
+        text.push_str(&stderr);
     }
     if let Some(ref error_msg) = output.error {
         if !text.is_empty() {
@@ -153,33 +364,169 @@ fn execute_sandboxed(workspace: &Path, params: &BashParams) -> Result<ToolCallRe
     }
 
     Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text,
-        }],
+        content: vec![ContentItem::text(redaction.apply(&text).into_owned())],
         is_error: output.exit_code != 0,
     })
 }
 
+/// Cap `output` at `max_bytes` (on a char boundary), appending a notice if cut.
+#[cfg(feature = "sandbox")]
+fn truncate_output(output: &str, max_bytes: usize) -> std::borrow::Cow<'_, str> {
+    if output.len() <= max_bytes {
+        return std::borrow::Cow::Borrowed(output);
+    }
+    let mut end = max_bytes;
+    while end > 0 && !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    std::borrow::Cow::Owned(format!(
+        "{}\n... output truncated at {max_bytes} bytes",
+        &output[..end]
+    ))
+}
+
 // ---------------------------------------------------------------------------
 // Direct execution (no sandbox)
 // ---------------------------------------------------------------------------
 
+/// Which child pipe a chunk of output came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Read `pipe` to EOF on a background thread, forwarding chunks to `tx`.
+fn spawn_reader(
+    mut pipe: impl Read + Send + 'static,
+    stream: Stream,
+    tx: mpsc::Sender<(Stream, Vec<u8>)>,
+) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send((stream, buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Accumulates child output (up to `cap` bytes per stream) and forwards each
+/// kept chunk as progress, or only its size while redacting.
+struct OutputCollector<'a> {
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    cap: usize,
+    truncated: bool,
+    redaction: &'a Redaction<'a>,
+    progress: &'a mut Progress,
+}
+
+impl OutputCollector<'_> {
+    fn push(&mut self, stream: Stream, chunk: &[u8]) {
+        let buf = match stream {
+            Stream::Stdout => &self.stdout,
+            Stream::Stderr => &self.stderr,
+        };
+        let room = self.cap.saturating_sub(buf.len());
+        if chunk.len() > room {
+            self.truncated = true;
+        }
+        let chunk = &chunk[..chunk.len().min(room)];
+        if chunk.is_empty() {
+            return;
+        }
+
+        if self.progress.is_enabled() {
+            let text = if self.redaction.is_active() {
+                format!("({} bytes)", chunk.len())
+            } else {
+                String::from_utf8_lossy(chunk).into_owned()
+            };
+            let message = match stream {
+                Stream::Stdout => text,
+                Stream::Stderr => format!("STDERR: {text}"),
+            };
+            self.progress.report(chunk.len() as u64, &message);
+        }
+        match stream {
+            Stream::Stdout => self.stdout.extend_from_slice(chunk),
+            Stream::Stderr => self.stderr.extend_from_slice(chunk),
+        }
+    }
+}
+
 /// Direct execution without sandbox.
-fn execute_direct(workspace: &Path, params: &BashParams) -> Result<ToolCallResult> {
-    let mut child = Command::new("sh")
+///
+/// stdout/stderr are drained on reader threads while the child runs, so
+/// output is streamed via `progress` and large outputs cannot fill the pipe
+/// buffer and stall the child.
+fn execute_direct(
+    workspace: &Path,
+    params: &BashParams,
+    timeout: Duration,
+    redaction: &Redaction<'_>,
+    progress: &mut Progress,
+    cancel: &CancelToken,
+) -> Result<ToolCallResult> {
+    let mut cmd = Command::new("sh");
+    if params.clear_env {
+        cmd.env_clear();
+    }
+    let mut child = cmd
+        .envs(&params.env)
         .arg("-c")
         .arg(&params.command)
         .current_dir(workspace)
+        .stdin(if params.stdin.is_some() {
+            std::process::Stdio::piped()
+        } else {
+            // Never inherit the server's stdin: it carries the JSON-RPC stream.
+            std::process::Stdio::null()
+        })
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .spawn()
-        .with_context(|| format!("failed to spawn: {}", params.command))?;
+        .with_context(|| format!("failed to spawn: {}", redaction.apply(&params.command)))?;
+
+    // Feed stdin from its own thread so a child that writes a lot before
+    // reading cannot deadlock us on a full pipe. Dropping the pipe sends EOF.
+    let stdin_writer = match (child.stdin.take(), params.stdin.clone()) {
+        (Some(mut pipe), Some(data)) => Some(std::thread::spawn(move || {
+            let _ = std::io::Write::write_all(&mut pipe, data.as_bytes());
+        })),
+        _ => None,
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let mut readers = Vec::new();
+    if let Some(out) = child.stdout.take() {
+        readers.push(spawn_reader(out, Stream::Stdout, tx.clone()));
+    }
+    if let Some(err) = child.stderr.take() {
+        readers.push(spawn_reader(err, Stream::Stderr, tx.clone()));
+    }
+    drop(tx);
+
+    let mut collected = OutputCollector {
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+        cap: params.max_output_bytes,
+        truncated: false,
+        redaction,
+        progress,
+    };
 
-    let timeout = std::time::Duration::from_secs(params.timeout);
     let start = std::time::Instant::now();
+    let mut stopped = false;
 
-    // Poll with short sleeps until process exits or timeout.
+    // Poll until process exits or timeout, forwarding output as it arrives.
     let status = loop {
         match child.try_wait().context("failed to check process status")? {
             Some(status) => break status,
@@ -187,33 +534,67 @@ fn execute_direct(workspace: &Path, params: &BashParams) -> Result<ToolCallResul
                 // Kill the process on timeout.
                 let _ = child.kill();
                 let _ = child.wait();
-                return Ok(ToolCallResult {
-                    content: vec![ContentItem {
-                        content_type: "text".to_owned(),
-                        text: format!(
-                            "Command timed out after {}s: {}",
-                            params.timeout, params.command
-                        ),
-                    }],
-                    is_error: true,
-                });
+                return Ok(ToolCallResult::error(format!(
+                    "Command timed out after {}s: {}",
+                    timeout.as_secs(),
+                    redaction.apply(&params.command)
+                )));
+            }
+            None if cancel.is_cancelled() => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(ToolCallResult::error(format!(
+                    "Command cancelled: {}",
+                    redaction.apply(&params.command)
+                )));
+            }
+            None if collected.truncated => {
+                // Output cap exceeded: stop the command rather than buffer more.
+                let _ = child.kill();
+                stopped = true;
+                break child.wait().context("failed to reap killed process")?;
+            }
+            None => {
+                if let Ok((stream, chunk)) = rx.recv_timeout(Duration::from_millis(50)) {
+                    collected.push(stream, &chunk);
+                }
             }
-            None => std::thread::sleep(std::time::Duration::from_millis(50)),
         }
     };
 
-    let mut stdout_buf = Vec::new();
-    let mut stderr_buf = Vec::new();
-    if let Some(mut out) = child.stdout.take() {
-        std::io::Read::read_to_end(&mut out, &mut stdout_buf).ok();
+    // Wait for the pipes to close, unless we killed the child: grandchildren
+    // may still hold them open. Dropping `rx` makes the reader threads exit
+    // on their next chunk.
+    if !collected.truncated {
+        if let Some(writer) = stdin_writer {
+            let _ = writer.join();
+        }
+        for reader in readers {
+            let _ = reader.join();
+        }
     }
-    if let Some(mut err) = child.stderr.take() {
-        std::io::Read::read_to_end(&mut err, &mut stderr_buf).ok();
+    while let Ok((stream, chunk)) = rx.try_recv() {
+        collected.push(stream, &chunk);
     }
 
-    let stdout = String::from_utf8_lossy(&stdout_buf);
-    let stderr = String::from_utf8_lossy(&stderr_buf);
-    let exit_code = status.code().unwrap_or(-1);
+    Ok(format_direct_output(&collected, status, stopped, params))
+}
+
+/// Footer of a command stopped because its output reached the cap. The kill
+/// is ours, so it is not reported as the command's exit status.
+const OUTPUT_LIMIT_FOOTER: &str = "(output limit reached, process stopped)";
+
+/// Render captured output plus an exit-code footer, or
+/// [`OUTPUT_LIMIT_FOOTER`] if `stopped` at the output cap.
+fn format_direct_output(
+    collected: &OutputCollector<'_>,
+    status: std::process::ExitStatus,
+    stopped: bool,
+    params: &BashParams,
+) -> ToolCallResult {
+    let stdout = String::from_utf8_lossy(&collected.stdout);
+    let stderr = String::from_utf8_lossy(&collected.stderr);
+    let footer = if stopped { OUTPUT_LIMIT_FOOTER.to_owned() } else { exit_footer(status) };
 
     let mut text = String::new();
     if !stdout.is_empty() {
@@ -226,17 +607,104 @@ fn execute_direct(workspace: &Path, params: &BashParams) -> Result<ToolCallResul
         text.push_str("STDERR:\n");
         text.push_str(&stderr);
     }
+    if collected.truncated {
+        let _ = write!(
+            text,
+            "\n... output truncated at {} bytes",
+            params.max_output_bytes
+        );
+    }
     if text.is_empty() {
-        text = format!("(exit code: {exit_code})");
+        text = footer;
     } else {
-        let _ = write!(text, "\n(exit code: {exit_code})");
+        let _ = write!(text, "\n{footer}");
     }
 
-    Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text,
-        }],
-        is_error: exit_code != 0,
+    ToolCallResult {
+        content: vec![ContentItem::text(collected.redaction.apply(&text).into_owned())],
+        is_error: !stopped && !status.success(),
+    }
+}
+
+/// The command output of a bash result's text, without the trailing
+/// `(exit code: ...)` / `(killed by signal ...)` / [`OUTPUT_LIMIT_FOOTER`]
+/// footer line.
+pub(crate) fn strip_footer(text: &str) -> &str {
+    let (body, last) = text.rsplit_once('\n').unwrap_or(("", text));
+    if last.starts_with("(exit code: ")
+        || last.starts_with("(killed by signal ")
+        || last == OUTPUT_LIMIT_FOOTER
+    {
+        body
+    } else {
+        text
+    }
+}
+
+/// A bash result's text taken apart: the command's stdout, its `STDERR:`
+/// block if any, and whether the output reached the cap (so either stream
+/// may be cut short).
+pub(crate) struct OutputParts<'a> {
+    pub stdout: &'a str,
+    pub stderr: Option<&'a str>,
+    pub truncated: bool,
+}
+
+/// Split a bash result's text into [`OutputParts`]. The split is only
+/// unambiguous for commands whose stdout cannot contain a bare `STDERR:`
+/// line, such as git's diff and porcelain formats.
+pub(crate) fn split_output(text: &str) -> OutputParts<'_> {
+    let body = strip_footer(text);
+    let truncated = text.ends_with(OUTPUT_LIMIT_FOOTER)
+        || body
+            .lines()
+            .any(|line| line.starts_with("... output truncated at ") && line.ends_with(" bytes"));
+    let split = body
+        .strip_prefix("STDERR:\n")
+        .map(|stderr| ("", stderr))
+        .or_else(|| body.split_once("\nSTDERR:\n"));
+    let (stdout, stderr) = split.map_or((body, None), |(stdout, stderr)| (stdout, Some(stderr)));
+    OutputParts { stdout, stderr, truncated }
+}
+
+/// Describe how the process ended: `(exit code: N)`, or on Unix
+/// `(killed by signal 11 / SIGSEGV)` when terminated by a signal.
+fn exit_footer(status: std::process::ExitStatus) -> String {
+    if let Some(code) = status.code() {
+        return format!("(exit code: {code})");
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt as _;
+        if let Some(signal) = status.signal() {
+            return signal_name(signal).map_or_else(
+                || format!("(killed by signal {signal})"),
+                |name| format!("(killed by signal {signal} / {name})"),
+            );
+        }
+    }
+
+    "(exit code: -1)".to_owned()
+}
+
+/// Conventional name for common POSIX signals (Linux/macOS numbering agree
+/// for all of these).
+#[cfg(unix)]
+const fn signal_name(signal: i32) -> Option<&'static str> {
+    Some(match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        4 => "SIGILL",
+        5 => "SIGTRAP",
+        6 => "SIGABRT",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        _ => return None,
     })
 }
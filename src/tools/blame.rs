@@ -0,0 +1,263 @@
+//! Blame tool — who last changed each line of a file, and when.
+//!
+//! Runs `git blame --line-porcelain` through the `bash` execution path (so it
+//! is sandboxed like any other command) and condenses the porcelain output
+//! to one `line: sha author date` row per line. `startLine`/`endLine` limit
+//! the blame to a range, which keeps both the git run and the output small.
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::bash::BashOptions;
+use super::Workspace;
+use crate::server::{ContentItem, ToolCallResult, ToolContext, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BlameParams {
+    /// File to blame.
+    pub file_path: String,
+    /// First line (1-based) to blame. Default: 1.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    /// Last line to blame. Default: the end of the file.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+}
+
+/// Environment variable holding the file passed to `git blame`.
+const PATH_VAR: &str = "OA_CODER_BLAME_PATH";
+
+/// Environment variable holding the directory `git` runs in.
+const DIR_VAR: &str = "OA_CODER_BLAME_DIR";
+
+/// Environment variable holding the `-L` range.
+const RANGE_VAR: &str = "OA_CODER_BLAME_RANGE";
+
+/// Characters of the commit hash shown per line.
+const SHORT_SHA_LEN: usize = 8;
+
+/// Last-change information of one line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// 1-based line number in the current file.
+    pub line: usize,
+    /// Full commit hash (all zeros for uncommitted lines).
+    pub sha: String,
+    pub author: String,
+    /// Author time, seconds since the Unix epoch.
+    pub author_time: u64,
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "blame".to_owned(),
+        description: "Show who last changed each line of a file and when (`git blame`), one \
+            `line: sha author date` row per line. Use startLine/endLine to blame only part of a \
+            large file."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "File to blame"
+                },
+                "startLine": {
+                    "type": "integer",
+                    "description": "First line to blame, 1-based (default: 1)",
+                    "minimum": 1
+                },
+                "endLine": {
+                    "type": "integer",
+                    "description": "Last line to blame, inclusive (default: the end of the file)",
+                    "minimum": 1
+                }
+            },
+            "required": ["filePath"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Execute the blame tool. The command runs through the `bash` execution
+/// path with `options`.
+pub fn execute(
+    workspace: &Workspace,
+    options: &BashOptions<'_>,
+    arguments: serde_json::Value,
+    ctx: &mut ToolContext,
+) -> Result<ToolCallResult> {
+    let params: BlameParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_path(workspace, &params.file_path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+    if !path.is_file() {
+        return Ok(ToolCallResult::error(format!("Error: file not found: {}", path.display())));
+    }
+    let range = match line_range(params.start_line, params.end_line) {
+        Ok(range) => range,
+        Err(message) => return Ok(ToolCallResult::error(format!("Error: {message}"))),
+    };
+    if which::which("git").is_err() {
+        return Ok(ToolCallResult::error("Error: git is not installed (not found on PATH)"));
+    }
+    let dir = path.parent().unwrap_or_else(|| Path::new("/"));
+    if !dir.ancestors().any(|dir| dir.join(".git").exists()) {
+        return Ok(ToolCallResult::error(format!(
+            "Error: {} is not inside a git repository",
+            path.display()
+        )));
+    }
+
+    let range_arg = if range.is_some() { format!(" -L \"${RANGE_VAR}\"") } else { String::new() };
+    let run = super::bash::execute(
+        workspace,
+        options,
+        serde_json::json!({
+            "command": format!(
+                "git -C \"${DIR_VAR}\" --no-pager blame --line-porcelain{range_arg} -- \"${PATH_VAR}\""
+            ),
+            "env": {
+                DIR_VAR: dir.display().to_string(),
+                RANGE_VAR: range.unwrap_or_default(),
+                PATH_VAR: path.display().to_string(),
+            },
+        }),
+        ctx,
+    )?;
+    let output = run.content.first().map_or("", |item| item.text.as_str());
+    if run.is_error {
+        return Ok(ToolCallResult::error(format!("Error: git blame failed\n{output}")));
+    }
+
+    let parts = super::bash::split_output(output);
+    if parts.truncated {
+        return Ok(ToolCallResult::error(format!(
+            "Error: the blame of {} exceeds the output limit; narrow it with startLine/endLine",
+            path.display()
+        )));
+    }
+    let lines = parse_line_porcelain(parts.stdout);
+    let text = if lines.is_empty() {
+        format!("No lines to blame in {}", path.display())
+    } else {
+        lines.iter().map(format_line).collect::<Vec<_>>().join("\n")
+    };
+    let mut result = ToolCallResult::text(text);
+    if let Some(stderr) = parts.stderr {
+        result.content.push(ContentItem::text(format!("git stderr:\n{stderr}")));
+    }
+    Ok(result)
+}
+
+/// The `-L` argument for the requested lines: `start,end`, or `start,` for
+/// the rest of the file; `None` for the whole file.
+fn line_range(start: Option<usize>, end: Option<usize>) -> Result<Option<String>, String> {
+    if start.is_none() && end.is_none() {
+        return Ok(None);
+    }
+    let start = start.unwrap_or(1);
+    if start == 0 {
+        return Err("startLine is 1-based".to_owned());
+    }
+    match end {
+        Some(end) if end < start => Err(format!("endLine {end} is before startLine {start}")),
+        Some(end) => Ok(Some(format!("{start},{end}"))),
+        None => Ok(Some(format!("{start},"))),
+    }
+}
+
+/// Parse `git blame --line-porcelain` output: per line, a `<sha> <orig>
+/// <final> [<count>]` header, `key value` lines, then the line's text
+/// prefixed with a tab.
+#[must_use]
+pub fn parse_line_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut lines = Vec::new();
+    let mut current: Option<BlameLine> = None;
+    for row in output.lines() {
+        if row.starts_with('\t') {
+            lines.extend(current.take());
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            let mut fields = row.split(' ');
+            let (Some(sha), Some(_), Some(line)) = (fields.next(), fields.next(), fields.next()) else {
+                continue;
+            };
+            let Ok(line) = line.parse() else {
+                continue;
+            };
+            if sha.len() >= 40 {
+                current = Some(BlameLine {
+                    line,
+                    sha: sha.to_owned(),
+                    author: String::new(),
+                    author_time: 0,
+                });
+            }
+            continue;
+        };
+        if let Some(author) = row.strip_prefix("author ") {
+            author.clone_into(&mut entry.author);
+        } else if let Some(time) = row.strip_prefix("author-time ") {
+            entry.author_time = time.parse().unwrap_or(0);
+        }
+    }
+    lines
+}
+
+/// `12: 1a2b3c4d Jane Doe 2025-01-31`.
+fn format_line(line: &BlameLine) -> String {
+    let sha = line.sha.get(..SHORT_SHA_LEN).unwrap_or(&line.sha);
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(line.author_time);
+    let timestamp = crate::util::time::format_rfc3339(time);
+    let date = timestamp.get(..10).unwrap_or(&timestamp);
+    format!("{}: {sha} {} {date}", line.line, line.author)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line_porcelain() {
+        let sha = "1a2b3c4d5e6f708192a3b4c5d6e7f80910111213";
+        let output = format!(
+            "{sha} 1 4 2\n\
+             author Jane Doe\n\
+             author-mail <jane@example.com>\n\
+             author-time 1738326896\n\
+             author-tz +0000\n\
+             summary init\n\
+             filename a.txt\n\
+             \tfirst\n\
+             {sha} 2 5\n\
+             author Jane Doe\n\
+             author-time 1738326896\n\
+             summary init\n\
+             filename a.txt\n\
+             \tauthor not a header\n"
+        );
+        let lines = parse_line_porcelain(&output);
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].line, lines[1].line), (4, 5));
+        assert_eq!(lines[1].author, "Jane Doe");
+        assert_eq!(format_line(&lines[0]), "4: 1a2b3c4d Jane Doe 2025-01-31");
+    }
+
+    #[test]
+    fn test_line_range() {
+        assert_eq!(line_range(None, None), Ok(None));
+        assert_eq!(line_range(Some(3), None), Ok(Some("3,".to_owned())));
+        assert_eq!(line_range(None, Some(7)), Ok(Some("1,7".to_owned())));
+        assert_eq!(line_range(Some(3), Some(3)), Ok(Some("3,3".to_owned())));
+        assert!(line_range(Some(0), None).is_err());
+        assert!(line_range(Some(5), Some(4)).is_err());
+    }
+}
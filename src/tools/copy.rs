@@ -0,0 +1,195 @@
+//! Copy tool — duplicate a file (or, with `recursive`, a directory tree)
+//! inside the workspace.
+//!
+//! Bytes are copied with [`std::fs::copy`], which also carries over the
+//! permission bits on Unix. Symlinks inside a copied tree are skipped rather
+//! than followed, so a copy never pulls in content from outside the roots.
+//! The workspace's end-of-file newline policy is then applied to each copied
+//! UTF-8 text file; binary files and those over the size limit stay as they
+//! were.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+use crate::util::eof_newline::EofNewline;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CopyParams {
+    /// File or directory to copy.
+    pub source: String,
+    /// Path of the copy.
+    pub destination: String,
+    /// Replace an existing destination (default: false).
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Allow copying a directory, with everything below it.
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+/// Files and bytes copied so far, and the end-of-file newline notes of the
+/// files the policy changed.
+#[derive(Debug, Default)]
+struct Copied {
+    files: usize,
+    bytes: u64,
+    eof_notes: Vec<String>,
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "copy".to_owned(),
+        description: "Copy a file to a new path in the workspace, creating parent directories. \
+            Refuses to replace an existing destination unless overwrite is true, and to copy a \
+            directory unless recursive is true."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "source": {
+                    "type": "string",
+                    "description": "File (or directory, with recursive) to copy"
+                },
+                "destination": {
+                    "type": "string",
+                    "description": "Path of the copy"
+                },
+                "overwrite": {
+                    "type": "boolean",
+                    "description": "Replace the destination if it exists (default: false)",
+                    "default": false
+                },
+                "recursive": {
+                    "type": "boolean",
+                    "description": "Copy a directory and its contents; symlinks inside are skipped (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["source", "destination"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: CopyParams = super::parse_params(arguments, tool_definition)?;
+
+    let paths = super::validate_path(workspace, &params.source)
+        .and_then(|source| Ok((source, super::validate_path(workspace, &params.destination)?)));
+    let (source, destination) = match paths {
+        Ok(paths) => paths,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+
+    let Ok(meta) = std::fs::metadata(&source) else {
+        return Ok(ToolCallResult::error(format!("Error: file not found: {}", source.display())));
+    };
+    if meta.is_dir() && !params.recursive {
+        return Ok(ToolCallResult::error(format!(
+            "Error: {} is a directory (set recursive to copy it)",
+            source.display()
+        )));
+    }
+    if destination.exists() && !params.overwrite {
+        return Ok(ToolCallResult::error(format!(
+            "Error: destination already exists: {} (overwrite is false)",
+            destination.display()
+        )));
+    }
+    if destination == source || (meta.is_dir() && destination.starts_with(&source)) {
+        return Ok(ToolCallResult::error(format!(
+            "Error: cannot copy {} into itself",
+            source.display()
+        )));
+    }
+
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directories for {}", destination.display()))?;
+    }
+
+    let mut copied = Copied::default();
+    if meta.is_dir() {
+        copy_dir(workspace, &source, &destination, &mut copied)?;
+    } else {
+        copy_file(workspace, &source, &destination, &mut copied)?;
+    }
+
+    let files = if meta.is_dir() {
+        format!("{} files, ", copied.files)
+    } else {
+        String::new()
+    };
+    let eof = match copied.eof_notes.as_slice() {
+        [] => String::new(),
+        [note] if !meta.is_dir() => format!("; {note}"),
+        notes => format!("; final newline adjusted in {} files", notes.len()),
+    };
+    Ok(ToolCallResult::text(format!(
+        "Copied {} -> {} ({files}{} bytes{eof})",
+        source.display(),
+        destination.display(),
+        copied.bytes
+    )))
+}
+
+fn copy_file(
+    workspace: &Workspace,
+    source: &Path,
+    destination: &Path,
+    copied: &mut Copied,
+) -> Result<()> {
+    let mut bytes = std::fs::copy(source, destination).with_context(|| {
+        format!("failed to copy {} to {}", source.display(), destination.display())
+    })?;
+    let policy = workspace.eof_newline();
+    let text = if policy == EofNewline::Preserve {
+        None
+    } else {
+        super::read_text_file(workspace, destination).ok().flatten()
+    };
+    if let Some(content) = text {
+        let (updated, note) = policy.apply(&content);
+        if let Some(note) = note {
+            // In place, so the copied permission bits are kept.
+            std::fs::write(destination, updated.as_bytes())
+                .with_context(|| format!("failed to write {}", destination.display()))?;
+            bytes = updated.len() as u64;
+            copied.eof_notes.push(note);
+        }
+    }
+    copied.files += 1;
+    copied.bytes += bytes;
+    Ok(())
+}
+
+/// Copy the tree under `source` to `destination`, merging into it if it
+/// already exists. Symlinks are skipped.
+fn copy_dir(
+    workspace: &Workspace,
+    source: &Path,
+    destination: &Path,
+    copied: &mut Copied,
+) -> Result<()> {
+    std::fs::create_dir_all(destination)
+        .with_context(|| format!("failed to create {}", destination.display()))?;
+    let entries = std::fs::read_dir(source)
+        .with_context(|| format!("failed to read directory {}", source.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("failed to read directory {}", source.display()))?;
+        let file_type = entry.file_type()?;
+        let target = destination.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(workspace, &entry.path(), &target, copied)?;
+        } else if file_type.is_file() {
+            copy_file(workspace, &entry.path(), &target, copied)?;
+        }
+    }
+    Ok(())
+}
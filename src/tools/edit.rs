@@ -2,38 +2,175 @@
 //!
 //! Finds `old_string` in a file using progressively fuzzier matching
 //! strategies and replaces it with `new_string`. Returns a unified diff
-//! of the changes.
+//! of the changes (or, with `diffFormat: "json"`, the hunks as JSON), followed
+//! by a second content item holding the replaced byte ranges of the original
+//! file as JSON (`{"ranges":[{"start":..,"end":..}]}`). With `returnContent`
+//! a third item holds the whole new file (or, above
+//! [`MAX_RETURN_CONTENT_BYTES`], a note that it was left out).
+//!
+//! With `startLine`/`endLine` the matching is skipped and exactly those lines
+//! are replaced, after an optional `expectedText` check against drift.
+//!
+//! Files over the workspace's size limit (see
+//! [`Workspace::max_file_bytes`]) are refused with
+//! [`CoderError::FileTooLarge`] before being read.
+//!
+//! With `verbose`, a match names the layer that made it and a failure lists
+//! what each layer found (see [`crate::edit::layer_summary`]).
+//!
+//! With `rejectWhitespaceOnly`, an edit whose result differs from the
+//! original only in whitespace (e.g. an accidental reindent by a fuzzy
+//! match) is refused without writing.
+//!
+//! With `normalizeUnicode`, smart quotes, dashes and non-breaking spaces
+//! are folded to ASCII on both sides before matching, so a find string
+//! with typographic punctuation still matches plain source (see
+//! [`crate::edit::replacers::unicode_normalized_replacer`]).
+//!
+//! The workspace's [`EofNewline`](crate::util::eof_newline::EofNewline)
+//! policy is applied to the new content before writing, and the report
+//! notes any newline it added or removed.
 //!
 //! Port of OpenAcosmi's `edit.ts` 9-layer replacer chain.
 
+use std::fmt::Write as _;
 use std::path::Path;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 
+use crate::edit::Replacement;
+use crate::error::CoderError;
+use super::Workspace;
 use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
 
 /// Parameters for the edit tool.
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct EditParams {
     /// Path to the file to edit (relative to workspace or absolute).
     pub file_path: String,
-    /// The text to find and replace.
-    pub old_string: String,
+    /// The text to find and replace. Required unless `start_line` is given.
+    #[serde(default)]
+    pub old_string: Option<String>,
     /// The replacement text.
     pub new_string: String,
     /// Replace all occurrences (default: false, replace first match only).
     #[serde(default)]
     pub replace_all: bool,
+    /// Only accept exact matches of `old_string` that are not part of a
+    /// longer word (fuzzy layers are unaffected).
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Only accept exact matches of `old_string` that span whole lines.
+    #[serde(default)]
+    pub whole_line: bool,
+    /// Share (0.0–1.0) of middle lines the context-aware layer requires to
+    /// match exactly (default: 0.5).
+    #[serde(default)]
+    pub context_match_ratio: Option<f64>,
+    /// Modification time reported by `read`; the edit is refused if the
+    /// file has changed since.
+    #[serde(default)]
+    pub expected_mtime: Option<String>,
+    /// Replace the whole file with `new_string` (no matching), still
+    /// returning a diff. Only creates a new file if `old_string` is empty.
+    #[serde(default)]
+    pub full_rewrite: bool,
+    /// Copy the original file to `<path>.bak` before writing.
+    #[serde(default)]
+    pub backup: bool,
+    /// Replace lines `start_line..=end_line` (1-based) with `new_string`
+    /// instead of searching for `old_string`.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    /// Last line replaced in line-range mode (default: `start_line`).
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    /// Line-range mode: the current text of the lines, checked before
+    /// editing so a stale range is refused.
+    #[serde(default)]
+    pub expected_text: Option<String>,
+    /// How the change is reported.
+    #[serde(default)]
+    pub diff_format: DiffFormat,
+    /// Line diff algorithm for the reported change.
+    #[serde(default)]
+    pub diff_algorithm: DiffAlgorithm,
+    /// Unchanged lines shown around each hunk of the reported change.
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+    /// Report which matching layer applied the edit, or on failure what
+    /// each layer found.
+    #[serde(default)]
+    pub verbose: bool,
+    /// Strip trailing whitespace and collapse long runs of blank lines in
+    /// the edited file before writing (and diffing) it.
+    #[serde(default)]
+    pub tidy: bool,
+    /// With `tidy`, the most consecutive blank lines kept.
+    #[serde(default = "default_max_blank_lines")]
+    pub max_blank_lines: usize,
+    /// Also return the complete new file content.
+    #[serde(default)]
+    pub return_content: bool,
+    /// Refuse the edit if it changes nothing but whitespace.
+    #[serde(default)]
+    pub reject_whitespace_only: bool,
+    /// Also match with unicode lookalikes (smart quotes, dashes, NBSP)
+    /// folded to ASCII in both the file and `old_string`.
+    #[serde(default)]
+    pub normalize_unicode: bool,
+}
+
+const fn default_max_blank_lines() -> usize { crate::util::tidy::DEFAULT_MAX_BLANK_LINES }
+
+const fn default_context_lines() -> usize { crate::edit::diff::DEFAULT_CONTEXT_LINES }
+
+/// Largest file `returnContent` sends back in full.
+pub const MAX_RETURN_CONTENT_BYTES: usize = 1024 * 1024;
+
+/// Format of the diff returned by a successful edit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffFormat {
+    /// Unified diff text followed by a `(+N -M, K hunks)` summary.
+    #[default]
+    Unified,
+    /// `{"file", "insertions", "deletions", "hunks"}`, see
+    /// [`crate::edit::diff::diff_json`].
+    Json,
+}
+
+/// Line diff algorithm used for the returned diff.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffAlgorithm {
+    /// Keeps source structure readable (unique lines anchor the diff).
+    #[default]
+    Patience,
+    /// Fewest changed lines; often smaller for large refactors.
+    Myers,
+}
+
+impl DiffAlgorithm {
+    const fn to_similar(self) -> similar::Algorithm {
+        match self {
+            Self::Patience => similar::Algorithm::Patience,
+            Self::Myers => similar::Algorithm::Myers,
+        }
+    }
 }
 
 /// Return the MCP tool definition for `edit`.
+#[allow(clippy::too_many_lines)] // One flat JSON schema.
 pub fn tool_definition() -> ToolDefinition {
     ToolDefinition {
         name: "edit".to_owned(),
         description: "Edit a file by replacing old_string with new_string using 9-layer fuzzy matching. \
-            If old_string is empty and the file doesn't exist, creates a new file with new_string as content."
+            If old_string is empty and the file doesn't exist, creates a new file with new_string as content. \
+            With startLine (and endLine), replaces exactly those lines instead, without any matching."
             .to_owned(),
         input_schema: serde_json::json!({
             "type": "object",
@@ -54,9 +191,121 @@ pub fn tool_definition() -> ToolDefinition {
                     "type": "boolean",
                     "description": "Replace all occurrences (default: false)",
                     "default": false
+                },
+                "wholeWord": {
+                    "type": "boolean",
+                    "description": "Exact matches of oldString must not sit inside a longer word, e.g. `id` \
+                        does not match in `width` (default: false)",
+                    "default": false
+                },
+                "wholeLine": {
+                    "type": "boolean",
+                    "description": "Exact matches of oldString must start at a line start and end at a line end \
+                        (default: false)",
+                    "default": false
+                },
+                "contextMatchRatio": {
+                    "type": "number",
+                    "description": "Share of a multi-line oldString's middle lines that must match exactly for the \
+                        context-aware fuzzy layer, which anchors on the first and last lines. Lower it for large \
+                        blocks with a few intended differences, raise it for small ones (default: 0.5)",
+                    "minimum": 0,
+                    "maximum": 1,
+                    "default": 0.5
+                },
+                "expectedMtime": {
+                    "type": "string",
+                    "description": "The file's mtime as reported by read; refuse the edit if the file changed since"
+                },
+                "fullRewrite": {
+                    "type": "boolean",
+                    "description": "Replace the entire file with newString, ignoring oldString, and return a diff \
+                        (default: false). Creating a new file still requires an empty oldString.",
+                    "default": false
+                },
+                "backup": {
+                    "type": "boolean",
+                    "description": "Copy the original file to <path>.bak before editing (default: false)",
+                    "default": false
+                },
+                "startLine": {
+                    "type": "integer",
+                    "description": "Replace lines startLine..=endLine (1-based) with newString instead of \
+                        matching oldString, which is then not needed. An empty newString deletes the lines.",
+                    "minimum": 1
+                },
+                "endLine": {
+                    "type": "integer",
+                    "description": "Last line replaced with startLine, inclusive (default: startLine)",
+                    "minimum": 1
+                },
+                "expectedText": {
+                    "type": "string",
+                    "description": "With startLine: the lines' current text; the edit is refused if it differs"
+                },
+                "diffFormat": {
+                    "type": "string",
+                    "enum": ["unified", "json"],
+                    "description": "Return the change as a unified diff (default) or as JSON hunks \
+                        ({oldStart, oldLines, newStart, newLines, lines: [{tag, text}]})",
+                    "default": "unified"
+                },
+                "diffAlgorithm": {
+                    "type": "string",
+                    "enum": ["patience", "myers"],
+                    "description": "Diff algorithm for the returned change: patience (default) keeps code \
+                        structure readable, myers minimises changed lines and can be smaller for large refactors",
+                    "default": "patience"
+                },
+                "contextLines": {
+                    "type": "integer",
+                    "description": "Unchanged lines shown around each hunk of the returned diff; 0 gives a \
+                        compact patch (default: 3)",
+                    "minimum": 0,
+                    "default": 3
+                },
+                "tidy": {
+                    "type": "boolean",
+                    "description": "After editing, strip trailing whitespace from every line of the file and \
+                        collapse runs of blank lines longer than maxBlankLines; the diff shows the tidied result \
+                        (default: false)",
+                    "default": false
+                },
+                "maxBlankLines": {
+                    "type": "integer",
+                    "description": "With tidy, the most consecutive blank lines kept (default: 2)",
+                    "minimum": 0,
+                    "default": 2
+                },
+                "returnContent": {
+                    "type": "boolean",
+                    "description": "Also return the complete new file content as a third content item, to \
+                        mirror the file without a follow-up read; files over 1 MiB get a note instead \
+                        (default: false)",
+                    "default": false
+                },
+                "normalizeUnicode": {
+                    "type": "boolean",
+                    "description": "If oldString does not match as given, retry with smart quotes, en/em \
+                        dashes and non-breaking spaces folded to ASCII in both oldString and the file; the \
+                        file's own text is what gets replaced (default: false)",
+                    "default": false
+                },
+                "rejectWhitespaceOnly": {
+                    "type": "boolean",
+                    "description": "Fail without writing if the edit changes only whitespace, e.g. \
+                        reindentation; checked after tidy (default: false)",
+                    "default": false
+                },
+                "verbose": {
+                    "type": "boolean",
+                    "description": "Name the matching layer that applied the edit; if none matched, summarise \
+                        what each layer found, e.g. ambiguous candidates (default: false)",
+                    "default": false
                 }
             },
-            "required": ["filePath", "oldString", "newString"]
+            "required": ["filePath", "newString"],
+            "additionalProperties": false
         }),
     }
 }
@@ -66,89 +315,399 @@ pub fn tool_definition() -> ToolDefinition {
 /// # Errors
 ///
 /// Returns an error if the file cannot be read/written or no match is found.
-pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCallResult> {
-    let params: EditParams =
-        serde_json::from_value(arguments).context("invalid edit parameters")?;
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: EditParams = super::parse_params(arguments, tool_definition)?;
 
     let file_path = match super::validate_path(workspace, &params.file_path) {
         Ok(p) => p,
-        Err(e) => {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!("Error: {e}"),
-                }],
-                is_error: true,
-            });
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+
+    if params.context_match_ratio.is_some_and(|ratio| !(0.0..=1.0).contains(&ratio)) {
+        return Ok(ToolCallResult::error("Error: contextMatchRatio must be between 0 and 1"));
+    }
+    if let Some(message) = line_mode_conflict(&params) {
+        return Ok(ToolCallResult::error(format!("Error: {message}")));
+    }
+    let old_string = params.old_string.as_deref().unwrap_or_default();
+
+    // Empty old_string: create new file or reject if file already exists
+    // (unless rewriting it wholesale).
+    let rewrite_existing = params.full_rewrite && file_path.exists();
+    if params.start_line.is_none() && old_string.is_empty() && !rewrite_existing {
+        return create_file(workspace, &file_path, &params);
+    }
+
+    workspace.check_file_size(&file_path)?;
+    let original = match std::fs::read_to_string(&file_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(CoderError::FileNotFound { path: file_path }.into());
         }
+        Err(e) => return Err(CoderError::Io { path: file_path, source: e }.into()),
     };
 
-    // Empty old_string: create new file or reject if file already exists.
-    if params.old_string.is_empty() {
-        if file_path.exists() {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!(
-                        "Error: old_string cannot be empty for existing file {}. \
-                         Provide the text to find and replace, or delete the file first to recreate it.",
-                        file_path.display()
-                    ),
-                }],
-                is_error: true,
-            });
+    // Delegate to the edit engine (Phase 2 will implement full 9-layer chain).
+    let mut layers = Vec::new();
+    let result = if let Some(start_line) = params.start_line {
+        let end_line = params.end_line.unwrap_or(start_line);
+        let expected = params.expected_text.as_deref();
+        match replace_lines(&original, start_line, end_line, &params.new_string, expected) {
+            Ok(replacement) => Some(replacement),
+            Err(message) => {
+                return Ok(ToolCallResult::error(format!(
+                    "Error: {}: {message}",
+                    file_path.display()
+                )));
+            }
         }
+    } else if params.full_rewrite {
+        Some(Replacement {
+            content: params.new_string.clone(),
+            ranges: vec![(0, original.len())],
+        })
+    } else {
+        let (result, reports) = find_and_replace(&original, old_string, &params);
+        layers = reports;
+        result
+    };
 
-        if let Some(parent) = file_path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create directories for {}", file_path.display()))?;
+    let Some(Replacement { content: mut new_content, ranges }) = result else {
+        return Ok(no_match_result(&file_path, &original, old_string, &layers));
+    };
+    if params.tidy {
+        let (tidied, report) = crate::util::tidy::tidy(&new_content, params.max_blank_lines);
+        if !report.is_empty() {
+            new_content = tidied.into_owned();
         }
-        crate::util::atomic::atomic_write(&file_path, &params.new_string)?;
+    }
+    let (updated, eof_note) = workspace.eof_newline().apply(&new_content);
+    if eof_note.is_some() {
+        new_content = updated.into_owned();
+    }
 
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: format!("Created new file: {}", file_path.display()),
-            }],
-            is_error: false,
-        });
+    if params.reject_whitespace_only
+        && new_content != original
+        && is_whitespace_only_change(&original, &new_content)
+    {
+        return Ok(ToolCallResult::error(format!(
+            "Error: edit to {} changes only whitespace (rejectWhitespaceOnly); file not written",
+            file_path.display()
+        )));
     }
 
-    let original = std::fs::read_to_string(&file_path)
-        .with_context(|| format!("failed to read {}", file_path.display()))?;
+    if let Some(expected) = &params.expected_mtime {
+        check_mtime(&file_path, expected)?;
+    }
+    let backup = super::write_with_backup(workspace, &file_path, &new_content, params.backup)?;
+    let diff_options = crate::edit::diff::DiffOptions {
+        algorithm: params.diff_algorithm.to_similar(),
+        context_lines: params.context_lines,
+    };
+    let notes = ReportNotes {
+        backup: backup.as_deref(),
+        layer: layers.last().map(|report| report.layer),
+        eof_newline: eof_note.as_deref(),
+    };
+    let report = diff_report(
+        &file_path,
+        &original,
+        &new_content,
+        params.diff_format,
+        diff_options,
+        &notes,
+    );
+    let mut result = success_result(report, ranges);
+    if params.return_content {
+        result.content.push(content_item(&new_content));
+    }
+    Ok(result)
+}
 
-    // Delegate to the edit engine (Phase 2 will implement full 9-layer chain).
-    let result = crate::edit::replace(&original, &params.old_string, &params.new_string, params.replace_all);
+/// Create `file_path` (and its parent directories) holding `newString`;
+/// an existing file is left alone.
+fn create_file(workspace: &Workspace, file_path: &Path, params: &EditParams) -> Result<ToolCallResult> {
+    if file_path.exists() {
+        return Ok(ToolCallResult::error(format!(
+            "Error: old_string cannot be empty for existing file {}. \
+             Provide the text to find and replace, or delete the file first to recreate it.",
+            file_path.display()
+        )));
+    }
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directories for {}", file_path.display()))?;
+    }
+    let content = if params.tidy {
+        crate::util::tidy::tidy(&params.new_string, params.max_blank_lines).0
+    } else {
+        params.new_string.as_str().into()
+    };
+    let (content, eof_note) = workspace.eof_newline().apply(&content);
+    crate::util::atomic::atomic_write(file_path, &content)?;
 
-    match result {
-        Some(new_content) => {
-            // Generate diff before writing.
-            let diff = crate::edit::diff::unified_diff(
+    let eof_note = eof_note.map_or_else(String::new, |note| format!(" ({note})"));
+    Ok(ToolCallResult::text(format!(
+        "Created new file: {}{eof_note}",
+        file_path.display()
+    )))
+}
+
+/// Replace `old_string` in `original` through the fuzzy matcher chain, with
+/// the matching options from `params`. The per-layer reports are only
+/// collected with `verbose`.
+fn find_and_replace(
+    original: &str,
+    old_string: &str,
+    params: &EditParams,
+) -> (Option<Replacement>, Vec<crate::edit::LayerReport>) {
+    let options = crate::edit::MatchOptions {
+        whole_word: params.whole_word,
+        whole_line: params.whole_line,
+        context_match_ratio: params
+            .context_match_ratio
+            .unwrap_or(crate::edit::replacers::DEFAULT_CONTEXT_MATCH_RATIO),
+        normalize_unicode: params.normalize_unicode,
+    };
+    if params.verbose {
+        return crate::edit::replace_with_diagnostics(
+            original,
+            old_string,
+            &params.new_string,
+            params.replace_all,
+            options,
+        );
+    }
+    let result =
+        crate::edit::replace_with_options(original, old_string, &params.new_string, params.replace_all, options);
+    (result, Vec::new())
+}
+
+/// Whether `original` and `new_content` are equal once all whitespace is
+/// removed.
+fn is_whitespace_only_change(original: &str, new_content: &str) -> bool {
+    let significant = |text: &str| text.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+    significant(original) == significant(new_content)
+}
+
+/// The new file content for `returnContent`, or a note if it is too large.
+fn content_item(new_content: &str) -> ContentItem {
+    if new_content.len() > MAX_RETURN_CONTENT_BYTES {
+        return ContentItem::text(format!(
+            "Warning: new content not returned: {} bytes exceeds the {MAX_RETURN_CONTENT_BYTES}-byte \
+             limit; use read instead",
+            new_content.len()
+        ));
+    }
+    ContentItem::text(new_content.to_owned())
+}
+
+/// What a diff report mentions besides the change itself.
+struct ReportNotes<'a> {
+    /// Where the original was backed up to.
+    backup: Option<&'a Path>,
+    /// The matching layer that made the edit, if known.
+    layer: Option<&'a str>,
+    /// How the end-of-file newline policy changed the content.
+    eof_newline: Option<&'a str>,
+}
+
+/// Report the change from `original` to `new_content` in `format`, with
+/// `notes`.
+fn diff_report(
+    file_path: &Path,
+    original: &str,
+    new_content: &str,
+    format: DiffFormat,
+    options: crate::edit::diff::DiffOptions,
+    notes: &ReportNotes<'_>,
+) -> String {
+    match format {
+        DiffFormat::Unified => {
+            let mut diff = crate::edit::diff::unified_diff_with_options(
                 &file_path.display().to_string(),
-                &original,
-                &new_content,
+                original,
+                new_content,
+                options,
             );
+            diff.push_str(&crate::edit::diff::diff_summary_with_options(original, new_content, options));
+            if let Some(path) = notes.backup {
+                let _ = write!(diff, "\nBackup: {}", path.display());
+            }
+            if let Some(layer) = notes.layer {
+                let _ = write!(diff, "\nMatched by {layer}");
+            }
+            if let Some(note) = notes.eof_newline {
+                let _ = write!(diff, "\nEnd of file: {note}");
+            }
+            diff
+        }
+        DiffFormat::Json => {
+            let (insertions, deletions, _) = crate::edit::diff::diff_stats(original, new_content, options);
+            let mut report = serde_json::json!({
+                "file": file_path.display().to_string(),
+                "insertions": insertions,
+                "deletions": deletions,
+                "hunks": crate::edit::diff::diff_json(original, new_content, options),
+            });
+            if let Some(path) = notes.backup {
+                report["backup"] = path.display().to_string().into();
+            }
+            if let Some(layer) = notes.layer {
+                report["layer"] = layer.into();
+            }
+            if let Some(note) = notes.eof_newline {
+                report["eofNewline"] = note.into();
+            }
+            report.to_string()
+        }
+    }
+}
 
-            crate::util::atomic::atomic_write(&file_path, &new_content)?;
+/// Why the parameters cannot be combined, if they cannot: line-range mode
+/// excludes the matching options, and plain mode needs `old_string`.
+const fn line_mode_conflict(params: &EditParams) -> Option<&'static str> {
+    if params.start_line.is_none() {
+        if params.end_line.is_some() || params.expected_text.is_some() {
+            return Some("endLine and expectedText require startLine");
+        }
+        if params.old_string.is_none() {
+            return Some("oldString is required unless startLine is given");
+        }
+    } else if params.replace_all
+        || params.full_rewrite
+        || params.whole_word
+        || params.whole_line
+        || params.context_match_ratio.is_some()
+        || params.normalize_unicode
+    {
+        return Some(
+            "startLine cannot be combined with replaceAll, fullRewrite, wholeWord, wholeLine, contextMatchRatio \
+             or normalizeUnicode",
+        );
+    }
+    None
+}
 
-            Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: diff,
-                }],
-                is_error: false,
-            })
+/// Replace lines `start..=end` (1-based) of `content` with `new`, line
+/// breaks included, so an empty `new` deletes them. A line break is added
+/// to a non-empty `new` lacking one when the replaced lines had one.
+/// `expected`, if given, must equal the lines' current text (a trailing line
+/// break on either side is ignored).
+fn replace_lines(
+    content: &str,
+    start: usize,
+    end: usize,
+    new: &str,
+    expected: Option<&str>,
+) -> std::result::Result<Replacement, String> {
+    if start == 0 || end < start {
+        return Err(format!("invalid line range {start}-{end}"));
+    }
+    let (mut from, mut to) = (None, None);
+    let mut pos = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if i + 1 == start {
+            from = Some(pos);
+        }
+        pos += line.len();
+        if i + 1 == end {
+            to = Some(pos);
+            break;
         }
-        None => Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: format!(
-                    "Error: no match found for the provided old_string in {}",
-                    file_path.display()
-                ),
-            }],
-            is_error: true,
-        }),
     }
+    let (Some(from), Some(to)) = (from, to) else {
+        return Err(format!(
+            "line range {start}-{end} is past the end of the file ({} lines)",
+            content.lines().count()
+        ));
+    };
+
+    let old = &content[from..to];
+    let trim_break = |s: &str| {
+        s.strip_suffix('\n')
+            .map_or(s, |s| s.strip_suffix('\r').unwrap_or(s))
+            .to_owned()
+    };
+    if let Some(expected) = expected {
+        if trim_break(old) != trim_break(expected) {
+            return Err(format!(
+                "lines {start}-{end} do not match expectedText; they currently read:\n{}",
+                trim_break(old)
+            ));
+        }
+    }
+
+    let mut result = String::with_capacity(content.len() + new.len());
+    result.push_str(&content[..from]);
+    result.push_str(new);
+    if !new.is_empty() && !new.ends_with('\n') && old.ends_with('\n') {
+        result.push_str(if old.ends_with("\r\n") { "\r\n" } else { "\n" });
+    }
+    result.push_str(&content[to..]);
+    Ok(Replacement {
+        content: result,
+        ranges: vec![(from, to)],
+    })
 }
 
+/// Error result for an `old_string` that matched nothing, with what each
+/// layer found (if `layers` were recorded) and the most similar block of the
+/// file as a hint when there is a close one.
+fn no_match_result(
+    file_path: &Path,
+    original: &str,
+    old_string: &str,
+    layers: &[crate::edit::LayerReport],
+) -> ToolCallResult {
+    let mut text = format!(
+        "Error: no match found for the provided old_string in {}",
+        file_path.display()
+    );
+    if !layers.is_empty() {
+        let _ = write!(text, "\n\nLayers: {}.", crate::edit::layer_summary(layers));
+    }
+    if let Some((line, block, score)) = crate::edit::closest_match(original, old_string) {
+        let _ = write!(
+            text,
+            "\n\nDid you mean this block at line {line} ({:.0}% similar)?\n{block}",
+            score * 100.0
+        );
+    }
+    ToolCallResult::error(text)
+}
+
+/// Build the success result: the diff report (see [`DiffFormat`]), then the
+/// replaced byte ranges as JSON.
+fn success_result(text: String, ranges: Vec<(usize, usize)>) -> ToolCallResult {
+    let ranges: Vec<serde_json::Value> = ranges
+        .into_iter()
+        .map(|(start, end)| serde_json::json!({ "start": start, "end": end }))
+        .collect();
+
+    ToolCallResult {
+        content: vec![
+            ContentItem::text(text),
+            ContentItem::text(serde_json::json!({ "ranges": ranges }).to_string()),
+        ],
+        is_error: false,
+    }
+}
+
+/// Fail with [`CoderError::FileModified`] if the file's current mtime differs
+/// from the token the caller saw when it read the file.
+fn check_mtime(file_path: &Path, expected: &str) -> Result<()> {
+    let actual = crate::util::time::mtime_token(file_path).map_err(|source| CoderError::Io {
+        path: file_path.to_path_buf(),
+        source,
+    })?;
+    if actual != expected {
+        return Err(CoderError::FileModified {
+            path: file_path.to_path_buf(),
+            expected: expected.to_owned(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
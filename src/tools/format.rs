@@ -0,0 +1,205 @@
+//! Format tool — run a code formatter on one file and return the diff.
+//!
+//! The formatter runs through the `bash` execution path — and so inside the
+//! sandbox when enabled. It gets the file's content on stdin and writes the
+//! formatted result to stdout; no file on disk is formatted, so formatters
+//! that follow imports (rustfmt and `mod foo;`) cannot touch other files. The
+//! file's own path is passed in `$OA_CODER_FORMAT_FILE` for formatters that
+//! pick the language or project config from it. The result, with the
+//! workspace's end-of-file newline policy applied, is written back
+//! atomically; if the formatter is missing, exits non-zero or prints nothing,
+//! the file is not touched.
+//!
+//! Since `formatter` is an arbitrary command, `format` is only available
+//! while `bash` is (see [`ToolFilter`](super::ToolFilter)).
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::bash::BashOptions;
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolContext, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FormatParams {
+    /// File to format.
+    pub file_path: String,
+    /// Formatter command, reading the source on stdin and writing the
+    /// result to stdout. Default: inferred from the file extension.
+    #[serde(default)]
+    pub formatter: Option<String>,
+}
+
+/// Environment variable holding the path of the file being formatted.
+const FILE_VAR: &str = "OA_CODER_FORMAT_FILE";
+
+/// Environment variable holding the temp file the formatter's stdout goes to.
+const OUT_VAR: &str = "OA_CODER_FORMAT_OUT";
+
+/// Built-in formatters by file extension. Each reads the source on stdin and
+/// writes the formatted result to stdout.
+const FORMATTERS: &[(&[&str], &str)] = &[
+    (&["rs"], "rustfmt --edition 2021 --emit stdout"),
+    (&["go"], "gofmt"),
+    (&["py", "pyi"], "black -q --stdin-filename \"$OA_CODER_FORMAT_FILE\" -"),
+    (
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx", "json", "css", "scss", "html", "md", "yaml", "yml"],
+        "prettier --log-level warn --stdin-filepath \"$OA_CODER_FORMAT_FILE\"",
+    ),
+    (
+        &["c", "h", "cc", "cpp", "hpp", "cxx"],
+        "clang-format --assume-filename=\"$OA_CODER_FORMAT_FILE\"",
+    ),
+    (&["sh", "bash"], "shfmt --filename \"$OA_CODER_FORMAT_FILE\""),
+    (&["toml"], "taplo fmt -"),
+];
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "format".to_owned(),
+        description: "Run a code formatter on a file, write the result back and return a unified \
+            diff. The formatter reads the file on stdin and writes the result to stdout. Without \
+            `formatter`, one is picked from the extension (rustfmt, gofmt, black, prettier, \
+            clang-format, shfmt, taplo). The file is left unchanged if the formatter is not \
+            installed or fails."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "File to format"
+                },
+                "formatter": {
+                    "type": "string",
+                    "description": "Formatter command that reads the source on stdin and writes the formatted result to stdout, e.g. \"rustfmt --edition 2021\"; the file's path is in $OA_CODER_FORMAT_FILE (default: inferred from the extension)"
+                }
+            },
+            "required": ["filePath"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Execute the format tool. The command runs through the `bash`
+/// execution path with `options`, which also handles cancellation.
+pub fn execute(
+    workspace: &Workspace,
+    options: &BashOptions<'_>,
+    arguments: serde_json::Value,
+    ctx: &mut ToolContext,
+) -> Result<ToolCallResult> {
+    let params: FormatParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_path(workspace, &params.file_path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+    let original = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    let formatter = match params.formatter.as_deref().map(str::trim) {
+        Some("") => return Ok(ToolCallResult::error("Error: formatter must not be empty")),
+        Some(command) => command.to_owned(),
+        None => match infer_formatter(&path) {
+            Some(command) => command.to_owned(),
+            None => {
+                return Ok(ToolCallResult::error(format!(
+                    "Error: no built-in formatter for {}; pass `formatter`",
+                    path.display()
+                )));
+            }
+        },
+    };
+    if let Some(program) = formatter.split_whitespace().next() {
+        if which::which(program).is_err() {
+            return Ok(ToolCallResult::error(format!(
+                "Error: formatter `{program}` is not installed (not found on PATH)"
+            )));
+        }
+    }
+
+    // stdout goes to a temp file rather than through the bash result, which
+    // is truncated and redacted. It lives in the primary root, where the
+    // sandbox may write.
+    let out = tempfile::Builder::new()
+        .prefix(".oa-coder-format-")
+        .suffix(".out")
+        .tempfile_in(&**workspace)
+        .with_context(|| format!("failed to create temp file in {}", workspace.display()))?;
+
+    let run = super::bash::execute(
+        workspace,
+        options,
+        serde_json::json!({
+            "command": format!("{{\n{formatter}\n}} > \"${OUT_VAR}\""),
+            "env": {
+                FILE_VAR: path.display().to_string(),
+                OUT_VAR: out.path().display().to_string(),
+            },
+            "stdin": original,
+        }),
+        ctx,
+    )?;
+    if run.is_error {
+        let mut text = format!("Error: formatter `{formatter}` failed; {} is unchanged", path.display());
+        for item in &run.content {
+            let _ = write!(text, "\n{}", item.text);
+        }
+        return Ok(ToolCallResult::error(text));
+    }
+
+    let printed = std::fs::read_to_string(out.path())
+        .with_context(|| format!("failed to read formatter output {}", out.path().display()))?;
+    if printed.is_empty() && !original.is_empty() {
+        return Ok(ToolCallResult::error(format!(
+            "Error: formatter `{formatter}` printed nothing; it must write the formatted source \
+             to stdout. {} is unchanged",
+            path.display()
+        )));
+    }
+    let (output, _) = workspace.eof_newline().apply(&printed);
+    if output == original {
+        return Ok(ToolCallResult::text(format!("Already formatted: {}", path.display())));
+    }
+    crate::util::atomic::atomic_write(&path, &output)?;
+
+    let name = path.display().to_string();
+    let mut diff = crate::edit::diff::unified_diff(&name, &original, &output);
+    diff.push_str(&crate::edit::diff::diff_summary(&original, &output));
+    Ok(ToolCallResult::text(diff))
+}
+
+/// Programs behind the built-in formatters, in table order.
+pub(crate) fn formatter_programs() -> impl Iterator<Item = &'static str> {
+    FORMATTERS
+        .iter()
+        .filter_map(|(_, command)| command.split_whitespace().next())
+}
+
+/// Pick a built-in formatter from the file extension (case-insensitive).
+fn infer_formatter(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    FORMATTERS
+        .iter()
+        .find(|(exts, _)| exts.contains(&ext.as_str()))
+        .map(|(_, command)| *command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_formatter() {
+        assert_eq!(infer_formatter(Path::new("src/main.rs")), Some("rustfmt --edition 2021 --emit stdout"));
+        assert_eq!(infer_formatter(Path::new("main.GO")), Some("gofmt"));
+        assert_eq!(infer_formatter(Path::new("notes.txt")), None);
+        assert_eq!(infer_formatter(Path::new("Makefile")), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Git diff tool — uncommitted (or staged) changes in the workspace.
+//!
+//! A thin wrapper over `git diff` that runs through the `bash` execution
+//! path, so it is sandboxed like any other command. Without `filePath` the
+//! diff is limited to the workspace directory, even when the repository
+//! root lies above it.
+
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::bash::BashOptions;
+use super::Workspace;
+use crate::server::{ContentItem, ToolCallResult, ToolContext, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct GitDiffParams {
+    /// File or directory to limit the diff to. Default: the workspace.
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// Show changes staged for commit (`git diff --staged`) instead of
+    /// unstaged ones.
+    #[serde(default)]
+    pub staged: bool,
+}
+
+/// Environment variable holding the pathspec passed to `git diff`.
+const PATH_VAR: &str = "OA_CODER_DIFF_PATH";
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "git_diff".to_owned(),
+        description: "Show uncommitted changes as a unified diff (`git diff`), or with staged \
+            the changes staged for commit. Optionally limited to one file or directory."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "File or directory to limit the diff to (default: the whole workspace)"
+                },
+                "staged": {
+                    "type": "boolean",
+                    "description": "Show staged changes (git diff --staged) instead of unstaged ones (default: false)",
+                    "default": false
+                }
+            },
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Execute the git diff tool. The command runs through the `bash`
+/// execution path with `options`.
+pub fn execute(
+    workspace: &Workspace,
+    options: &BashOptions<'_>,
+    arguments: serde_json::Value,
+    ctx: &mut ToolContext,
+) -> Result<ToolCallResult> {
+    let params: GitDiffParams = super::parse_params(arguments, tool_definition)?;
+
+    let pathspec = match &params.file_path {
+        Some(file_path) => match super::validate_path(workspace, file_path) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+    if which::which("git").is_err() {
+        return Ok(ToolCallResult::error("Error: git is not installed (not found on PATH)"));
+    }
+    if !in_git_repo(&pathspec) {
+        return Ok(ToolCallResult::error(format!(
+            "Error: {} is not inside a git repository",
+            pathspec.display()
+        )));
+    }
+
+    let staged = if params.staged { " --staged" } else { "" };
+    let run = super::bash::execute(
+        workspace,
+        options,
+        serde_json::json!({
+            "command": format!("git --no-pager diff --no-color --no-ext-diff{staged} -- \"${PATH_VAR}\""),
+            "env": { PATH_VAR: pathspec.display().to_string() },
+        }),
+        ctx,
+    )?;
+    let output = run.content.first().map_or("", |item| item.text.as_str());
+    if run.is_error {
+        return Ok(ToolCallResult::error(format!("Error: git diff failed\n{output}")));
+    }
+
+    let parts = super::bash::split_output(output);
+    if parts.truncated {
+        return Ok(ToolCallResult::error(format!(
+            "Error: the diff of {} exceeds the output limit; narrow it with filePath",
+            pathspec.display()
+        )));
+    }
+    let text = if parts.stdout.trim().is_empty() {
+        let kind = if params.staged { "staged" } else { "unstaged" };
+        format!("No {kind} changes in {}", pathspec.display())
+    } else {
+        parts.stdout.to_owned()
+    };
+    let mut result = ToolCallResult::text(text);
+    if let Some(stderr) = parts.stderr {
+        result.content.push(ContentItem::text(format!("git stderr:\n{stderr}")));
+    }
+    Ok(result)
+}
+
+/// Whether `path` (or the directory containing it) is inside a git work tree,
+/// judged by a `.git` entry in it or any ancestor.
+fn in_git_repo(path: &Path) -> bool {
+    path.ancestors().any(|dir| dir.join(".git").exists())
+}
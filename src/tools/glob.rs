@@ -1,39 +1,99 @@
 //! Glob tool — file discovery via globset patterns.
+//!
+//! Respects `.gitignore` by default (best-effort, see
+//! [`crate::util::gitignore`]); `includeIgnored` disables that filtering.
+//! Hidden (`.`-prefixed) files and directories are matched like any other
+//! unless `hidden` is false; VCS directories are on the skip list either way.
 
+use std::fmt::Write as _;
 use std::path::Path;
+use std::time::SystemTime;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 
-use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+use crate::util::gitignore::GitignoreStack;
+use crate::util::time::format_rfc3339;
 
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct GlobParams {
-    /// Glob pattern (e.g. "**/*.rs", "src/**/*.ts").
-    pub pattern: String,
+    /// Glob pattern(s) (e.g. "**/*.rs", or `["src/**/*.ts", "*.json"]`).
+    /// A file matches if any pattern matches.
+    #[serde(deserialize_with = "super::string_or_vec")]
+    pub pattern: Vec<String>,
+    /// Glob pattern(s) to exclude; evaluated on the same relative path as `pattern`.
+    #[serde(default, deserialize_with = "super::string_or_vec")]
+    pub exclude: Vec<String>,
     /// Directory to search in (relative to workspace).
     #[serde(default)]
     pub path: Option<String>,
     /// Maximum number of results.
     #[serde(default = "default_max_results")]
     pub max_results: usize,
+    /// Number of sorted matches to skip, for paging past `max_results`.
+    #[serde(default)]
+    pub offset: usize,
+    /// Include files excluded by `.gitignore` (and the `.git` directory).
+    #[serde(default)]
+    pub include_ignored: bool,
+    /// Match hidden (`.`-prefixed) files and directories (default: true).
+    #[serde(default = "default_hidden")]
+    pub hidden: bool,
+    /// Result ordering (default: by path).
+    #[serde(default)]
+    pub sort_by: SortBy,
+    /// Append each file's modification time to its output line.
+    #[serde(default)]
+    pub show_times: bool,
+}
+
+/// Ordering of glob results.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Lexicographic by relative path.
+    #[default]
+    Path,
+    /// Most recently modified first.
+    Mtime,
 }
 
 const fn default_max_results() -> usize { 500 }
+pub(crate) const fn default_hidden() -> bool { true }
+
+/// Maximum number of matches collected (and sorted) before paging. The walk
+/// stops at the cap, so a capped result is sorted among the matches found
+/// first, not all of them.
+const MAX_GLOB_MATCHES: usize = 100_000;
 
 pub fn tool_definition() -> ToolDefinition {
     ToolDefinition {
         name: "glob".to_owned(),
-        description: "Find files matching a glob pattern. Supports ** for recursive matching, \
-            * for wildcards, {a,b} for alternation."
-            .to_owned(),
+        description: format!(
+            "Find files matching one or more glob patterns. Supports ** for recursive matching, \
+             * for wildcards, {{a,b}} for alternation. At most {MAX_GLOB_MATCHES} matches are \
+             collected, in directory walk order, before sorting: past that, sortBy (path or mtime) \
+             orders only the files collected, so narrow the pattern or path."
+        ),
         input_schema: serde_json::json!({
             "type": "object",
             "properties": {
                 "pattern": {
-                    "type": "string",
-                    "description": "Glob pattern (e.g. \"**/*.rs\", \"src/**/*.ts\")"
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+                    ],
+                    "description": "Glob pattern or list of patterns (e.g. \"**/*.rs\", [\"src/**/*.ts\", \"*.json\"])"
+                },
+                "exclude": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" } }
+                    ],
+                    "description": "Glob pattern or list of patterns to exclude (e.g. \"tests/**\")"
                 },
                 "path": {
                     "type": "string",
@@ -43,87 +103,231 @@ pub fn tool_definition() -> ToolDefinition {
                     "type": "integer",
                     "description": "Maximum number of results (default: 500)",
                     "default": 500
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Number of sorted matches to skip, for paging (default: 0)",
+                    "default": 0
+                },
+                "includeIgnored": {
+                    "type": "boolean",
+                    "description": "Include files excluded by .gitignore (default: false)",
+                    "default": false
+                },
+                "hidden": {
+                    "type": "boolean",
+                    "description": "Match hidden (dot-prefixed) files and directories such as .github; VCS directories like .git stay skipped (default: true)",
+                    "default": true
+                },
+                "sortBy": {
+                    "type": "string",
+                    "enum": ["path", "mtime"],
+                    "description": "Sort by path (default) or by modification time, newest first",
+                    "default": "path"
+                },
+                "showTimes": {
+                    "type": "boolean",
+                    "description": "Include each file's modification time in the output (default: false)",
+                    "default": false
                 }
             },
-            "required": ["pattern"]
+            "required": ["pattern"],
+            "additionalProperties": false
         }),
     }
 }
 
-pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCallResult> {
-    let params: GlobParams =
-        serde_json::from_value(arguments).context("invalid glob parameters")?;
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: GlobParams = super::parse_params(arguments, tool_definition)?;
 
     let search_dir = match &params.path {
         Some(p) => match super::validate_dir_path(workspace, p) {
             Ok(path) => path,
-            Err(e) => {
-                return Ok(ToolCallResult {
-                    content: vec![ContentItem {
-                        content_type: "text".to_owned(),
-                        text: format!("Error: {e}"),
-                    }],
-                    is_error: true,
-                });
-            }
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
         },
         None => workspace.to_path_buf(),
     };
 
-    let glob = globset::GlobBuilder::new(&params.pattern)
-        .literal_separator(false)
-        .build()
-        .with_context(|| format!("invalid glob pattern: {}", params.pattern))?
-        .compile_matcher();
+    if params.pattern.is_empty() {
+        return Ok(ToolCallResult::error("Error: at least one glob pattern is required"));
+    }
+
+    // Collect everything (up to a hard cap) so the sorted order, and with it
+    // every `offset` window, is the same on each call.
+    let mut matches = find_files(
+        workspace,
+        &search_dir,
+        &params.pattern,
+        &params.exclude,
+        params.include_ignored,
+        params.hidden,
+        MAX_GLOB_MATCHES + 1,
+    )?;
+    let capped = matches.len() > MAX_GLOB_MATCHES;
+    matches.truncate(MAX_GLOB_MATCHES);
+
+    if matches.is_empty() {
+        return Ok(ToolCallResult::text(format!("No files matching pattern: {}", params.pattern.join(", "))));
+    }
+
+    let total = matches.len();
+    let end = params.offset.saturating_add(params.max_results).min(total);
+    let mut output = if params.offset >= total {
+        format!("No more files: offset {} is past the last of {total} matches", params.offset)
+    } else if params.sort_by == SortBy::Path && !params.show_times {
+        // Sort by path for deterministic output.
+        matches.sort();
+        matches[params.offset..end].join("\n")
+    } else {
+        format_with_times(&search_dir, matches, &params)
+    };
+
+    if end < total {
+        let more = if capped { "+" } else { "" };
+        let _ = write!(
+            output,
+            "\n\n(showing {}-{end} of {total}{more} matches; has more, use offset={end} for the next page)",
+            params.offset + 1
+        );
+    }
+    if capped {
+        let _ = write!(
+            output,
+            "\n(match limit of {MAX_GLOB_MATCHES} reached: sorted among the first {MAX_GLOB_MATCHES} \
+             files found, not all matches; narrow the pattern or path)"
+        );
+    }
+
+    Ok(ToolCallResult::text(output))
+}
+
+/// Find up to `max` files under `search_dir` matching any of `patterns` and
+/// none of `exclude`, as paths relative to `search_dir` in walk order.
+///
+/// Unless `include_ignored` is set, `.gitignore`d entries and names on the
+/// workspace skip list are skipped; unless `hidden` is set, so are
+/// `.`-prefixed entries.
+pub(crate) fn find_files(
+    workspace: &Workspace,
+    search_dir: &Path,
+    patterns: &[String],
+    exclude: &[String],
+    include_ignored: bool,
+    hidden: bool,
+    max: usize,
+) -> Result<Vec<String>> {
+    let include = build_glob_set(patterns)?;
+    let exclude = build_glob_set(exclude)?;
+
+    let mut ignores = if include_ignored {
+        None
+    } else {
+        let root = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+        Some(GitignoreStack::for_ancestors(&root, search_dir))
+    };
 
     let mut matches = Vec::new();
-    collect_matches(&search_dir, &search_dir, &glob, &mut matches, params.max_results)?;
+    collect_matches(
+        search_dir,
+        search_dir,
+        &Filter { include: &include, exclude: &exclude, hidden, workspace },
+        &mut matches,
+        max,
+        ignores.as_mut(),
+    )?;
+    Ok(matches)
+}
 
-    // Sort by path for deterministic output.
-    matches.sort();
+/// Include/exclude pattern sets applied to each workspace-relative path,
+/// whether hidden entries are walked, and the workspace whose symlink policy
+/// the walk follows.
+struct Filter<'a> {
+    include: &'a globset::GlobSet,
+    exclude: &'a globset::GlobSet,
+    hidden: bool,
+    workspace: &'a Workspace,
+}
 
-    if matches.is_empty() {
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: format!("No files matching pattern: {}", params.pattern),
-            }],
-            is_error: false,
-        });
+impl Filter<'_> {
+    fn is_match(&self, relative: &Path) -> bool {
+        self.include.is_match(relative) && !self.exclude.is_match(relative)
     }
+}
 
-    let output = matches.join("\n");
+/// Compile patterns into a single [`globset::GlobSet`] (OR semantics).
+fn build_glob_set(patterns: &[String]) -> Result<globset::GlobSet> {
+    let mut builder = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = globset::GlobBuilder::new(pattern)
+            .literal_separator(false)
+            .build()
+            .with_context(|| format!("invalid glob pattern: {pattern}"))?;
+        builder.add(glob);
+    }
+    builder.build().context("failed to compile glob patterns")
+}
+
+/// Stat each match and render the requested page of it, optionally sorted by
+/// mtime and/or with the modification time appended
+/// (`path  2025-01-31T12:34:56Z`).
+fn format_with_times(search_dir: &Path, matches: Vec<String>, params: &GlobParams) -> String {
+    let mut entries: Vec<(String, Option<SystemTime>)> = matches
+        .into_iter()
+        .map(|rel| {
+            let mtime = std::fs::metadata(search_dir.join(&rel))
+                .and_then(|m| m.modified())
+                .ok();
+            (rel, mtime)
+        })
+        .collect();
+
+    match params.sort_by {
+        // Newest first; ties (and unknown mtimes, sorted last) fall back to path.
+        SortBy::Mtime => entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0))),
+        SortBy::Path => entries.sort(),
+    }
 
-    Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text: output,
-        }],
-        is_error: false,
-    })
+    entries
+        .into_iter()
+        .skip(params.offset)
+        .take(params.max_results)
+        .map(|(rel, mtime)| match (params.show_times, mtime) {
+            (true, Some(t)) => format!("{rel}  {}", format_rfc3339(t)),
+            (true, None) => format!("{rel}  (mtime unavailable)"),
+            (false, _) => rel,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 /// Maximum recursion depth for glob file walker.
 const MAX_WALK_DEPTH: usize = 50;
 
-/// Recursively collect files matching the glob pattern.
+/// Recursively collect files matching any include pattern and no exclude pattern.
 /// Uses `entry.file_type()` (no symlink following) and depth limit to prevent loops.
+/// Symlinks are skipped unless the workspace follows them (see
+/// [`Workspace::follow_symlink`]).
+///
+/// When `ignores` is `Some`, entries excluded by `.gitignore` and names on
+/// the workspace skip list are skipped.
 fn collect_matches(
     root: &Path,
     dir: &Path,
-    glob: &globset::GlobMatcher,
+    filter: &Filter<'_>,
     matches: &mut Vec<String>,
     max: usize,
+    ignores: Option<&mut GitignoreStack>,
 ) -> Result<()> {
-    collect_matches_inner(root, dir, glob, matches, max, 0)
+    collect_matches_inner(root, dir, filter, matches, max, ignores, 0)
 }
 
 fn collect_matches_inner(
     root: &Path,
     dir: &Path,
-    glob: &globset::GlobMatcher,
+    filter: &Filter<'_>,
     matches: &mut Vec<String>,
     max: usize,
+    mut ignores: Option<&mut GitignoreStack>,
     depth: usize,
 ) -> Result<()> {
     if matches.len() >= max || depth > MAX_WALK_DEPTH {
@@ -133,38 +337,65 @@ fn collect_matches_inner(
     let entries = std::fs::read_dir(dir)
         .with_context(|| format!("failed to read directory {}", dir.display()))?;
 
+    let pushed = ignores.as_deref_mut().is_some_and(|ig| ig.push_dir(dir));
+    let result = collect_entries(root, entries, filter, matches, max, ignores.as_deref_mut(), depth);
+    if pushed {
+        if let Some(ig) = ignores {
+            ig.pop();
+        }
+    }
+    result
+}
+
+fn collect_entries(
+    root: &Path,
+    entries: std::fs::ReadDir,
+    filter: &Filter<'_>,
+    matches: &mut Vec<String>,
+    max: usize,
+    mut ignores: Option<&mut GitignoreStack>,
+    depth: usize,
+) -> Result<()> {
     for entry in entries {
         if matches.len() >= max {
             break;
         }
 
         let entry = entry.context("failed to read directory entry")?;
+        if !filter.hidden && entry.file_name().as_encoded_bytes().starts_with(b".") {
+            continue;
+        }
         let path = entry.path();
 
-        // Skip hidden directories and common noise.
-        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') || name == "node_modules" || name == "target" {
+        // Use entry.file_type() which does NOT follow symlinks.
+        let Ok(ft) = entry.file_type() else {
+            continue;
+        };
+        let (is_dir, is_file) = if ft.is_symlink() {
+            match filter.workspace.follow_symlink(&path) {
+                Some(meta) => (meta.is_dir(), meta.is_file()),
+                None => continue,
+            }
+        } else {
+            (ft.is_dir(), ft.is_file())
+        };
+
+        if let Some(ig) = ignores.as_deref() {
+            if filter.workspace.skips(&entry.file_name()) || ig.is_ignored(&path, is_dir) {
                 continue;
             }
         }
 
-        // Use entry.file_type() which does NOT follow symlinks.
-        let ft = match entry.file_type() {
-            Ok(ft) => ft,
-            Err(_) => continue,
-        };
-
-        if ft.is_dir() {
-            collect_matches_inner(root, &path, glob, matches, max, depth + 1)?;
-        } else if ft.is_file() {
+        if is_dir {
+            collect_matches_inner(root, &path, filter, matches, max, ignores.as_deref_mut(), depth + 1)?;
+        } else if is_file {
             // Match against relative path from root.
             if let Ok(relative) = path.strip_prefix(root) {
-                if glob.is_match(relative) {
+                if filter.is_match(relative) {
                     matches.push(relative.display().to_string());
                 }
             }
         }
-        // Symlinks are skipped.
     }
 
     Ok(())
@@ -2,37 +2,198 @@
 //!
 //! Shells out to `rg` for full ripgrep functionality (type filtering,
 //! .gitignore support, parallel search, SIMD acceleration).
-//! Falls back to a basic Rust regex search if `rg` is not installed.
+//! Falls back to a basic Rust regex search if `rg` is not installed; the
+//! fallback honours `.gitignore` on a best-effort basis.
+//!
+//! Line-mode results carry a second content item with a JSON summary,
+//! `{"truncated", "returned", "totalAtLeast"}`, so clients can tell whether
+//! to narrow their search. `totalAtLeast` is a lower bound: both searches
+//! stop early once more than `maxResults` matches have been seen.
+//!
+//! With `smartContext`, each match is widened to the code block around it
+//! (see [`crate::util::block`]) instead of a fixed number of lines; matches
+//! whose block cannot be determined get `contextLines` of plain context.
+//! Overlapping blocks in one file are merged into one group.
+//!
+//! `fileType` restricts the search to one of the built-in [`FILE_TYPES`],
+//! passed to `rg --type` or, in the fallback, matched by extension.
+//!
+//! Hidden (`.`-prefixed) files and directories are searched, as `glob`
+//! matches them, unless `hidden` is false; `rg` and the fallback walker
+//! agree on this. VCS directories (`.git`, `.hg`, `.svn`) stay excluded.
+//!
+//! Matched lines that are not valid UTF-8 (files in another encoding) show
+//! U+FFFD for the invalid bytes, and a trailing note says how many there were.
+//!
+//! With `inlineContent`, the given text is searched instead of files (with
+//! the built-in regex search, never touching the filesystem) and matches are
+//! reported as `line:text` (`line:col:text` with `columns`).
+//!
+//! `output: "grouped"` returns the matches as JSON instead, one
+//! `{path, matches: [{line, text}]}` group per file (like `rg --heading`),
+//! sorted by path and line; `maxResults` counts matches across all groups.
+//!
+//! With `columns`, text-mode match lines become `path:line:col:text`, where
+//! `col` is the 1-based byte column of the line's first match (`rg --column`;
+//! the fallback takes it from the regex match start). Lines a multiline match
+//! continues onto report column 1.
+//!
+//! Binary files (a NUL in the first 8 KiB) are skipped unless `searchBinary`
+//! is set, in which case they are searched as lossily decoded text (`rg
+//! --text`) and the text of each of their match lines starts with
+//! [`BINARY_LABEL`].
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
+use super::Workspace;
+use crate::error::CoderError;
+use crate::server::{CancelToken, ContentItem, ToolCallResult, ToolDefinition};
+use crate::util::gitignore::GitignoreStack;
 
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct GrepParams {
     /// Regex pattern to search for.
     pub pattern: String,
     /// Directory or file to search in (relative to workspace).
     #[serde(default)]
     pub path: Option<String>,
+    /// Text to search instead of files (exclusive with `path`).
+    #[serde(default)]
+    pub inline_content: Option<String>,
     /// Glob pattern to filter files (e.g. "*.rs", "*.{ts,tsx}").
     #[serde(default)]
     pub include: Option<String>,
+    /// Only search files of this type (a name from [`FILE_TYPES`]).
+    #[serde(default)]
+    pub file_type: Option<String>,
     /// Maximum number of results.
     #[serde(default = "default_max_results")]
     pub max_results: usize,
     /// Include N lines of context around matches.
     #[serde(default)]
     pub context_lines: usize,
+    /// Allow patterns to span lines (`.` also matches newlines).
+    #[serde(default)]
+    pub multiline: bool,
+    /// Return per-file match counts and a grand total instead of lines.
+    #[serde(default)]
+    pub count: bool,
+    /// Expand each match to its enclosing brace or indentation block,
+    /// falling back to `context_lines` when no block is found.
+    #[serde(default)]
+    pub smart_context: bool,
+    /// Search hidden (`.`-prefixed) files and directories, except VCS
+    /// directories such as `.git` (default: true).
+    #[serde(default = "super::glob::default_hidden")]
+    pub hidden: bool,
+    /// Fail with [`CoderError::RipgrepNotFound`] instead of falling back to
+    /// the built-in search when `rg` is not installed.
+    #[serde(default)]
+    pub require_ripgrep: bool,
+    /// How line-mode matches are returned.
+    #[serde(default)]
+    pub output: GrepOutput,
+    /// Report the column of each line's first match as `path:line:col:text`.
+    #[serde(default)]
+    pub columns: bool,
+    /// Also search binary files, labelling their matches with
+    /// [`BINARY_LABEL`].
+    #[serde(default)]
+    pub search_binary: bool,
+}
+
+/// Prefix of the text of match lines found in binary files.
+pub const BINARY_LABEL: &str = "[binary] ";
+
+/// Bytes checked for NUL when deciding whether a file is binary.
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// Whether `bytes` look binary: a NUL in the first [`BINARY_CHECK_BYTES`].
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0)
+}
+
+/// Format of line-mode results.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrepOutput {
+    /// `path:line:text` lines.
+    #[default]
+    Text,
+    /// A JSON array of [`FileMatches`], sorted by path.
+    Grouped,
 }
 
 const fn default_max_results() -> usize { 100 }
 
+/// File types accepted by `fileType`, with the extensions the fallback
+/// search matches for each (`rg --type` uses its own, similar lists).
+pub const FILE_TYPES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("js", &["js", "jsx", "mjs", "cjs", "vue"]),
+    ("ts", &["ts", "tsx", "mts", "cts"]),
+    ("py", &["py", "pyi"]),
+    ("go", &["go"]),
+    ("c", &["c", "h"]),
+    ("cpp", &["cpp", "cc", "cxx", "c++", "hpp", "hh", "hxx", "h++", "h", "inl"]),
+];
+
+/// The extensions of the file type `name`, if it is one of [`FILE_TYPES`].
+fn file_type_extensions(name: &str) -> Option<&'static [&'static str]> {
+    FILE_TYPES
+        .iter()
+        .find(|(type_name, _)| *type_name == name)
+        .map(|(_, extensions)| *extensions)
+}
+
+/// Machine-readable summary of a line-mode search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MatchSummary {
+    /// Whether more results were found than returned.
+    truncated: bool,
+    /// Number of results (match groups with context lines) returned.
+    returned: usize,
+    /// Number of results found; more may exist beyond it.
+    total_at_least: usize,
+}
+
+impl MatchSummary {
+    fn new(found: usize, max_results: usize) -> Self {
+        Self {
+            truncated: found > max_results,
+            returned: found.min(max_results),
+            total_at_least: found,
+        }
+    }
+}
+
+/// One file's matches in [`GrepOutput::Grouped`] results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileMatches {
+    pub path: String,
+    /// In line order.
+    pub matches: Vec<LineMatch>,
+}
+
+/// A matched line in [`GrepOutput::Grouped`] results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineMatch {
+    /// 1-based line number.
+    pub line: usize,
+    pub text: String,
+}
+
 pub fn tool_definition() -> ToolDefinition {
     ToolDefinition {
         name: "grep".to_owned(),
@@ -50,10 +211,21 @@ pub fn tool_definition() -> ToolDefinition {
                     "type": "string",
                     "description": "Directory or file to search in (default: workspace root)"
                 },
+                "inlineContent": {
+                    "type": "string",
+                    "description": "Search this text instead of files, e.g. generated code before writing it; \
+                        matches come back as line:text. Cannot be combined with path, include, fileType, \
+                        hidden, contextLines, smartContext or grouped output"
+                },
                 "include": {
                     "type": "string",
                     "description": "Glob pattern to filter files (e.g. \"*.rs\")"
                 },
+                "fileType": {
+                    "type": "string",
+                    "enum": ["rust", "js", "ts", "py", "go", "c", "cpp"],
+                    "description": "Only search files of this type, like rg --type (e.g. \"rust\" for *.rs)"
+                },
                 "maxResults": {
                     "type": "integer",
                     "description": "Maximum number of results (default: 100)",
@@ -63,29 +235,113 @@ pub fn tool_definition() -> ToolDefinition {
                     "type": "integer",
                     "description": "Lines of context around matches (default: 0)",
                     "default": 0
+                },
+                "multiline": {
+                    "type": "boolean",
+                    "description": "Allow matches to span multiple lines (default: false)",
+                    "default": false
+                },
+                "count": {
+                    "type": "boolean",
+                    "description": "Only return per-file match counts and a total (default: false)",
+                    "default": false
+                },
+                "smartContext": {
+                    "type": "boolean",
+                    "description": "Return the enclosing block (braces, or indentation for Python) of each match \
+                        instead of fixed context lines; contextLines is used where no block is found (default: false)",
+                    "default": false
+                },
+                "hidden": {
+                    "type": "boolean",
+                    "description": "Search hidden (dot-prefixed) files and directories such as .github or \
+                        .env.example; VCS directories like .git stay skipped (default: true)",
+                    "default": true
+                },
+                "requireRipgrep": {
+                    "type": "boolean",
+                    "description": "Fail if ripgrep (rg) is not installed instead of falling back to a \
+                        slower built-in search (default: false)",
+                    "default": false
+                },
+                "output": {
+                    "type": "string",
+                    "enum": ["text", "grouped"],
+                    "description": "Return path:line:text lines (default) or a JSON array of \
+                        {path, matches: [{line, text}]} groups sorted by path; maxResults counts matches \
+                        across groups. Cannot be combined with count, contextLines or smartContext",
+                    "default": "text"
+                },
+                "columns": {
+                    "type": "boolean",
+                    "description": "Return match lines as path:line:col:text, where col is the 1-based byte \
+                        column of the first match on the line. Cannot be combined with count, smartContext \
+                        or grouped output (default: false)",
+                    "default": false
+                },
+                "searchBinary": {
+                    "type": "boolean",
+                    "description": "Also search binary files (e.g. for a version string in a build artifact), \
+                        as lossily decoded text; their match lines are labelled \"[binary] \". Cannot be \
+                        combined with contextLines or smartContext (default: false)",
+                    "default": false
                 }
             },
-            "required": ["pattern"]
+            "required": ["pattern"],
+            "additionalProperties": false
         }),
     }
 }
 
-pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCallResult> {
-    let params: GrepParams =
-        serde_json::from_value(arguments).context("invalid grep parameters")?;
+/// Execute the grep tool. An `rg` subprocess is killed early if `cancel` fires.
+///
+/// With `require_ripgrep` (or the `requireRipgrep` parameter) a missing `rg`
+/// is an error rather than a reason to use the fallback search.
+pub fn execute(
+    workspace: &Workspace,
+    arguments: serde_json::Value,
+    cancel: &CancelToken,
+    require_ripgrep: bool,
+) -> Result<ToolCallResult> {
+    let mut params: GrepParams = super::parse_params(arguments, tool_definition)?;
+    params.require_ripgrep |= require_ripgrep;
+
+    if params.columns
+        && (params.count || params.smart_context || params.output == GrepOutput::Grouped)
+    {
+        return Ok(ToolCallResult::error(
+            "Error: columns cannot be combined with count, smartContext or grouped output",
+        ));
+    }
+    if params.search_binary && (params.context_lines > 0 || params.smart_context) {
+        return Ok(ToolCallResult::error(
+            "Error: searchBinary cannot be combined with contextLines or smartContext",
+        ));
+    }
+    if let Some(content) = &params.inline_content {
+        return execute_inline(content, &params);
+    }
+    if params.output == GrepOutput::Grouped
+        && (params.count || params.context_lines > 0 || params.smart_context)
+    {
+        return Ok(ToolCallResult::error(
+            "Error: output \"grouped\" cannot be combined with count, contextLines or smartContext",
+        ));
+    }
+    if let Some(name) = &params.file_type {
+        if file_type_extensions(name).is_none() {
+            let supported: Vec<&str> = FILE_TYPES.iter().map(|(name, _)| *name).collect();
+            return Ok(ToolCallResult::error(format!(
+                "Error: unknown fileType {name:?}; supported types: {}",
+                supported.join(", ")
+            )));
+        }
+    }
 
     let search_path = match &params.path {
         Some(p) => match super::validate_dir_path(workspace, p) {
             Ok(path) => path,
-            Err(e) => {
-                return Ok(ToolCallResult {
-                    content: vec![ContentItem {
-                        content_type: "text".to_owned(),
-                        text: format!("Error: {e}"),
-                    }],
-                    is_error: true,
-                });
-            }
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
         },
         None => workspace.to_path_buf(),
     };
@@ -94,10 +350,11 @@ pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCal
     let rg_path = which::which("rg");
 
     match rg_path {
-        Ok(rg) => execute_rg(&rg, &search_path, &params),
+        Ok(rg) => execute_rg(&rg, workspace, &search_path, &params, cancel),
+        Err(_) if params.require_ripgrep => Err(CoderError::RipgrepNotFound.into()),
         Err(_) => {
-            // Fallback: basic regex search (no gitignore, no parallel).
-            execute_fallback(&search_path, &params)
+            // Fallback: built-in regex search, reading files in parallel.
+            execute_fallback(workspace, &search_path, &params)
         }
     }
 }
@@ -105,9 +362,113 @@ pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCal
 /// Execute search using ripgrep subprocess.
 fn execute_rg(
     rg: &Path,
+    workspace: &Workspace,
     search_path: &Path,
     params: &GrepParams,
+    cancel: &CancelToken,
 ) -> Result<ToolCallResult> {
+    let mut cmd = rg_command(rg, workspace, params);
+    cmd.arg(&params.pattern).arg(search_path);
+
+    let (mut stdout, lossy_lines) = match run_rg(&mut cmd, workspace, cancel)? {
+        Ok(output) => output,
+        Err(result) => return Ok(result),
+    };
+    if params.search_binary && !params.count {
+        let single_file = search_path.is_file().then_some(search_path);
+        stdout = label_binary_matches(&stdout, single_file, params.columns);
+    }
+
+    if params.output == GrepOutput::Grouped {
+        return Ok(grouped_result(stdout.lines(), params.max_results));
+    }
+
+    if stdout.is_empty() {
+        let text = "No matches found.".to_owned();
+        if !params.count {
+            return Ok(matches_result(text, MatchSummary::new(0, params.max_results)));
+        }
+        return Ok(ToolCallResult::text(text));
+    }
+
+    if params.count {
+        let counts: Vec<(String, usize)> = stdout
+            .lines()
+            .filter_map(|line| {
+                let (path, n) = line.rsplit_once(':')?;
+                Some((path.to_owned(), n.trim().parse().ok()?))
+            })
+            .collect();
+        return Ok(ToolCallResult::text(format_counts(counts) + &lossy_note(lossy_lines)));
+    }
+
+    let (mut text, summary) = if params.smart_context {
+        smart_results(&stdout, params)
+    } else {
+        truncate_results(stdout, params.max_results, params.context_lines > 0)
+    };
+    text.push_str(&lossy_note(lossy_lines));
+    Ok(matches_result(text, summary))
+}
+
+/// Maximum number of file arguments passed to a single `rg` run.
+const RG_FILES_PER_RUN: usize = 256;
+
+/// Search an explicit list of files (used by the `search` tool).
+///
+/// Uses ripgrep when available, running it over batches of files until
+/// `maxResults` matches are found, else the fallback regex search.
+///
+/// # Errors
+///
+/// Returns an error if `rg` cannot be run or the fallback regex is invalid.
+pub(super) fn search_files(
+    workspace: &Workspace,
+    files: &[PathBuf],
+    params: &GrepParams,
+    cancel: &CancelToken,
+) -> Result<ToolCallResult> {
+    let Ok(rg) = which::which("rg") else {
+        if params.require_ripgrep {
+            return Err(CoderError::RipgrepNotFound.into());
+        }
+        return Ok(fallback_search(files, &fallback_regex(params)?, params));
+    };
+
+    let mut stdout = String::new();
+    let mut lossy_lines = 0;
+    for batch in files.chunks(RG_FILES_PER_RUN) {
+        let mut cmd = rg_command(&rg, workspace, params);
+        cmd.arg("--with-filename").arg(&params.pattern).arg("--").args(batch);
+        let (out, lossy) = match run_rg(&mut cmd, workspace, cancel)? {
+            Ok(output) => output,
+            Err(result) => return Ok(result),
+        };
+        lossy_lines += lossy;
+        if params.context_lines > 0 && !stdout.is_empty() && !out.is_empty() {
+            // rg separates context groups with `--`; keep that across batches.
+            stdout.push_str("--\n");
+        }
+        stdout.push_str(&out);
+        if result_groups(&stdout, params.context_lines > 0).len() > params.max_results {
+            break;
+        }
+    }
+
+    let (mut text, summary) = if stdout.is_empty() {
+        ("No matches found.".to_owned(), MatchSummary::new(0, params.max_results))
+    } else {
+        truncate_results(stdout, params.max_results, params.context_lines > 0)
+    };
+    text.push_str(&lossy_note(lossy_lines));
+    Ok(matches_result(text, summary))
+}
+
+/// Directories still skipped by `rg --hidden`.
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+
+/// Build the `rg` command line for `params`, without pattern or paths.
+fn rg_command(rg: &Path, workspace: &Workspace, params: &GrepParams) -> Command {
     // Note: rg --max-count is per-file, not total. Use a higher limit
     // to avoid missing results spread across many files, then truncate
     // client-side to params.max_results.
@@ -116,10 +477,32 @@ fn execute_rg(
     let mut cmd = Command::new(rg);
     cmd.arg("--color").arg("never")
         .arg("--line-number")
-        .arg("--no-heading")
-        .arg("--max-count").arg(rg_max.to_string());
+        .arg("--no-heading");
+
+    if params.count {
+        // Count every match; --with-filename keeps `path:N` for single files.
+        cmd.arg("--count-matches").arg("--with-filename");
+    } else {
+        cmd.arg("--max-count").arg(rg_max.to_string());
+    }
+
+    if params.multiline {
+        cmd.arg("-U").arg("--multiline-dotall");
+    }
+
+    if params.columns {
+        cmd.arg("--column");
+    }
 
-    if params.context_lines > 0 {
+    if params.search_binary {
+        cmd.arg("--text");
+    }
+
+    if params.smart_context || params.output == GrepOutput::Grouped {
+        // Blocks and groups are built afterwards from the result lines;
+        // that needs every one to name its file.
+        cmd.arg("--with-filename");
+    } else if params.context_lines > 0 {
         cmd.arg("-C").arg(params.context_lines.to_string());
     }
 
@@ -127,117 +510,649 @@ fn execute_rg(
         cmd.arg("--glob").arg(include);
     }
 
-    cmd.arg(&params.pattern).arg(search_path);
+    if let Some(file_type) = &params.file_type {
+        cmd.arg("--type").arg(file_type);
+    }
+
+    if params.hidden {
+        cmd.arg("--hidden");
+        for name in VCS_DIRS {
+            cmd.arg("--glob").arg(format!("!{name}"));
+        }
+    }
+
+    if workspace.follows_symlinks() {
+        // NUL after each path lets results through links that leave the
+        // workspace be dropped reliably (see `drop_escaped_paths`).
+        cmd.arg("--follow").arg("--null");
+    }
+
+    cmd
+}
 
-    let output = cmd.output().context("failed to execute rg")?;
+/// Run `rg`, returning its stdout and the number of output lines that were
+/// not valid UTF-8 (see [`decode_lines`]), or the tool result to report
+/// instead if it was cancelled or failed.
+fn run_rg(
+    cmd: &mut Command,
+    workspace: &Workspace,
+    cancel: &CancelToken,
+) -> Result<std::result::Result<(String, usize), ToolCallResult>> {
+    let Some(output) = crate::util::process::output_cancellable(cmd, cancel)
+        .context("failed to execute rg")?
+    else {
+        return Ok(Err(ToolCallResult::error("grep cancelled")));
+    };
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (mut stdout, lossy_lines) = decode_lines(&output.stdout);
     let stderr = String::from_utf8_lossy(&output.stderr);
+    if workspace.follows_symlinks() {
+        stdout = drop_escaped_paths(workspace, &stdout);
+    }
 
-    // rg exit code: 0 = matches found, 1 = no matches, 2 = error.
-    if output.status.code() == Some(2) {
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: format!("grep error: {stderr}"),
-            }],
-            is_error: true,
-        });
+    // rg exit code: 0 = matches found, 1 = no matches, 2 = error. With
+    // --follow, symlink loops are reported as errors alongside real results,
+    // so keep whatever was found.
+    let partial = workspace.follows_symlinks() && !stdout.is_empty();
+    if output.status.code() == Some(2) && !partial {
+        return Ok(Err(ToolCallResult::error(format!("grep error: {stderr}"))));
     }
 
-    if stdout.is_empty() {
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: "No matches found.".to_owned(),
-            }],
-            is_error: false,
-        });
+    Ok(Ok((stdout, lossy_lines)))
+}
+
+/// Decode rg output line by line, replacing invalid UTF-8 (from matched
+/// files in other encodings) with U+FFFD. Returns the text and how many
+/// lines needed replacing.
+fn decode_lines(bytes: &[u8]) -> (String, usize) {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_owned(), 0);
+    }
+    let mut text = String::with_capacity(bytes.len());
+    let mut lossy = 0;
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        let decoded = String::from_utf8_lossy(line);
+        if matches!(decoded, std::borrow::Cow::Owned(_)) {
+            lossy += 1;
+        }
+        text.push_str(&decoded);
     }
+    (text, lossy)
+}
+
+/// Note appended to results when `lossy_lines` lines were not valid UTF-8.
+fn lossy_note(lossy_lines: usize) -> String {
+    match lossy_lines {
+        0 => String::new(),
+        1 => "\n\n(note: 1 output line was not valid UTF-8; invalid bytes are shown as \u{fffd})"
+            .to_owned(),
+        n => format!(
+            "\n\n(note: {n} output lines were not valid UTF-8; invalid bytes are shown as \u{fffd})"
+        ),
+    }
+}
 
-    // Client-side truncation to respect max_results (rg --max-count is per-file).
-    let lines: Vec<&str> = stdout.lines().collect();
-    let truncated = if lines.len() > params.max_results {
-        let mut out = lines[..params.max_results].join("\n");
-        out.push_str(&format!(
+/// Client-side truncation to respect `max_results` (rg --max-count is per-file).
+///
+/// With context lines (`grouped`), rg separates hunks with `--` lines; each
+/// hunk then counts as one result and is kept or dropped whole, so context
+/// blocks and their separators stay intact.
+fn truncate_results(stdout: String, max_results: usize, grouped: bool) -> (String, MatchSummary) {
+    let groups = result_groups(&stdout, grouped);
+    let summary = MatchSummary::new(groups.len(), max_results);
+    if !summary.truncated {
+        return (stdout, summary);
+    }
+    let separator = if grouped { "\n--\n" } else { "\n" };
+    let mut out = groups[..max_results].join(separator);
+    let _ = write!(
+        out,
+        "\n\n... truncated ({max_results} {} shown out of {}+)",
+        if grouped { "match groups" } else { "results" },
+        groups.len()
+    );
+    (out, summary)
+}
+
+/// Smart-context mode: keep the first `maxResults` match lines of `stdout`
+/// and widen them to their enclosing blocks.
+fn smart_results(stdout: &str, params: &GrepParams) -> (String, MatchSummary) {
+    let lines = result_groups(stdout, false);
+    let summary = MatchSummary::new(lines.len(), params.max_results);
+    let kept = &lines[..summary.returned];
+    let mut out = expand_blocks(kept, params.context_lines);
+    if summary.truncated {
+        let _ = write!(
+            out,
             "\n\n... truncated ({} results shown out of {}+)",
             params.max_results,
             lines.len()
+        );
+    }
+    (out, summary)
+}
+
+/// Widen `path:line:text` match lines to the block around each match (see
+/// [`crate::util::block`]), or to `context_lines` of context where no block
+/// is found. Files keep the order of their first match; ranges overlapping
+/// or touching within a file are merged, and groups are separated by `--`
+/// like `rg -C` output. Lines that do not parse are passed through.
+fn expand_blocks(matches: &[&str], context_lines: usize) -> String {
+    // Per file (in first-seen order), the 1-based matched line numbers.
+    let mut files: Vec<(&str, Vec<usize>)> = Vec::new();
+    let mut groups: Vec<String> = Vec::new();
+    for &line in matches {
+        let Some((path, n)) = split_match_line(line) else {
+            groups.push(line.to_owned());
+            continue;
+        };
+        match files.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, numbers)) => numbers.push(n),
+            None => files.push((path, vec![n])),
+        }
+    }
+
+    for (path, numbers) in &files {
+        let content = std::fs::read(path).unwrap_or_default();
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<&str> = content.lines().collect();
+        let style = crate::util::block::BlockStyle::for_path(Path::new(path));
+
+        let mut ranges: Vec<(usize, usize)> = numbers
+            .iter()
+            .map(|&n| {
+                let i = n.saturating_sub(1);
+                crate::util::block::enclosing_block(&lines, i, style).unwrap_or_else(|| {
+                    let last = lines.len().saturating_sub(1);
+                    (i.saturating_sub(context_lines), (i + context_lines).min(last))
+                })
+            })
+            .collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        for (start, end) in merged {
+            let mut group = String::new();
+            for (i, text) in lines.iter().enumerate().take(end + 1).skip(start) {
+                let sep = if numbers.contains(&(i + 1)) { ':' } else { '-' };
+                let _ = writeln!(group, "{path}{sep}{}{sep}{text}", i + 1);
+            }
+            group.pop();
+            groups.push(group);
+        }
+    }
+    groups.join("\n--\n")
+}
+
+/// Split a `path:line:text` result line into its path and line number. The
+/// path may itself contain `:`, so the first split whose prefix is an
+/// existing file wins.
+fn split_match_line(line: &str) -> Option<(&str, usize)> {
+    line.match_indices(':').find_map(|(i, _)| {
+        let (number, _) = line[i + 1..].split_once(':')?;
+        let n = number.parse().ok()?;
+        let path = &line[..i];
+        Path::new(path).is_file().then_some((path, n))
+    })
+}
+
+/// Insert [`BINARY_LABEL`] before the text of `rg --text` match lines that
+/// come from binary files. Lines are `path:line[:col]:text`, or without the
+/// path when the search was of `single_file`; anything else passes through.
+fn label_binary_matches(stdout: &str, single_file: Option<&Path>, columns: bool) -> String {
+    let single = single_file.map(|path| path.to_string_lossy());
+    let mut binary: HashMap<&str, bool> = HashMap::new();
+    let mut out = String::with_capacity(stdout.len());
+    for line in stdout.lines() {
+        let parsed = single.as_ref().map_or_else(
+            || split_match_line(line).map(|(path, _)| (path, path.len() + 1)),
+            |path| Some((path.as_ref(), 0)),
+        );
+        // Skip the line number, and the column if present, to the text.
+        let text_start = parsed.and_then(|(path, start)| {
+            let rest = &line[start..];
+            let mut fields = rest.splitn(if columns { 3 } else { 2 }, ':');
+            let numbers_ok = fields
+                .by_ref()
+                .take(if columns { 2 } else { 1 })
+                .all(|field| !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit()));
+            let text = fields.next()?;
+            let is_binary = *binary.entry(path).or_insert_with(|| file_is_binary(Path::new(path)));
+            (numbers_ok && is_binary).then(|| line.len() - text.len())
+        });
+        match text_start {
+            Some(i) => {
+                out.push_str(&line[..i]);
+                out.push_str(BINARY_LABEL);
+                out.push_str(&line[i..]);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether the file at `path` looks binary (see [`is_binary`]).
+fn file_is_binary(path: &Path) -> bool {
+    use std::io::Read as _;
+
+    let mut head = Vec::with_capacity(BINARY_CHECK_BYTES);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(BINARY_CHECK_BYTES as u64).read_to_end(&mut head))
+        .is_ok_and(|_| is_binary(&head))
+}
+
+/// Grouped-mode result: `path:line:text` match lines sorted by path and line,
+/// cut to the first `max_results` and grouped per file, as JSON, then the
+/// [`MatchSummary`]. Lines that do not parse (such as rg's binary file
+/// notices) are dropped.
+fn grouped_result<'a>(lines: impl Iterator<Item = &'a str>, max_results: usize) -> ToolCallResult {
+    let mut matches: Vec<(&str, usize, &str)> = lines
+        .filter_map(|line| {
+            let (path, n) = split_match_line(line)?;
+            let (_, text) = line[path.len() + 1..].split_once(':')?;
+            Some((path, n, text))
+        })
+        .collect();
+    // Compare paths by component, the order the fallback walks files in.
+    matches.sort_by(|a, b| (Path::new(a.0), a.1).cmp(&(Path::new(b.0), b.1)));
+    let summary = MatchSummary::new(matches.len(), max_results);
+    matches.truncate(max_results);
+
+    let mut groups: Vec<FileMatches> = Vec::new();
+    for (path, line, text) in matches {
+        let entry = LineMatch {
+            line,
+            text: text.to_owned(),
+        };
+        match groups.last_mut() {
+            Some(group) if group.path == path => group.matches.push(entry),
+            _ => groups.push(FileMatches {
+                path: path.to_owned(),
+                matches: vec![entry],
+            }),
+        }
+    }
+    matches_result(serde_json::to_string(&groups).unwrap_or_default(), summary)
+}
+
+/// Line-mode result: the matches as text, then the [`MatchSummary`] as JSON.
+fn matches_result(text: String, summary: MatchSummary) -> ToolCallResult {
+    ToolCallResult {
+        content: vec![
+            ContentItem::text(text),
+            ContentItem::text(serde_json::to_value(summary).unwrap_or_default().to_string()),
+        ],
+        is_error: false,
+    }
+}
+
+/// Split rg output into results: single lines, or `--`-separated hunks when
+/// `grouped`.
+fn result_groups(stdout: &str, grouped: bool) -> Vec<&str> {
+    if !grouped {
+        return stdout.lines().collect();
+    }
+    stdout
+        .trim_end_matches('\n')
+        .split("\n--\n")
+        .filter(|group| !group.is_empty())
+        .collect()
+}
+
+/// Post-process `rg --null` output: drop lines from files whose real path is
+/// outside the workspace (reached through a followed symlink) and restore
+/// the usual `path:` / `path-` separator in place of the NUL.
+fn drop_escaped_paths(workspace: &Workspace, stdout: &str) -> String {
+    let mut inside: HashMap<&str, bool> = HashMap::new();
+    let mut out = String::with_capacity(stdout.len());
+    for line in stdout.lines() {
+        let Some((path, rest)) = line.split_once('\0') else {
+            // Context separators and single-file output carry no path.
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+        let keep = *inside.entry(path).or_insert_with(|| {
+            Path::new(path)
+                .canonicalize()
+                .is_ok_and(|real| workspace.contains(&real))
+        });
+        if !keep {
+            continue;
+        }
+        // Context lines use `-` after the line number, matches (and counts) `:`.
+        let sep = if rest.trim_start_matches(|c: char| c.is_ascii_digit()).starts_with('-') {
+            '-'
+        } else {
+            ':'
+        };
+        out.push_str(path);
+        out.push(sep);
+        out.push_str(rest);
+        out.push('\n');
+    }
+    out
+}
+
+/// Search `content` given inline rather than files.
+fn execute_inline(content: &str, params: &GrepParams) -> Result<ToolCallResult> {
+    if params.path.is_some()
+        || params.include.is_some()
+        || params.file_type.is_some()
+        || !params.hidden
+        || params.context_lines > 0
+        || params.smart_context
+        || params.output == GrepOutput::Grouped
+    {
+        return Ok(ToolCallResult::error(
+            "Error: inlineContent cannot be combined with path, include, fileType, hidden, \
+             contextLines, smartContext or grouped output",
         ));
-        out
+    }
+    let re = fallback_regex(params)?;
+
+    if params.count {
+        let n: usize = if params.multiline {
+            re.find_iter(content).count()
+        } else {
+            content.lines().map(|line| re.find_iter(line).count()).sum()
+        };
+        return Ok(ToolCallResult::text(format!("Total: {n}")));
+    }
+
+    let matches = match_text("", content, &re, params, params.max_results.saturating_add(1), "");
+    let summary = MatchSummary::new(matches.len(), params.max_results);
+    let lines: Vec<String> = matches.into_iter().take(params.max_results).flatten().collect();
+    let text = if lines.is_empty() {
+        "No matches found.".to_owned()
     } else {
-        stdout.into_owned()
+        lines.join("\n")
     };
-
-    Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text: truncated,
-        }],
-        is_error: false,
-    })
+    Ok(matches_result(text, summary))
 }
 
 /// Fallback: basic regex file search without ripgrep.
+///
+/// Honours `.gitignore` files from `workspace` down to each searched
+/// directory so results roughly match `rg`. Parity is best-effort; see
+/// [`crate::util::gitignore`].
 fn execute_fallback(
+    workspace: &Workspace,
     search_path: &Path,
     params: &GrepParams,
 ) -> Result<ToolCallResult> {
-    let re = regex::Regex::new(&params.pattern)
-        .with_context(|| format!("invalid regex pattern: {}", params.pattern))?;
+    let re = fallback_regex(params)?;
 
-    let mut results = Vec::new();
-    let mut count = 0;
+    let files = collect_files(workspace, search_path, params.file_type.as_deref(), params.hidden)?;
+    if params.count {
+        return Ok(fallback_count(&files, &re, params));
+    }
+    Ok(fallback_search(&files, &re, params))
+}
 
-    walk_files(search_path, &mut |path| {
-        if count >= params.max_results {
-            return;
-        }
+/// Compile the fallback regex for `params` (`(?s)` in multiline mode).
+fn fallback_regex(params: &GrepParams) -> Result<regex::Regex> {
+    let pattern = if params.multiline {
+        format!("(?s){}", params.pattern)
+    } else {
+        params.pattern.clone()
+    };
+    regex::Regex::new(&pattern)
+        .with_context(|| format!("invalid regex pattern: {}", params.pattern))
+}
 
-        let Ok(content) = std::fs::read_to_string(path) else {
-            return;
+/// Search `files` in order with the fallback regex, keeping the first
+/// `maxResults` matches.
+fn fallback_search(
+    files: &[PathBuf],
+    re: &regex::Regex,
+    params: &GrepParams,
+) -> ToolCallResult {
+    let max = params.max_results;
+    // One match past `max` is enough to know the results were truncated.
+    let wanted = max.saturating_add(1);
+    let found = AtomicUsize::new(0);
+
+    // Each file yields its matches, each match being the output lines it spans.
+    let per_file = map_files_parallel(
+        files,
+        &|| found.load(Ordering::Relaxed) >= wanted,
+        &|path| {
+            let matches = match_file(path, re, params, wanted);
+            found.fetch_add(matches.len(), Ordering::Relaxed);
+            matches
+        },
+    );
+    let matches: Vec<Vec<String>> = per_file.into_iter().flatten().collect();
+    if params.output == GrepOutput::Grouped {
+        return grouped_result(matches.iter().flatten().map(String::as_str), max);
+    }
+    let summary = MatchSummary::new(matches.len(), max);
+    // Files are claimed in order, so the first `max` matches are deterministic.
+    let results: Vec<String> = matches.into_iter().take(max).flatten().collect();
+
+    if results.is_empty() {
+        return matches_result(
+            "No matches found. (Note: rg not installed, using basic fallback)".to_owned(),
+            summary,
+        );
+    }
+
+    if params.smart_context {
+        let lines: Vec<&str> = results.iter().map(String::as_str).collect();
+        return matches_result(expand_blocks(&lines, params.context_lines), summary);
+    }
+    matches_result(results.join("\n"), summary)
+}
+
+/// Fallback count mode: tally matches per file without storing lines.
+fn fallback_count(files: &[PathBuf], re: &regex::Regex, params: &GrepParams) -> ToolCallResult {
+    let counts = map_files_parallel(files, &|| false, &|path| {
+        let (content, _) = read_searchable(path, params.search_binary)?;
+        let n: usize = if params.multiline {
+            re.find_iter(&content).count()
+        } else {
+            content.lines().map(|line| re.find_iter(line).count()).sum()
         };
+        (n > 0).then(|| (path.display().to_string(), n))
+    });
+    let counts = counts.into_iter().flatten().collect();
 
-        for (i, line) in content.lines().enumerate() {
-            if count >= params.max_results {
-                return;
-            }
-            if re.is_match(line) {
-                results.push(format!("{}:{}:{}", path.display(), i + 1, line));
-                count += 1;
-            }
+    ToolCallResult::text(format_counts(counts))
+}
+
+/// Render per-file counts as `path: N` lines (sorted by path) followed by `Total: M`.
+fn format_counts(mut counts: Vec<(String, usize)>) -> String {
+    counts.sort();
+    let mut out = String::new();
+    let mut total = 0;
+    for (path, n) in &counts {
+        let _ = writeln!(out, "{path}: {n}");
+        total += n;
+    }
+    let _ = write!(out, "Total: {total}");
+    out
+}
+
+/// The text of the file at `path` to search, and whether it is binary.
+///
+/// Binary files, and files that are not valid UTF-8, are skipped (`None`)
+/// unless `search_binary` is set; then they are decoded lossily.
+fn read_searchable(path: &Path, search_binary: bool) -> Option<(String, bool)> {
+    let bytes = std::fs::read(path).ok()?;
+    let binary = is_binary(&bytes);
+    if !search_binary {
+        if binary {
+            return None;
         }
-    })?;
+        return String::from_utf8(bytes).ok().map(|text| (text, false));
+    }
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    };
+    Some((text, binary))
+}
 
-    if results.is_empty() {
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: "No matches found. (Note: rg not installed, using basic fallback)".to_owned(),
-            }],
-            is_error: false,
-        });
+/// Find up to `max` matches in one file. Each match is rendered as the
+/// `path:line:text` lines it spans (one line unless `multiline`), the text
+/// labelled with [`BINARY_LABEL`] if the file is binary.
+fn match_file(path: &Path, re: &regex::Regex, params: &GrepParams, max: usize) -> Vec<Vec<String>> {
+    let Some((content, binary)) = read_searchable(path, params.search_binary) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}:", path.display());
+    let label = if binary { BINARY_LABEL } else { "" };
+    match_text(&prefix, &content, re, params, max, label)
+}
+
+/// Find up to `max` matches in `content`, rendering each as the
+/// `{prefix}line:{label}text` lines it spans (one line unless `multiline`),
+/// or `{prefix}line:col:{label}text` with `columns`.
+fn match_text(
+    prefix: &str,
+    content: &str,
+    re: &regex::Regex,
+    params: &GrepParams,
+    max: usize,
+    label: &str,
+) -> Vec<Vec<String>> {
+    let render = |n: usize, col: usize, line: &str| {
+        if params.columns {
+            format!("{prefix}{n}:{col}:{label}{line}")
+        } else {
+            format!("{prefix}{n}:{label}{line}")
+        }
+    };
+
+    if params.multiline {
+        // Match against the whole file so patterns can cross line breaks,
+        // then report each line the match spans (like `rg -U`).
+        let lines: Vec<&str> = content.lines().collect();
+        return re
+            .find_iter(content)
+            .take(max)
+            .map(|m| {
+                let line_start = content[..m.start()].rfind('\n').map_or(0, |i| i + 1);
+                let first = content[..m.start()].matches('\n').count() + 1;
+                let last = first + m.as_str().trim_end_matches('\n').matches('\n').count();
+                (first..=last)
+                    .map(|n| {
+                        let line = lines.get(n - 1).copied().unwrap_or_default();
+                        let col = if n == first { m.start() - line_start + 1 } else { 1 };
+                        render(n, col, line)
+                    })
+                    .collect()
+            })
+            .collect();
     }
 
-    Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text: results.join("\n"),
-        }],
-        is_error: false,
-    })
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| re.find(line).map(|m| (i, line, m.start())))
+        .take(max)
+        .map(|(i, line, start)| vec![render(i + 1, start + 1, line)])
+        .collect()
+}
+
+/// Maximum number of fallback worker threads.
+const MAX_WORKERS: usize = 8;
+
+/// Apply `f` to `files` on a small thread pool, returning results in file
+/// order.
+///
+/// Workers claim files in order and stop claiming once `done()` is true, so
+/// the result always covers a complete prefix of `files`.
+fn map_files_parallel<T: Send>(
+    files: &[PathBuf],
+    done: &(dyn Fn() -> bool + Sync),
+    f: &(dyn Fn(&Path) -> T + Sync),
+) -> Vec<T> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, std::num::NonZeroUsize::get)
+        .clamp(1, MAX_WORKERS)
+        .min(files.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while !done() {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = files.get(i) else { break };
+                    let value = f(path);
+                    results.lock().unwrap_or_else(PoisonError::into_inner).push((i, value));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, value)| value).collect()
+}
+
+/// Collect the files to search, sorted by path for deterministic output,
+/// keeping only those of `file_type` if given and skipping hidden entries
+/// unless `hidden` is set.
+fn collect_files(
+    workspace: &Workspace,
+    search_path: &Path,
+    file_type: Option<&str>,
+    hidden: bool,
+) -> Result<Vec<PathBuf>> {
+    let extensions = file_type.and_then(file_type_extensions);
+    let mut files = Vec::new();
+    walk_files(workspace, search_path, hidden, &mut |path| {
+        let wanted = extensions.is_none_or(|extensions| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.contains(&ext))
+        });
+        if wanted {
+            files.push(path.to_path_buf());
+        }
+    })?;
+    files.sort();
+    Ok(files)
 }
 
 /// Maximum recursion depth for fallback file walker.
 const MAX_WALK_DEPTH: usize = 50;
 
-/// Simple recursive file walker (fallback only, no gitignore support).
+/// Simple recursive file walker (fallback only, best-effort gitignore support).
 /// Uses `entry.file_type()` (no symlink following) and depth limit to prevent loops.
-fn walk_files(dir: &Path, cb: &mut impl FnMut(&Path)) -> Result<()> {
-    walk_files_inner(dir, cb, 0)
+/// Symlinks are skipped unless the workspace follows them, and `.`-prefixed
+/// entries below `dir` unless `hidden` is set.
+fn walk_files(
+    workspace: &Workspace,
+    dir: &Path,
+    hidden: bool,
+    cb: &mut impl FnMut(&Path),
+) -> Result<()> {
+    let root = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+    let mut ignores = GitignoreStack::for_ancestors(&root, dir);
+    walk_files_inner(workspace, dir, hidden, cb, &mut ignores, 0)
 }
 
-fn walk_files_inner(dir: &Path, cb: &mut impl FnMut(&Path), depth: usize) -> Result<()> {
+fn walk_files_inner(
+    workspace: &Workspace,
+    dir: &Path,
+    hidden: bool,
+    cb: &mut impl FnMut(&Path),
+    ignores: &mut GitignoreStack,
+    depth: usize,
+) -> Result<()> {
     if depth > MAX_WALK_DEPTH {
         return Ok(()); // Silently stop at max depth.
     }
@@ -255,33 +1170,484 @@ fn walk_files_inner(dir: &Path, cb: &mut impl FnMut(&Path), depth: usize) -> Res
         _ => {}
     }
 
+    walk_dir(workspace, dir, hidden, cb, ignores, depth)
+}
+
+/// Walk the entries of `dir`, which is known to be a directory (possibly
+/// reached through a followed symlink).
+fn walk_dir(
+    workspace: &Workspace,
+    dir: &Path,
+    hidden: bool,
+    cb: &mut impl FnMut(&Path),
+    ignores: &mut GitignoreStack,
+    depth: usize,
+) -> Result<()> {
     let entries = std::fs::read_dir(dir)
         .with_context(|| format!("failed to read directory {}", dir.display()))?;
 
+    let pushed = ignores.push_dir(dir);
+    let result = walk_entries(workspace, entries, hidden, cb, ignores, depth);
+    if pushed {
+        ignores.pop();
+    }
+    result
+}
+
+fn walk_entries(
+    workspace: &Workspace,
+    entries: std::fs::ReadDir,
+    hidden: bool,
+    cb: &mut impl FnMut(&Path),
+    ignores: &mut GitignoreStack,
+    depth: usize,
+) -> Result<()> {
     for entry in entries {
         let entry = entry.context("failed to read directory entry")?;
         let path = entry.path();
 
-        // Skip hidden directories and common noise.
-        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') || name == "node_modules" || name == "target" {
-                continue;
-            }
+        if workspace.skips(&entry.file_name())
+            || (!hidden && entry.file_name().as_encoded_bytes().starts_with(b"."))
+        {
+            continue;
         }
 
         // Use entry.file_type() which does NOT follow symlinks.
-        let ft = match entry.file_type() {
-            Ok(ft) => ft,
-            Err(_) => continue,
+        let Ok(ft) = entry.file_type() else {
+            continue;
         };
 
+        if ft.is_symlink() {
+            // Followed only if the policy allows and the target stays inside.
+            let Some(meta) = workspace.follow_symlink(&path) else {
+                continue;
+            };
+            if ignores.is_ignored(&path, meta.is_dir()) {
+                continue;
+            }
+            if meta.is_dir() && depth < MAX_WALK_DEPTH {
+                walk_dir(workspace, &path, hidden, cb, ignores, depth + 1)?;
+            } else if meta.is_file() {
+                cb(&path);
+            }
+            continue;
+        }
+
+        if ignores.is_ignored(&path, ft.is_dir()) {
+            continue;
+        }
+
         if ft.is_dir() {
-            walk_files_inner(&path, cb, depth + 1)?;
+            walk_files_inner(workspace, &path, hidden, cb, ignores, depth + 1)?;
         } else if ft.is_file() {
             cb(&path);
         }
-        // Symlinks are skipped (ft.is_symlink() falls through).
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pattern: &str) -> GrepParams {
+        serde_json::from_value(serde_json::json!({ "pattern": pattern }))
+            .expect("valid params")
+    }
+
+    #[test]
+    fn test_decode_lines_counts_invalid_utf8() {
+        assert_eq!(decode_lines(b"a:1:ok\n"), ("a:1:ok\n".to_owned(), 0));
+        let (text, lossy) = decode_lines(b"a:1:caf\xe9\na:2:ok\nb:1:\xff\xfe\n");
+        assert_eq!(text, "a:1:caf\u{fffd}\na:2:ok\nb:1:\u{fffd}\u{fffd}\n");
+        assert_eq!(lossy, 2);
+        assert!(lossy_note(2).contains("2 output lines were not valid UTF-8"));
+        assert!(lossy_note(0).is_empty());
+    }
+
+    #[test]
+    fn test_inline_content() {
+        let mut p = params("fo+");
+        p.inline_content = Some("foo\nbar\nfooo baz\n".to_owned());
+        let result = execute_inline(p.inline_content.as_deref().unwrap_or_default(), &p).expect("grep");
+        assert_eq!(result.content[0].text, "1:foo\n3:fooo baz");
+
+        p.count = true;
+        let result = execute_inline("foo foo\nfo\n", &p).expect("grep");
+        assert_eq!(result.content[0].text, "Total: 3");
+
+        p.count = false;
+        p.path = Some("src".to_owned());
+        assert!(execute_inline("foo", &p).expect("grep").is_error);
+    }
+
+    #[test]
+    fn test_fallback_respects_gitignore() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join(".gitignore"), "build/\n").expect("write");
+        std::fs::create_dir_all(root.join("build")).expect("mkdir");
+        std::fs::write(root.join("build/out.txt"), "needle\n").expect("write");
+        std::fs::write(root.join("src.txt"), "needle\n").expect("write");
+
+        let result = execute_fallback(&ws, &root, &params("needle")).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.contains("src.txt"));
+        assert!(!text.contains("out.txt"));
+    }
+
+    #[test]
+    fn test_fallback_uses_skip_list() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        for sub in ["target", ".github", "__pycache__"] {
+            std::fs::create_dir_all(root.join(sub)).expect("mkdir");
+            std::fs::write(root.join(sub).join("a.txt"), "needle\n").expect("write");
+        }
+
+        let ws = Workspace::new(root.clone()).with_skip_names(["__pycache__".to_owned()]);
+        let result = execute_fallback(&ws, &root, &params("needle")).expect("grep");
+        assert_eq!(result.content[0].text, format!("{}/.github/a.txt:1:needle", root.display()));
+    }
+
+    #[test]
+    fn test_fallback_skips_hidden() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::create_dir_all(root.join(".github")).expect("mkdir");
+        std::fs::write(root.join(".github/ci.yml"), "needle\n").expect("write");
+        std::fs::write(root.join(".env.example"), "needle\n").expect("write");
+        std::fs::write(root.join("main.rs"), "needle\n").expect("write");
+
+        let mut p = params("needle");
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.contains(".env.example") && text.contains(".github/ci.yml"), "{text}");
+        p.hidden = false;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        assert_eq!(result.content[0].text, format!("{}/main.rs:1:needle", root.display()));
+    }
+
+    #[test]
+    fn test_fallback_root_gitignore_applies_to_subdir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join(".gitignore"), "*.log\n").expect("write");
+        std::fs::create_dir_all(root.join("sub")).expect("mkdir");
+        std::fs::write(root.join("sub/a.log"), "needle\n").expect("write");
+        std::fs::write(root.join("sub/a.txt"), "needle\n").expect("write");
+
+        let result = execute_fallback(&ws, &root.join("sub"), &params("needle")).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.contains("a.txt"));
+        assert!(!text.contains("a.log"));
+    }
+
+    #[test]
+    fn test_fallback_count() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join("a.txt"), "x x\nx\n").expect("write");
+
+        let mut p = params("x");
+        p.count = true;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.contains("a.txt: 3"));
+        assert!(text.ends_with("Total: 3"));
+    }
+
+    #[test]
+    fn test_fallback_file_type() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join("lib.rs"), "needle\n").expect("write");
+        std::fs::write(root.join("app.tsx"), "needle\n").expect("write");
+        std::fs::write(root.join("notes.txt"), "needle\n").expect("write");
+
+        let mut p = params("needle");
+        p.file_type = Some("ts".to_owned());
+        let text = &execute_fallback(&ws, &root, &p).expect("grep").content[0].text;
+        assert!(text.contains("app.tsx"));
+        assert!(!text.contains("lib.rs") && !text.contains("notes.txt"));
+
+        p.file_type = Some("rust".to_owned());
+        p.count = true;
+        let text = &execute_fallback(&ws, &root, &p).expect("grep").content[0].text;
+        assert!(text.contains("lib.rs: 1"));
+        assert!(text.ends_with("Total: 1"));
+
+        // Unknown types are refused before searching, rg or not.
+        let args = serde_json::json!({ "pattern": "needle", "fileType": "cobol" });
+        let result = execute(&ws, args, &CancelToken::new(), false).expect("grep");
+        assert!(result.is_error);
+        assert!(result.content[0].text.contains("supported types: rust, js, ts, py, go, c, cpp"));
+    }
+
+    #[test]
+    fn test_fallback_grouped() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join("b.txt"), "needle 1\nhay\nneedle 3\n").expect("write");
+        std::fs::write(root.join("a.txt"), "hay\nneedle 2\n").expect("write");
+        std::fs::write(root.join("c.txt"), "needle 1\n").expect("write");
+
+        let mut p = params("needle");
+        p.output = GrepOutput::Grouped;
+        p.max_results = 3;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        let groups: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+        let path = |name: &str| root.join(name).display().to_string();
+        assert_eq!(
+            groups,
+            serde_json::json!([
+                { "path": path("a.txt"), "matches": [{ "line": 2, "text": "needle 2" }] },
+                { "path": path("b.txt"), "matches": [
+                    { "line": 1, "text": "needle 1" },
+                    { "line": 3, "text": "needle 3" },
+                ] },
+            ])
+        );
+        assert!(result.content[1].text.contains("\"truncated\":true"));
+
+        let args = serde_json::json!({ "pattern": "needle", "output": "grouped", "count": true });
+        assert!(execute(&ws, args, &CancelToken::new(), false).expect("grep").is_error);
+    }
+
+    #[test]
+    fn test_fallback_columns() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join("a.txt"), "  needle\nhay\nh\u{e9} needle needle\n").expect("write");
+
+        let mut p = params("needle");
+        p.columns = true;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        let path = root.join("a.txt").display().to_string();
+        assert_eq!(
+            result.content[0].text,
+            format!("{path}:1:3:  needle\n{path}:3:5:h\u{e9} needle needle")
+        );
+
+        // A multiline match reports its start column, then column 1.
+        p.pattern = "hay\\nh".to_owned();
+        p.multiline = true;
+        let result = execute_inline("x hay\nhay\n", &p).expect("grep");
+        assert_eq!(result.content[0].text, "1:3:x hay\n2:1:hay");
+
+        p.multiline = false;
+        p.pattern = "needle".to_owned();
+        let result = execute_inline("a needle", &p).expect("grep");
+        assert_eq!(result.content[0].text, "1:3:a needle");
+
+        let args = serde_json::json!({ "pattern": "needle", "columns": true, "count": true });
+        assert!(execute(&ws, args, &CancelToken::new(), false).expect("grep").is_error);
+    }
+
+    #[test]
+    fn test_fallback_search_binary() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        std::fs::write(root.join("app.bin"), b"\x7fELF\0\0\xff version 1.2.3\0\n").expect("write");
+        std::fs::write(root.join("notes.txt"), "version 1.2.3\n").expect("write");
+        let text_match = format!("{}:1:version 1.2.3", root.join("notes.txt").display());
+
+        let mut p = params(r"version \d+\.\d+\.\d+");
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        assert_eq!(result.content[0].text, text_match);
+
+        p.search_binary = true;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        let lines: Vec<&str> = result.content[0].text.lines().collect();
+        let binary_prefix = format!("{}:1:{BINARY_LABEL}\x7fELF", root.join("app.bin").display());
+        assert_eq!(lines.len(), 2, "{lines:?}");
+        assert!(lines[0].starts_with(&binary_prefix), "{lines:?}");
+        assert_eq!(lines[1], text_match);
+
+        let args = serde_json::json!({ "pattern": "version", "searchBinary": true, "contextLines": 1 });
+        assert!(execute(&ws, args, &CancelToken::new(), false).expect("grep").is_error);
+    }
+
+    #[test]
+    fn test_fallback_parallel_matches_serial_order() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let ws = Workspace::new(root.clone());
+        let mut expected = Vec::new();
+        for d in 0..10 {
+            let sub = root.join(format!("d{d:02}"));
+            std::fs::create_dir_all(&sub).expect("mkdir");
+            for f in 0..30 {
+                let path = sub.join(format!("f{f:02}.txt"));
+                std::fs::write(&path, "noise\nneedle one\nnoise\nneedle two\n").expect("write");
+                expected.push(format!("{}:2:needle one", path.display()));
+                expected.push(format!("{}:4:needle two", path.display()));
+            }
+        }
+
+        let mut p = params("needle");
+        p.max_results = 10_000;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        assert_eq!(result.content[0].text, expected.join("\n"));
+
+        // Truncation keeps the first matches in path order.
+        p.max_results = 25;
+        let result = execute_fallback(&ws, &root, &p).expect("grep");
+        assert_eq!(result.content[0].text, expected[..25].join("\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fallback_follows_symlinks_inside_workspace() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let outside = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::create_dir(root.join("real")).expect("mkdir");
+        std::fs::write(root.join("real/a.txt"), "needle\n").expect("write");
+        std::fs::write(outside.path().join("secret.txt"), "needle\n").expect("write");
+        std::os::unix::fs::symlink(root.join("real"), root.join("link")).expect("symlink");
+        std::os::unix::fs::symlink(outside.path(), root.join("out")).expect("symlink");
+        std::os::unix::fs::symlink(&root, root.join("real/loop")).expect("symlink");
+
+        let ws = Workspace::new(root.clone());
+        let result = execute_fallback(&ws, &root, &params("needle")).expect("grep");
+        assert_eq!(result.content[0].text, format!("{}/real/a.txt:1:needle", root.display()));
+
+        let ws = ws.with_follow_symlinks(true);
+        let result = execute_fallback(&ws, &root, &params("needle")).expect("grep");
+        assert_eq!(
+            result.content[0].text,
+            format!("{0}/link/a.txt:1:needle\n{0}/real/a.txt:1:needle", root.display())
+        );
+    }
+
+    #[test]
+    fn test_truncate_keeps_context_groups_whole() {
+        // rg -C 2 output: three hunks separated by `--`.
+        let stdout = "\
+/w/a.txt-1-one
+/w/a.txt-2-two
+/w/a.txt:3:needle
+/w/a.txt-4-four
+/w/a.txt-5-five
+--
+/w/a.txt-9-nine
+/w/a.txt:10:needle
+/w/a.txt-11-eleven
+--
+/w/b.txt:1:needle
+/w/b.txt-2-two
+";
+        let (text, summary) = truncate_results(stdout.to_owned(), 3, true);
+        assert_eq!(text, stdout);
+        assert_eq!(summary, MatchSummary::new(3, 3));
+        assert!(!summary.truncated);
+
+        let (text, summary) = truncate_results(stdout.to_owned(), 2, true);
+        assert!(summary.truncated);
+        assert_eq!((summary.returned, summary.total_at_least), (2, 3));
+        assert_eq!(
+            text,
+            "\
+/w/a.txt-1-one
+/w/a.txt-2-two
+/w/a.txt:3:needle
+/w/a.txt-4-four
+/w/a.txt-5-five
+--
+/w/a.txt-9-nine
+/w/a.txt:10:needle
+/w/a.txt-11-eleven
+
+... truncated (2 match groups shown out of 3+)"
+        );
+    }
+
+    #[test]
+    fn test_rg_columns() {
+        let Ok(rg) = which::which("rg") else {
+            return; // ripgrep not installed.
+        };
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::write(root.join("a.txt"), "  needle\nhay\nh\u{e9} needle needle\n").expect("write");
+
+        let mut p = params("needle");
+        p.columns = true;
+        let ws = Workspace::new(root.clone());
+        let result = execute_rg(&rg, &ws, &root, &p, &CancelToken::new()).expect("grep");
+        let path = root.join("a.txt").display().to_string();
+        assert_eq!(
+            result.content[0].text.trim_end(),
+            format!("{path}:1:3:  needle\n{path}:3:5:h\u{e9} needle needle")
+        );
+    }
+
+    #[test]
+    fn test_rg_search_binary() {
+        let Ok(rg) = which::which("rg") else {
+            return; // ripgrep not installed.
+        };
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::write(root.join("app.bin"), b"\x7fELF\0\0 version 1.2.3\0\n").expect("write");
+        std::fs::write(root.join("notes.txt"), "version 1.2.3\n").expect("write");
+        let ws = Workspace::new(root.clone());
+
+        let mut p = params("version");
+        let result = execute_rg(&rg, &ws, &root, &p, &CancelToken::new()).expect("grep");
+        assert!(!result.content[0].text.contains("app.bin:1:"), "{}", result.content[0].text);
+
+        p.search_binary = true;
+        p.columns = true;
+        let result = execute_rg(&rg, &ws, &root, &p, &CancelToken::new()).expect("grep");
+        let text = &result.content[0].text;
+        let binary_prefix = format!("{}:1:8:{BINARY_LABEL}\x7fELF", root.join("app.bin").display());
+        assert!(text.lines().any(|line| line.starts_with(&binary_prefix)), "{text}");
+        let text_match = format!("{}:1:1:version 1.2.3", root.join("notes.txt").display());
+        assert!(text.lines().any(|line| line == text_match), "{text}");
+
+        // A single-file search prints no path, but is labelled all the same.
+        let bin = root.join("app.bin");
+        let result = execute_rg(&rg, &ws, &bin, &p, &CancelToken::new()).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.starts_with(&format!("1:8:{BINARY_LABEL}")), "{text}");
+    }
+
+    #[test]
+    fn test_rg_context_separators_survive() {
+        let Ok(rg) = which::which("rg") else {
+            return; // ripgrep not installed.
+        };
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let content: String = (1..=20)
+            .map(|n| if n % 10 == 0 { "needle\n".to_owned() } else { format!("line {n}\n") })
+            .collect();
+        std::fs::write(root.join("a.txt"), content).expect("write");
+
+        let mut p = params("needle");
+        p.context_lines = 2;
+        let ws = Workspace::new(root.clone());
+        let result = execute_rg(&rg, &ws, &root, &p, &CancelToken::new()).expect("grep");
+        let text = &result.content[0].text;
+        assert_eq!(text.lines().filter(|l| *l == "--").count(), 1, "got:\n{text}");
+        assert!(text.contains(":10:needle") && text.contains(":20:needle"), "got:\n{text}");
+
+        p.max_results = 1;
+        let result = execute_rg(&rg, &ws, &root, &p, &CancelToken::new()).expect("grep");
+        let text = &result.content[0].text;
+        assert!(text.contains("-12-line 12"), "got:\n{text}");
+        assert!(!text.contains(":20:needle"), "got:\n{text}");
+        assert!(text.ends_with("(1 match groups shown out of 2+)"), "got:\n{text}");
+    }
+}
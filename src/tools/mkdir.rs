@@ -0,0 +1,89 @@
+//! Mkdir tool — create a directory, e.g. an empty one for `.gitkeep`-style
+//! conventions that no file write would create.
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MkdirParams {
+    /// Directory to create.
+    pub path: String,
+    /// Like `mkdir -p`: create missing parents, and succeed if the directory
+    /// already exists (default: false).
+    #[serde(default)]
+    pub parents: bool,
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "mkdir".to_owned(),
+        description: "Create a directory. With parents (like mkdir -p), missing parent \
+            directories are created too and an existing directory is not an error."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Directory to create"
+                },
+                "parents": {
+                    "type": "boolean",
+                    "description": "Create missing parent directories and accept an existing directory (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["path"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: MkdirParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_dir_path(workspace, &params.path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+
+    if path.exists() {
+        if !path.is_dir() {
+            return Ok(ToolCallResult::error(format!(
+                "Error: {} exists and is not a directory",
+                path.display()
+            )));
+        }
+        if !params.parents {
+            return Ok(ToolCallResult::error(format!(
+                "Error: directory already exists: {} (set parents to accept it)",
+                path.display()
+            )));
+        }
+        return Ok(ToolCallResult::text(format!("Directory already exists: {}", path.display())));
+    }
+
+    let created = if params.parents {
+        std::fs::create_dir_all(&path)
+    } else {
+        std::fs::create_dir(&path)
+    };
+    if let Err(e) = created {
+        let hint = if e.kind() == std::io::ErrorKind::NotFound {
+            " (parent directory is missing; set parents to create it)"
+        } else {
+            ""
+        };
+        return Ok(ToolCallResult::error(format!(
+            "Error: failed to create {}: {e}{hint}",
+            path.display()
+        )));
+    }
+
+    Ok(ToolCallResult::text(format!("Created directory: {}", path.display())))
+}
@@ -5,41 +5,372 @@
 //! provides `list_tools()` / `call_tool()` for the MCP server.
 
 pub mod bash;
+pub mod blame;
+pub mod copy;
 pub mod edit;
+pub mod format;
+pub mod git_diff;
 pub mod glob;
 pub mod grep;
+pub mod mkdir;
 pub mod read;
+pub mod rename_symbol;
+pub mod replace_in_files;
+pub mod search;
+pub mod stat;
+pub mod symbols;
+pub mod touch;
+pub mod tree;
 pub mod write;
+pub mod write_many;
 
 use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
-use anyhow::{bail, Result};
-use tracing::debug;
+use anyhow::{bail, Context, Result};
+use tracing::{debug, warn};
 
-use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
+use crate::error::CoderError;
+use crate::server::{Progress, ToolCallResult, ToolContext, ToolDefinition};
+use crate::util::eof_newline::EofNewline;
+use crate::util::path_cache::PathCache;
+use crate::util::redact::Redactor;
+
+/// The directories tools may access.
+///
+/// The first root is the workspace proper: relative paths resolve against it
+/// and it is what `bash` runs in. Additional roots (e.g. a dependency
+/// checkout) are reachable by absolute path. Derefs to the primary root.
+///
+/// Directory walkers (glob, grep) skip symlinks unless `follow_symlinks` is
+/// set, in which case a link is followed only if its target resolves inside
+/// an allowed root. They also skip entries named in the skip list
+/// ([`DEFAULT_SKIP_NAMES`] unless changed).
+///
+/// Canonical roots and recently validated paths are cached briefly (see
+/// [`PathCache`]); clones share the cache.
+///
+/// A case-insensitive workspace compares paths against its roots without
+/// regard to case (see [`crate::util::case_fold`]), for filesystems where
+/// `SRC/main.rs` and `src/main.rs` are the same file.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    roots: Vec<PathBuf>,
+    follow_symlinks: bool,
+    case_insensitive: bool,
+    max_file_bytes: u64,
+    skip_names: Vec<String>,
+    eof_newline: EofNewline,
+    cache: Arc<PathCache>,
+}
+
+/// File and directory names the walkers skip by default: VCS metadata and
+/// the usual dependency/build output directories.
+pub const DEFAULT_SKIP_NAMES: &[&str] = &[".git", ".hg", ".svn", "node_modules", "target"];
+
+/// Largest file `read` and `edit` load whole (see [`Workspace::check_file_size`]).
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+fn default_skip_names() -> Vec<String> {
+    DEFAULT_SKIP_NAMES.iter().map(|&name| name.to_owned()).collect()
+}
+
+impl Workspace {
+    /// A workspace with a single root.
+    #[must_use]
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            roots: vec![root],
+            follow_symlinks: false,
+            case_insensitive: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            skip_names: default_skip_names(),
+            eof_newline: EofNewline::Preserve,
+            cache: Arc::default(),
+        }
+    }
+
+    /// A workspace with a primary root plus additional allowed roots.
+    #[must_use]
+    pub fn with_roots(root: PathBuf, additional: impl IntoIterator<Item = PathBuf>) -> Self {
+        let mut roots = vec![root];
+        roots.extend(additional);
+        Self {
+            roots,
+            follow_symlinks: false,
+            case_insensitive: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            skip_names: default_skip_names(),
+            eof_newline: EofNewline::Preserve,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Set whether directory walkers follow symlinks (default: no).
+    #[must_use]
+    pub const fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Set whether paths are compared to the roots ignoring case (default:
+    /// no). See [`crate::util::case_fold::is_case_insensitive`] to detect it.
+    #[must_use]
+    pub const fn with_case_insensitive(mut self, insensitive: bool) -> Self {
+        self.case_insensitive = insensitive;
+        self
+    }
+
+    /// Set the largest file `read` and `edit` load whole.
+    #[must_use]
+    pub const fn with_max_file_bytes(mut self, max: u64) -> Self {
+        self.max_file_bytes = max;
+        self
+    }
+
+    /// The largest file `read` and `edit` load whole.
+    #[must_use]
+    pub const fn max_file_bytes(&self) -> u64 {
+        self.max_file_bytes
+    }
+
+    /// Fail with [`CoderError::FileTooLarge`] if the file at `path` is over
+    /// [`max_file_bytes`](Self::max_file_bytes). Only its metadata is read;
+    /// a file that cannot be stat'ed passes, for the read that follows to
+    /// report.
+    pub(crate) fn check_file_size(&self, path: &Path) -> Result<()> {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+        let size = meta.len();
+        if size > self.max_file_bytes {
+            return Err(CoderError::FileTooLarge {
+                path: path.to_path_buf(),
+                size,
+                limit: self.max_file_bytes,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Add `names` to the walkers' skip list.
+    #[must_use]
+    pub fn with_skip_names(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        for name in names {
+            if !self.skip_names.contains(&name) {
+                self.skip_names.push(name);
+            }
+        }
+        self
+    }
+
+    /// Remove `names` from the walkers' skip list (e.g. to search `target`).
+    #[must_use]
+    pub fn without_skip_names(mut self, names: &[String]) -> Self {
+        self.skip_names.retain(|name| !names.contains(name));
+        self
+    }
+
+    /// The names directory walkers skip.
+    #[must_use]
+    pub fn skip_names(&self) -> &[String] {
+        &self.skip_names
+    }
+
+    /// Set the newline-at-end-of-file policy of the writing tools (default:
+    /// [`EofNewline::Preserve`]).
+    #[must_use]
+    pub const fn with_eof_newline(mut self, policy: EofNewline) -> Self {
+        self.eof_newline = policy;
+        self
+    }
+
+    /// The newline-at-end-of-file policy of the writing tools.
+    #[must_use]
+    pub const fn eof_newline(&self) -> EofNewline {
+        self.eof_newline
+    }
+
+    /// Whether directory walkers skip an entry called `name`.
+    pub(crate) fn skips(&self, name: &std::ffi::OsStr) -> bool {
+        self.skip_names.iter().any(|skip| name == skip.as_str())
+    }
+
+    /// The primary root.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.roots[0]
+    }
+
+    /// This workspace with `root` as its primary root, other settings and
+    /// additional roots unchanged. The path cache is not shared with `self`.
+    #[must_use]
+    pub fn with_primary_root(&self, root: PathBuf) -> Self {
+        let mut workspace = self.clone();
+        workspace.roots[0] = root;
+        workspace.cache = Arc::default();
+        workspace
+    }
+
+    /// All allowed roots, primary first.
+    #[must_use]
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Whether directory walkers follow symlinks.
+    #[must_use]
+    pub const fn follows_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// Whether paths are compared to the roots ignoring case.
+    #[must_use]
+    pub const fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Whether a canonical path lies under one of the roots.
+    pub(crate) fn contains(&self, canonical_path: &Path) -> bool {
+        // Canonicalize roots too (resolves symlinks like /var → /private/var
+        // on macOS).
+        self.cache
+            .canonical_roots(&self.roots)
+            .iter()
+            .any(|root| self.starts_with(canonical_path, root))
+    }
+
+    /// Whether a canonical path lies inside the primary root.
+    pub(crate) fn in_primary_root(&self, canonical_path: &Path) -> bool {
+        self.cache
+            .canonical_roots(&self.roots)
+            .first()
+            .is_some_and(|root| self.starts_with(canonical_path, root))
+    }
+
+    /// Whether a cached canonical path still holds: it lies inside a root
+    /// and no component below that root has become a symlink since it was
+    /// resolved (one `lstat` per component).
+    fn still_resolved(&self, canonical_path: &Path) -> bool {
+        let roots = self.cache.canonical_roots(&self.roots);
+        let Some(root) = roots.iter().find(|root| self.starts_with(canonical_path, root)) else {
+            return false;
+        };
+        let root_depth = root.components().count();
+        canonical_path
+            .ancestors()
+            .take_while(|ancestor| ancestor.components().count() > root_depth)
+            .all(|ancestor| {
+                ancestor
+                    .symlink_metadata()
+                    .is_ok_and(|meta| !meta.file_type().is_symlink())
+            })
+    }
+
+    /// `path.starts_with(base)`, ignoring case if the workspace does.
+    fn starts_with(&self, path: &Path, base: &Path) -> bool {
+        if self.case_insensitive {
+            crate::util::case_fold::starts_with_ignore_case(path, base)
+        } else {
+            path.starts_with(base)
+        }
+    }
+
+    /// Decide whether a walker should follow the symlink `link`.
+    ///
+    /// Returns the target's metadata if symlinks are followed, the target
+    /// resolves inside an allowed root, and it is not a directory containing
+    /// the link itself (which would loop). Longer cycles through several
+    /// links are cut off by the walkers' depth limits.
+    pub(crate) fn follow_symlink(&self, link: &Path) -> Option<std::fs::Metadata> {
+        if !self.follow_symlinks {
+            return None;
+        }
+        let target = link.canonicalize().ok()?;
+        if !self.contains(&target) {
+            return None;
+        }
+        let meta = std::fs::metadata(&target).ok()?;
+        if meta.is_dir() {
+            let parent = link.parent()?.canonicalize().ok()?;
+            if self.starts_with(&parent, &target) {
+                return None;
+            }
+        }
+        Some(meta)
+    }
+}
+
+impl std::ops::Deref for Workspace {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        self.root()
+    }
+}
+
+/// Most symlinks followed while resolving a dangling link in [`validate_path`].
+const MAX_SYMLINK_HOPS: usize = 40;
 
 /// Resolve and validate a file path, ensuring it stays within the workspace.
 ///
-/// Rejects null bytes, `..` traversal escapes, and symlinks that resolve outside
-/// the workspace boundary. Returns the canonicalized (or safely joined) path.
-pub fn validate_path(workspace: &Path, file_path: &str) -> Result<PathBuf> {
+/// Relative paths resolve against the primary root. Rejects null bytes, `..`
+/// traversal escapes, and symlinks that resolve outside every allowed root.
+/// Returns the canonicalized (or safely joined) path.
+///
+/// Symlinks are always resolved, so tools act on the link's target and
+/// writes go through to it (the link itself is never replaced). This
+/// includes a dangling link, whose not-yet-existing target is validated in
+/// its place: a write through it creates the target inside the roots, or is
+/// refused if the target lies outside them.
+///
+/// Existing paths are remembered for a short while (see [`PathCache`]); a
+/// cached resolution is reused only if it is still inside a root and none of
+/// its components below that root has since been replaced by a symlink.
+pub fn validate_path(workspace: &Workspace, file_path: &str) -> Result<PathBuf> {
     // Reject null bytes (can bypass C-based path APIs).
     if file_path.contains('\0') {
         bail!("path contains null byte");
     }
 
-    let raw_path = if Path::new(file_path).is_absolute() {
+    let requested = if Path::new(file_path).is_absolute() {
         PathBuf::from(file_path)
     } else {
         workspace.join(file_path)
     };
 
-    // Canonicalize workspace first (resolves symlinks like /var → /private/var on macOS).
-    let canonical_workspace = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+    if let Some(cached) = workspace.cache.get(&requested) {
+        if workspace.still_resolved(&cached) {
+            return Ok(cached);
+        }
+        workspace.cache.remove(&requested);
+    }
+
+    let mut raw_path = requested.clone();
+
+    // `exists()` follows links, so a dangling one would otherwise pass as a
+    // new file and be written through (or replaced) without its target
+    // being checked.
+    let mut hops = 0;
+    while !raw_path.exists() && raw_path.is_symlink() {
+        hops += 1;
+        if hops > MAX_SYMLINK_HOPS {
+            bail!("too many levels of symbolic links: {file_path}");
+        }
+        let target = std::fs::read_link(&raw_path)
+            .with_context(|| format!("failed to read symlink {}", raw_path.display()))?;
+        raw_path = match raw_path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        };
+    }
 
     // For existing paths, canonicalize to resolve symlinks and `..`.
     // For non-existing paths (write/edit create), normalize manually.
-    let canonical_path = if raw_path.exists() {
+    let existed = raw_path.exists();
+    let canonical_path = if existed {
         raw_path.canonicalize()?
     } else {
         // Find the deepest existing ancestor and canonicalize that.
@@ -67,49 +398,356 @@ pub fn validate_path(workspace: &Path, file_path: &str) -> Result<PathBuf> {
         }
     };
 
-    // Verify the resolved path is within the workspace.
-    if !canonical_path.starts_with(&canonical_workspace) {
-        bail!(
-            "path escapes workspace boundary: {}",
-            file_path
-        );
+    // Verify the resolved path is within one of the roots.
+    if !workspace.contains(&canonical_path) {
+        bail!("path escapes workspace boundary: {file_path}");
     }
 
+    // Only existing paths: a new path's resolution changes once it is created.
+    if existed {
+        workspace.cache.insert(requested, canonical_path.clone());
+    }
     Ok(canonical_path)
 }
 
+/// Atomically write `content` to `file_path`, first copying the existing
+/// file to a `<name>.bak` sidecar when `backup` is set.
+///
+/// The sidecar path is validated like any tool path (so a planted `.bak`
+/// symlink cannot redirect the copy outside the workspace) and is removed
+/// again if the write fails. Returns the backup path, if one was made.
+pub(crate) fn write_with_backup(
+    workspace: &Workspace,
+    file_path: &Path,
+    content: &str,
+    backup: bool,
+) -> Result<Option<PathBuf>> {
+    let backup_path = if backup && file_path.exists() {
+        let mut name = file_path
+            .file_name()
+            .with_context(|| format!("no file name in {}", file_path.display()))?
+            .to_os_string();
+        name.push(".bak");
+        let sidecar = file_path.with_file_name(name);
+        let sidecar = sidecar
+            .to_str()
+            .with_context(|| format!("backup path is not valid UTF-8: {}", sidecar.display()))?;
+        let sidecar = validate_path(workspace, sidecar)?;
+        std::fs::copy(file_path, &sidecar)
+            .with_context(|| format!("failed to back up {} to {}", file_path.display(), sidecar.display()))?;
+        Some(sidecar)
+    } else {
+        None
+    };
+
+    if let Err(e) = crate::util::atomic::atomic_write(file_path, content) {
+        if let Some(sidecar) = &backup_path {
+            let _ = std::fs::remove_file(sidecar);
+        }
+        return Err(e);
+    }
+    Ok(backup_path)
+}
+
+/// Max bytes checked for NUL when the bulk-editing tools skip binary files.
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// The files a bulk-editing tool (`rename_symbol`, `replace_in_files`) looks
+/// at: those under `search_dir` matching `globs`, selected like the `glob`
+/// tool (gitignore-aware, hidden entries included), sorted.
+///
+/// # Errors
+///
+/// Fails if more than `max_files` match, or the walk fails.
+pub(crate) fn bulk_edit_files(
+    workspace: &Workspace,
+    search_dir: &Path,
+    globs: &[String],
+    max_files: usize,
+) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> =
+        glob::find_files(workspace, search_dir, globs, &[], false, true, max_files + 1)?
+            .into_iter()
+            .map(|rel| search_dir.join(rel))
+            .collect();
+    if files.len() > max_files {
+        anyhow::bail!(
+            "{} matches more than {max_files} files; narrow the glob or path",
+            globs.join(", ")
+        );
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The content of `path` for a bulk edit, or `None` if it cannot be read or
+/// is not UTF-8 text (a NUL in its first 8KB marks it binary).
+///
+/// # Errors
+///
+/// Fails with [`CoderError::FileTooLarge`] over the workspace's size limit.
+pub(crate) fn read_text_file(workspace: &Workspace, path: &Path) -> Result<Option<String>> {
+    workspace.check_file_size(path)?;
+    Ok(std::fs::read(path)
+        .ok()
+        .filter(|bytes| !bytes[..bytes.len().min(BINARY_CHECK_BYTES)].contains(&0))
+        .and_then(|bytes| String::from_utf8(bytes).ok()))
+}
+
+/// Deserialize a tool's arguments into its parameter struct.
+///
+/// On failure the error names the tool, gives serde's reason and lists the
+/// fields accepted by the tool's input schema, so a misspelt or misplaced
+/// argument can be corrected without a second `tools/list`.
+pub(crate) fn parse_params<T: serde::de::DeserializeOwned>(
+    arguments: serde_json::Value,
+    definition: fn() -> ToolDefinition,
+) -> Result<T> {
+    serde_json::from_value(arguments).map_err(|e| {
+        let definition = definition();
+        let schema = &definition.input_schema;
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .map(|fields| fields.iter().filter_map(serde_json::Value::as_str).collect())
+            .unwrap_or_default();
+        let accepted: Vec<String> = schema["properties"]
+            .as_object()
+            .map(|properties| {
+                properties
+                    .keys()
+                    .map(|name| {
+                        if required.contains(&name.as_str()) {
+                            format!("{name} (required)")
+                        } else {
+                            name.clone()
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        anyhow::anyhow!(
+            "invalid {} parameters: {e}. Accepted fields: {}",
+            definition.name,
+            accepted.join(", ")
+        )
+    })
+}
+
+/// Deserialize a parameter that accepts either a single string or an array of strings.
+pub(crate) fn string_or_vec<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match <OneOrMany as serde::Deserialize>::deserialize(deserializer)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
 /// Resolve a directory path for grep/glob operations, ensuring it stays within workspace.
-pub fn validate_dir_path(workspace: &Path, dir_path: &str) -> Result<PathBuf> {
+pub fn validate_dir_path(workspace: &Workspace, dir_path: &str) -> Result<PathBuf> {
     validate_path(workspace, dir_path)
 }
 
+/// Which tools a router exposes.
+///
+/// A tool is enabled if it is in `allow` (or `allow` is `None`) and not in
+/// `deny`; deny overrides allow. Tools that run a caller-supplied command
+/// (`format`) also need `bash` to be enabled.
+#[derive(Debug, Clone, Default)]
+pub struct ToolFilter {
+    /// If set, only these tools are exposed.
+    pub allow: Option<Vec<String>>,
+    /// Tools that are never exposed, even if allowed.
+    pub deny: Vec<String>,
+}
+
+impl ToolFilter {
+    /// Whether the tool `name` is enabled.
+    #[must_use]
+    pub fn is_enabled(&self, name: &str) -> bool {
+        let allowed = self
+            .allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(|a| a == name));
+        let enabled = allowed && !self.deny.iter().any(|d| d == name);
+        enabled && (name != "format" || self.is_enabled("bash"))
+    }
+}
+
 /// Tool router that dispatches MCP tool calls to implementations.
+#[derive(Clone)]
 pub struct ToolRouter {
-    /// Working directory for file operations.
-    workspace: PathBuf,
+    /// Directories file operations may access (primary root first).
+    workspace: Workspace,
     /// Whether bash tool uses sandbox.
     sandboxed: bool,
+    /// Which tools are exposed.
+    filter: ToolFilter,
+    /// Whether grep/search fail instead of falling back when `rg` is missing.
+    require_ripgrep: bool,
+    /// Longest a single tool call may run (no limit when `None`).
+    call_timeout: Option<Duration>,
+    /// Secret masking for command output (off when `None`).
+    redactor: Option<Arc<Redactor>>,
+    /// Timeout of `bash` calls that give none.
+    bash_timeout: Duration,
+    /// Ceiling on the timeout a `bash` call may ask for.
+    bash_max_timeout: Duration,
 }
 
 impl ToolRouter {
     /// Create a new tool router.
     pub fn new(workspace: PathBuf, sandboxed: bool) -> Self {
+        Self::with_workspace(Workspace::new(workspace), sandboxed)
+    }
+
+    /// Create a tool router over a (possibly multi-root) workspace.
+    #[must_use]
+    pub const fn with_workspace(workspace: Workspace, sandboxed: bool) -> Self {
         Self {
             workspace,
             sandboxed,
+            filter: ToolFilter {
+                allow: None,
+                deny: Vec::new(),
+            },
+            require_ripgrep: false,
+            call_timeout: None,
+            redactor: None,
+            bash_timeout: bash::DEFAULT_TIMEOUT,
+            bash_max_timeout: bash::DEFAULT_MAX_TIMEOUT,
         }
     }
 
-    /// List all available tools with their JSON Schema definitions.
+    /// Restrict the tools this router exposes.
+    #[must_use]
+    pub fn with_filter(mut self, filter: ToolFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Make grep and search fail with [`CoderError::RipgrepNotFound`] rather
+    /// than use the built-in fallback when `rg` is not installed.
+    #[must_use]
+    pub const fn with_require_ripgrep(mut self, require: bool) -> Self {
+        self.require_ripgrep = require;
+        self
+    }
+
+    /// Bound every tool call to `timeout`. Calls then run on a worker thread;
+    /// one that overruns is answered with an `is_error` timeout result and
+    /// its cancel token is fired. `bash` and `format` kill their command,
+    /// and `grep`/`search` kill `rg`, as soon as they notice; the other
+    /// tools (including the built-in grep fallback) cannot be interrupted,
+    /// so their thread is detached and left to finish in the background —
+    /// a timed-out `write` or `edit` may still land.
+    #[must_use]
+    pub const fn with_call_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+
+    /// Mask secrets in the output of `bash` and the tools built on it.
+    #[must_use]
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.redactor = redactor.map(Arc::new);
+        self
+    }
+
+    /// Set the timeout of `bash` calls that do not give one, and the ceiling
+    /// larger requested timeouts are clamped to. A `default` above `max` is
+    /// clamped as well.
+    #[must_use]
+    pub const fn with_bash_timeouts(mut self, default: Duration, max: Duration) -> Self {
+        self.bash_timeout = default;
+        self.bash_max_timeout = max;
+        self
+    }
+
+    /// The workspace this router operates on.
+    #[must_use]
+    pub const fn workspace(&self) -> &Workspace {
+        &self.workspace
+    }
+
+    /// A copy of this router whose primary workspace root is `root` (see
+    /// [`Workspace::with_primary_root`]).
+    #[must_use]
+    pub fn with_workspace_root(&self, root: PathBuf) -> Self {
+        Self {
+            workspace: self.workspace.with_primary_root(root),
+            ..self.clone()
+        }
+    }
+
+    /// Whether `bash` (and the tools built on it) asked for the sandbox.
+    #[must_use]
+    pub const fn sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    /// Whether grep and search refuse to fall back when `rg` is missing.
+    #[must_use]
+    pub const fn requires_ripgrep(&self) -> bool {
+        self.require_ripgrep
+    }
+
+    /// List the enabled tools with their JSON Schema definitions.
     pub fn list_tools(&self) -> Vec<ToolDefinition> {
-        vec![
+        let mut tools = vec![
             edit::tool_definition(),
             read::tool_definition(),
             write::tool_definition(),
             grep::tool_definition(),
             glob::tool_definition(),
             bash::tool_definition(),
-        ]
+            tree::tool_definition(),
+            search::tool_definition(),
+            write_many::tool_definition(),
+            rename_symbol::tool_definition(),
+            stat::tool_definition(),
+            format::tool_definition(),
+            copy::tool_definition(),
+            mkdir::tool_definition(),
+            git_diff::tool_definition(),
+            replace_in_files::tool_definition(),
+            touch::tool_definition(),
+            symbols::tool_definition(),
+            blame::tool_definition(),
+        ];
+        tools.retain(|tool| self.filter.is_enabled(&tool.name));
+        tools
+    }
+
+    /// One page of [`list_tools`](Self::list_tools): up to `page_size`
+    /// definitions starting at `cursor` (the `nextCursor` of the previous
+    /// page), plus the cursor of the next page if more remain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor is invalid.
+    pub fn list_tools_page(
+        &self,
+        cursor: Option<&str>,
+        page_size: usize,
+    ) -> Result<(Vec<ToolDefinition>, Option<String>)> {
+        let offset = match cursor {
+            Some(c) => c.parse::<usize>().with_context(|| format!("invalid cursor: {c}"))?,
+            None => 0,
+        };
+        let tools = self.list_tools();
+        let total = tools.len();
+        let page = tools.into_iter().skip(offset).take(page_size).collect();
+        let next = offset.saturating_add(page_size);
+        Ok((page, (next < total).then(|| next.to_string())))
     }
 
     /// Call a tool by name with the given JSON arguments.
@@ -122,25 +760,125 @@ impl ToolRouter {
         name: &str,
         arguments: serde_json::Value,
     ) -> Result<ToolCallResult> {
-        debug!(tool = name, "dispatching tool call");
+        self.call_tool_with_context(name, arguments, &mut ToolContext::none())
+    }
+
+    /// Call a tool with a per-call context, letting long-running tools report
+    /// incremental progress and observe cancellation. The call runs in a
+    /// `tool_call` span carrying the tool name and `ctx.request_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CoderError::ToolDisabled`] if the tool is filtered out, or
+    /// an error if the tool execution fails.
+    pub fn call_tool_with_context(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+        ctx: &mut ToolContext,
+    ) -> Result<ToolCallResult> {
+        let span = tracing::info_span!("tool_call", tool = name, request_id = tracing::field::Empty);
+        if let Some(id) = &ctx.request_id {
+            span.record("request_id", id.as_str());
+        }
+        let _entered = span.enter();
+        debug!("dispatching tool call");
+
+        if !self.filter.is_enabled(name) {
+            return Err(CoderError::ToolDisabled {
+                name: name.to_owned(),
+            }
+            .into());
+        }
+
+        match self.call_timeout {
+            Some(timeout) => self.dispatch_with_timeout(name, arguments, ctx, timeout),
+            None => self.dispatch(name, arguments, ctx),
+        }
+    }
+
+    /// Run [`dispatch`](Self::dispatch) on a worker thread and give up on it
+    /// after `timeout`, cancelling `ctx.cancel`.
+    fn dispatch_with_timeout(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+        ctx: &mut ToolContext,
+        timeout: Duration,
+    ) -> Result<ToolCallResult> {
+        let router = self.clone();
+        let tool = name.to_owned();
+        let mut worker_ctx = ToolContext {
+            progress: std::mem::replace(&mut ctx.progress, Progress::none()),
+            cancel: ctx.cancel.clone(),
+            request_id: ctx.request_id.clone(),
+        };
+        let span = tracing::Span::current();
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name(format!("tool-{name}"))
+            .spawn(move || {
+                let _entered = span.entered();
+                // The receiver is gone if the call already timed out.
+                let _ = tx.send(router.dispatch(&tool, arguments, &mut worker_ctx));
+            })
+            .context("failed to spawn tool worker thread")?;
 
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                warn!(tool = name, ?timeout, "tool call timed out");
+                ctx.cancel.cancel();
+                // Uninterruptible tools keep running; say so rather than
+                // invite a blind retry of, say, a write that still lands.
+                Ok(ToolCallResult::error(format!(
+                    "Error: {name} timed out after {timeout:?} and was cancelled, but it may \
+                     still complete in the background; check its effects before retrying"
+                )))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("{name} worker thread panicked"),
+        }
+    }
+
+    /// Command settings for `bash` and the tools built on it.
+    fn bash_options(&self) -> bash::BashOptions<'_> {
+        bash::BashOptions {
+            sandboxed: self.sandboxed,
+            redactor: self.redactor.as_deref(),
+            default_timeout: self.bash_timeout,
+            max_timeout: self.bash_max_timeout,
+        }
+    }
+
+    fn dispatch(
+        &self,
+        name: &str,
+        arguments: serde_json::Value,
+        ctx: &mut ToolContext,
+    ) -> Result<ToolCallResult> {
         match name {
             "edit" => edit::execute(&self.workspace, arguments),
             "read" => read::execute(&self.workspace, arguments),
             "write" => write::execute(&self.workspace, arguments),
-            "grep" => grep::execute(&self.workspace, arguments),
+            "grep" => grep::execute(&self.workspace, arguments, &ctx.cancel, self.require_ripgrep),
             "glob" => glob::execute(&self.workspace, arguments),
-            "bash" => bash::execute(&self.workspace, self.sandboxed, arguments),
-            _ => {
-                let result = ToolCallResult {
-                    content: vec![ContentItem {
-                        content_type: "text".to_owned(),
-                        text: format!("Unknown tool: {name}"),
-                    }],
-                    is_error: true,
-                };
-                Ok(result)
+            "bash" => bash::execute(&self.workspace, &self.bash_options(), arguments, ctx),
+            "tree" => tree::execute(&self.workspace, arguments),
+            "search" => {
+                search::execute(&self.workspace, arguments, &ctx.cancel, self.require_ripgrep)
             }
+            "write_many" => write_many::execute(&self.workspace, arguments),
+            "rename_symbol" => rename_symbol::execute(&self.workspace, arguments),
+            "stat" => stat::execute(&self.workspace, arguments),
+            "format" => format::execute(&self.workspace, &self.bash_options(), arguments, ctx),
+            "copy" => copy::execute(&self.workspace, arguments),
+            "mkdir" => mkdir::execute(&self.workspace, arguments),
+            "git_diff" => git_diff::execute(&self.workspace, &self.bash_options(), arguments, ctx),
+            "replace_in_files" => replace_in_files::execute(&self.workspace, arguments),
+            "touch" => touch::execute(&self.workspace, arguments),
+            "symbols" => symbols::execute(&self.workspace, arguments),
+            "blame" => blame::execute(&self.workspace, &self.bash_options(), arguments, ctx),
+            _ => Ok(ToolCallResult::error(format!("Unknown tool: {name}"))),
         }
     }
 }
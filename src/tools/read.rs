@@ -1,41 +1,126 @@
 //! Read tool — file reading with line numbers, offset/limit, binary detection.
+//!
+//! Text files are streamed line by line and only the requested window is
+//! kept, so large files can be walked in bounded memory by following the
+//! `nextOffset` reported when `limit` truncates the output. Reading stops at
+//! the end of the window, so the total line count is only known when the
+//! window reaches the end of the file. The `nextByteOffset` reported with
+//! `nextOffset` is where that line starts; passing it back as `byteOffset`
+//! seeks there instead of re-scanning the file from the top, so walking a
+//! file chunk by chunk reads it once.
+//!
+//! With `output: "json"` the window comes back as one JSON object instead
+//! (see [`ReadOutput::Json`]); the same detection and truncation rules apply.
+//!
+//! Files over the workspace's size limit (see
+//! [`Workspace::max_file_bytes`]) are only read when `offset` asks for a
+//! window of a UTF-8 file whose `limit` lines of at most `maxLineLength`
+//! bytes each fit within that limit. Only that much of a line is ever
+//! buffered; the rest is skipped.
+//!
+//! Images (recognized by their magic bytes, or by their extension when the
+//! head is not text) are returned as an MCP `image` content item with base64 data, up to
+//! [`MAX_IMAGE_BYTES`]; other binary files are rejected.
 
-use std::io::Read as _;
+use std::fmt::Write as _;
+use std::io::{Read as _, Seek as _};
 use std::path::Path;
 
 use anyhow::{Context, Result};
+use base64::Engine as _;
 use serde::Deserialize;
 
+use super::Workspace;
 use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
 
 /// Parameters for the read tool.
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ReadParams {
     /// Path to the file to read.
     pub file_path: String,
     /// Starting line number (1-based). Default: 1.
-    #[serde(default = "default_offset")]
-    pub offset: usize,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// Byte position where line `offset` starts, from a previous read's
+    /// `nextByteOffset`; the read seeks there instead of scanning from the
+    /// top. UTF-8 files only.
+    #[serde(default)]
+    pub byte_offset: Option<u64>,
     /// Maximum number of lines to return. Default: 2000.
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// Bytes of each line shown before it is truncated. Default: 2000.
+    #[serde(default = "default_max_line_length")]
+    pub max_line_length: usize,
+    /// Fraction of control bytes in the first 8KB above which a file
+    /// containing NUL bytes is treated as binary. Default: 0.3.
+    #[serde(default = "default_binary_threshold")]
+    pub binary_threshold: f64,
+    /// Decode the file as text whatever it looks like (invalid UTF-8 is
+    /// replaced). Default: false.
+    #[serde(default)]
+    pub force_text: bool,
+    /// How the lines are returned.
+    #[serde(default)]
+    pub output: ReadOutput,
+    /// Also report the SHA-256 of the whole file, for `write`'s
+    /// `expectedSha256`.
+    #[serde(default)]
+    pub sha256: bool,
+}
+
+/// Format of a text file read.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadOutput {
+    /// `cat -n` style numbered lines.
+    #[default]
+    Text,
+    /// `{"path", "totalLines", "startLine", "lines", "truncated"}`, plus
+    /// `nextOffset`/`nextByteOffset` when truncated, `mtime`, and `sha256`
+    /// if asked for.
+    /// `totalLines` is `null` when the window stops before the end of the
+    /// file, since the rest is not read.
+    Json,
 }
 
-const fn default_offset() -> usize { 1 }
 const fn default_limit() -> usize { 2000 }
+const fn default_max_line_length() -> usize { MAX_LINE_LENGTH }
+const fn default_binary_threshold() -> f64 { DEFAULT_BINARY_THRESHOLD }
 
 /// Max bytes to check for binary content detection.
 const BINARY_CHECK_BYTES: usize = 8192;
 
-/// Max line length before truncation.
+/// Default fraction of control bytes that makes a NUL-containing file binary.
+const DEFAULT_BINARY_THRESHOLD: f64 = 0.3;
+
+/// Signatures of common binary formats, rejected without further checks.
+const BINARY_MAGIC: &[&[u8]] = &[
+    b"\x7fELF",
+    b"PK\x03\x04",
+    b"\x1f\x8b",
+    b"\0asm",
+    b"SQLite format 3\0",
+    b"\xca\xfe\xba\xbe",
+    b"\xcf\xfa\xed\xfe",
+];
+
+/// Default max line length (bytes) before truncation.
 const MAX_LINE_LENGTH: usize = 2000;
 
+/// Largest image returned as base64 content.
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
 pub fn tool_definition() -> ToolDefinition {
     ToolDefinition {
         name: "read".to_owned(),
         description: "Read a file with line numbers. Returns content in `cat -n` format. \
-            Supports offset and limit for large files. Detects binary files."
+            Supports offset and limit for large files; when output is cut short, a second \
+            content item {\"nextOffset\": N, \"nextByteOffset\": B} gives the offset to continue \
+            from; pass B back as byteOffset with it to skip re-reading the start of the file. \
+            Images (PNG, JPEG, GIF, WebP, BMP) are returned as image content; other binary files are rejected. \
+            Ends with the file's mtime, which can be passed to edit as expectedMtime."
             .to_owned(),
         input_schema: serde_json::json!({
             "type": "object",
@@ -50,116 +135,570 @@ pub fn tool_definition() -> ToolDefinition {
                     "default": 1,
                     "minimum": 1
                 },
+                "byteOffset": {
+                    "type": "integer",
+                    "description": "Byte position of line `offset`, from a previous read's nextByteOffset; seeks there instead of scanning from the top (UTF-8 files only)",
+                    "minimum": 0
+                },
                 "limit": {
                     "type": "integer",
                     "description": "Maximum number of lines to return (default: 2000)",
                     "default": 2000,
                     "minimum": 1
+                },
+                "maxLineLength": {
+                    "type": "integer",
+                    "description": "Bytes of each line shown before it is truncated with a \
+                        `… (+N bytes)` marker (default: 2000)",
+                    "default": 2000,
+                    "minimum": 1
+                },
+                "binaryThreshold": {
+                    "type": "number",
+                    "description": "Fraction of control bytes in the first 8KB above which a file \
+                        with NUL bytes counts as binary (default: 0.3)",
+                    "default": 0.3,
+                    "minimum": 0,
+                    "maximum": 1
+                },
+                "forceText": {
+                    "type": "boolean",
+                    "description": "Decode as text even if the file looks binary; invalid UTF-8 \
+                        is replaced (default: false)",
+                    "default": false
+                },
+                "output": {
+                    "type": "string",
+                    "enum": ["text", "json"],
+                    "description": "Return numbered `cat -n` text (default) or one JSON object \
+                        {path, totalLines, startLine, lines: [...], truncated} (totalLines is null when \
+                        truncated; nextOffset and nextByteOffset continue the read)",
+                    "default": "text"
+                },
+                "sha256": {
+                    "type": "boolean",
+                    "description": "Also report the SHA-256 of the whole file, to pass to write as \
+                        expectedSha256 (default: false)",
+                    "default": false
                 }
             },
-            "required": ["filePath"]
+            "required": ["filePath"],
+            "additionalProperties": false
         }),
     }
 }
 
-/// Execute the read tool.
-pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCallResult> {
-    let params: ReadParams =
-        serde_json::from_value(arguments).context("invalid read parameters")?;
+/// Execute the read tool. When `limit` cuts the file short, a second content
+/// item holds `{"nextOffset": N, "nextByteOffset": B}`, the `offset` and
+/// `byteOffset` that continue the read.
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: ReadParams = super::parse_params(arguments, tool_definition)?;
 
     let file_path = match super::validate_path(workspace, &params.file_path) {
         Ok(p) => p,
-        Err(e) => {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!("Error: {e}"),
-                }],
-                is_error: true,
-            });
-        }
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
     };
 
     if !file_path.exists() {
-        return Ok(ToolCallResult {
-            content: vec![ContentItem {
-                content_type: "text".to_owned(),
-                text: format!("Error: file not found: {}", file_path.display()),
-            }],
-            is_error: true,
-        });
+        return Ok(ToolCallResult::error(format!("Error: file not found: {}", file_path.display())));
     }
 
-    // Binary detection: only read first 8KB to check for null bytes,
-    // avoiding loading entire large binary files into memory.
-    {
-        let mut file = std::fs::File::open(&file_path)
-            .with_context(|| format!("failed to open {}", file_path.display()))?;
-        let mut check_buf = vec![0u8; BINARY_CHECK_BYTES];
-        let n = file
-            .by_ref()
-            .take(BINARY_CHECK_BYTES as u64)
-            .read(&mut check_buf)
-            .with_context(|| format!("failed to read {}", file_path.display()))?;
-        if check_buf[..n].contains(&0) {
-            let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
-            return Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!(
-                        "Binary file detected: {} ({} bytes)",
-                        file_path.display(),
-                        file_size,
-                    ),
-                }],
-                is_error: true,
-            });
-        }
-    }
-
-    // Not binary — read full file as text.
-    let content_raw = std::fs::read_to_string(&file_path)
+    let encoding = match sniff(&file_path, &params)? {
+        Sniffed::Text(encoding) => encoding,
+        Sniffed::Image(mime_type) => return read_image(&file_path, mime_type),
+        Sniffed::Binary(file_size) => {
+            return Ok(ToolCallResult::error(format!(
+                "Binary file detected: {} ({file_size} bytes); pass forceText to read it anyway",
+                file_path.display(),
+            )));
+        }
+    };
+
+    // UTF-8 is streamed, so a large file is fine once the caller asks for a
+    // window no larger than the limit; UTF-16 is decoded whole.
+    let window_bytes = (params.limit as u64).saturating_mul(params.max_line_length as u64);
+    let windowed = encoding == TextEncoding::Utf8
+        && params.offset.is_some()
+        && window_bytes <= workspace.max_file_bytes();
+    if !windowed {
+        if let Err(e) = workspace.check_file_size(&file_path) {
+            let hint = if encoding != TextEncoding::Utf8 {
+                "files in UTF-16 are decoded whole and cannot be read in windows".to_owned()
+            } else if params.offset.is_some() {
+                format!(
+                    "lower limit or maxLineLength so that limit × maxLineLength is at most {} bytes",
+                    workspace.max_file_bytes()
+                )
+            } else {
+                "read it in windows with offset and limit, passing each nextByteOffset back as byteOffset"
+                    .to_owned()
+            };
+            return Ok(ToolCallResult::error(format!("Error: {e}; {hint}")));
+        }
+    }
+
+    if let Some(result) = cursor_guard(&file_path, &params, encoding)? {
+        return Ok(result);
+    }
+
+    // Not binary — stream the lines, keeping only the requested window.
+    let window = read_text(&file_path, &params, encoding)
         .with_context(|| format!("failed to read {}", file_path.display()))?;
-    let content = std::borrow::Cow::Borrowed(content_raw.as_str());
-    let lines: Vec<&str> = content.lines().collect();
-    let total_lines = lines.len();
+    // Byte positions in transcoded text mean nothing in the file.
+    let next_byte = (encoding == TextEncoding::Utf8).then_some(window.next_byte);
 
-    // Apply offset (1-based) and limit.
-    let start = params.offset.saturating_sub(1).min(total_lines);
-    let end = (start + params.limit).min(total_lines);
+    let start = window.first_line;
+    let end = start + window.lines.len();
+    let sha256 = if params.sha256 {
+        Some(
+            crate::util::digest::file_sha256(&file_path)
+                .with_context(|| format!("failed to hash {}", file_path.display()))?,
+        )
+    } else {
+        None
+    };
+    if params.output == ReadOutput::Json {
+        return Ok(json_result(&file_path, &window, next_byte, encoding, sha256.as_deref()));
+    }
 
     let mut output = String::new();
-    let line_num_width = format!("{}", end).len();
+    let line_num_width = end.max(1).to_string().len();
 
-    for (i, line) in lines[start..end].iter().enumerate() {
+    for (i, line) in window.lines.iter().enumerate() {
         let line_num = start + i + 1;
-        let truncated = if line.len() > MAX_LINE_LENGTH {
-            // Find last valid UTF-8 char boundary at or before MAX_LINE_LENGTH
-            // to avoid panicking on multi-byte characters (CJK, emoji, etc).
-            let mut end = MAX_LINE_LENGTH;
-            while end > 0 && !line.is_char_boundary(end) {
-                end -= 1;
-            }
-            &line[..end]
+        let _ = writeln!(output, "{line_num:>line_num_width$}\t{line}");
+    }
+
+    if encoding != TextEncoding::Utf8 {
+        let _ = write!(output, "\n(decoded from {})\n", encoding.name());
+    }
+    let next_offset = window.more.then_some(end + 1);
+    match (next_offset, next_byte) {
+        (Some(next), Some(byte)) => {
+            let _ = write!(output, "\n... (more lines; continue with offset {next} and byteOffset {byte})\n");
+        }
+        (Some(next), None) => {
+            let _ = write!(output, "\n... (more lines; continue with offset {next})\n");
+        }
+        (None, _) => {}
+    }
+
+    // Concurrency token for `edit`'s `expectedMtime`.
+    if let Ok(mtime) = crate::util::time::mtime_token(&file_path) {
+        output.push_str("\n(mtime: ");
+        output.push_str(&mtime);
+        output.push_str(")\n");
+    }
+    if let Some(hash) = sha256 {
+        let _ = writeln!(output, "(sha256: {hash})");
+    }
+
+    let mut content = vec![ContentItem::text(output)];
+    if let Some(next) = next_offset {
+        let mut meta = serde_json::json!({ "nextOffset": next });
+        if let Some(byte) = next_byte {
+            meta["nextByteOffset"] = byte.into();
+        }
+        content.push(ContentItem::text(meta.to_string()));
+    }
+    Ok(ToolCallResult {
+        content,
+        is_error: false,
+    })
+}
+
+/// The [`ReadOutput::Json`] result for `window`.
+fn json_result(
+    file_path: &Path,
+    window: &Window,
+    next_byte: Option<u64>,
+    encoding: TextEncoding,
+    sha256: Option<&str>,
+) -> ToolCallResult {
+    let end = window.first_line + window.lines.len();
+    let mut report = serde_json::json!({
+        "path": file_path.display().to_string(),
+        "totalLines": (!window.more).then_some(end),
+        "startLine": window.first_line + 1,
+        "lines": window.lines,
+        "truncated": window.more,
+    });
+    if window.more {
+        report["nextOffset"] = (end + 1).into();
+        if let Some(byte) = next_byte {
+            report["nextByteOffset"] = byte.into();
+        }
+    }
+    if encoding != TextEncoding::Utf8 {
+        report["encoding"] = encoding.name().into();
+    }
+    if let Ok(mtime) = crate::util::time::mtime_token(file_path) {
+        report["mtime"] = mtime.into();
+    }
+    if let Some(hash) = sha256 {
+        report["sha256"] = hash.into();
+    }
+    ToolCallResult::text(report.to_string())
+}
+
+/// Stream the lines of the text file at `file_path`, keeping only the window
+/// `params` asks for. UTF-8 files are read from `byteOffset`; UTF-16 is
+/// transcoded up front.
+fn read_text(file_path: &Path, params: &ReadParams, encoding: TextEncoding) -> Result<Window> {
+    let offset = params.offset.unwrap_or(1);
+    match encoding {
+        TextEncoding::Utf8 => {
+            let mut file = std::fs::File::open(file_path)
+                .with_context(|| format!("failed to open {}", file_path.display()))?;
+            let byte = params.byte_offset.unwrap_or(0);
+            file.seek(std::io::SeekFrom::Start(byte))?;
+            let start = (if byte > 0 { offset.saturating_sub(1) } else { 0 }, byte);
+            let reader = std::io::BufReader::new(file);
+            read_window(reader, start, offset, params.limit, params.max_line_length, params.force_text)
+        }
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let bytes = std::fs::read(file_path)
+                .with_context(|| format!("failed to read {}", file_path.display()))?;
+            let text = decode_utf16(&bytes, encoding == TextEncoding::Utf16Le);
+            read_window(text.as_bytes(), (0, 0), offset, params.limit, params.max_line_length, false)
+        }
+    }
+}
+
+/// Refuse a `byteOffset` that cannot be the start of line `offset` from an
+/// earlier read: one given without `offset`, on a UTF-16 file, past the end
+/// of the file or not at the start of a line (usually because the file
+/// changed since).
+fn cursor_guard(file_path: &Path, params: &ReadParams, encoding: TextEncoding) -> Result<Option<ToolCallResult>> {
+    let Some(byte) = params.byte_offset else {
+        return Ok(None);
+    };
+    let problem = if params.offset.is_none() {
+        Some("byteOffset needs the offset (line number) it was reported with".to_owned())
+    } else if encoding != TextEncoding::Utf8 {
+        Some(format!("byteOffset is not supported for {} files; use offset alone", encoding.name()))
+    } else if byte > 0 {
+        let mut file = std::fs::File::open(file_path)
+            .with_context(|| format!("failed to open {}", file_path.display()))?;
+        let len = file.metadata()?.len();
+        let mut prev = [0u8];
+        if byte > len {
+            Some(format!("byteOffset {byte} is past the end of the file ({len} bytes)"))
+        } else if file.seek(std::io::SeekFrom::Start(byte - 1)).is_err()
+            || file.read_exact(&mut prev).is_err()
+            || prev[0] != b'\n'
+        {
+            Some(format!("byteOffset {byte} is not at the start of a line; the file may have changed"))
         } else {
-            line
+            None
+        }
+    } else {
+        None
+    };
+    Ok(problem.map(|text| ToolCallResult::error(format!("Error: {text}"))))
+}
+
+/// The lines of a file shown by one `read` call.
+struct Window {
+    /// 0-based index of the first line in `lines`.
+    first_line: usize,
+    /// Up to `limit` lines, without their line endings, each cut to
+    /// `max_line_length` bytes (see [`decode_line`]).
+    lines: Vec<String>,
+    /// Whether more lines follow the window.
+    more: bool,
+    /// Byte position just past the window, where the next line starts.
+    next_byte: u64,
+}
+
+/// Read lines `offset..offset + limit` (1-based) from `reader`, splitting as
+/// [`str::lines`] does. `reader` is positioned at the start of line
+/// `start.0` (0-based), byte `start.1`, at or before the window. Only the
+/// window is kept in memory, and of each of its lines only the first
+/// `max_line_length` bytes: lines before the window and the rest of long
+/// lines are skipped without being buffered. Reading stops one line past the
+/// window.
+/// Invalid UTF-8 in the window is an error unless `lossy` is set.
+fn read_window(
+    mut reader: impl std::io::BufRead,
+    start: (usize, u64),
+    offset: usize,
+    limit: usize,
+    max_line_length: usize,
+    lossy: bool,
+) -> Result<Window> {
+    let first = offset.saturating_sub(1);
+    let (mut line_index, mut byte) = start;
+    let mut lines = Vec::new();
+    let mut buf = Vec::new();
+    let mut more = false;
+    loop {
+        buf.clear();
+        let keep = if line_index >= first { max_line_length } else { 0 };
+        let (n, len) = read_line_capped(&mut reader, &mut buf, keep)?;
+        if n == 0 {
+            break;
+        }
+        if line_index >= first && lines.len() == limit {
+            more = true;
+            break;
+        }
+        if line_index >= first {
+            let line = decode_line(&buf, len, lossy)
+                .with_context(|| format!("line {} is not valid UTF-8", line_index + 1))?;
+            lines.push(line);
+        }
+        line_index += 1;
+        byte += n;
+    }
+    Ok(Window {
+        first_line: first.min(line_index),
+        lines,
+        more,
+        next_byte: byte,
+    })
+}
+/// Read one line from `reader`, through its `\n` or to the end of input,
+/// appending at most `keep` bytes of it (line ending excluded) to `buf`.
+/// Returns the bytes consumed and the length of the line without its `\n`
+/// or `\r\n`.
+fn read_line_capped(
+    reader: &mut impl std::io::BufRead,
+    buf: &mut Vec<u8>,
+    keep: usize,
+) -> std::io::Result<(u64, usize)> {
+    let mut consumed = 0;
+    let mut len = 0;
+    let mut last = None;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
         };
-        output.push_str(&format!("{line_num:>line_num_width$}\t{truncated}\n"));
+        let (content, newline) = available
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or((available, false), |pos| (&available[..pos], true));
+        let room = keep.saturating_sub(buf.len()).min(content.len());
+        buf.extend_from_slice(&content[..room]);
+        len += content.len();
+        last = content.last().copied().or(last);
+        let used = content.len() + usize::from(newline);
+        reader.consume(used);
+        consumed += used as u64;
+        if newline || used == 0 {
+            if newline && last == Some(b'\r') {
+                len -= 1;
+                buf.truncate(len);
+            }
+            return Ok((consumed, len));
+        }
     }
+}
 
-    if end < total_lines {
-        output.push_str(&format!(
-            "\n... ({} more lines, {} total)\n",
-            total_lines - end,
-            total_lines
-        ));
+/// The text of a window line `len` bytes long, of which `kept` holds the
+/// first bytes. A line longer than `kept` is cut at the last whole character
+/// and ends with a `… (+N bytes)` marker. `None` if the kept bytes are not
+/// valid UTF-8, unless `lossy` replaces them.
+fn decode_line(kept: &[u8], len: usize, lossy: bool) -> Option<String> {
+    let cut = kept.len() < len;
+    let mut text = String::with_capacity(kept.len());
+    let mut shown = 0;
+    let mut chunks = kept.utf8_chunks().peekable();
+    while let Some(chunk) = chunks.next() {
+        text.push_str(chunk.valid());
+        shown += chunk.valid().len();
+        // A character split by the cut is left out whole.
+        if chunk.invalid().is_empty() || (cut && chunks.peek().is_none()) {
+            continue;
+        }
+        if !lossy {
+            return None;
+        }
+        text.push(char::REPLACEMENT_CHARACTER);
+        shown += chunk.invalid().len();
+    }
+    if cut {
+        let _ = write!(text, "… (+{} bytes)", len - shown);
     }
+    Some(text)
+}
+
+/// What the head of a file says about how to read it.
+enum Sniffed {
+    Text(TextEncoding),
+    Image(&'static str),
+    /// Binary, with the file size.
+    Binary(u64),
+}
 
+/// Classify a file from its first 8KB, avoiding loading entire large binary
+/// files into memory. `forceText` skips the checks (a BOM is still honored).
+fn sniff(path: &Path, params: &ReadParams) -> Result<Sniffed> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut check_buf = vec![0u8; BINARY_CHECK_BYTES];
+    let n = file
+        .by_ref()
+        .take(BINARY_CHECK_BYTES as u64)
+        .read(&mut check_buf)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let head = &check_buf[..n];
+    if params.force_text {
+        return Ok(Sniffed::Text(bom_encoding(head).unwrap_or(TextEncoding::Utf8)));
+    }
+    let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if let Some(mime_type) = image_magic(head, file_size) {
+        return Ok(Sniffed::Image(mime_type));
+    }
+    if let Some(encoding) = detect_encoding(head, params.binary_threshold) {
+        return Ok(Sniffed::Text(encoding));
+    }
+    Ok(image_extension(path).map_or(Sniffed::Binary(file_size), Sniffed::Image))
+}
+
+/// How a text file's bytes are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextEncoding {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+        }
+    }
+}
+
+/// Encoding announced by a byte order mark at the start of `head`.
+fn bom_encoding(head: &[u8]) -> Option<TextEncoding> {
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        Some(TextEncoding::Utf8)
+    } else if head.starts_with(&[0xFF, 0xFE]) {
+        Some(TextEncoding::Utf16Le)
+    } else if head.starts_with(&[0xFE, 0xFF]) {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Decide from the first bytes of a file how to decode it, or `None` if it
+/// is binary. In order: a BOM decides; a known binary signature is binary;
+/// without NUL bytes the file is text; NULs in every other byte mean UTF-16
+/// without a BOM; otherwise the file is binary if more than `threshold` of
+/// its bytes are control characters (other than whitespace and ESC).
+#[allow(clippy::cast_precision_loss)] // `head` is at most a few KiB.
+fn detect_encoding(head: &[u8], threshold: f64) -> Option<TextEncoding> {
+    if let Some(encoding) = bom_encoding(head) {
+        return Some(encoding);
+    }
+    if BINARY_MAGIC.iter().any(|magic| head.starts_with(magic)) {
+        return None;
+    }
+    if !head.contains(&0) {
+        return Some(TextEncoding::Utf8);
+    }
+    if let Some(encoding) = utf16_without_bom(head) {
+        return Some(encoding);
+    }
+    let control = head
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B)) || b == 0x7F)
+        .count();
+    (control as f64 <= threshold * head.len() as f64).then_some(TextEncoding::Utf8)
+}
+
+/// Recognize BOM-less UTF-16 of mostly ASCII text: at least 90% of the
+/// code units have a zero high byte and a non-zero low byte.
+fn utf16_without_bom(head: &[u8]) -> Option<TextEncoding> {
+    let units = head.len() / 2;
+    if units < 2 {
+        return None;
+    }
+    let pairs = head.chunks_exact(2);
+    let le = pairs.clone().filter(|p| p[0] != 0 && p[1] == 0).count();
+    let be = pairs.filter(|p| p[0] == 0 && p[1] != 0).count();
+    if le * 10 >= units * 9 {
+        Some(TextEncoding::Utf16Le)
+    } else if be * 10 >= units * 9 {
+        Some(TextEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Decode UTF-16 `bytes` (minus any BOM), replacing invalid code units.
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units = bytes.chunks_exact(2).map(|p| {
+        if little_endian {
+            u16::from_le_bytes([p[0], p[1]])
+        } else {
+            u16::from_be_bytes([p[0], p[1]])
+        }
+    });
+    let text: String = char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+    text.strip_prefix('\u{feff}').map(str::to_owned).unwrap_or(text)
+}
+
+/// MIME type of an image file from its leading bytes. A BMP header must
+/// also record the file's size and a known DIB header size.
+fn image_magic(head: &[u8], file_size: u64) -> Option<&'static str> {
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if head.starts_with(b"BM")
+        && le_u32(head, 2).is_some_and(|size| u64::from(size) == file_size)
+        && le_u32(head, 14).is_some_and(|dib| matches!(dib, 12 | 40 | 52 | 56 | 64 | 108 | 124))
+    {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// Little-endian `u32` at `offset` in `bytes`, if there are enough bytes.
+fn le_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let field = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(field.try_into().ok()?))
+}
+
+/// MIME type of an image file from its extension (SVG is text, not an image).
+fn image_extension(path: &Path) -> Option<&'static str> {
+    crate::resources::mime_type(path)
+        .filter(|mime| mime.starts_with("image/") && *mime != "image/svg+xml")
+}
+
+/// Return an image as base64 `image` content plus a short text description.
+fn read_image(path: &Path, mime_type: &str) -> Result<ToolCallResult> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("failed to stat {}", path.display()))?
+        .len();
+    if size > MAX_IMAGE_BYTES {
+        return Ok(ToolCallResult::error(format!(
+            "Image too large: {} ({size} bytes, limit {MAX_IMAGE_BYTES})",
+            path.display()
+        )));
+    }
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
     Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text: output,
-        }],
+        content: vec![
+            ContentItem::image(base64::engine::general_purpose::STANDARD.encode(&bytes), mime_type),
+            ContentItem::text(format!("Image: {} ({mime_type}, {size} bytes)", path.display())),
+        ],
         is_error: false,
     })
 }
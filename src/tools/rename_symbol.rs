@@ -0,0 +1,171 @@
+//! Rename-symbol tool — replace an identifier across files.
+//!
+//! Files are selected like the `search` tool (glob, gitignore-aware), and
+//! every whole-word occurrence of `oldName` (`\boldName\b`) is replaced with
+//! `newName`. Substrings of longer identifiers are left alone. Changed files
+//! get the workspace's end-of-file newline policy, are staged and renamed
+//! into place together once all are computed, and are each reported as a
+//! unified diff. A file over the size limit fails the call before anything
+//! is written.
+
+use anyhow::{Context, Result};
+use regex::{NoExpand, Regex};
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RenameSymbolParams {
+    /// Identifier to rename.
+    pub old_name: String,
+    /// Replacement identifier.
+    pub new_name: String,
+    /// Glob pattern(s) limiting which files are touched (default: all files).
+    #[serde(default = "default_glob", deserialize_with = "super::string_or_vec")]
+    pub glob: Vec<String>,
+    /// Directory to search in (relative to workspace).
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Only report what would change; write nothing.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn default_glob() -> Vec<String> {
+    vec!["**".to_owned()]
+}
+
+/// Maximum number of files a single rename will look at.
+const MAX_RENAME_FILES: usize = 10_000;
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "rename_symbol".to_owned(),
+        description: "Rename an identifier across files: every whole-word occurrence of oldName \
+            is replaced with newName (substrings of longer identifiers are untouched). Returns a \
+            diff per changed file. Use dryRun to preview."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "oldName": {
+                    "type": "string",
+                    "description": "Identifier to rename"
+                },
+                "newName": {
+                    "type": "string",
+                    "description": "New identifier"
+                },
+                "glob": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+                    ],
+                    "description": "Glob pattern or list of patterns limiting the files to change (default: \"**\")"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search in (default: workspace root)"
+                },
+                "dryRun": {
+                    "type": "boolean",
+                    "description": "List what would change without writing (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["oldName", "newName"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: RenameSymbolParams = super::parse_params(arguments, tool_definition)?;
+
+    for (field, name) in [("oldName", &params.old_name), ("newName", &params.new_name)] {
+        if !is_identifier(name) {
+            return Ok(ToolCallResult::error(format!(
+                "Error: {field} must be a non-empty identifier (letters, digits, _): {name:?}"
+            )));
+        }
+    }
+    if params.old_name == params.new_name {
+        return Ok(ToolCallResult::error("Error: oldName and newName are the same"));
+    }
+    if params.glob.is_empty() {
+        return Ok(ToolCallResult::error("Error: at least one glob pattern is required"));
+    }
+
+    let search_dir = match &params.path {
+        Some(p) => match super::validate_dir_path(workspace, p) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+
+    let files =
+        match super::bulk_edit_files(workspace, &search_dir, &params.glob, MAX_RENAME_FILES) {
+            Ok(files) => files,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        };
+
+    let re = Regex::new(&format!(r"\b{}\b", regex::escape(&params.old_name)))
+        .context("failed to build rename pattern")?;
+
+    let mut diffs = String::new();
+    let mut staged = crate::util::atomic::StagedWrites::new();
+    let mut changed_files = 0;
+    let mut occurrences = 0;
+    for path in &files {
+        let original = match super::read_text_file(workspace, path) {
+            Ok(Some(original)) => original,
+            Ok(None) => continue,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        };
+        let count = re.find_iter(&original).count();
+        if count == 0 {
+            continue;
+        }
+        let renamed = re.replace_all(&original, NoExpand(&params.new_name));
+        let (updated, _) = workspace.eof_newline().apply(&renamed);
+        if !params.dry_run {
+            staged.stage(path, &updated)?;
+        }
+        changed_files += 1;
+        occurrences += count;
+        diffs.push_str(&crate::edit::diff::unified_diff(
+            &path.display().to_string(),
+            &original,
+            &updated,
+        ));
+    }
+
+    if changed_files == 0 {
+        return Ok(ToolCallResult::text(format!("No occurrences of {} found", params.old_name)));
+    }
+    staged.commit()?;
+
+    let mut text = format!(
+        "{} {} -> {}: {occurrences} occurrence{} in {changed_files} file{}",
+        if params.dry_run { "Would rename" } else { "Renamed" },
+        params.old_name,
+        params.new_name,
+        if occurrences == 1 { "" } else { "s" },
+        if changed_files == 1 { "" } else { "s" },
+    );
+    if params.dry_run {
+        text.push_str(" (dry run, nothing written)");
+    }
+    text.push_str("\n\n");
+    text.push_str(&diffs);
+    Ok(ToolCallResult::text(text))
+}
+
+/// Whether `name` consists only of word characters, so `\b` delimits it.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
@@ -0,0 +1,183 @@
+//! Replace-in-files tool — the same edit applied across a glob of files.
+//!
+//! Files are selected like the `search` tool (glob, gitignore-aware) and
+//! `oldString` is matched exactly in each ([`crate::edit::replace_exact`]):
+//! the fuzzy layers of `edit` are too loose to run unseen across many files.
+//! Changed files get the workspace's end-of-file newline policy, are staged
+//! and renamed into place together, and are reported as unified diffs, or
+//! as a per-file count once there are many. Files without a match are
+//! skipped unless `strict` is set, in which case the call fails before
+//! anything is written; a file over the size limit fails the call.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReplaceInFilesParams {
+    /// Glob pattern(s) selecting the files to edit.
+    #[serde(deserialize_with = "super::string_or_vec")]
+    pub glob: Vec<String>,
+    /// Directory to search in (relative to workspace).
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The text to find in each file.
+    pub old_string: String,
+    /// The replacement text.
+    pub new_string: String,
+    /// Replace every occurrence in a file, not just a unique one.
+    #[serde(default)]
+    pub replace_all: bool,
+    /// Fail (writing nothing) if any selected file has no match.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// Maximum number of files a single call will look at.
+const MAX_REPLACE_FILES: usize = 1_000;
+
+/// Above this many changed files, list per-file counts instead of diffs.
+const MAX_DIFF_FILES: usize = 20;
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "replace_in_files".to_owned(),
+        description: "Replace oldString with newString in every file matching a glob. Unlike edit, \
+            only exact occurrences match. Files without a match are skipped (or, with strict, fail the call \
+            before anything is written). Returns a diff per changed file, or per-file counts when many \
+            files change."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "glob": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+                    ],
+                    "description": "Glob pattern or list of patterns selecting the files to edit"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search in (default: workspace root)"
+                },
+                "oldString": {
+                    "type": "string",
+                    "description": "The text to find in each file"
+                },
+                "newString": {
+                    "type": "string",
+                    "description": "The replacement text"
+                },
+                "replaceAll": {
+                    "type": "boolean",
+                    "description": "Replace all occurrences in each file; otherwise a file's match must be \
+                        unique (default: false)",
+                    "default": false
+                },
+                "strict": {
+                    "type": "boolean",
+                    "description": "Fail without writing anything if any matching file lacks oldString \
+                        (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["glob", "oldString", "newString"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: ReplaceInFilesParams = super::parse_params(arguments, tool_definition)?;
+
+    if params.old_string.is_empty() {
+        return Ok(ToolCallResult::error("Error: oldString must not be empty"));
+    }
+    if params.old_string == params.new_string {
+        return Ok(ToolCallResult::error("Error: oldString and newString are the same"));
+    }
+    if params.glob.is_empty() {
+        return Ok(ToolCallResult::error("Error: at least one glob pattern is required"));
+    }
+
+    let search_dir = match &params.path {
+        Some(p) => match super::validate_dir_path(workspace, p) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+
+    let files =
+        match super::bulk_edit_files(workspace, &search_dir, &params.glob, MAX_REPLACE_FILES) {
+            Ok(files) => files,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        };
+
+    // Compute every change first so `strict` can refuse before writing.
+    let mut changes = Vec::new();
+    let mut unmatched = Vec::new();
+    for path in files {
+        let original = match super::read_text_file(workspace, &path) {
+            Ok(original) => original,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        };
+        let updated = original.as_deref().and_then(|original| {
+            crate::edit::replace_exact(original, &params.old_string, &params.new_string, params.replace_all)
+        });
+        let updated = updated.map(|updated| workspace.eof_newline().apply(&updated).0.into_owned());
+        match (original, updated) {
+            (Some(original), Some(updated)) if updated != original => {
+                changes.push((path, original, updated));
+            }
+            _ => unmatched.push(path),
+        }
+    }
+
+    if params.strict && !unmatched.is_empty() {
+        let mut text = format!(
+            "Error: no match for oldString in {} file{} (strict, nothing written):",
+            unmatched.len(),
+            if unmatched.len() == 1 { "" } else { "s" },
+        );
+        for path in &unmatched {
+            let _ = write!(text, "\n{}", path.display());
+        }
+        return Ok(ToolCallResult::error(text));
+    }
+    if changes.is_empty() {
+        return Ok(ToolCallResult::text("No matches found for oldString"));
+    }
+
+    let mut staged = crate::util::atomic::StagedWrites::new();
+    for (path, _, updated) in &changes {
+        staged.stage(path, updated)?;
+    }
+    staged.commit()?;
+
+    let mut text = format!(
+        "Replaced in {} file{}",
+        changes.len(),
+        if changes.len() == 1 { "" } else { "s" },
+    );
+    if !unmatched.is_empty() {
+        let _ = write!(text, " ({} without a match skipped)", unmatched.len());
+    }
+    text.push_str("\n\n");
+    for (path, original, updated) in &changes {
+        let name = path.display().to_string();
+        if changes.len() > MAX_DIFF_FILES {
+            let _ = writeln!(text, "{name} {}", crate::edit::diff::diff_summary(original, updated));
+        } else {
+            text.push_str(&crate::edit::diff::unified_diff(&name, original, updated));
+        }
+    }
+    Ok(ToolCallResult::text(text))
+}
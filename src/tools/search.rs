@@ -0,0 +1,161 @@
+//! Search tool — glob for files, then grep within them in one call.
+//!
+//! Files are collected exactly like the `glob` tool (gitignore-aware,
+//! symlink policy included) and then searched like `grep`, with ripgrep
+//! when available and the fallback regex search otherwise.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::grep::{GrepOutput, GrepParams};
+use super::Workspace;
+use crate::server::{CancelToken, ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchParams {
+    /// Glob pattern(s) selecting the files to search.
+    #[serde(deserialize_with = "super::string_or_vec")]
+    pub glob: Vec<String>,
+    /// Regex pattern to search for within the selected files.
+    pub pattern: String,
+    /// Directory to search in (relative to workspace).
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Maximum number of matching lines returned.
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Include N lines of context around matches.
+    #[serde(default)]
+    pub context_lines: usize,
+    /// Allow patterns to span lines (`.` also matches newlines).
+    #[serde(default)]
+    pub multiline: bool,
+}
+
+const fn default_max_results() -> usize { 100 }
+
+/// Maximum number of files a single search will look at.
+const MAX_SEARCH_FILES: usize = 10_000;
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "search".to_owned(),
+        description: "Find files matching a glob pattern, then search their contents with a \
+            regex — a glob + grep in one call. Gitignore-aware."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "glob": {
+                    "oneOf": [
+                        { "type": "string" },
+                        { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+                    ],
+                    "description": "Glob pattern or list of patterns selecting files (e.g. \"src/**/*.rs\")"
+                },
+                "pattern": {
+                    "type": "string",
+                    "description": "Regex pattern to search for within the matched files"
+                },
+                "path": {
+                    "type": "string",
+                    "description": "Directory to search in (default: workspace root)"
+                },
+                "maxResults": {
+                    "type": "integer",
+                    "description": "Maximum number of results (default: 100)",
+                    "default": 100
+                },
+                "contextLines": {
+                    "type": "integer",
+                    "description": "Lines of context around matches (default: 0)",
+                    "default": 0
+                },
+                "multiline": {
+                    "type": "boolean",
+                    "description": "Allow matches to span multiple lines (default: false)",
+                    "default": false
+                }
+            },
+            "required": ["glob", "pattern"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Execute the search tool. An `rg` subprocess is killed early if `cancel` fires.
+/// With `require_ripgrep`, a missing `rg` is an error instead of a fallback.
+pub fn execute(
+    workspace: &Workspace,
+    arguments: serde_json::Value,
+    cancel: &CancelToken,
+    require_ripgrep: bool,
+) -> Result<ToolCallResult> {
+    let params: SearchParams = super::parse_params(arguments, tool_definition)?;
+
+    if params.glob.is_empty() {
+        return Ok(ToolCallResult::error("Error: at least one glob pattern is required"));
+    }
+
+    let search_dir = match &params.path {
+        Some(p) => match super::validate_dir_path(workspace, p) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+
+    let found = super::glob::find_files(
+        workspace,
+        &search_dir,
+        &params.glob,
+        &[],
+        false,
+        true,
+        MAX_SEARCH_FILES + 1,
+    )?;
+    let capped = found.len() > MAX_SEARCH_FILES;
+    let mut files: Vec<PathBuf> = found
+        .into_iter()
+        .take(MAX_SEARCH_FILES)
+        .map(|rel| search_dir.join(rel))
+        .collect();
+    files.sort();
+
+    if files.is_empty() {
+        return Ok(ToolCallResult::text(format!("No files matching glob: {}", params.glob.join(", "))));
+    }
+
+    let grep_params = GrepParams {
+        pattern: params.pattern,
+        path: None,
+        inline_content: None,
+        include: None,
+        file_type: None,
+        max_results: params.max_results,
+        context_lines: params.context_lines,
+        multiline: params.multiline,
+        count: false,
+        smart_context: false,
+        hidden: false,
+        require_ripgrep,
+        output: GrepOutput::Text,
+        columns: false,
+        search_binary: false,
+    };
+    let mut result = super::grep::search_files(workspace, &files, &grep_params, cancel)?;
+    if capped && !result.is_error {
+        if let Some(item) = result.content.first_mut() {
+            let _ = write!(
+                item.text,
+                "\n\n(only the first {MAX_SEARCH_FILES} matching files were searched)"
+            );
+        }
+    }
+    Ok(result)
+}
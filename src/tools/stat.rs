@@ -0,0 +1,183 @@
+//! Stat tool — file metadata without reading the content into memory.
+//!
+//! Reports whether a path exists, its type, size and mtime, and for files
+//! whether it looks binary (any NUL in the first 8KB, stricter than `read`'s
+//! heuristics) and, for text files, its line count. Lines are counted by
+//! streaming the file in chunks, so large files are never loaded whole.
+//! Useful to pre-flight a read or edit.
+
+use std::io::Read as _;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StatParams {
+    /// Path to inspect.
+    pub file_path: String,
+}
+
+/// Max bytes checked for NUL when deciding whether a file is binary.
+const BINARY_CHECK_BYTES: usize = 8192;
+
+/// Chunk size used when streaming a file to count its lines.
+const LINE_COUNT_CHUNK: usize = 64 * 1024;
+
+/// Metadata reported for one path.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatInfo {
+    exists: bool,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    file_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_binary: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_count: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified_ms: Option<u64>,
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "stat".to_owned(),
+        description: "Report a path's metadata without reading it: exists, type, sizeBytes, \
+            isBinary, lineCount (text files) and modifiedMs, as a JSON object. Cheap way to \
+            check a file before reading or editing it."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "Path to inspect"
+                }
+            },
+            "required": ["filePath"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: StatParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_path(workspace, &params.file_path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+
+    let info = stat(&path)?;
+    Ok(ToolCallResult::text(serde_json::to_string_pretty(&info).context("failed to serialize stat")?))
+}
+
+fn stat(path: &Path) -> Result<StatInfo> {
+    // `validate_path` has already resolved symlinks, so this is the target.
+    let Ok(meta) = std::fs::metadata(path) else {
+        return Ok(StatInfo::default());
+    };
+    let file_type = if meta.is_dir() {
+        "directory"
+    } else if meta.is_file() {
+        "file"
+    } else {
+        "other"
+    };
+    let modified_ms = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .and_then(|d| u64::try_from(d.as_millis()).ok());
+
+    let mut info = StatInfo {
+        exists: true,
+        file_type: Some(file_type),
+        size_bytes: Some(meta.len()),
+        modified_ms,
+        ..StatInfo::default()
+    };
+    if meta.is_file() {
+        let (is_binary, line_count) = scan_file(path)?;
+        info.is_binary = Some(is_binary);
+        info.line_count = line_count;
+    }
+    Ok(info)
+}
+
+/// Check the head of the file for NUL and, unless binary, count its lines
+/// in chunks. A final line without a trailing newline still counts.
+fn scan_file(path: &Path) -> Result<(bool, Option<u64>)> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut buf = vec![0u8; LINE_COUNT_CHUNK];
+    let mut lines = 0u64;
+    let mut last = None;
+    let mut checked = 0;
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        if checked < BINARY_CHECK_BYTES {
+            let head = &chunk[..n.min(BINARY_CHECK_BYTES - checked)];
+            if head.contains(&0) {
+                return Ok((true, None));
+            }
+            checked += head.len();
+        }
+        #[allow(clippy::naive_bytecount)] // Not worth a dependency.
+        let newlines = chunk.iter().filter(|&&b| b == b'\n').count();
+        lines += newlines as u64;
+        last = chunk.last().copied();
+    }
+    if last.is_some_and(|b| b != b'\n') {
+        lines += 1;
+    }
+    Ok((false, Some(lines)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_file_counts_lines() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let cases: [(&str, &[u8], Option<u64>); 4] = [
+            ("empty", b"", Some(0)),
+            ("trailing", b"a\nb\n", Some(2)),
+            ("unterminated", b"a\nb", Some(2)),
+            ("binary", b"a\0b\n", None),
+        ];
+        for (name, content, expected) in cases {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content).expect("write");
+            let (is_binary, lines) = scan_file(&path).expect("scan");
+            assert_eq!(lines, expected, "{name}");
+            assert_eq!(is_binary, expected.is_none(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_stat_missing_and_directory() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        assert!(!stat(&dir.path().join("nope")).expect("stat").exists);
+
+        let info = stat(dir.path()).expect("stat");
+        assert_eq!(info.file_type, Some("directory"));
+        assert!(info.line_count.is_none());
+        assert!(info.is_binary.is_none());
+    }
+}
@@ -0,0 +1,282 @@
+//! Symbols tool — a quick outline of a file's top-level definitions.
+//!
+//! Each supported language has a few line-anchored regexes (see
+//! [`patterns`]): Rust `fn`/`struct`/`enum`/`trait`/`impl`, JavaScript and
+//! TypeScript `function`/`class`/`const ... =`, Python `def`/`class`. Only
+//! unindented lines are matched, so methods and nested items are left out.
+//!
+//! This is a heuristic, not a parser: a definition split over several lines
+//! before its name, or text in a comment or string that looks like one, can
+//! be missed or reported. It is still far cheaper than a language server.
+//! The language is picked from the file extension.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SymbolsParams {
+    /// File to outline.
+    pub file_path: String,
+}
+
+/// Languages with symbol patterns, and their file extensions.
+pub const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("javascript", &["js", "jsx", "mjs", "cjs"]),
+    ("typescript", &["ts", "tsx", "mts", "cts"]),
+    ("python", &["py", "pyi"]),
+];
+
+/// Result of the symbols tool.
+#[derive(Debug, Serialize)]
+pub struct Outline {
+    /// A name from [`LANGUAGES`].
+    pub language: &'static str,
+    /// In line order.
+    pub symbols: Vec<Symbol>,
+}
+
+/// One top-level definition.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    /// The defining keyword: `fn`, `struct`, `class`, `def`, ...
+    pub kind: &'static str,
+    /// 1-based line number.
+    pub line: usize,
+}
+
+/// Identifier-start prefix shared by the Rust item patterns: optional
+/// visibility (`pub`, `pub(crate)`, ...).
+const RUST_VIS: &str = r"^(?:pub(?:\s*\([^)]*\))?\s+)?";
+
+/// The `(kind, regex)` pairs for `language`; group 1 of each regex is the
+/// symbol name.
+fn patterns(language: &str) -> Vec<(&'static str, String)> {
+    match language {
+        "rust" => vec![
+            (
+                "fn",
+                format!(r#"{RUST_VIS}(?:(?:const|async|unsafe|extern(?:\s+"[^"]*")?)\s+)*fn\s+([A-Za-z_]\w*)"#),
+            ),
+            ("struct", format!(r"{RUST_VIS}struct\s+([A-Za-z_]\w*)")),
+            ("enum", format!(r"{RUST_VIS}enum\s+([A-Za-z_]\w*)")),
+            ("trait", format!(r"{RUST_VIS}(?:unsafe\s+)?(?:auto\s+)?trait\s+([A-Za-z_]\w*)")),
+            // The implemented type (and trait): `Display for Point`.
+            ("impl", r"^(?:unsafe\s+)?impl(?:\s*<.*?>)?\s+(.+?)\s*(?:\bwhere\b.*|\{.*)?$".to_owned()),
+        ],
+        "javascript" | "typescript" => vec![
+            (
+                "function",
+                r"^(?:export\s+)?(?:default\s+)?(?:async\s+)?function\s*\*?\s*([A-Za-z_$][\w$]*)".to_owned(),
+            ),
+            (
+                "class",
+                r"^(?:export\s+)?(?:default\s+)?(?:abstract\s+)?class\s+([A-Za-z_$][\w$]*)".to_owned(),
+            ),
+            ("const", r"^(?:export\s+)?const\s+([A-Za-z_$][\w$]*)\s*(?::[^=]*)?=".to_owned()),
+        ],
+        "python" => vec![
+            ("def", r"^(?:async\s+)?def\s+([A-Za-z_]\w*)".to_owned()),
+            ("class", r"^class\s+([A-Za-z_]\w*)".to_owned()),
+        ],
+        _ => Vec::new(),
+    }
+}
+
+/// The language of `path`, from its extension.
+fn language_of(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&ext.as_str()))
+        .map(|(language, _)| *language)
+}
+
+/// The top-level definitions in `content`, written in `language`.
+///
+/// # Errors
+///
+/// Returns an error if a built-in pattern fails to compile.
+pub fn outline(language: &str, content: &str) -> Result<Vec<Symbol>> {
+    let patterns = patterns(language)
+        .into_iter()
+        .map(|(kind, pattern)| {
+            regex::Regex::new(&pattern)
+                .map(|re| (kind, re))
+                .with_context(|| format!("invalid built-in {language} symbol pattern"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut symbols = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let found = patterns.iter().find_map(|(kind, re)| {
+            let name = re.captures(line)?.get(1)?.as_str();
+            Some((*kind, name))
+        });
+        if let Some((kind, name)) = found {
+            symbols.push(Symbol {
+                name: name.to_owned(),
+                kind,
+                line: i + 1,
+            });
+        }
+    }
+    Ok(symbols)
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "symbols".to_owned(),
+        description: "Outline a file's top-level definitions (Rust fn/struct/enum/trait/impl, \
+            JS/TS function/class/const, Python def/class) as JSON {language, symbols: [{name, kind, \
+            line}]}. Regex-based and language picked by extension: fast, but heuristic."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "File to outline (.rs, .js/.jsx/.mjs/.cjs, .ts/.tsx/.mts/.cts, .py/.pyi)"
+                }
+            },
+            "required": ["filePath"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: SymbolsParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_path(workspace, &params.file_path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+    let Some(language) = language_of(&path) else {
+        let extensions: Vec<&str> = LANGUAGES.iter().flat_map(|(_, exts)| exts.iter().copied()).collect();
+        return Ok(ToolCallResult::error(format!(
+            "Error: no symbol patterns for {}; supported extensions: {}",
+            path.display(),
+            extensions.join(", ")
+        )));
+    };
+
+    if !path.is_file() {
+        return Ok(ToolCallResult::error(format!("Error: file not found: {}", path.display())));
+    }
+    workspace.check_file_size(&path)?;
+    let bytes = std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let content = String::from_utf8_lossy(&bytes);
+
+    let outline = Outline {
+        language,
+        symbols: outline(language, &content)?,
+    };
+    Ok(ToolCallResult::text(serde_json::to_string(&outline).context("failed to serialize symbols")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(language: &str, content: &str) -> Vec<(&'static str, String, usize)> {
+        outline(language, content)
+            .expect("outline")
+            .into_iter()
+            .map(|symbol| (symbol.kind, symbol.name, symbol.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_outline() {
+        let content = "\
+use std::fmt;
+
+pub(crate) struct Point<T> {
+    x: T,
+}
+
+impl<T: Into<String>> fmt::Display for Point<T> where T: Clone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { Ok(()) }
+}
+
+pub async unsafe fn run() {}
+enum Shape { Circle }
+pub trait Draw {}
+// fn commented() {}
+";
+        assert_eq!(
+            names("rust", content),
+            vec![
+                ("struct", "Point".to_owned(), 3),
+                ("impl", "fmt::Display for Point<T>".to_owned(), 7),
+                ("fn", "run".to_owned(), 11),
+                ("enum", "Shape".to_owned(), 12),
+                ("trait", "Draw".to_owned(), 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_js_and_python_outline() {
+        let js = "\
+export default async function main() {}
+function* gen() {}
+export class Widget extends Base {
+  render() {}
+}
+const handler = (req) => req;
+export const LIMIT: number = 3;
+let mutable = 1;
+";
+        assert_eq!(
+            names("typescript", js),
+            vec![
+                ("function", "main".to_owned(), 1),
+                ("function", "gen".to_owned(), 2),
+                ("class", "Widget".to_owned(), 3),
+                ("const", "handler".to_owned(), 6),
+                ("const", "LIMIT".to_owned(), 7),
+            ]
+        );
+
+        let py = "\
+class Parser:
+    def parse(self):
+        pass
+
+async def fetch(url):
+    pass
+def main():
+    pass
+";
+        assert_eq!(
+            names("python", py),
+            vec![
+                ("class", "Parser".to_owned(), 1),
+                ("def", "fetch".to_owned(), 5),
+                ("def", "main".to_owned(), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_language_from_extension() {
+        assert_eq!(language_of(Path::new("src/lib.rs")), Some("rust"));
+        assert_eq!(language_of(Path::new("app.TSX")), Some("typescript"));
+        assert_eq!(language_of(Path::new("tool.pyi")), Some("python"));
+        assert_eq!(language_of(Path::new("README.md")), None);
+        assert_eq!(language_of(Path::new("Makefile")), None);
+    }
+}
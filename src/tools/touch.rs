@@ -0,0 +1,98 @@
+//! Touch tool — create an empty file, or bump an existing file's mtime to
+//! now, without writing any content.
+
+use std::fs::OpenOptions;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TouchParams {
+    /// File to create or touch.
+    pub file_path: String,
+    /// Create the file (and its parent directories) if it is missing
+    /// (default: true). When false, a missing file is an error.
+    #[serde(default = "default_create")]
+    pub create: bool,
+}
+
+const fn default_create() -> bool { true }
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "touch".to_owned(),
+        description: "Create an empty file (with parent directories) or, if it exists, set its \
+            modification time to now without changing its content."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filePath": {
+                    "type": "string",
+                    "description": "File to create or touch"
+                },
+                "create": {
+                    "type": "boolean",
+                    "description": "Create the file if it is missing; when false a missing file is an error (default: true)",
+                    "default": true
+                }
+            },
+            "required": ["filePath"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: TouchParams = super::parse_params(arguments, tool_definition)?;
+
+    let path = match super::validate_path(workspace, &params.file_path) {
+        Ok(path) => path,
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+    };
+    if path.is_dir() {
+        return Ok(ToolCallResult::error(format!("Error: {} is a directory", path.display())));
+    }
+
+    let created = if path.exists() {
+        false
+    } else if !params.create {
+        return Ok(ToolCallResult::error(format!(
+            "Error: file not found: {} (create is false)",
+            path.display()
+        )));
+    } else {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create directories for {}", path.display()))?;
+        }
+        // `create_new` so a file that appeared meanwhile is touched, not
+        // truncated.
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => false,
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to create {}", path.display()));
+            }
+        }
+    };
+
+    if !created {
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .with_context(|| format!("failed to update the mtime of {}", path.display()))?;
+    }
+
+    let action = if created { "Created empty file" } else { "Touched" };
+    let mtime = crate::util::time::mtime_token(&path)
+        .map_or_else(|_| String::new(), |mtime| format!(" (mtime: {mtime})"));
+    Ok(ToolCallResult::text(format!("{action}: {}{mtime}", path.display())))
+}
@@ -0,0 +1,225 @@
+//! Tree tool — ASCII directory tree, like `tree(1)`.
+//!
+//! Directories sort before files at each level; symlinks are shown with
+//! their target but never followed. Unless `showAll` is set, hidden
+//! (`.`-prefixed) entries, `node_modules` and `target` are left out, and so
+//! is anything gitignored, as in the glob walker.
+
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+use crate::util::gitignore::GitignoreStack;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TreeParams {
+    /// Directory to render (relative to workspace).
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Maximum depth below the root to descend.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// Maximum number of entries to print.
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Include hidden, `node_modules`/`target` and gitignored entries.
+    #[serde(default)]
+    pub show_all: bool,
+}
+
+const fn default_max_depth() -> usize { 3 }
+const fn default_max_entries() -> usize { 500 }
+
+/// Hard limit on depth regardless of `maxDepth`.
+const MAX_WALK_DEPTH: usize = 50;
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "tree".to_owned(),
+        description: "Show the directory structure as an indented tree (like `tree`). \
+            Directories are listed before files; symlinks are marked but not followed."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Directory to show (default: workspace root)"
+                },
+                "maxDepth": {
+                    "type": "integer",
+                    "description": "Maximum depth to descend (default: 3)",
+                    "default": 3,
+                    "minimum": 1
+                },
+                "maxEntries": {
+                    "type": "integer",
+                    "description": "Maximum number of entries to list (default: 500)",
+                    "default": 500,
+                    "minimum": 1
+                },
+                "showAll": {
+                    "type": "boolean",
+                    "description": "Include hidden entries, node_modules, target and gitignored paths (default: false)",
+                    "default": false
+                }
+            },
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: TreeParams = super::parse_params(arguments, tool_definition)?;
+
+    let root = match &params.path {
+        Some(p) => match super::validate_dir_path(workspace, p) {
+            Ok(path) if path.is_dir() => path,
+            Ok(path) => {
+                return Ok(ToolCallResult::error(format!("Error: not a directory: {}", path.display())));
+            }
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
+        },
+        None => workspace.to_path_buf(),
+    };
+
+    let ignores = (!params.show_all).then(|| {
+        let workspace_root = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+        GitignoreStack::for_ancestors(&workspace_root, &root)
+    });
+    let mut tree = Tree {
+        params: &params,
+        ignores,
+        output: String::new(),
+        dirs: 0,
+        files: 0,
+        truncated: false,
+    };
+    let label = params.path.as_deref().unwrap_or(".");
+    let _ = writeln!(tree.output, "{}/", label.trim_end_matches('/'));
+    tree.render_dir(&root, "", 1)?;
+
+    let mut text = tree.output;
+    let _ = write!(
+        text,
+        "\n{} director{}, {} file{}",
+        tree.dirs,
+        if tree.dirs == 1 { "y" } else { "ies" },
+        tree.files,
+        if tree.files == 1 { "" } else { "s" },
+    );
+    if tree.truncated {
+        let _ = write!(text, "\n(output truncated at {} entries)", params.max_entries);
+    }
+
+    Ok(ToolCallResult::text(text))
+}
+
+/// Rendering state for one tree.
+struct Tree<'a> {
+    params: &'a TreeParams,
+    /// `.gitignore` layers down to the directory being rendered; `None`
+    /// with `showAll`.
+    ignores: Option<GitignoreStack>,
+    output: String,
+    dirs: usize,
+    files: usize,
+    truncated: bool,
+}
+
+/// A directory entry classified without following symlinks.
+enum Kind {
+    Dir,
+    File,
+    Symlink(String),
+}
+
+impl Tree<'_> {
+    fn render_dir(&mut self, dir: &Path, prefix: &str, depth: usize) -> Result<()> {
+        let pushed = self.ignores.as_mut().is_some_and(|ig| ig.push_dir(dir));
+        let result = self.render_entries(dir, prefix, depth);
+        if pushed {
+            if let Some(ig) = self.ignores.as_mut() {
+                ig.pop();
+            }
+        }
+        result
+    }
+
+    fn render_entries(&mut self, dir: &Path, prefix: &str, depth: usize) -> Result<()> {
+        let mut entries: Vec<(OsString, Kind)> = std::fs::read_dir(dir)
+            .with_context(|| format!("failed to read directory {}", dir.display()))?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                // entry.file_type() does NOT follow symlinks.
+                let ft = entry.file_type().ok()?;
+                if let Some(ig) = &self.ignores {
+                    if name.as_encoded_bytes().starts_with(b".")
+                        || name == "node_modules"
+                        || name == "target"
+                        || ig.is_ignored(&entry.path(), ft.is_dir())
+                    {
+                        return None;
+                    }
+                }
+                let kind = if ft.is_symlink() {
+                    let target = std::fs::read_link(entry.path())
+                        .map(|t| t.display().to_string())
+                        .unwrap_or_default();
+                    Kind::Symlink(target)
+                } else if ft.is_dir() {
+                    Kind::Dir
+                } else {
+                    Kind::File
+                };
+                Some((name, kind))
+            })
+            .collect();
+        entries.sort_by(|(a, ka), (b, kb)| {
+            matches!(kb, Kind::Dir)
+                .cmp(&matches!(ka, Kind::Dir))
+                .then_with(|| a.cmp(b))
+        });
+
+        let count = entries.len();
+        for (i, (name, kind)) in entries.into_iter().enumerate() {
+            if self.dirs + self.files >= self.params.max_entries {
+                self.truncated = true;
+                return Ok(());
+            }
+
+            let last = i + 1 == count;
+            let connector = if last { "└── " } else { "├── " };
+            let shown = name.to_string_lossy();
+            match kind {
+                Kind::Dir => {
+                    self.dirs += 1;
+                    let _ = writeln!(self.output, "{prefix}{connector}{shown}/");
+                    if depth < self.params.max_depth.min(MAX_WALK_DEPTH) {
+                        let child_prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                        // Unreadable subdirectories are listed but not expanded.
+                        let _ = self.render_dir(&dir.join(&name), &child_prefix, depth + 1);
+                    }
+                }
+                Kind::File => {
+                    self.files += 1;
+                    let _ = writeln!(self.output, "{prefix}{connector}{shown}");
+                }
+                Kind::Symlink(target) => {
+                    self.files += 1;
+                    let _ = writeln!(self.output, "{prefix}{connector}{shown} -> {target}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,26 +1,64 @@
 //! Write tool — file creation and overwrite with directory auto-creation.
+//!
+//! With `expectedSha256` (as reported by `read` with `sha256`), an existing
+//! file is only replaced if its content still hashes to that value: a
+//! compare-and-swap that fails with [`CoderError::ContentChanged`].
+//!
+//! The workspace's [`EofNewline`](crate::util::eof_newline::EofNewline)
+//! policy is applied before the per-call `ensureTrailingNewline`, so an
+//! explicit request wins.
 
-use std::path::Path;
+use std::borrow::Cow;
 
 use anyhow::{Context, Result};
 use serde::Deserialize;
 
-use crate::server::{ContentItem, ToolCallResult, ToolDefinition};
+use super::Workspace;
+use crate::error::CoderError;
+use crate::server::{ToolCallResult, ToolDefinition};
+use crate::util::eof_newline::EofNewline;
 
+// Independent on/off tool options, not an encoded state machine.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct WriteParams {
     /// Path to the file to write.
     pub file_path: String,
     /// Content to write.
     pub content: String,
+    /// Replace the file if it already exists (default: true). When false,
+    /// the write fails instead of overwriting.
+    #[serde(default = "default_overwrite")]
+    pub overwrite: bool,
+    /// Copy an existing file to `<path>.bak` before overwriting it.
+    #[serde(default)]
+    pub backup: bool,
+    /// Convert CRLF and lone CR line endings to LF before writing.
+    #[serde(default)]
+    pub normalize_newlines: bool,
+    /// End the file with exactly one newline (none added to empty content).
+    #[serde(default)]
+    pub ensure_trailing_newline: bool,
+    /// Strip trailing whitespace and collapse long runs of blank lines.
+    #[serde(default)]
+    pub tidy: bool,
+    /// With `tidy`, the most consecutive blank lines kept.
+    #[serde(default = "default_max_blank_lines")]
+    pub max_blank_lines: usize,
+    /// Only overwrite if the file's current content has this SHA-256 (hex).
+    #[serde(default)]
+    pub expected_sha256: Option<String>,
 }
 
+const fn default_overwrite() -> bool { true }
+const fn default_max_blank_lines() -> usize { crate::util::tidy::DEFAULT_MAX_BLANK_LINES }
+
 pub fn tool_definition() -> ToolDefinition {
     ToolDefinition {
         name: "write".to_owned(),
         description: "Write content to a file. Creates the file and parent directories if they don't exist. \
-            Overwrites existing content."
+            Overwrites existing content unless overwrite is false."
             .to_owned(),
         input_schema: serde_json::json!({
             "type": "object",
@@ -32,47 +70,173 @@ pub fn tool_definition() -> ToolDefinition {
                 "content": {
                     "type": "string",
                     "description": "Content to write to the file"
+                },
+                "overwrite": {
+                    "type": "boolean",
+                    "description": "Replace the file if it already exists (default: true). \
+                        Set false to only create new files.",
+                    "default": true
+                },
+                "backup": {
+                    "type": "boolean",
+                    "description": "Copy an existing file to <path>.bak before overwriting (default: false)",
+                    "default": false
+                },
+                "normalizeNewlines": {
+                    "type": "boolean",
+                    "description": "Convert CRLF/CR line endings to LF (default: false)",
+                    "default": false
+                },
+                "ensureTrailingNewline": {
+                    "type": "boolean",
+                    "description": "End the file with exactly one newline (default: false)",
+                    "default": false
+                },
+                "tidy": {
+                    "type": "boolean",
+                    "description": "Strip trailing whitespace from every line and collapse runs of blank lines \
+                        longer than maxBlankLines (default: false)",
+                    "default": false
+                },
+                "maxBlankLines": {
+                    "type": "integer",
+                    "description": "With tidy, the most consecutive blank lines kept (default: 2)",
+                    "minimum": 0,
+                    "default": 2
+                },
+                "expectedSha256": {
+                    "type": "string",
+                    "description": "Only overwrite if the file's current content has this SHA-256 (hex, as \
+                        returned by read with sha256); otherwise fail with a conflict and write nothing"
                 }
             },
-            "required": ["filePath", "content"]
+            "required": ["filePath", "content"],
+            "additionalProperties": false
         }),
     }
 }
 
-pub fn execute(workspace: &Path, arguments: serde_json::Value) -> Result<ToolCallResult> {
-    let params: WriteParams =
-        serde_json::from_value(arguments).context("invalid write parameters")?;
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: WriteParams = super::parse_params(arguments, tool_definition)?;
 
     let file_path = match super::validate_path(workspace, &params.file_path) {
         Ok(p) => p,
-        Err(e) => {
-            return Ok(ToolCallResult {
-                content: vec![ContentItem {
-                    content_type: "text".to_owned(),
-                    text: format!("Error: {e}"),
-                }],
-                is_error: true,
-            });
-        }
+        Err(e) => return Ok(ToolCallResult::error(format!("Error: {e}"))),
     };
 
+    if let Some(expected) = &params.expected_sha256 {
+        if !params.overwrite {
+            return Ok(ToolCallResult::error(
+                "Error: expectedSha256 needs an existing file to replace; it cannot be combined with \
+                 overwrite: false",
+            ));
+        }
+        check_sha256(&file_path, expected)?;
+    }
+
+    let (content, normalized) = normalize(workspace, &params);
+
     // Create parent directories.
     if let Some(parent) = file_path.parent() {
         std::fs::create_dir_all(parent)
             .with_context(|| format!("failed to create directories for {}", file_path.display()))?;
     }
 
-    let existed = file_path.exists();
-    crate::util::atomic::atomic_write(&file_path, &params.content)?;
+    let mut backup = None;
+    let existed = if params.overwrite {
+        let existed = file_path.exists();
+        backup = super::write_with_backup(workspace, &file_path, &content, params.backup)?;
+        existed
+    } else if crate::util::atomic::atomic_create(&file_path, &content)? {
+        false
+    } else {
+        return Ok(ToolCallResult::error(format!(
+            "Error: file already exists: {} (overwrite is false)",
+            file_path.display()
+        )));
+    };
 
     let action = if existed { "Updated" } else { "Created" };
-    let line_count = params.content.lines().count();
-
-    Ok(ToolCallResult {
-        content: vec![ContentItem {
-            content_type: "text".to_owned(),
-            text: format!("{action} {}: {line_count} lines written", file_path.display()),
-        }],
-        is_error: false,
-    })
+    let line_count = content.lines().count();
+
+    let backup_note = backup.map_or_else(String::new, |path| format!(" (backup: {})", path.display()));
+    let normalized_note = if normalized.is_empty() {
+        String::new()
+    } else {
+        format!(" (normalized: {})", normalized.join(", "))
+    };
+    let text = format!(
+        "{action} {}: {line_count} lines written{normalized_note}{backup_note}",
+        file_path.display()
+    );
+
+    Ok(ToolCallResult::text(text))
+}
+
+/// Fail with [`CoderError::ContentChanged`] unless the file exists and its
+/// content hashes to `expected` (hex, any case).
+fn check_sha256(file_path: &std::path::Path, expected: &str) -> Result<()> {
+    let actual = match crate::util::digest::file_sha256(file_path) {
+        Ok(hash) => hash,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => "missing".to_owned(),
+        Err(e) => return Err(CoderError::Io { path: file_path.to_path_buf(), source: e }.into()),
+    };
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(CoderError::ContentChanged {
+            path: file_path.to_path_buf(),
+            expected: expected.to_owned(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Apply the requested newline normalization and tidying, then the
+/// workspace's end-of-file newline policy, returning the content to write
+/// and a description of each change made.
+fn normalize<'a>(workspace: &Workspace, params: &'a WriteParams) -> (Cow<'a, str>, Vec<String>) {
+    let mut content = Cow::Borrowed(params.content.as_str());
+    let mut notes = Vec::new();
+
+    if params.normalize_newlines && content.contains('\r') {
+        let crlf = content.matches("\r\n").count();
+        let lone_cr = content.matches('\r').count() - crlf;
+        content = Cow::Owned(content.replace("\r\n", "\n").replace('\r', "\n"));
+        if crlf > 0 {
+            notes.push(format!("{crlf} CRLF -> LF"));
+        }
+        if lone_cr > 0 {
+            notes.push(format!("{lone_cr} CR -> LF"));
+        }
+    }
+
+    if params.tidy {
+        let (tidied, report) = crate::util::tidy::tidy(&content, params.max_blank_lines);
+        if report.trimmed_lines > 0 {
+            notes.push(format!("trailing whitespace stripped from {} lines", report.trimmed_lines));
+        }
+        if report.removed_blank_lines > 0 {
+            notes.push(format!("{} blank lines removed", report.removed_blank_lines));
+        }
+        if !report.is_empty() {
+            content = Cow::Owned(tidied.into_owned());
+        }
+    }
+
+    let (updated, note) = workspace.eof_newline().apply(&content);
+    if let Some(note) = note {
+        content = Cow::Owned(updated.into_owned());
+        notes.push(note);
+    }
+
+    if params.ensure_trailing_newline {
+        let (updated, note) = EofNewline::SingleTrailingNewline.apply(&content);
+        if let Some(note) = note {
+            content = Cow::Owned(updated.into_owned());
+            notes.push(note);
+        }
+    }
+
+    (content, notes)
 }
@@ -0,0 +1,159 @@
+//! Write-many tool — create or overwrite several files all-or-nothing.
+//!
+//! Every path is validated first, then each file is staged to a temp file
+//! next to its target (see [`crate::util::atomic::StagedWrites`]). Only when
+//! every staged write succeeded are they renamed into place; otherwise the
+//! temps and any directories created for them are removed, leaving the
+//! workspace unchanged.
+//!
+//! Each file's content gets the workspace's
+//! [`EofNewline`](crate::util::eof_newline::EofNewline) policy first.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use super::Workspace;
+use crate::server::{ToolCallResult, ToolDefinition};
+use crate::util::atomic::StagedWrites;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WriteManyParams {
+    /// Files to write, in order.
+    pub files: Vec<FileWrite>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FileWrite {
+    /// Path to the file to write.
+    pub file_path: String,
+    /// Content to write.
+    pub content: String,
+}
+
+#[must_use]
+pub fn tool_definition() -> ToolDefinition {
+    ToolDefinition {
+        name: "write_many".to_owned(),
+        description: "Write several files at once, all-or-nothing. Every file is staged first and \
+            only moved into place if all writes succeed; otherwise nothing is changed."
+            .to_owned(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "files": {
+                    "type": "array",
+                    "description": "Files to create or overwrite",
+                    "minItems": 1,
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "filePath": {
+                                "type": "string",
+                                "description": "Path to the file to write"
+                            },
+                            "content": {
+                                "type": "string",
+                                "description": "Content to write to the file"
+                            }
+                        },
+                        "required": ["filePath", "content"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["files"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+pub fn execute(workspace: &Workspace, arguments: serde_json::Value) -> Result<ToolCallResult> {
+    let params: WriteManyParams = super::parse_params(arguments, tool_definition)?;
+
+    if params.files.is_empty() {
+        return Ok(ToolCallResult::error("Error: at least one file is required"));
+    }
+
+    // Validate every path before touching the filesystem.
+    let mut targets = Vec::with_capacity(params.files.len());
+    let mut seen = HashSet::new();
+    for file in &params.files {
+        let path = match super::validate_path(workspace, &file.file_path) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolCallResult::error(format!("Error: {}: {e}", file.file_path))),
+        };
+        if path.is_dir() {
+            return Ok(ToolCallResult::error(format!("Error: {}: is a directory", file.file_path)));
+        }
+        if !seen.insert(path.clone()) {
+            return Ok(ToolCallResult::error(format!("Error: {}: listed more than once", file.file_path)));
+        }
+        targets.push(path);
+    }
+
+    let policy = workspace.eof_newline();
+    let contents: Vec<_> = params.files.iter().map(|file| policy.apply(&file.content)).collect();
+
+    let mut created_dirs = Vec::new();
+    let mut staged = StagedWrites::new();
+    for ((file, path), (content, _)) in params.files.iter().zip(&targets).zip(&contents) {
+        if let Err(e) = stage_file(&mut staged, &mut created_dirs, path, content) {
+            // Drop the temps before removing the directories holding them.
+            drop(staged);
+            remove_created_dirs(&created_dirs);
+            return Ok(ToolCallResult::error(format!(
+                "Error: failed to write {}: {e:#}. No files were changed.",
+                file.file_path
+            )));
+        }
+    }
+
+    let existed: Vec<bool> = targets.iter().map(|path| path.exists()).collect();
+    staged.commit()?;
+
+    let mut text = format!("Wrote {} files:", targets.len());
+    for ((path, (content, note)), existed) in targets.iter().zip(&contents).zip(existed) {
+        let action = if existed { "Updated" } else { "Created" };
+        let _ = write!(text, "\n  {action} {}: {} lines", path.display(), content.lines().count());
+        if let Some(note) = note {
+            let _ = write!(text, " ({note})");
+        }
+    }
+
+    Ok(ToolCallResult::text(text))
+}
+
+/// Create any missing parent directories of `path` (recording them in
+/// `created_dirs`, outermost first), then stage the write.
+fn stage_file(
+    staged: &mut StagedWrites,
+    created_dirs: &mut Vec<PathBuf>,
+    path: &Path,
+    content: &str,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        let mut missing = Vec::new();
+        for dir in parent.ancestors().take_while(|dir| !dir.exists()) {
+            missing.push(dir.to_path_buf());
+        }
+        for dir in missing.into_iter().rev() {
+            std::fs::create_dir(&dir)
+                .with_context(|| format!("failed to create directory {}", dir.display()))?;
+            created_dirs.push(dir);
+        }
+    }
+    staged.stage(path, content)
+}
+
+/// Remove directories created for an aborted write, innermost first.
+fn remove_created_dirs(created_dirs: &[PathBuf]) {
+    for dir in created_dirs.iter().rev() {
+        let _ = std::fs::remove_dir(dir);
+    }
+}
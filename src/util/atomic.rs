@@ -7,21 +7,18 @@
 //! Reference: VS Code and Claude Code both use write-temp-then-rename.
 
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 
-/// Atomically write `content` to `path`.
-///
-/// Creates a temporary file in the same directory as `path`, writes `content`
-/// to it, then renames (persists) it to `path`. The rename is atomic on most
-/// filesystems (ext4, APFS, NTFS), ensuring no partial writes.
+/// Write `content` to a new temp file next to `path`, ready to be renamed
+/// over it. The file is deleted if dropped without being persisted.
 ///
 /// # Errors
 ///
-/// Returns an error if the parent directory doesn't exist, writing fails,
-/// or the rename fails (e.g., cross-device).
-pub fn atomic_write(path: &Path, content: &str) -> Result<()> {
+/// Returns an error if the parent directory doesn't exist or the temp file
+/// cannot be created or written.
+fn stage_temp(path: &Path, content: &str) -> Result<tempfile::NamedTempFile> {
     let parent = path
         .parent()
         .with_context(|| format!("no parent directory for {}", path.display()))?;
@@ -35,8 +32,87 @@ pub fn atomic_write(path: &Path, content: &str) -> Result<()> {
     tmp.flush()
         .with_context(|| format!("failed to flush temp file for {}", path.display()))?;
 
-    tmp.persist(path)
+    Ok(tmp)
+}
+
+/// Atomically write `content` to `path`.
+///
+/// Creates a temporary file in the same directory as `path`, writes `content`
+/// to it, then renames (persists) it to `path`. The rename is atomic on most
+/// filesystems (ext4, APFS, NTFS), ensuring no partial writes.
+///
+/// # Errors
+///
+/// Returns an error if the parent directory doesn't exist, writing fails,
+/// or the rename fails (e.g., cross-device).
+pub fn atomic_write(path: &Path, content: &str) -> Result<()> {
+    stage_temp(path, content)?
+        .persist(path)
         .with_context(|| format!("failed to atomically replace {}", path.display()))?;
 
     Ok(())
 }
+
+/// Atomically create `path` with `content`, refusing to replace an existing file.
+///
+/// Like [`atomic_write`], but persists with a no-clobber rename (hard link +
+/// unlink on Unix), so the existence check and the write cannot race.
+/// Returns `Ok(false)` without writing if `path` already exists.
+///
+/// # Errors
+///
+/// Returns an error if the parent directory doesn't exist, writing fails,
+/// or the persist fails for a reason other than the file existing.
+pub fn atomic_create(path: &Path, content: &str) -> Result<bool> {
+    match stage_temp(path, content)?.persist_noclobber(path) {
+        Ok(_) => Ok(true),
+        Err(e) if e.error.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("failed to create {}", path.display())),
+    }
+}
+
+/// A set of writes staged to temporary files and renamed into place together.
+///
+/// Each [`stage`](Self::stage) writes the content to a temp file next to its
+/// target; nothing is visible until [`commit`](Self::commit). Dropping an
+/// uncommitted set deletes the temp files, leaving every target untouched.
+#[derive(Debug, Default)]
+pub struct StagedWrites {
+    staged: Vec<(tempfile::NamedTempFile, PathBuf)>,
+}
+
+impl StagedWrites {
+    /// An empty set.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `content` to a temp file that will replace `path` on commit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parent directory doesn't exist or the temp
+    /// file cannot be created or written.
+    pub fn stage(&mut self, path: &Path, content: &str) -> Result<()> {
+        self.staged.push((stage_temp(path, content)?, path.to_path_buf()));
+        Ok(())
+    }
+
+    /// Rename every staged file into place, in staging order.
+    ///
+    /// Each rename is atomic. Renames only fail in unusual conditions (e.g.
+    /// the target became a directory); if one does, the files before it are
+    /// already in place and the remaining temp files are deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the file whose rename failed.
+    pub fn commit(self) -> Result<()> {
+        for (tmp, path) in self.staged {
+            tmp.persist(&path)
+                .with_context(|| format!("failed to atomically replace {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
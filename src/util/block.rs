@@ -0,0 +1,135 @@
+//! Enclosing-block heuristic for `grep`'s `smartContext` mode.
+//!
+//! Given a line, find the code block around it without parsing: scan up to
+//! the nearest line indented less than it (the block header), then down to
+//! the brace that closes the header's block — or, for indentation-structured
+//! languages such as Python, to the last line indented deeper than the header.
+//! Braces inside strings and comments are not recognised, so the result is a
+//! best guess; [`enclosing_block`] returns `None` when it cannot tell.
+
+use std::path::Path;
+
+/// Largest block returned, in lines; anything longer is treated as unknown.
+pub const MAX_BLOCK_LINES: usize = 200;
+
+/// How a file delimits its blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStyle {
+    /// `{` ... `}` (C, Rust, Go, JavaScript, ...).
+    Braces,
+    /// Indentation after a `:` header (Python).
+    Indentation,
+}
+
+impl BlockStyle {
+    /// Pick the style from the file extension; braces unless Python-like.
+    #[must_use]
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("py" | "pyi" | "pyw") => Self::Indentation,
+            _ => Self::Braces,
+        }
+    }
+}
+
+/// The block enclosing `lines[line]`, as an inclusive 0-based line range.
+///
+/// The block starts at the nearest non-blank line above `line` with less
+/// indentation; if there is none, `line` itself must open a block. Returns
+/// `None` when no block can be found or it exceeds [`MAX_BLOCK_LINES`].
+#[must_use]
+pub fn enclosing_block(lines: &[&str], line: usize, style: BlockStyle) -> Option<(usize, usize)> {
+    let indent = indentation(lines.get(line)?);
+    let start = (0..line)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty() && indentation(lines[i]) < indent)
+        .or_else(|| opens_block(lines[line], style).then_some(line))?;
+    let end = match style {
+        BlockStyle::Braces => closing_brace(lines, start)?,
+        BlockStyle::Indentation => indented_end(lines, start),
+    };
+    (end >= line && end - start < MAX_BLOCK_LINES).then_some((start, end))
+}
+
+/// Width of the leading whitespace of `line` (a tab counts as one column).
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn opens_block(line: &str, style: BlockStyle) -> bool {
+    match style {
+        BlockStyle::Braces => line.contains('{'),
+        BlockStyle::Indentation => line.trim_end().ends_with(':'),
+    }
+}
+
+/// The line holding the brace that closes the first `{` at or after `start`.
+/// Closing braces before that `{` (as in `} else {`) are ignored.
+fn closing_brace(lines: &[&str], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(start).take(MAX_BLOCK_LINES) {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' if opened => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// The last non-blank line after `start` that is indented deeper than it.
+fn indented_end(lines: &[&str], start: usize) -> usize {
+    let indent = indentation(lines[start]);
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indentation(line) <= indent {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brace_block() {
+        let src = "fn main() {\n    let x = 1;\n    if x > 0 {\n        println!(\"{x}\");\n    } else {\n        x += 1;\n    }\n}\n\nconst Y: u8 = 2;";
+        let lines: Vec<&str> = src.lines().collect();
+        assert_eq!(enclosing_block(&lines, 1, BlockStyle::Braces), Some((0, 7)));
+        assert_eq!(enclosing_block(&lines, 3, BlockStyle::Braces), Some((2, 4)));
+        // `} else {` headers its own block.
+        assert_eq!(enclosing_block(&lines, 5, BlockStyle::Braces), Some((4, 6)));
+        // A top-level line opening a block is its own header.
+        assert_eq!(enclosing_block(&lines, 0, BlockStyle::Braces), Some((0, 7)));
+        // A top-level line without a block has no structure.
+        assert_eq!(enclosing_block(&lines, 9, BlockStyle::Braces), None);
+    }
+
+    #[test]
+    fn test_indentation_block() {
+        let src = "class A:\n    def f(self):\n        x = 1\n\n        return x\n\n    def g(self):\n        pass\nprint(1)";
+        let lines: Vec<&str> = src.lines().collect();
+        assert_eq!(enclosing_block(&lines, 2, BlockStyle::Indentation), Some((1, 4)));
+        assert_eq!(enclosing_block(&lines, 1, BlockStyle::Indentation), Some((0, 7)));
+        assert_eq!(enclosing_block(&lines, 8, BlockStyle::Indentation), None);
+        assert_eq!(BlockStyle::for_path(Path::new("a/b.py")), BlockStyle::Indentation);
+        assert_eq!(BlockStyle::for_path(Path::new("a/b.rs")), BlockStyle::Braces);
+    }
+}
@@ -0,0 +1,97 @@
+//! Case-insensitive path comparison for the workspace boundary check.
+//!
+//! On case-insensitive filesystems (the macOS and Windows defaults) `SRC` and
+//! `src` name the same directory, but `Path::starts_with` compares bytes, and
+//! whether `canonicalize` restores the on-disk case depends on the platform.
+//! A workspace marked case-insensitive therefore compares components with
+//! [`starts_with_ignore_case`].
+
+use std::path::Path;
+
+/// Whether `path` lies under `base`, comparing components without regard to
+/// case (Unicode lowercase, which matches APFS and NTFS for common names).
+#[must_use]
+pub fn starts_with_ignore_case(path: &Path, base: &Path) -> bool {
+    let mut components = path.components();
+    base.components().all(|expected| {
+        components.next().is_some_and(|actual| {
+            actual == expected
+                || actual.as_os_str().to_string_lossy().to_lowercase()
+                    == expected.as_os_str().to_string_lossy().to_lowercase()
+        })
+    })
+}
+
+/// Whether the filesystem holding `dir` ignores case in file names.
+///
+/// Probes the first component of `dir` (from the end) that changes when its
+/// case is swapped: the filesystem is case-insensitive if the swapped name
+/// resolves to the same file. Without such a component, or if `dir` cannot
+/// be inspected, falls back to the platform default.
+#[must_use]
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    let Ok(dir) = dir.canonicalize() else {
+        return cfg!(any(target_os = "macos", windows));
+    };
+    for ancestor in dir.ancestors() {
+        let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name()) else {
+            continue;
+        };
+        let name = name.to_string_lossy();
+        let swapped: String = name
+            .chars()
+            .map(|c| {
+                if c.is_uppercase() {
+                    c.to_lowercase().next().unwrap_or(c)
+                } else {
+                    c.to_uppercase().next().unwrap_or(c)
+                }
+            })
+            .collect();
+        if swapped == name {
+            continue;
+        }
+        return same_file(ancestor, &parent.join(swapped));
+    }
+    cfg!(any(target_os = "macos", windows))
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt as _;
+
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &Path, b: &Path) -> bool {
+    // Without inode numbers, the swapped name existing is taken as proof.
+    b.exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starts_with_ignore_case() {
+        assert!(starts_with_ignore_case(Path::new("/Users/Me/Proj/SRC/main.rs"), Path::new("/users/me/proj")));
+        assert!(starts_with_ignore_case(Path::new("/ws/src"), Path::new("/WS")));
+        assert!(starts_with_ignore_case(Path::new("/ws"), Path::new("/ws")));
+        assert!(!starts_with_ignore_case(Path::new("/ws-other/a"), Path::new("/ws")));
+        assert!(!starts_with_ignore_case(Path::new("/ws"), Path::new("/ws/src")));
+        assert!(!starts_with_ignore_case(Path::new("/elsewhere/ws"), Path::new("/ws")));
+    }
+
+    #[test]
+    fn test_detection_matches_filesystem() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let probe = dir.path().join("Probe");
+        std::fs::create_dir(&probe).expect("mkdir");
+        let insensitive = dir.path().join("pROBE").exists();
+        assert_eq!(is_case_insensitive(&probe), insensitive);
+    }
+}
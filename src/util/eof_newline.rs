@@ -0,0 +1,116 @@
+//! Newline-at-end-of-file policy shared by the writing tools.
+//!
+//! Projects disagree on whether files end with a newline, so the policy is
+//! set once in the server config and applied by every tool that writes a
+//! file's text (`write`, `write_many`, `edit`, `replace_in_files`,
+//! `rename_symbol`, `format` and `copy`) to the content it is about to
+//! write. The default, [`EofNewline::Preserve`], writes content exactly as
+//! given. `write`'s own `ensureTrailingNewline` is
+//! [`EofNewline::SingleTrailingNewline`].
+
+use std::borrow::Cow;
+
+use serde::Deserialize;
+
+/// What to do with the newline(s) at the end of written content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EofNewline {
+    /// Leave the end of the content alone.
+    #[default]
+    Preserve,
+    /// Add a newline to non-empty content that does not end with one.
+    EnsureTrailingNewline,
+    /// End non-empty content with exactly one newline: add a missing one and
+    /// collapse extra ones.
+    SingleTrailingNewline,
+    /// Remove every newline at the end of the content.
+    StripTrailingNewlines,
+}
+
+impl EofNewline {
+    /// `content` with the policy applied, and a note describing the change
+    /// if one was made.
+    ///
+    /// A newline added follows the content's own line endings (`\r\n` if it
+    /// contains any), and one kept from a run keeps its own; empty content is
+    /// never changed.
+    #[must_use]
+    pub fn apply(self, content: &str) -> (Cow<'_, str>, Option<String>) {
+        match self {
+            Self::Preserve => (Cow::Borrowed(content), None),
+            Self::EnsureTrailingNewline => {
+                if content.is_empty() || content.ends_with('\n') {
+                    return (Cow::Borrowed(content), None);
+                }
+                let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+                (Cow::Owned(format!("{content}{eol}")), Some("added final newline".to_owned()))
+            }
+            Self::SingleTrailingNewline => {
+                let body = content.trim_end_matches(['\n', '\r']);
+                let trailing = &content[body.len()..];
+                if content.is_empty() || trailing == "\n" || trailing == "\r\n" {
+                    return (Cow::Borrowed(content), None);
+                }
+                if trailing.is_empty() {
+                    return Self::EnsureTrailingNewline.apply(content);
+                }
+                let eol = if trailing.starts_with("\r\n") { "\r\n" } else { "\n" };
+                (Cow::Owned(format!("{body}{eol}")), Some("collapsed trailing newlines".to_owned()))
+            }
+            Self::StripTrailingNewlines => {
+                let body = content.trim_end_matches(['\n', '\r']);
+                if body.len() == content.len() {
+                    return (Cow::Borrowed(content), None);
+                }
+                let removed = content[body.len()..].matches('\n').count().max(1);
+                let note = if removed == 1 {
+                    "removed final newline".to_owned()
+                } else {
+                    format!("removed {removed} final newlines")
+                };
+                (Cow::Borrowed(body), Some(note))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preserve() {
+        for text in ["", "a", "a\n", "a\n\n"] {
+            assert_eq!(EofNewline::Preserve.apply(text), (Cow::Borrowed(text), None));
+        }
+    }
+
+    #[test]
+    fn test_ensure_trailing_newline() {
+        let ensure = |text| EofNewline::EnsureTrailingNewline.apply(text);
+        assert_eq!(ensure("a"), (Cow::Owned("a\n".to_owned()), Some("added final newline".to_owned())));
+        assert_eq!(ensure("a\r\nb").0, "a\r\nb\r\n");
+        assert_eq!(ensure("a\n\n"), (Cow::Borrowed("a\n\n"), None));
+        assert_eq!(ensure(""), (Cow::Borrowed(""), None));
+    }
+
+    #[test]
+    fn test_single_trailing_newline() {
+        let single = |text| EofNewline::SingleTrailingNewline.apply(text);
+        assert_eq!(single("a").0, "a\n");
+        assert_eq!(single("a\r\n\r\n\n").0, "a\r\n");
+        assert_eq!(single("a\n\n").1.as_deref(), Some("collapsed trailing newlines"));
+        assert_eq!(single("a\n"), (Cow::Borrowed("a\n"), None));
+        assert_eq!(single(""), (Cow::Borrowed(""), None));
+    }
+
+    #[test]
+    fn test_strip_trailing_newlines() {
+        let strip = |text| EofNewline::StripTrailingNewlines.apply(text);
+        assert_eq!(strip("a\n"), (Cow::Borrowed("a"), Some("removed final newline".to_owned())));
+        assert_eq!(strip("a\r\n\r\n\n").0, "a");
+        assert_eq!(strip("a\n\n\n").1.as_deref(), Some("removed 3 final newlines"));
+        assert_eq!(strip("a"), (Cow::Borrowed("a"), None));
+    }
+}
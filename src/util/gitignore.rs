@@ -0,0 +1,98 @@
+//! Minimal `.gitignore` support for the fallback file walkers.
+//!
+//! ripgrep honours `.gitignore` natively; when `rg` is unavailable the
+//! fallback walkers use [`GitignoreStack`] so their result sets roughly match.
+//! Parity with `rg` is best-effort: only `.gitignore` files are consulted
+//! (no `.ignore`, `.rgignore`, global excludes, or `.git/info/exclude`).
+//!
+//! Matching uses [`ignore::gitignore`] from the ripgrep ecosystem, which
+//! implements the full gitignore glob syntax including negation (`!pattern`).
+
+use std::path::Path;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+/// A stack of `.gitignore` matchers, one per directory level being walked.
+///
+/// The walker pushes a layer when it descends into a directory and pops it
+/// on the way back out. Deeper files take precedence over shallower ones,
+/// mirroring git's own semantics.
+#[derive(Debug, Default)]
+pub struct GitignoreStack {
+    layers: Vec<Gitignore>,
+}
+
+impl GitignoreStack {
+    /// Create an empty stack (nothing ignored).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a stack preloaded with every `.gitignore` from `root` down to
+    /// (but not including) `start`.
+    ///
+    /// Use this when a walk begins in a subdirectory of the workspace so that
+    /// ancestor rules (e.g. a root-level `.gitignore`) still apply. The
+    /// walker is expected to [`push_dir`](Self::push_dir) `start` itself.
+    #[must_use]
+    pub fn for_ancestors(root: &Path, start: &Path) -> Self {
+        let mut stack = Self::new();
+        let Ok(relative) = start.strip_prefix(root) else {
+            return stack;
+        };
+
+        let mut dir = root.to_path_buf();
+        for component in relative.components() {
+            stack.push_dir(&dir);
+            dir.push(component);
+        }
+        stack
+    }
+
+    /// Load `dir/.gitignore` (if present) as a new layer.
+    ///
+    /// Returns `true` if a layer was pushed; callers must [`pop`](Self::pop)
+    /// exactly when this returns `true`.
+    pub fn push_dir(&mut self, dir: &Path) -> bool {
+        let file = dir.join(".gitignore");
+        if !file.is_file() {
+            return false;
+        }
+
+        let mut builder = GitignoreBuilder::new(dir);
+        if let Some(err) = builder.add(&file) {
+            tracing::debug!(path = %file.display(), error = %err, "partially invalid .gitignore");
+        }
+        match builder.build() {
+            Ok(gi) if !gi.is_empty() => {
+                self.layers.push(gi);
+                true
+            }
+            Ok(_) => false,
+            Err(err) => {
+                tracing::debug!(path = %file.display(), error = %err, "ignoring unreadable .gitignore");
+                false
+            }
+        }
+    }
+
+    /// Remove the most recently pushed layer.
+    pub fn pop(&mut self) {
+        self.layers.pop();
+    }
+
+    /// Whether `path` is ignored by any layer (deepest layer wins).
+    #[must_use]
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for gi in self.layers.iter().rev() {
+            match gi.matched(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+}
@@ -1,3 +1,13 @@
 //! Utility modules for oa-coder.
 
 pub mod atomic;
+pub mod block;
+pub mod case_fold;
+pub mod digest;
+pub mod eof_newline;
+pub mod gitignore;
+pub mod path_cache;
+pub mod process;
+pub mod redact;
+pub mod tidy;
+pub mod time;
@@ -0,0 +1,161 @@
+//! Short-lived cache of canonicalized paths for [`validate_path`].
+//!
+//! Every tool call validates its paths, and validation canonicalizes both
+//! the path and each workspace root — one `lstat` per path component. Paths
+//! rarely move while a session runs, so [`PathCache`] keeps the canonical
+//! roots and a small LRU of recently validated paths for a short TTL.
+//!
+//! A cached path is only a shortcut for resolution: callers still check it
+//! against the (cached) canonical roots, and `lstat` each of its components
+//! below the root to make sure none has been replaced by a symlink. That
+//! skips resolving the root's own components and any `..` or links in the
+//! requested path.
+//!
+//! [`validate_path`]: crate::tools::validate_path
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long canonical roots and paths are trusted before being resolved again.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(2);
+
+/// Most validated paths remembered.
+pub const DEFAULT_CAPACITY: usize = 128;
+
+/// Canonical workspace roots plus an LRU of `raw path → canonical path`.
+#[derive(Debug)]
+pub struct PathCache {
+    ttl: Duration,
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Canonical roots and when they were resolved.
+    roots: Option<(Instant, Vec<PathBuf>)>,
+    /// Least recently used first.
+    paths: VecDeque<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    raw: PathBuf,
+    canonical: PathBuf,
+    at: Instant,
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL, DEFAULT_CAPACITY)
+    }
+}
+
+impl PathCache {
+    /// A cache trusting entries for `ttl` and holding up to `capacity` paths.
+    #[must_use]
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// The canonical forms of `roots`, resolved again once the TTL expires.
+    ///
+    /// A root that cannot be canonicalized is used as given.
+    pub fn canonical_roots(&self, roots: &[PathBuf]) -> Vec<PathBuf> {
+        let mut state = self.lock();
+        if let Some((at, canonical)) = &state.roots {
+            if at.elapsed() < self.ttl {
+                return canonical.clone();
+            }
+        }
+        let canonical: Vec<PathBuf> = roots
+            .iter()
+            .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+            .collect();
+        state.roots = Some((Instant::now(), canonical.clone()));
+        canonical
+    }
+
+    /// The canonical path recorded for `raw`, if still fresh.
+    pub fn get(&self, raw: &Path) -> Option<PathBuf> {
+        let mut state = self.lock();
+        let index = state.paths.iter().position(|entry| entry.raw == raw)?;
+        let entry = state.paths.remove(index)?;
+        if entry.at.elapsed() >= self.ttl {
+            return None;
+        }
+        let canonical = entry.canonical.clone();
+        state.paths.push_back(entry);
+        drop(state);
+        Some(canonical)
+    }
+
+    /// Record that `raw` resolved to `canonical`, evicting the least
+    /// recently used entry when full.
+    pub fn insert(&self, raw: PathBuf, canonical: PathBuf) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut state = self.lock();
+        state.paths.retain(|entry| entry.raw != raw);
+        if state.paths.len() >= self.capacity {
+            state.paths.pop_front();
+        }
+        state.paths.push_back(Entry {
+            raw,
+            canonical,
+            at: Instant::now(),
+        });
+    }
+
+    /// Forget `raw` (e.g. because its cached resolution no longer holds).
+    pub fn remove(&self, raw: &Path) {
+        self.lock().paths.retain(|entry| entry.raw != raw);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // The state is always left consistent, so a poisoned lock is usable.
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction_and_ttl() {
+        let cache = PathCache::new(Duration::from_secs(60), 2);
+        cache.insert("a".into(), "/ws/a".into());
+        cache.insert("b".into(), "/ws/b".into());
+        // Touch `a` so `b` is least recently used.
+        assert_eq!(cache.get(Path::new("a")), Some(PathBuf::from("/ws/a")));
+        cache.insert("c".into(), "/ws/c".into());
+        assert_eq!(cache.get(Path::new("b")), None);
+        assert_eq!(cache.get(Path::new("a")), Some(PathBuf::from("/ws/a")));
+        assert_eq!(cache.get(Path::new("c")), Some(PathBuf::from("/ws/c")));
+
+        let expired = PathCache::new(Duration::ZERO, 2);
+        expired.insert("a".into(), "/ws/a".into());
+        assert_eq!(expired.get(Path::new("a")), None);
+    }
+
+    #[test]
+    fn test_canonical_roots_cached() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().join("root");
+        std::fs::create_dir(&root).expect("mkdir");
+        let cache = PathCache::new(Duration::from_secs(60), 2);
+        let canonical = cache.canonical_roots(std::slice::from_ref(&root));
+        assert_eq!(canonical, vec![root.canonicalize().expect("canonicalize")]);
+        // Still served from the cache after the root disappears.
+        std::fs::remove_dir(&root).expect("rmdir");
+        assert_eq!(cache.canonical_roots(std::slice::from_ref(&root)), canonical);
+    }
+}
@@ -0,0 +1,58 @@
+//! Subprocess helpers shared by tools that shell out (e.g. `rg`).
+
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::server::CancelToken;
+
+/// How often a running child is polled for exit / cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Like [`Command::output`], but kills the child early if `cancel` fires.
+///
+/// stdout/stderr are drained on background threads so a chatty child cannot
+/// block on a full pipe while we poll. Returns `Ok(None)` if cancelled.
+pub fn output_cancellable(cmd: &mut Command, cancel: &CancelToken) -> Result<Option<Output>> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to spawn subprocess")?;
+
+    let stdout = child.stdout.take().map(spawn_read_to_end);
+    let stderr = child.stderr.take().map(spawn_read_to_end);
+
+    let status = loop {
+        if let Some(status) = child.try_wait().context("failed to check process status")? {
+            break status;
+        }
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let join = |h: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        h.and_then(|h| h.join().ok()).unwrap_or_default()
+    };
+
+    Ok(Some(Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    }))
+}
+
+fn spawn_read_to_end(mut pipe: impl Read + Send + 'static) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
@@ -0,0 +1,86 @@
+//! Whitespace tidying for `edit` and `write` (`tidy: true`).
+//!
+//! Strips trailing whitespace from every line and collapses runs of blank
+//! lines longer than a limit. Line endings (LF or CRLF) are kept as they are.
+
+use std::borrow::Cow;
+
+/// Default for `maxBlankLines`: the longest run of blank lines kept.
+pub const DEFAULT_MAX_BLANK_LINES: usize = 2;
+
+/// What [`tidy`] changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TidyReport {
+    /// Lines that lost trailing whitespace.
+    pub trimmed_lines: usize,
+    /// Blank lines removed from over-long runs.
+    pub removed_blank_lines: usize,
+}
+
+impl TidyReport {
+    /// Whether nothing was changed.
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.trimmed_lines == 0 && self.removed_blank_lines == 0
+    }
+}
+
+/// Strip trailing whitespace from each line of `text` and keep at most
+/// `max_blank_lines` consecutive blank lines.
+#[must_use]
+pub fn tidy(text: &str, max_blank_lines: usize) -> (Cow<'_, str>, TidyReport) {
+    let mut report = TidyReport::default();
+    let mut result = String::with_capacity(text.len());
+    let mut blank_run = 0;
+
+    for line in text.split_inclusive('\n') {
+        let body_len = line
+            .strip_suffix("\r\n")
+            .or_else(|| line.strip_suffix('\n'))
+            .unwrap_or(line)
+            .len();
+        let (body, eol) = line.split_at(body_len);
+        let trimmed = body.trim_end();
+        if trimmed.len() != body.len() {
+            report.trimmed_lines += 1;
+        }
+
+        if trimmed.is_empty() {
+            blank_run += 1;
+            if blank_run > max_blank_lines {
+                report.removed_blank_lines += 1;
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        result.push_str(trimmed);
+        result.push_str(eol);
+    }
+
+    if report.is_empty() {
+        (Cow::Borrowed(text), report)
+    } else {
+        (Cow::Owned(result), report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tidy() {
+        let (text, report) = tidy("a  \r\n\t\n\n\n\nb\t\nc", 2);
+        assert_eq!(text, "a\r\n\n\nb\nc");
+        assert_eq!(report, TidyReport { trimmed_lines: 3, removed_blank_lines: 2 });
+
+        let (text, report) = tidy("a\n\n\nb\n", 0);
+        assert_eq!(text, "a\nb\n");
+        assert_eq!(report.removed_blank_lines, 2);
+
+        let (text, report) = tidy("clean\n\nfile\n", 2);
+        assert!(matches!(text, Cow::Borrowed(_)));
+        assert!(report.is_empty());
+    }
+}
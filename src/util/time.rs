@@ -0,0 +1,84 @@
+//! Timestamp formatting without a date/time dependency.
+//!
+//! Tools report file modification times as RFC 3339 UTC strings
+//! (`2025-01-31T12:34:56Z`). The civil-date conversion follows Howard
+//! Hinnant's `civil_from_days` algorithm.
+//!
+//! [`mtime_token`] renders an mtime with nanosecond precision; `read` reports
+//! it and `edit` accepts it back (`expectedMtime`) to detect concurrent changes.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Format a [`SystemTime`] as an RFC 3339 UTC timestamp with second precision.
+///
+/// Times before the Unix epoch are clamped to `1970-01-01T00:00:00Z`.
+#[must_use]
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let days = secs / 86_400;
+    let rem = secs % 86_400;
+    let (hour, minute, second) = (rem / 3600, (rem % 3600) / 60, rem % 60);
+    let (year, month, day) = civil_from_days(days);
+
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Format a [`SystemTime`] as an RFC 3339 UTC timestamp with nanosecond
+/// precision (`2025-01-31T12:34:56.123456789Z`).
+#[must_use]
+pub fn format_rfc3339_nanos(time: SystemTime) -> String {
+    let nanos = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    let seconds = format_rfc3339(time);
+    format!("{}.{nanos:09}Z", seconds.trim_end_matches('Z'))
+}
+
+/// The modification time of `path` as an opaque concurrency token.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be stat'ed or the platform does not
+/// report modification times.
+pub fn mtime_token(path: &Path) -> std::io::Result<String> {
+    Ok(format_rfc3339_nanos(std::fs::metadata(path)?.modified()?))
+}
+
+/// Convert days since 1970-01-01 to a `(year, month, day)` triple.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_epoch() {
+        assert_eq!(format_rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_known_timestamp() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_709_251_199); // leap year
+        assert_eq!(format_rfc3339(t), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn test_nanos() {
+        let t = UNIX_EPOCH + Duration::new(1_709_251_199, 5_000);
+        assert_eq!(format_rfc3339_nanos(t), "2024-02-29T23:59:59.000005000Z");
+    }
+}
@@ -0,0 +1,248 @@
+//! Workspace file watching — resource change notifications.
+//!
+//! With the `watch` feature and `--watch`, the primary workspace root is
+//! watched recursively (via [`notify`]) and every created, modified or
+//! deleted file is reported to the client as
+//! `notifications/resources/updated` with its `file://` URI, which the
+//! session only delivers once the client has sent `resources/subscribe` for
+//! that URI. Creations and deletions additionally send
+//! `notifications/resources/list_changed`.
+//!
+//! Events are debounced: a burst of changes is collected until the
+//! filesystem has been quiet for [`DEBOUNCE`], then each changed path is
+//! reported once. Paths the glob walker would skip (`.git/`, the
+//! workspace's skip list such as `target` and `node_modules`, anything
+//! matched by a `.gitignore`) are never reported, so build-output churn
+//! stays silent.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{debug, warn};
+
+use crate::resources::file_uri;
+use crate::server::{JsonRpcNotification, NotificationSink};
+use crate::tools::Workspace;
+use crate::util::gitignore::GitignoreStack;
+
+/// Quiet period that ends a burst of filesystem events.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// A running workspace watcher. Watching stops when this is dropped.
+pub struct ResourceWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+/// How a path changed within one debounced burst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Created,
+    Modified,
+    Removed,
+}
+
+/// Start watching the primary root of `workspace`, sending notifications
+/// through `sink`.
+///
+/// # Errors
+///
+/// Returns an error if the platform watcher cannot be created or the root
+/// cannot be watched.
+pub fn spawn(workspace: &Workspace, sink: NotificationSink) -> Result<ResourceWatcher> {
+    let root = workspace
+        .root()
+        .canonicalize()
+        .unwrap_or_else(|_| workspace.root().to_path_buf());
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // The receiver only goes away once the watcher itself is dropped.
+        let _ = tx.send(event);
+    })
+    .context("failed to create file watcher")?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("failed to watch {}", root.display()))?;
+
+    let workspace = workspace.clone();
+    std::thread::spawn(move || debounce_loop(&root, &workspace, &rx, &sink));
+    Ok(ResourceWatcher { _watcher: watcher })
+}
+
+/// Collect events into bursts and report each burst once it settles. Ends
+/// when the watcher (and with it the sender) is dropped.
+fn debounce_loop(
+    root: &Path,
+    workspace: &Workspace,
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+    sink: &NotificationSink,
+) {
+    let mut pending = BTreeMap::new();
+    while let Ok(first) = rx.recv() {
+        record(&mut pending, first);
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => record(&mut pending, event),
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        flush(root, workspace, &mut pending, sink);
+    }
+}
+
+fn record(pending: &mut BTreeMap<PathBuf, Change>, event: notify::Result<notify::Event>) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            warn!(error = %e, "file watcher error");
+            return;
+        }
+    };
+    let change = match event.kind {
+        EventKind::Create(_) => Change::Created,
+        EventKind::Modify(_) => Change::Modified,
+        EventKind::Remove(_) => Change::Removed,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return,
+    };
+    for path in event.paths {
+        let merged = match (pending.get(&path), change) {
+            // Created then modified within a burst is still a creation.
+            (Some(Change::Created), Change::Modified) => Change::Created,
+            // Removed and recreated (e.g. an atomic rename) is a modification.
+            (Some(Change::Removed), Change::Created) => Change::Modified,
+            _ => change,
+        };
+        pending.insert(path, merged);
+    }
+}
+
+fn flush(
+    root: &Path,
+    workspace: &Workspace,
+    pending: &mut BTreeMap<PathBuf, Change>,
+    sink: &NotificationSink,
+) {
+    let mut list_changed = false;
+    for (path, change) in std::mem::take(pending) {
+        if path.is_dir() || is_ignored(root, workspace, &path) {
+            continue;
+        }
+        debug!(path = %path.display(), ?change, "workspace file changed");
+        list_changed |= change != Change::Modified;
+        sink(JsonRpcNotification::new(
+            "notifications/resources/updated",
+            serde_json::json!({ "uri": file_uri(&path) }),
+        ));
+    }
+    if list_changed {
+        sink(JsonRpcNotification::new(
+            "notifications/resources/list_changed",
+            serde_json::json!({}),
+        ));
+    }
+}
+
+/// Whether the glob walker would skip `path`: it lies outside `root`, under
+/// `.git/`, or it or one of its ancestor directories is in the workspace's
+/// skip list or gitignored.
+fn is_ignored(root: &Path, workspace: &Workspace, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return true;
+    };
+    let mut ignores = GitignoreStack::new();
+    let mut dir = root.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        if component.as_os_str() == ".git" || workspace.skips(component.as_os_str()) {
+            return true;
+        }
+        ignores.push_dir(&dir);
+        dir.push(component);
+        let is_dir = components.peek().is_some() || dir.is_dir();
+        if ignores.is_ignored(&dir, is_dir) {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ignored_follows_gitignore() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").expect("write");
+        std::fs::create_dir_all(root.join("target/debug")).expect("mkdir");
+
+        let workspace = Workspace::new(root.clone()).without_skip_names(&["target".to_owned()]);
+
+        assert!(is_ignored(&root, &workspace, &root.join("target/debug/app")));
+        assert!(is_ignored(&root, &workspace, &root.join("build.log")));
+        assert!(is_ignored(&root, &workspace, &root.join(".git/index")));
+        assert!(is_ignored(Path::new("/elsewhere"), &workspace, &root.join("src/lib.rs")));
+        assert!(!is_ignored(&root, &workspace, &root.join("src/lib.rs")));
+    }
+
+    #[test]
+    fn test_is_ignored_follows_skip_list() {
+        // No .gitignore: the workspace's skip list alone hides build output.
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        let workspace = Workspace::new(root.clone());
+
+        assert!(is_ignored(&root, &workspace, &root.join("target/debug/app")));
+        assert!(is_ignored(&root, &workspace, &root.join("web/node_modules/x/index.js")));
+        assert!(!is_ignored(&root, &workspace, &root.join("src/target.rs")));
+
+        let workspace = workspace.without_skip_names(&["node_modules".to_owned()]);
+        assert!(!is_ignored(&root, &workspace, &root.join("web/node_modules/x/index.js")));
+    }
+
+    #[test]
+    fn test_spawn_reports_changed_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().canonicalize().expect("canonicalize");
+        std::fs::write(root.join(".gitignore"), "*.log\n").expect("write");
+
+        let (tx, rx) = mpsc::channel();
+        let tx = std::sync::Mutex::new(tx);
+        let sink: NotificationSink = std::sync::Arc::new(move |n: JsonRpcNotification| {
+            let _ = tx.lock().expect("lock").send((n.method, n.params));
+        });
+        let _watcher = spawn(&Workspace::new(root.clone()), sink).expect("spawn");
+
+        std::fs::write(root.join("noise.log"), "x").expect("write");
+        std::fs::write(root.join("a.rs"), "fn main() {}").expect("write");
+
+        let (method, params) = rx.recv_timeout(Duration::from_secs(5)).expect("notification");
+        assert_eq!(method, "notifications/resources/updated");
+        assert_eq!(params["uri"], file_uri(&root.join("a.rs")));
+        let (method, _) = rx.recv_timeout(Duration::from_secs(5)).expect("notification");
+        assert_eq!(method, "notifications/resources/list_changed");
+    }
+
+    #[test]
+    fn test_record_merges_burst() {
+        use notify::event::{CreateKind, ModifyKind, RemoveKind};
+
+        let mut pending = BTreeMap::new();
+        let path = PathBuf::from("/ws/a.rs");
+        let event = |kind| Ok(notify::Event::new(kind).add_path(path.clone()));
+        record(&mut pending, event(EventKind::Create(CreateKind::File)));
+        record(&mut pending, event(EventKind::Modify(ModifyKind::Any)));
+        assert_eq!(pending.get(&path), Some(&Change::Created));
+
+        pending.clear();
+        record(&mut pending, event(EventKind::Remove(RemoveKind::File)));
+        record(&mut pending, event(EventKind::Create(CreateKind::File)));
+        assert_eq!(pending.get(&path), Some(&Change::Modified));
+    }
+}
@@ -3,6 +3,8 @@
 //! Tests the JSON-RPC 2.0 MCP server by simulating client requests
 //! via stdin/stdout pipes.
 
+use std::fmt::Write as _;
+
 use serde_json::json;
 
 /// Find the compiled test binary for oa-coder MCP server.
@@ -70,7 +72,7 @@ fn test_tool_definitions_complete() {
     let router = oa_coder::tools::ToolRouter::new(std::path::PathBuf::from("/tmp"), false);
 
     let tools = router.list_tools();
-    assert_eq!(tools.len(), 6);
+    assert_eq!(tools.len(), 19);
 
     let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
     assert!(names.contains(&"edit"));
@@ -79,6 +81,7 @@ fn test_tool_definitions_complete() {
     assert!(names.contains(&"grep"));
     assert!(names.contains(&"glob"));
     assert!(names.contains(&"bash"));
+    assert!(names.contains(&"tree"));
 
     // Verify each tool has a description and input_schema.
     for tool in &tools {
@@ -92,7 +95,37 @@ fn test_tool_definitions_complete() {
             "tool {} missing input_schema",
             tool.name
         );
+        assert_eq!(
+            tool.input_schema["additionalProperties"],
+            json!(false),
+            "tool {} accepts unknown fields",
+            tool.name
+        );
+    }
+}
+
+#[test]
+fn test_tool_list_paging() {
+    let router = oa_coder::tools::ToolRouter::new(std::path::PathBuf::from("/tmp"), false);
+    let all: Vec<String> = router.list_tools().into_iter().map(|t| t.name).collect();
+
+    let mut paged = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (page, next) = router.list_tools_page(cursor.as_deref(), 3).expect("page");
+        assert!(page.len() <= 3);
+        paged.extend(page.into_iter().map(|t| t.name));
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
     }
+    assert_eq!(paged, all);
+
+    let (page, next) = router.list_tools_page(None, 100).expect("page");
+    assert_eq!(page.len(), all.len());
+    assert!(next.is_none());
+    assert!(router.list_tools_page(Some("bogus"), 3).is_err());
 }
 
 #[test]
@@ -112,6 +145,579 @@ fn test_tool_call_read_nonexistent() {
     assert!(result.content[0].text.contains("not found"));
 }
 
+#[test]
+fn test_tool_call_read_max_line_length() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("min.js"), format!("short\n{}\n", "é".repeat(3000))).expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    // Default: 2000 bytes, i.e. 1000 two-byte chars.
+    let result = router.call_tool("read", json!({ "filePath": "min.js" })).expect("read");
+    let text = &result.content[0].text;
+    assert!(text.contains("1\tshort\n"), "got: {text}");
+    assert!(text.contains(&format!("2\t{}… (+4000 bytes)\n", "é".repeat(1000))));
+
+    // A limit inside a multi-byte char backs off to the char boundary.
+    let result = router
+        .call_tool("read", json!({ "filePath": "min.js", "maxLineLength": 5 }))
+        .expect("read");
+    assert!(result.content[0].text.contains("1\tshort\n"));
+    assert!(result.content[0].text.contains("2\téé… (+5996 bytes)\n"));
+
+    // Lines longer than the read buffer are skipped past, not buffered; a
+    // CRLF ending is not counted.
+    std::fs::write(dir.path().join("long.txt"), format!("{}\r\nend\r\n", "x".repeat(100_000))).expect("write");
+    let result = router
+        .call_tool("read", json!({ "filePath": "long.txt", "maxLineLength": 3 }))
+        .expect("read");
+    assert!(result.content[0].text.contains("1\txxx… (+99997 bytes)\n"), "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("2\tend\n"));
+}
+
+#[test]
+fn test_tool_call_read_json_output() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\nfour\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("read", json!({ "filePath": "a.txt", "output": "json" }))
+        .expect("read");
+    assert_eq!(result.content.len(), 1);
+    let report: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert!(report["path"].as_str().expect("path").ends_with("a.txt"));
+    assert_eq!(report["totalLines"], 4);
+    assert_eq!(report["startLine"], 1);
+    assert_eq!(report["lines"], json!(["one", "two", "three", "four"]));
+    assert_eq!(report["truncated"], false);
+    assert!(report["mtime"].is_string());
+
+    let args = json!({ "filePath": "a.txt", "output": "json", "offset": 2, "limit": 2, "maxLineLength": 2 });
+    let result = router.call_tool("read", args).expect("read");
+    let report: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert_eq!(report["startLine"], 2);
+    assert_eq!(report["lines"], json!(["tw… (+1 bytes)", "th… (+3 bytes)"]));
+    assert_eq!(report["truncated"], true);
+    assert!(report["totalLines"].is_null());
+    assert_eq!(report["nextOffset"], 4);
+}
+
+#[test]
+fn test_tool_call_rejects_unknown_fields() {
+    let router = oa_coder::tools::ToolRouter::new(std::path::PathBuf::from("/tmp"), false);
+
+    let err = router
+        .call_tool("read", json!({ "filepath": "a.txt" }))
+        .expect_err("unknown field");
+    let message = err.to_string();
+    assert!(message.contains("invalid read parameters"), "got: {message}");
+    assert!(message.contains("unknown field `filepath`"), "got: {message}");
+    assert!(message.contains("Accepted fields:"), "got: {message}");
+    assert!(message.contains("filePath (required)"), "got: {message}");
+
+    // Nested objects are strict too.
+    let err = router
+        .call_tool(
+            "write_many",
+            json!({ "files": [{ "filePath": "a.txt", "content": "", "mode": "644" }] }),
+        )
+        .expect_err("unknown nested field");
+    assert!(err.to_string().contains("unknown field `mode`"), "got: {err}");
+}
+
+#[test]
+fn test_tool_call_stat() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router.call_tool("stat", json!({ "filePath": "a.txt" })).expect("stat");
+    assert!(!result.is_error);
+    let info: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert_eq!(info["exists"], true);
+    assert_eq!(info["type"], "file");
+    assert_eq!(info["sizeBytes"], 13);
+    assert_eq!(info["isBinary"], false);
+    assert_eq!(info["lineCount"], 3);
+    assert!(info["modifiedMs"].as_u64().is_some_and(|ms| ms > 0));
+
+    let result = router.call_tool("stat", json!({ "filePath": "missing.txt" })).expect("stat");
+    assert!(!result.is_error);
+    assert_eq!(result.content[0].text, "{\n  \"exists\": false\n}");
+}
+
+#[test]
+fn test_tool_call_timeout() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false)
+        .with_call_timeout(Some(std::time::Duration::from_millis(300)));
+
+    let started = std::time::Instant::now();
+    let result = router.call_tool("bash", json!({ "command": "sleep 10" })).expect("bash");
+    assert!(result.is_error);
+    assert_eq!(
+        result.content[0].text,
+        "Error: bash timed out after 300ms and was cancelled, but it may still complete in the \
+         background; check its effects before retrying"
+    );
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    let result = router.call_tool("bash", json!({ "command": "echo hi" })).expect("bash");
+    assert!(!result.is_error);
+    assert!(result.content[0].text.starts_with("hi"));
+}
+
+#[test]
+fn test_tool_call_read_next_offset_chains_to_end() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let content: Vec<String> = (1..=25).map(|i| format!("line {i}")).collect();
+    std::fs::write(dir.path().join("big.txt"), content.join("\n")).expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let mut offset = 1;
+    let mut byte_offset = 0;
+    let mut seen = Vec::new();
+    loop {
+        let args = json!({ "filePath": "big.txt", "offset": offset, "byteOffset": byte_offset, "limit": 10 });
+        let result = router.call_tool("read", args).expect("read");
+        assert!(!result.is_error, "{}", result.content[0].text);
+        seen.extend(
+            result.content[0]
+                .text
+                .lines()
+                .filter_map(|l| l.split_once('\t'))
+                .map(|(n, _)| n.trim().parse::<usize>().expect("line number")),
+        );
+        let Some(meta) = result.content.get(1) else { break };
+        let meta: serde_json::Value = serde_json::from_str(&meta.text).expect("json");
+        offset = usize::try_from(meta["nextOffset"].as_u64().expect("nextOffset")).expect("usize");
+        byte_offset = meta["nextByteOffset"].as_u64().expect("nextByteOffset");
+    }
+    assert_eq!(seen, (1..=25).collect::<Vec<_>>());
+    assert_eq!(offset, 21);
+    assert_eq!(byte_offset, 151);
+
+    // Chaining on offset alone works too, and a position that is not a line
+    // start (the file changed) is refused.
+    let result = router.call_tool("read", json!({ "filePath": "big.txt", "offset": 21 })).expect("read");
+    assert!(result.content[0].text.starts_with("21\tline 21\n"));
+    let result = router
+        .call_tool("read", json!({ "filePath": "big.txt", "offset": 21, "byteOffset": 170 }))
+        .expect("read");
+    assert!(result.is_error && result.content[0].text.contains("not at the start of a line"));
+    let result = router.call_tool("read", json!({ "filePath": "big.txt", "byteOffset": 0 })).expect("read");
+    assert!(result.is_error);
+}
+
+#[test]
+fn test_tool_call_copy() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir_all(dir.path().join("tpl/sub")).expect("mkdir");
+    std::fs::write(dir.path().join("tpl/a.txt"), "hello").expect("write");
+    std::fs::write(dir.path().join("tpl/sub/b.txt"), "world!").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("copy", json!({ "source": "tpl/a.txt", "destination": "new/dir/a.txt" }))
+        .expect("copy");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.ends_with("(5 bytes)"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("new/dir/a.txt")).expect("read"), "hello");
+
+    // Existing destinations need overwrite; directories need recursive.
+    let result = router
+        .call_tool("copy", json!({ "source": "tpl/sub/b.txt", "destination": "new/dir/a.txt" }))
+        .expect("copy");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("already exists"));
+    let result = router
+        .call_tool("copy", json!({ "source": "tpl", "destination": "tpl2" }))
+        .expect("copy");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("is a directory"));
+
+    let result = router
+        .call_tool("copy", json!({ "source": "tpl", "destination": "tpl2", "recursive": true }))
+        .expect("copy");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.ends_with("(2 files, 11 bytes)"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("tpl2/sub/b.txt")).expect("read"), "world!");
+
+    let result = router
+        .call_tool("copy", json!({ "source": "tpl", "destination": "tpl/inner", "recursive": true }))
+        .expect("copy");
+    assert!(result.is_error);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tool_call_copy_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt as _;
+    let dir = tempfile::tempdir().expect("tempdir");
+    let script = dir.path().join("run.sh");
+    std::fs::write(&script, "#!/bin/sh\n").expect("write");
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).expect("chmod");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("copy", json!({ "source": "run.sh", "destination": "run2.sh" }))
+        .expect("copy");
+    assert!(!result.is_error);
+    let mode = std::fs::metadata(dir.path().join("run2.sh")).expect("stat").permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+}
+
+#[test]
+fn test_tool_call_mkdir() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("file"), "").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let mkdir = |args: serde_json::Value| router.call_tool("mkdir", args).expect("mkdir");
+
+    let result = mkdir(json!({ "path": "a/b" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("set parents"));
+
+    let result = mkdir(json!({ "path": "a/b", "parents": true }));
+    assert!(!result.is_error);
+    assert!(result.content[0].text.starts_with("Created directory"));
+    assert!(dir.path().join("a/b").is_dir());
+
+    let result = mkdir(json!({ "path": "a/b", "parents": true }));
+    assert!(!result.is_error);
+    assert!(result.content[0].text.starts_with("Directory already exists"));
+    assert!(mkdir(json!({ "path": "a/b" })).is_error);
+    assert!(mkdir(json!({ "path": "file", "parents": true })).is_error);
+    assert!(!mkdir(json!({ "path": "a/c" })).is_error);
+}
+
+#[test]
+fn test_tool_call_touch() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let touch = |args: serde_json::Value| router.call_tool("touch", args).expect("touch");
+
+    let result = touch(json!({ "filePath": "a/new.txt", "create": false }));
+    assert!(result.is_error);
+    assert!(!dir.path().join("a").exists());
+
+    let result = touch(json!({ "filePath": "a/new.txt" }));
+    assert!(!result.is_error);
+    assert!(result.content[0].text.starts_with("Created empty file"));
+    assert_eq!(std::fs::read(dir.path().join("a/new.txt")).expect("read"), b"");
+
+    let path = dir.path().join("old.txt");
+    std::fs::write(&path, "keep me").expect("write");
+    let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|file| file.set_modified(old))
+        .expect("set mtime");
+    let result = touch(json!({ "filePath": "old.txt", "create": false }));
+    assert!(!result.is_error);
+    assert!(result.content[0].text.starts_with("Touched"));
+    assert_eq!(std::fs::read_to_string(&path).expect("read"), "keep me");
+    assert!(std::fs::metadata(&path).expect("stat").modified().expect("mtime") > old);
+
+    assert!(touch(json!({ "filePath": "a" })).is_error);
+    assert!(touch(json!({ "filePath": "../outside.txt" })).is_error);
+}
+
+#[test]
+fn test_tool_call_symbols() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let symbols = |path: &str| router.call_tool("symbols", json!({ "filePath": path })).expect("symbols");
+
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        "pub struct Config;\n\nimpl Config {\n    pub fn new() -> Self { Self }\n}\n\npub fn run() {}\n",
+    )
+    .expect("write");
+    let result = symbols("lib.rs");
+    assert!(!result.is_error);
+    let outline: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert_eq!(
+        outline,
+        json!({
+            "language": "rust",
+            "symbols": [
+                { "name": "Config", "kind": "struct", "line": 1 },
+                { "name": "Config", "kind": "impl", "line": 3 },
+                { "name": "run", "kind": "fn", "line": 7 }
+            ]
+        })
+    );
+
+    std::fs::write(dir.path().join("app.ts"), "export class App {}\nconst port = 8080;\n").expect("write");
+    let outline: serde_json::Value =
+        serde_json::from_str(&symbols("app.ts").content[0].text).expect("json");
+    assert_eq!(outline["language"], "typescript");
+    assert_eq!(outline["symbols"][1], json!({ "name": "port", "kind": "const", "line": 2 }));
+
+    std::fs::write(dir.path().join("tool.py"), "def main():\n    def inner():\n        pass\n").expect("write");
+    let outline: serde_json::Value =
+        serde_json::from_str(&symbols("tool.py").content[0].text).expect("json");
+    assert_eq!(outline["symbols"], json!([{ "name": "main", "kind": "def", "line": 1 }]));
+
+    std::fs::write(dir.path().join("notes.md"), "# fn title\n").expect("write");
+    let result = symbols("notes.md");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("supported extensions"));
+    let result = symbols("missing.rs");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("not found"));
+}
+
+#[test]
+fn test_tool_call_git_diff() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let result = router.call_tool("git_diff", json!({})).expect("git_diff");
+    assert!(result.is_error);
+    if which::which("git").is_err() {
+        return;
+    }
+    assert!(result.content[0].text.contains("not inside a git repository"));
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@t"])
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .expect("git");
+        assert!(status.status.success(), "git {args:?}");
+    };
+    git(&["init", "-q"]);
+    std::fs::write(dir.path().join("a.txt"), "one\n").expect("write");
+    std::fs::write(dir.path().join("b.txt"), "two\n").expect("write");
+    git(&["add", "."]);
+    git(&["commit", "-qm", "init"]);
+
+    let result = router.call_tool("git_diff", json!({})).expect("git_diff");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.starts_with("No unstaged changes"));
+
+    std::fs::write(dir.path().join("a.txt"), "ONE\n").expect("write");
+    std::fs::write(dir.path().join("b.txt"), "TWO\n").expect("write");
+    let result = router.call_tool("git_diff", json!({ "filePath": "a.txt" })).expect("git_diff");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let text = &result.content[0].text;
+    assert!(text.starts_with("diff --git a/a.txt b/a.txt"));
+    assert!(text.contains("-one\n+ONE") && !text.contains("b.txt") && !text.contains("exit code"));
+
+    git(&["add", "b.txt"]);
+    let result = router.call_tool("git_diff", json!({ "staged": true })).expect("git_diff");
+    assert!(result.content[0].text.contains("+TWO") && !result.content[0].text.contains("+ONE"));
+
+    // A diff past the output cap is refused rather than returned cut short.
+    std::fs::write(dir.path().join("a.txt"), "x\n".repeat(800_000)).expect("write");
+    let result = router.call_tool("git_diff", json!({ "filePath": "a.txt" })).expect("git_diff");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("exceeds the output limit"), "{}", result.content[0].text);
+}
+
+#[test]
+fn test_tool_call_blame() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let blame = |args: serde_json::Value| router.call_tool("blame", args).expect("blame");
+
+    let result = blame(json!({ "filePath": "a.txt" }));
+    assert!(result.is_error);
+    if which::which("git").is_err() {
+        return;
+    }
+    assert!(result.content[0].text.contains("not inside a git repository"));
+
+    let git = |args: &[&str]| {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=Jane Doe", "-c", "user.email=jane@example.com"])
+            .args(args)
+            .current_dir(dir.path())
+            .env("GIT_AUTHOR_DATE", "2025-01-31T12:00:00Z")
+            .output()
+            .expect("git");
+        assert!(status.status.success(), "git {args:?}");
+    };
+    git(&["init", "-q"]);
+    git(&["add", "."]);
+    git(&["commit", "-qm", "init"]);
+    std::fs::write(dir.path().join("a.txt"), "one\nTWO\nthree\n").expect("write");
+
+    let result = blame(json!({ "filePath": "a.txt" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let rows: Vec<&str> = result.content[0].text.lines().collect();
+    assert_eq!(rows.len(), 3);
+    assert!(rows[0].starts_with("1: ") && rows[0].ends_with(" Jane Doe 2025-01-31"), "got: {}", rows[0]);
+    assert!(rows[1].starts_with("2: 00000000 Not Committed Yet"), "got: {}", rows[1]);
+
+    let result = blame(json!({ "filePath": "a.txt", "startLine": 3 }));
+    assert!(result.content[0].text.starts_with("3: ") && result.content[0].text.lines().count() == 1);
+    let result = blame(json!({ "filePath": "a.txt", "startLine": 2, "endLine": 1 }));
+    assert!(result.is_error);
+    let result = blame(json!({ "filePath": "a.txt", "startLine": 9 }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.starts_with("Error: git blame failed"));
+
+    // Blame output past the output cap is refused rather than returned cut short.
+    std::fs::write(dir.path().join("a.txt"), "x\n".repeat(10_000)).expect("write");
+    let result = blame(json!({ "filePath": "a.txt" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("exceeds the output limit"), "{}", result.content[0].text);
+    assert!(!blame(json!({ "filePath": "a.txt", "endLine": 10 })).is_error);
+}
+
+#[test]
+fn test_tool_call_format() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let file = dir.path().join("a.txt");
+    std::fs::write(&file, "one  two\nthree\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("format", json!({ "filePath": "a.txt", "formatter": "sed s/two/2/" }))
+        .expect("format");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("+one  2"));
+    assert!(result.content[0].text.ends_with("(+1 -1, 1 hunk)"));
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "one  2\nthree\n");
+
+    // A failing formatter leaves the file alone, and so do a missing one and
+    // one that prints nothing.
+    let result = router
+        .call_tool("format", json!({ "filePath": "a.txt", "formatter": "sh -c 'echo x; exit 3'" }))
+        .expect("format");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("(exit code: 3)"));
+    let result = router
+        .call_tool("format", json!({ "filePath": "a.txt", "formatter": "true" }))
+        .expect("format");
+    assert!(result.is_error && result.content[0].text.contains("printed nothing"));
+    let result = router
+        .call_tool("format", json!({ "filePath": "a.txt", "formatter": "no-such-formatter-oa" }))
+        .expect("format");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("not installed"));
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "one  2\nthree\n");
+
+    // No built-in formatter for .txt.
+    let result = router.call_tool("format", json!({ "filePath": "a.txt" })).expect("format");
+    assert!(result.is_error);
+    assert!(std::fs::read_dir(dir.path()).expect("read_dir").count() == 1, "temp file left behind");
+}
+
+#[test]
+fn test_tool_call_format_leaves_other_files_alone() {
+    if which::which("rustfmt").is_err() {
+        return;
+    }
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("main.rs"), "mod child;\nfn  main( ){}\n").expect("write");
+    std::fs::write(dir.path().join("child.rs"), "fn  child( ){}\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router.call_tool("format", json!({ "filePath": "main.rs" })).expect("format");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).expect("read");
+    assert_eq!(read("main.rs"), "mod child;\nfn main() {}\n");
+    // rustfmt follows `mod child;` when given a file, but not on stdin.
+    assert_eq!(read("child.rs"), "fn  child( ){}\n");
+}
+
+#[test]
+fn test_tool_call_read_binary_heuristics() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let utf16 = |text: &str, bom: bool| {
+        let mut bytes = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    };
+    std::fs::write(dir.path().join("bom.txt"), utf16("héllo\nworld\n", true)).expect("write");
+    std::fs::write(dir.path().join("nobom.txt"), utf16("plain ascii\n", false)).expect("write");
+    std::fs::write(dir.path().join("nul.txt"), "before\0after\nsecond line\n").expect("write");
+    std::fs::write(dir.path().join("prog"), b"\x7fELF\x02\x01\x01 mostly text otherwise").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let read = |args: serde_json::Value| router.call_tool("read", args).expect("read");
+
+    let result = read(json!({ "filePath": "bom.txt" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.starts_with("1\théllo\n2\tworld\n"));
+    assert!(result.content[0].text.contains("(decoded from UTF-16LE)"));
+    let result = read(json!({ "filePath": "nobom.txt" }));
+    assert!(result.content[0].text.starts_with("1\tplain ascii\n"), "{}", result.content[0].text);
+
+    // A single NUL in otherwise normal text no longer makes it binary...
+    let result = read(json!({ "filePath": "nul.txt" }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.starts_with("1\tbefore\0after\n2\tsecond line\n"));
+    // ...unless the threshold says so.
+    assert!(read(json!({ "filePath": "nul.txt", "binaryThreshold": 0.0 })).is_error);
+
+    // Known binary signatures are rejected up front; forceText overrides.
+    let result = read(json!({ "filePath": "prog" }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("forceText"));
+    let result = read(json!({ "filePath": "prog", "forceText": true }));
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains("mostly text otherwise"));
+}
+
+#[test]
+fn test_tool_call_read_image() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0";
+    std::fs::write(dir.path().join("pixel.dat"), png).expect("write");
+    std::fs::write(dir.path().join("blob.bin"), b"\0\x01\x02").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    // Sniffed from the magic bytes, whatever the extension.
+    let result = router.call_tool("read", json!({ "filePath": "pixel.dat" })).expect("read");
+    assert!(!result.is_error);
+    let item = serde_json::to_value(&result.content[0]).expect("serialize");
+    assert_eq!(item["type"], "image");
+    assert_eq!(item["mimeType"], "image/png");
+    assert!(item.get("text").is_none());
+    let data = item["data"].as_str().expect("data");
+    assert!(data.starts_with("iVBORw0KGgo"), "got: {data}");
+    assert!(result.content[1].text.contains("image/png"));
+
+    // Text items still serialize as before.
+    let item = serde_json::to_value(&result.content[1]).expect("serialize");
+    assert_eq!(item["type"], "text");
+    assert!(item.get("data").is_none());
+
+    let result = router.call_tool("read", json!({ "filePath": "blob.bin" })).expect("read");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("Binary file detected"));
+
+    // A BMP header must record the file size and a known DIB header size.
+    let mut bmp = b"BM".to_vec();
+    bmp.extend_from_slice(&30u32.to_le_bytes());
+    bmp.extend_from_slice(&[0; 8]);
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.resize(30, 0);
+    std::fs::write(dir.path().join("pixel.raw"), &bmp).expect("write");
+    std::fs::write(dir.path().join("notes.txt"), "BMW and Audi sales notes\n").expect("write");
+    let result = router.call_tool("read", json!({ "filePath": "pixel.raw" })).expect("read");
+    let item = serde_json::to_value(&result.content[0]).expect("serialize");
+    assert_eq!(item["mimeType"], "image/bmp");
+    let result = router.call_tool("read", json!({ "filePath": "notes.txt" })).expect("read");
+    assert!(result.content[0].text.contains("1\tBMW and Audi"), "{}", result.content[0].text);
+
+    // The extension only counts once the head is known not to be text.
+    std::fs::write(dir.path().join("fake.png"), "not an image\n").expect("write");
+    std::fs::write(dir.path().join("odd.png"), b"\0\x01\x02").expect("write");
+    let result = router.call_tool("read", json!({ "filePath": "fake.png" })).expect("read");
+    assert!(result.content[0].text.contains("1\tnot an image"), "{}", result.content[0].text);
+    let result = router.call_tool("read", json!({ "filePath": "odd.png" })).expect("read");
+    let item = serde_json::to_value(&result.content[0]).expect("serialize");
+    assert_eq!(item["mimeType"], "image/png");
+}
+
 #[test]
 fn test_tool_call_unknown() {
     let router = oa_coder::tools::ToolRouter::new(std::path::PathBuf::from("/tmp"), false);
@@ -183,6 +789,7 @@ fn test_tool_call_edit() {
     assert!(
         result.content[0].text.contains("-foo bar") || result.content[0].text.contains("+baz qux")
     );
+    assert!(result.content[0].text.ends_with("(+1 -1, 1 hunk)"));
 
     // Verify file was actually changed.
     let content = std::fs::read_to_string(&file_path).expect("read");
@@ -190,6 +797,86 @@ fn test_tool_call_edit() {
     assert!(!content.contains("foo bar"));
 }
 
+#[test]
+fn test_tool_call_edit_line_range() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let file = dir.path().join("a.txt");
+    std::fs::write(&file, "one\ntwo\nthree\nfour\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let edit = |args: serde_json::Value| router.call_tool("edit", args).expect("edit");
+
+    // No oldString needed; the replaced lines keep their line break.
+    let result = edit(json!({
+        "filePath": "a.txt", "startLine": 2, "endLine": 3, "newString": "TWO-THREE",
+        "expectedText": "two\nthree",
+    }));
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("-two\n-three\n+TWO-THREE"));
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "one\nTWO-THREE\nfour\n");
+
+    // A stale expectedText is refused and shows the current lines.
+    let result = edit(json!({
+        "filePath": "a.txt", "startLine": 2, "newString": "x", "expectedText": "two",
+    }));
+    assert!(result.is_error);
+    assert!(result.content[0].text.ends_with("they currently read:\nTWO-THREE"));
+
+    // An empty newString deletes the lines; ranges past the end fail.
+    assert!(!edit(json!({ "filePath": "a.txt", "startLine": 1, "newString": "" })).is_error);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "TWO-THREE\nfour\n");
+    assert!(edit(json!({ "filePath": "a.txt", "startLine": 2, "endLine": 5, "newString": "" })).is_error);
+
+    // oldString is still required without startLine.
+    assert!(edit(json!({ "filePath": "a.txt", "newString": "x" })).is_error);
+}
+
+#[test]
+fn test_tool_call_edit_whole_word() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let file = dir.path().join("a.rs");
+    std::fs::write(&file, "let width = 1;\nlet id = 2;\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "a.rs", "oldString": "id", "newString": "key", "wholeWord": true }),
+        )
+        .expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "let width = 1;\nlet key = 2;\n");
+}
+
+#[test]
+fn test_tool_call_edit_json_diff() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "a.txt", "oldString": "two", "newString": "2", "diffFormat": "json" }),
+        )
+        .expect("edit");
+    assert!(!result.is_error);
+    let report: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert_eq!(report["insertions"], 1);
+    assert_eq!(report["deletions"], 1);
+    assert_eq!(
+        report["hunks"],
+        json!([{
+            "oldStart": 1, "oldLines": 3, "newStart": 1, "newLines": 3,
+            "lines": [
+                { "tag": "equal", "text": "one" },
+                { "tag": "delete", "text": "two" },
+                { "tag": "insert", "text": "2" },
+                { "tag": "equal", "text": "three" }
+            ]
+        }])
+    );
+}
+
 #[test]
 fn test_tool_call_edit_create_new_file() {
     let dir = tempfile::tempdir().expect("tempdir");
@@ -313,3 +1000,2267 @@ fn test_tool_call_bash_sandboxed() {
         result.content[0].text
     );
 }
+
+#[test]
+fn test_tool_call_grep_require_ripgrep() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "needle\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false)
+        .with_require_ripgrep(true);
+
+    for (tool, args) in [
+        ("grep", json!({ "pattern": "needle" })),
+        ("search", json!({ "glob": "*.txt", "pattern": "needle" })),
+    ] {
+        let result = router.call_tool(tool, args);
+        if which::which("rg").is_ok() {
+            assert!(result.expect(tool).content[0].text.contains("a.txt"));
+        } else {
+            let err = result.expect_err("rg is missing");
+            assert!(matches!(
+                err.downcast_ref::<oa_coder::error::CoderError>(),
+                Some(oa_coder::error::CoderError::RipgrepNotFound)
+            ));
+        }
+    }
+
+    // The per-call parameter works without the router setting too.
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let result = router.call_tool("grep", json!({ "pattern": "needle", "requireRipgrep": true }));
+    assert_eq!(result.is_ok(), which::which("rg").is_ok());
+}
+
+#[test]
+fn test_tool_call_grep_notes_invalid_utf8() {
+    if which::which("rg").is_err() {
+        // The fallback search skips files that are not valid UTF-8.
+        return;
+    }
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("latin1.txt"), b"needle caf\xe9\n").expect("write");
+    std::fs::write(dir.path().join("utf8.txt"), "needle ok\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router.call_tool("grep", json!({ "pattern": "needle" })).expect("grep");
+    let text = &result.content[0].text;
+    assert!(text.contains("needle caf\u{fffd}"), "got: {text}");
+    assert!(text.contains("needle ok"), "got: {text}");
+    assert!(text.ends_with("(note: 1 output line was not valid UTF-8; invalid bytes are shown as \u{fffd})"));
+}
+
+#[test]
+fn test_tool_call_grep_multiline() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        dir.path().join("lib.rs"),
+        "fn main()\n{\n    println!(\"hi\");\n}\n",
+    )
+    .expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "grep",
+            json!({
+                "pattern": r"fn main\(\)\n\{",
+                "multiline": true
+            }),
+        )
+        .expect("grep should succeed");
+
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains(":1:fn main()"));
+}
+
+#[test]
+fn test_tool_call_grep_smart_context() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir(dir.path().join("rs")).expect("mkdir");
+    std::fs::create_dir(dir.path().join("py")).expect("mkdir");
+    std::fs::write(
+        dir.path().join("rs/lib.rs"),
+        "use std::io;\n\nfn first() {\n    let a = 1;\n    needle(a);\n}\n\nfn second() {\n    other();\n}\n",
+    )
+    .expect("write");
+    std::fs::write(
+        dir.path().join("py/app.py"),
+        "import os\n\ndef run():\n    x = 1\n    needle(x)\n\nneedle(2)\nprint(3)\n",
+    )
+    .expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("grep", json!({ "pattern": "needle", "smartContext": true, "path": "rs" }))
+        .expect("grep");
+    assert!(!result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("lib.rs-3-fn first() {"), "got: {text}");
+    assert!(text.contains("lib.rs:5:    needle(a);"), "got: {text}");
+    assert!(text.ends_with("lib.rs-6-}"), "got: {text}");
+    assert!(!text.contains("second"), "got: {text}");
+
+    // A top-level match without a block falls back to contextLines.
+    let result = router
+        .call_tool(
+            "grep",
+            json!({ "pattern": "needle", "smartContext": true, "contextLines": 1, "path": "py" }),
+        )
+        .expect("grep");
+    let text = &result.content[0].text;
+    assert!(text.contains("app.py-3-def run():"), "got: {text}");
+    assert!(text.contains("app.py:5:    needle(x)"), "got: {text}");
+    assert!(!text.contains("import os"), "got: {text}");
+    // The fallback range touches the block, so both form one group.
+    assert!(text.contains("app.py:7:needle(2)"), "got: {text}");
+    assert!(text.ends_with("app.py-8-print(3)"), "got: {text}");
+    assert!(!text.contains("\n--\n"), "got: {text}");
+}
+
+#[test]
+fn test_tool_call_grep_count() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "foo foo\nbar\nfoo\n").expect("write");
+    std::fs::write(dir.path().join("b.txt"), "foo\n").expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("grep", json!({ "pattern": "foo", "count": true }))
+        .expect("grep should succeed");
+
+    assert!(!result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("a.txt: 3"), "got: {text}");
+    assert!(text.contains("b.txt: 1"), "got: {text}");
+    assert!(text.ends_with("Total: 4"), "got: {text}");
+}
+
+#[test]
+fn test_tool_call_grep_reports_truncation() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "foo 1\nfoo 2\nfoo 3\n").expect("write");
+    std::fs::write(dir.path().join("b.txt"), "foo 4\nbar\n").expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let summary = |args| {
+        let result = router.call_tool("grep", args).expect("grep should succeed");
+        assert!(!result.is_error);
+        assert_eq!(result.content.len(), 2);
+        serde_json::from_str::<serde_json::Value>(&result.content[1].text).expect("json summary")
+    };
+
+    // Searches stop early, so the total is only a lower bound once truncated.
+    let truncated = summary(json!({ "pattern": "foo", "maxResults": 2 }));
+    assert_eq!(truncated["truncated"], true);
+    assert_eq!(truncated["returned"], 2);
+    assert!(truncated["totalAtLeast"].as_u64().expect("total") >= 3, "got: {truncated}");
+    assert_eq!(
+        summary(json!({ "pattern": "foo" })),
+        json!({ "truncated": false, "returned": 4, "totalAtLeast": 4 })
+    );
+    assert_eq!(
+        summary(json!({ "pattern": "nothing" })),
+        json!({ "truncated": false, "returned": 0, "totalAtLeast": 0 })
+    );
+}
+
+#[test]
+fn test_tool_call_glob_respects_gitignore() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join(".gitignore"), "dist/\n").expect("write");
+    std::fs::create_dir_all(dir.path().join("dist")).expect("mkdir");
+    std::fs::create_dir_all(dir.path().join(".github")).expect("mkdir");
+    std::fs::write(dir.path().join("dist/bundle.js"), "").expect("write");
+    std::fs::write(dir.path().join(".github/ci.js"), "").expect("write");
+    std::fs::write(dir.path().join("main.js"), "").expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("glob", json!({ "pattern": "**/*.js" }))
+        .expect("glob should succeed");
+    let text = &result.content[0].text;
+    assert!(text.contains("main.js"));
+    assert!(text.contains("ci.js"));
+    assert!(!text.contains("bundle.js"));
+
+    let result = router
+        .call_tool("glob", json!({ "pattern": "**/*.js", "includeIgnored": true }))
+        .expect("glob should succeed");
+    assert!(result.content[0].text.contains("bundle.js"));
+}
+
+#[test]
+fn test_tool_call_glob_sort_by_mtime() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let old = dir.path().join("a_old.rs");
+    let new = dir.path().join("b_new.rs");
+    std::fs::write(&old, "").expect("write");
+    std::fs::write(&new, "").expect("write");
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(&old)
+        .and_then(|f| f.set_modified(past))
+        .expect("set mtime");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "glob",
+            json!({ "pattern": "*.rs", "sortBy": "mtime", "showTimes": true }),
+        )
+        .expect("glob should succeed");
+
+    let text = &result.content[0].text;
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("b_new.rs  "), "got: {text}");
+    assert!(lines[1].starts_with("a_old.rs  "), "got: {text}");
+    assert!(lines[1].ends_with('Z'), "got: {text}");
+}
+
+#[test]
+fn test_tool_call_glob_pattern_array() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("main.rs"), "").expect("write");
+    std::fs::write(dir.path().join("Cargo.toml"), "").expect("write");
+    std::fs::write(dir.path().join("README.md"), "").expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    // Overlapping patterns must not produce duplicate entries.
+    let result = router
+        .call_tool(
+            "glob",
+            json!({ "pattern": ["**/*.rs", "*.toml", "main.*"] }),
+        )
+        .expect("glob should succeed");
+
+    assert!(!result.is_error);
+    let lines: Vec<&str> = result.content[0].text.lines().collect();
+    assert_eq!(lines, vec!["Cargo.toml", "main.rs"]);
+}
+
+#[test]
+fn test_tool_call_glob_pattern_array_respects_max_results() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    for name in ["a.rs", "b.rs", "c.toml", "d.toml"] {
+        std::fs::write(dir.path().join(name), "").expect("write");
+    }
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "glob",
+            json!({ "pattern": ["*.rs", "*.toml"], "maxResults": 3 }),
+        )
+        .expect("glob should succeed");
+
+    let (page, note) = result.content[0].text.split_once("\n\n").expect("has-more note");
+    assert_eq!(page.lines().count(), 3);
+    assert!(note.contains("showing 1-3 of 4 matches"), "got: {note}");
+}
+
+#[test]
+fn test_tool_call_glob_paging() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    for i in 0..10 {
+        std::fs::write(dir.path().join(format!("f{i}.txt")), "").expect("write");
+    }
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let first = router
+        .call_tool("glob", json!({ "pattern": "*.txt", "maxResults": 5 }))
+        .expect("glob");
+    let (page, note) = first.content[0].text.split_once("\n\n").expect("has-more note");
+    assert_eq!(page, "f0.txt\nf1.txt\nf2.txt\nf3.txt\nf4.txt");
+    assert!(note.contains("has more, use offset=5"), "got: {note}");
+
+    let second = router
+        .call_tool("glob", json!({ "pattern": "*.txt", "maxResults": 5, "offset": 5 }))
+        .expect("glob");
+    assert_eq!(second.content[0].text, "f5.txt\nf6.txt\nf7.txt\nf8.txt\nf9.txt");
+
+    let past = router
+        .call_tool("glob", json!({ "pattern": "*.txt", "offset": 10 }))
+        .expect("glob");
+    assert!(!past.is_error);
+    assert!(past.content[0].text.starts_with("No more files"), "got: {}", past.content[0].text);
+}
+
+#[test]
+fn test_tool_call_glob_exclude() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir_all(dir.path().join("src")).expect("mkdir");
+    std::fs::create_dir_all(dir.path().join("tests")).expect("mkdir");
+    std::fs::write(dir.path().join("src/lib.rs"), "").expect("write");
+    std::fs::write(dir.path().join("tests/it.rs"), "").expect("write");
+
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "glob",
+            json!({ "pattern": "**/*.rs", "exclude": "tests/**" }),
+        )
+        .expect("glob should succeed");
+
+    let text = &result.content[0].text;
+    assert!(text.contains("lib.rs"));
+    assert!(!text.contains("it.rs"));
+}
+
+#[test]
+fn test_tool_call_bash_streams_progress() {
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let sink: oa_coder::server::NotificationSink = {
+        let sent = Arc::clone(&sent);
+        Arc::new(move |n| sent.lock().expect("lock").push(n))
+    };
+    let mut ctx = oa_coder::server::ToolContext {
+        progress: oa_coder::server::Progress::new(json!("tok-1"), sink),
+        cancel: oa_coder::server::CancelToken::new(),
+        request_id: None,
+    };
+
+    let result = router
+        .call_tool_with_context(
+            "bash",
+            json!({ "command": "echo first; sleep 0.2; echo second >&2" }),
+            &mut ctx,
+        )
+        .expect("bash should succeed");
+
+    // The final result still carries the full output.
+    assert!(result.content[0].text.contains("first"));
+    assert!(result.content[0].text.contains("second"));
+
+    let notes = std::mem::take(&mut *sent.lock().expect("lock"));
+    assert!(!notes.is_empty());
+    assert!(notes.iter().all(|n| n.method == "notifications/progress"));
+    assert_eq!(notes[0].params["progressToken"], json!("tok-1"));
+    let streamed: String = notes
+        .iter()
+        .filter_map(|n| n.params["message"].as_str())
+        .collect();
+    assert!(streamed.contains("first"));
+    assert!(streamed.contains("STDERR: second"));
+
+    // While redacting, progress carries byte counts only: a secret split
+    // across chunks must not leak.
+    let redactor = oa_coder::util::redact::Redactor::new(&[]).expect("patterns");
+    let router = router.with_redactor(Some(redactor));
+    let result = router
+        .call_tool_with_context(
+            "bash",
+            json!({ "command": "printf GITHUB_TOK; sleep 0.2; printf EN=abc123def" }),
+            &mut ctx,
+        )
+        .expect("bash should succeed");
+    assert!(result.content[0].text.contains("GITHUB_TOKEN=***"), "{}", result.content[0].text);
+    let notes = std::mem::take(&mut *sent.lock().expect("lock"));
+    let streamed: Vec<&str> = notes.iter().filter_map(|n| n.params["message"].as_str()).collect();
+    assert!(streamed.contains(&"(10 bytes)"), "{streamed:?}");
+    assert!(!streamed.concat().contains("abc123def"));
+}
+
+#[test]
+fn test_tool_call_bash_stdin() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "bash",
+            json!({ "command": "tr a-z A-Z", "stdin": "piped input\n" }),
+        )
+        .expect("bash should succeed");
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains("PIPED INPUT"));
+
+    // Large input must not deadlock against the child's own output.
+    let big = "x".repeat(1024 * 1024);
+    let result = router
+        .call_tool("bash", json!({ "command": "wc -c", "stdin": big }))
+        .expect("bash should succeed");
+    assert!(result.content[0].text.contains("1048576"));
+
+    // Without stdin the child sees EOF immediately.
+    let result = router
+        .call_tool("bash", json!({ "command": "cat; echo done", "timeout": 5 }))
+        .expect("bash should succeed");
+    assert!(result.content[0].text.contains("done"));
+}
+
+#[test]
+fn test_tool_call_bash_env() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "bash",
+            json!({
+                "command": "echo \"value=$OA_CODER_TEST_VAR\"",
+                "env": { "OA_CODER_TEST_VAR": "hello-env" }
+            }),
+        )
+        .expect("bash should succeed");
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains("value=hello-env"));
+
+    let result = router
+        .call_tool(
+            "bash",
+            json!({
+                "command": "test -z \"$HOME\" && echo \"home-unset var=$ONLY\"",
+                "env": { "ONLY": "me" },
+                "clearEnv": true
+            }),
+        )
+        .expect("bash should succeed");
+    assert!(result.content[0].text.contains("home-unset var=me"));
+}
+
+#[test]
+fn test_tool_call_bash_timeout_default_and_clamp() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false)
+        .with_bash_timeouts(std::time::Duration::from_secs(1), std::time::Duration::from_secs(2));
+
+    // Without `timeout` the configured default applies.
+    let result = router
+        .call_tool("bash", json!({ "command": "sleep 10" }))
+        .expect("bash should succeed");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("timed out after 1s"), "got: {}", result.content[0].text);
+
+    // A larger request is clamped to the maximum.
+    let start = std::time::Instant::now();
+    let result = router
+        .call_tool("bash", json!({ "command": "sleep 10", "timeout": 86_400 }))
+        .expect("bash should succeed");
+    assert!(start.elapsed() < std::time::Duration::from_secs(8));
+    assert!(result.content[0].text.contains("timed out after 2s"), "got: {}", result.content[0].text);
+
+    let result = router
+        .call_tool("bash", json!({ "command": "echo ok", "timeout": 86_400 }))
+        .expect("bash should succeed");
+    assert!(!result.is_error);
+}
+
+#[test]
+fn test_tool_call_bash_redacts_secrets() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let redactor = oa_coder::util::redact::Redactor::new(&["internal-[0-9]{4}".to_owned()])
+        .expect("patterns");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false)
+        .with_redactor(Some(redactor));
+
+    let result = router
+        .call_tool(
+            "bash",
+            json!({
+                "command": "echo GITHUB_TOKEN=abc123def; echo \"key $DEPLOY_KEY\" internal-1234 >&2",
+                "env": { "DEPLOY_KEY": "s3cr3t-value" }
+            }),
+        )
+        .expect("bash should succeed");
+    let text = &result.content[0].text;
+    assert!(text.contains("GITHUB_TOKEN=***"), "got: {text}");
+    assert!(text.contains("key *** ***"), "got: {text}");
+    assert!(!text.contains("abc123def") && !text.contains("s3cr3t-value"), "got: {text}");
+
+    // The command echoed in a timeout message is redacted too.
+    let result = router
+        .call_tool("bash", json!({ "command": "sleep 5 # API_TOKEN=abc123def", "timeout": 1 }))
+        .expect("bash should succeed");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("API_TOKEN=***"), "got: {}", result.content[0].text);
+
+    // Without a redactor output is untouched.
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let result = router
+        .call_tool("bash", json!({ "command": "echo GITHUB_TOKEN=abc123def" }))
+        .expect("bash should succeed");
+    assert!(result.content[0].text.contains("GITHUB_TOKEN=abc123def"));
+}
+
+#[test]
+fn test_tool_call_bash_cwd() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir_all(dir.path().join("sub")).expect("mkdir");
+    std::fs::write(dir.path().join("sub/marker.txt"), "").expect("write");
+    std::fs::write(dir.path().join("file.txt"), "").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("bash", json!({ "command": "ls", "cwd": "sub" }))
+        .expect("bash should succeed");
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains("marker.txt"));
+
+    for bad in ["missing", "file.txt", "../"] {
+        let result = router
+            .call_tool("bash", json!({ "command": "ls", "cwd": bad }))
+            .expect("bash should return a tool error");
+        assert!(result.is_error, "cwd {bad} should be rejected");
+    }
+}
+
+#[test]
+fn test_tool_call_bash_cwd_uses_workspace_policy() {
+    use oa_coder::tools::{ToolRouter, Workspace};
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let extra = tempfile::tempdir().expect("tempdir");
+    let base = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir_all(base.join("Proj/src")).expect("mkdir");
+    std::fs::write(base.join("Proj/src/marker.txt"), "").expect("write");
+    let src = base.join("Proj/src");
+
+    // The root differs in case from the directory; the cwd is checked with
+    // the workspace's case policy, not a default one.
+    let workspace = Workspace::with_roots(base.join("PROJ"), [extra.path().to_path_buf()])
+        .with_case_insensitive(true);
+    let router = ToolRouter::with_workspace(workspace, false);
+    let result = router
+        .call_tool("bash", json!({ "command": "ls", "cwd": src }))
+        .expect("bash should succeed");
+    assert!(!result.is_error, "got: {}", result.content[0].text);
+    assert!(result.content[0].text.contains("marker.txt"));
+
+    // Additional roots are valid tool paths but not command directories.
+    let result = router
+        .call_tool("bash", json!({ "command": "ls", "cwd": extra.path() }))
+        .expect("bash should return a tool error");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("primary workspace root"));
+}
+
+#[test]
+fn test_tool_call_bash_output_truncated() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("bash", json!({ "command": "yes | head -c 5M", "timeout": 30 }))
+        .expect("bash should succeed");
+
+    let text = &result.content[0].text;
+    assert!(text.contains("output truncated at 1048576 bytes"));
+    assert!(text.len() < 1024 * 1024 + 1024);
+
+    // A command that would never finish is stopped at the cap; that is not
+    // reported as the command failing or being killed by a signal.
+    let result = router
+        .call_tool("bash", json!({ "command": "yes", "maxOutputBytes": 4096, "timeout": 30 }))
+        .expect("bash should succeed");
+    let text = &result.content[0].text;
+    assert!(!result.is_error, "got: {text}");
+    assert!(text.ends_with("... output truncated at 4096 bytes\n(output limit reached, process stopped)"));
+    assert!(!text.contains("SIGKILL"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_tool_call_bash_reports_signal() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool("bash", json!({ "command": "kill -SEGV $$" }))
+        .expect("bash should succeed");
+
+    assert!(result.is_error);
+    assert!(
+        result.content[0].text.contains("killed by signal 11 / SIGSEGV"),
+        "got: {}",
+        result.content[0].text
+    );
+
+    let result = router
+        .call_tool("bash", json!({ "command": "sleep 5", "timeout": 1 }))
+        .expect("bash should succeed");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("timed out"));
+}
+
+/// Run the `oa-coder` binary over stdio, feed it `input` and collect every
+/// JSON line it writes before exiting on EOF.
+fn run_server(workspace: &std::path::Path, input: &str) -> Vec<serde_json::Value> {
+    run_server_with_args(workspace, &[], input)
+}
+
+/// Like [`run_server`], with extra command-line options.
+fn run_server_with_args(workspace: &std::path::Path, args: &[&str], input: &str) -> Vec<serde_json::Value> {
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_oa-coder"))
+        .arg("--workspace")
+        .arg(workspace)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn oa-coder");
+
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(input.as_bytes())
+        .expect("write stdin");
+
+    let output = child.wait_with_output().expect("wait");
+    String::from_utf8(output.stdout)
+        .expect("utf8 stdout")
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("json line"))
+        .collect()
+}
+
+#[test]
+fn test_server_max_concurrent_calls() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let call = |id: u32| {
+        format!(
+            r#"{{"jsonrpc":"2.0","id":{id},"method":"tools/call","params":{{"name":"bash","arguments":{{"command":"sleep 0.5 && echo call{id}"}}}}}}"#
+        )
+    };
+    let input = format!("{}\n{}\n", call(1), call(2));
+
+    // With the default single worker the two calls run back to back.
+    let started = std::time::Instant::now();
+    let responses = run_server(dir.path(), &input);
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1), "{:?}", started.elapsed());
+
+    assert_eq!(responses.len(), 2);
+    let mut ids: Vec<u64> = responses.iter().map(|r| r["id"].as_u64().expect("id")).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, vec![1, 2]);
+    for response in &responses {
+        let text = response["result"]["content"][0]["text"].as_str().expect("text");
+        assert!(text.contains(&format!("call{}", response["id"])), "got: {text}");
+    }
+}
+
+#[test]
+fn test_server_max_output_chars() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut body = String::new();
+    for i in 1..=2000 {
+        let _ = writeln!(body, "line {i}");
+    }
+    std::fs::write(dir.path().join("big.txt"), &body).expect("write");
+    std::fs::write(dir.path().join("small.txt"), "tiny\n").expect("write");
+    let config = dir.path().join("oa-coder.toml");
+    std::fs::write(&config, "max_output_chars = 500\n").expect("write");
+
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"read","arguments":{"filePath":"big.txt"}}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"read","arguments":{"filePath":"small.txt"}}}"#,
+        "\n",
+    );
+    let responses = run_server_with_args(dir.path(), &["--config", config.to_str().expect("utf-8")], input);
+    let text = |id: u64| {
+        let response = responses.iter().find(|r| r["id"] == json!(id)).expect("response");
+        response["result"]["content"][0]["text"].as_str().expect("text").to_owned()
+    };
+
+    let big = text(1);
+    assert!(big.chars().count() <= 500, "{} characters", big.chars().count());
+    let (shown, marker) = big.rsplit_once("\n\n").expect("marker");
+    assert!(marker.starts_with(&format!("[output truncated: {} of ", shown.chars().count())), "got: {marker}");
+    assert!(text(2).contains("tiny"));
+    assert!(!text(2).contains("output truncated"));
+}
+
+#[test]
+fn test_server_batch_requests() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(
+        r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},"#,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"},"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"},"#,
+        r#"{"id":3}]"#,
+        "\n",
+        "[]\n",
+        r#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#,
+        "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    assert_eq!(lines.len(), 1, "only the first batch produces output");
+
+    let batch = lines[0].as_array().expect("array response");
+    assert_eq!(batch.len(), 3);
+    assert_eq!(batch[0]["id"], json!(1));
+    assert_eq!(batch[1]["id"], json!(2));
+    assert!(batch[1]["result"]["tools"].is_array());
+    assert_eq!(batch[2]["id"], json!(3));
+    assert_eq!(batch[2]["error"]["code"], json!(-32600));
+}
+
+#[test]
+fn test_server_health() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(r#"{"jsonrpc":"2.0","id":1,"method":"oa-coder/health"}"#, "\n");
+
+    let lines = run_server(dir.path(), input);
+    let health = &lines[0]["result"];
+    assert_eq!(health["ripgrep"], json!(which::which("rg").is_ok()));
+    assert_eq!(health["sandbox"], json!(cfg!(feature = "sandbox")));
+    assert_eq!(health["workspaceWritable"], json!(true));
+    assert_eq!(health["protocolVersion"], json!(oa_coder::server::SUPPORTED_PROTOCOL_VERSIONS[0]));
+    assert_eq!(health["serverVersion"], json!(env!("CARGO_PKG_VERSION")));
+    // The probe file is cleaned up again.
+    assert_eq!(std::fs::read_dir(dir.path()).expect("read_dir").count(), 0);
+}
+
+#[test]
+fn test_server_set_workspace() {
+    let first = tempfile::tempdir().expect("tempdir");
+    let second = tempfile::tempdir().expect("tempdir");
+    std::fs::write(first.path().join("a.txt"), "first\n").expect("write");
+    std::fs::write(second.path().join("a.txt"), "second\n").expect("write");
+    let second_root = second.path().canonicalize().expect("canonicalize");
+
+    let request = |id: u32, method: &str, params: serde_json::Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string() + "\n"
+    };
+    let read = json!({ "name": "read", "arguments": { "filePath": "a.txt" } });
+    let input = [
+        request(1, "oa-coder/setWorkspace", json!({ "path": second.path().join("missing") })),
+        request(2, "oa-coder/setWorkspace", json!({ "path": second.path().join("a.txt") })),
+        request(3, "oa-coder/setWorkspace", json!({ "path": second.path() })),
+        request(4, "tools/call", read),
+        request(5, "oa-coder/health", json!({})),
+        request(6, "oa-coder/setWorkspace", json!({ "path": "/" })),
+    ]
+    .concat();
+
+    // Only directories inside the configured roots are accepted.
+    let allow_root = second.path().to_str().expect("utf-8");
+    let lines = run_server_with_args(first.path(), &["--allow-root", allow_root], &input);
+    let response = |id: u32| {
+        lines.iter().find(|line| line["id"] == id).unwrap_or_else(|| panic!("response {id}"))
+    };
+    assert_eq!(response(1)["error"]["code"], -32602);
+    assert_eq!(response(2)["error"]["code"], -32602);
+    assert!(response(2)["error"]["message"].as_str().expect("message").contains("not a directory"));
+    assert_eq!(response(6)["error"]["code"], -32602);
+    assert!(
+        response(6)["error"]["message"].as_str().expect("message").contains("outside the workspace roots")
+    );
+
+    let result = &response(3)["result"];
+    assert_eq!(result["workspace"], json!(second_root.display().to_string()));
+    assert_eq!(
+        result["previousWorkspace"],
+        json!(first.path().canonicalize().expect("canonicalize").display().to_string())
+    );
+    let text = response(4)["result"]["content"][0]["text"].as_str().expect("text");
+    assert!(text.contains("second") && !text.contains("first"), "{text}");
+    assert_eq!(response(5)["result"]["workspace"], json!(second_root.display().to_string()));
+}
+
+#[test]
+fn test_server_queued_call_keeps_its_workspace() {
+    let first = tempfile::tempdir().expect("tempdir");
+    let second = tempfile::tempdir().expect("tempdir");
+    std::fs::write(first.path().join("a.txt"), "first\n").expect("write");
+    std::fs::write(second.path().join("a.txt"), "second\n").expect("write");
+
+    let request = |id: u32, method: &str, params: serde_json::Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string() + "\n"
+    };
+    let read = json!({ "name": "read", "arguments": { "filePath": "a.txt" } });
+    // Call 2 waits behind call 1 for the only slot while the workspace moves.
+    let input = [
+        request(1, "tools/call", json!({ "name": "bash", "arguments": { "command": "sleep 0.5" } })),
+        request(2, "tools/call", read.clone()),
+        request(3, "oa-coder/setWorkspace", json!({ "path": second.path() })),
+        request(4, "tools/call", read),
+    ]
+    .concat();
+
+    let allow_root = second.path().to_str().expect("utf-8");
+    let lines = run_server_with_args(
+        first.path(),
+        &["--max-concurrent-calls", "1", "--allow-root", allow_root],
+        &input,
+    );
+    let text = |id: u32| {
+        let response = lines.iter().find(|line| line["id"] == id).unwrap_or_else(|| panic!("response {id}"));
+        response["result"]["content"][0]["text"].as_str().expect("text").to_owned()
+    };
+    assert!(text(2).contains("first"), "{}", text(2));
+    assert!(text(4).contains("second"), "{}", text(4));
+}
+
+#[test]
+fn test_server_capabilities() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(r#"{"jsonrpc":"2.0","id":1,"method":"oa-coder/capabilities"}"#, "\n");
+
+    let lines = run_server(dir.path(), input);
+    let caps = &lines[0]["result"];
+    assert_eq!(caps["workspaceWritable"], json!(true));
+    assert_eq!(caps["sandbox"], json!(cfg!(feature = "sandbox")));
+    let tools = caps["tools"].as_array().expect("tools");
+    assert_eq!(tools.len(), 19);
+    let tool = |name: &str| {
+        tools.iter().find(|t| t["name"] == name).unwrap_or_else(|| panic!("{name} listed"))
+    };
+
+    assert_eq!(tool("read")["readOnly"], json!(true));
+    assert_eq!(tool("write")["readOnly"], json!(false));
+    assert_eq!(tool("write")["ready"], json!(true));
+    // grep falls back without rg, so it is ready either way.
+    assert_eq!(tool("grep")["ready"], json!(true));
+    assert_eq!(tool("grep")["dependencies"][0]["name"], json!("rg"));
+    assert_eq!(tool("grep")["dependencies"][0]["present"], json!(which::which("rg").is_ok()));
+    assert_eq!(tool("git_diff")["ready"], json!(which::which("git").is_ok()));
+    assert_eq!(tool("format")["dependencies"][0]["name"], json!("rustfmt"));
+    assert!(tool("bash")["sandboxed"].is_boolean());
+    assert!(tool("read").get("sandboxed").is_none());
+    assert_eq!(std::fs::read_dir(dir.path()).expect("read_dir").count(), 0);
+}
+
+#[test]
+fn test_server_completion_complete() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::create_dir(dir.path().join("src")).expect("mkdir");
+    std::fs::write(dir.path().join("src/main.rs"), "").expect("write");
+    std::fs::write(dir.path().join("setup.py"), "").expect("write");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"completion/complete","params":{"ref":{"type":"ref/prompt","name":"explain_file"},"argument":{"name":"path","value":"s"}}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":3,"method":"completion/complete","params":{"ref":{"type":"ref/prompt","name":"explain_file"},"argument":{"name":"focus","value":"s"}}}"#,
+        "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    assert_eq!(lines[0]["result"]["capabilities"]["completions"], json!({}));
+    assert_eq!(
+        lines[1]["result"]["completion"],
+        json!({ "values": ["setup.py", "src/"], "total": 2, "hasMore": false })
+    );
+    assert_eq!(lines[2]["result"]["completion"]["values"], json!([]));
+}
+
+#[test]
+fn test_server_cancels_tool_call() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"bash","arguments":{"command":"sleep 30"}}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{"requestId":7,"reason":"user abort"}}"#,
+        "\n",
+    );
+
+    let start = std::time::Instant::now();
+    let lines = run_server(dir.path(), input);
+    assert!(start.elapsed() < std::time::Duration::from_secs(20));
+
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["id"], json!(7));
+    assert_eq!(lines[0]["result"]["isError"], json!(true));
+    let text = lines[0]["result"]["content"][0]["text"].as_str().expect("text");
+    assert!(text.contains("cancelled"), "got: {text}");
+}
+
+#[test]
+fn test_server_logging_set_level() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"bogus/before"}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":3,"method":"logging/setLevel","params":{"level":"loud"}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":4,"method":"logging/setLevel","params":{"level":"warning"}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":5,"method":"bogus/after"}"#,
+        "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    assert_eq!(lines[0]["result"]["capabilities"]["logging"], json!({}));
+
+    let logs: Vec<&serde_json::Value> = lines
+        .iter()
+        .filter(|l| l["method"] == json!("notifications/message"))
+        .collect();
+    assert_eq!(logs.len(), 1, "only events after setLevel are forwarded: {logs:?}");
+    assert_eq!(logs[0]["params"]["level"], json!("warning"));
+    assert_eq!(logs[0]["params"]["data"]["message"], json!("unknown method"));
+    assert_eq!(logs[0]["params"]["data"]["method"], json!("bogus/after"));
+    assert_eq!(logs[0]["params"]["data"]["id"], json!("5"));
+
+    let response = |id: i64| {
+        lines
+            .iter()
+            .find(|l| l["id"] == json!(id))
+            .expect("response")
+    };
+    assert_eq!(response(3)["error"]["code"], json!(-32602));
+    assert_eq!(response(4)["result"], json!({}));
+}
+
+#[test]
+fn test_server_logs_carry_request_id() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "a\n").expect("write");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"debug"}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","id":"call-7","method":"tools/call","params":{"name":"read","arguments":{"filePath":"a.txt"}}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","method":"notifications/cancelled","params":{}}"#,
+        "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    let log = |message: &str| {
+        lines
+            .iter()
+            .find(|l| {
+                l["method"] == json!("notifications/message")
+                    && l["params"]["data"]["message"] == json!(message)
+            })
+            .map_or_else(|| panic!("no {message:?} log in {lines:?}"), |l| &l["params"]["data"])
+    };
+
+    let finished = log("tool call finished");
+    assert_eq!(finished["id"], json!("\"call-7\""));
+    assert_eq!(finished["method"], json!("tools/call"));
+    assert_eq!(finished["tool"], json!("read"));
+    assert_eq!(log("dispatching tool call")["request_id"], json!("\"call-7\""));
+
+    // Notifications have no id; their logs still name the method.
+    let cancelled = log("notifications/cancelled without requestId");
+    assert_eq!(cancelled["method"], json!("notifications/cancelled"));
+    assert!(cancelled.get("id").is_none(), "got: {cancelled}");
+}
+
+#[test]
+fn test_cli_version_and_help() {
+    let run = |arg: &str| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_oa-coder"))
+            .arg(arg)
+            .output()
+            .expect("run oa-coder")
+    };
+
+    let version = run("--version");
+    assert!(version.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&version.stdout).trim(),
+        format!("oa-coder {}", env!("CARGO_PKG_VERSION"))
+    );
+
+    let help = run("--help");
+    assert!(help.status.success());
+    assert!(String::from_utf8_lossy(&help.stdout).contains("--workspace <path>"));
+}
+
+#[test]
+fn test_cli_rejects_unknown_flag() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_oa-coder"))
+        .arg("--workspce")
+        .arg(".")
+        .output()
+        .expect("run oa-coder");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unrecognized option: --workspce"), "got: {stderr}");
+}
+
+#[test]
+fn test_server_maps_coder_error_to_rpc_error() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"edit","arguments":{"filePath":"missing.txt","oldString":"a","newString":"b"}}}"#,
+        "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    assert_eq!(lines.len(), 1);
+    let error = &lines[0]["error"];
+    assert_eq!(error["code"], json!(-32001));
+    assert_eq!(error["data"]["kind"], json!("fileNotFound"));
+    assert!(
+        error["data"]["path"].as_str().expect("path").ends_with("missing.txt"),
+        "got: {error}"
+    );
+}
+
+#[test]
+fn test_tool_call_write_no_overwrite() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let first = router
+        .call_tool("write", json!({ "filePath": "new.txt", "content": "one\n", "overwrite": false }))
+        .expect("should not error");
+    assert!(!first.is_error, "{}", first.content[0].text);
+    assert!(first.content[0].text.starts_with("Created"));
+
+    let second = router
+        .call_tool("write", json!({ "filePath": "new.txt", "content": "two\n", "overwrite": false }))
+        .expect("should not error");
+    assert!(second.is_error);
+    assert!(second.content[0].text.contains("already exists"));
+    assert!(second.content[0].text.contains("new.txt"));
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("new.txt")).expect("read"),
+        "one\n"
+    );
+}
+
+#[test]
+fn test_edit_expected_mtime() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("f.txt");
+    std::fs::write(&path, "alpha\nbeta\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let read = router.call_tool("read", json!({ "filePath": "f.txt" })).expect("read");
+    let text = &read.content[0].text;
+    let mtime = text
+        .rsplit_once("(mtime: ")
+        .and_then(|(_, rest)| rest.split_once(')'))
+        .map(|(token, _)| token.to_owned())
+        .expect("mtime footer");
+
+    let ok = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "alpha", "newString": "gamma", "expectedMtime": mtime }),
+        )
+        .expect("edit with current mtime");
+    assert!(!ok.is_error, "{}", ok.content[0].text);
+
+    // The successful edit changed the mtime, so the old token is now stale.
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    std::fs::write(&path, "gamma\nbeta\ndelta\n").expect("write");
+    let err = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "beta", "newString": "BETA", "expectedMtime": mtime }),
+        )
+        .expect_err("stale mtime must be rejected");
+    assert!(matches!(
+        err.downcast_ref::<oa_coder::CoderError>(),
+        Some(oa_coder::CoderError::FileModified { .. })
+    ));
+    assert_eq!(std::fs::read_to_string(&path).expect("read"), "gamma\nbeta\ndelta\n");
+}
+
+#[test]
+fn test_edit_no_match_suggests_closest_block() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("lib.rs"), "fn main() {\n    let total = compute(1);\n}\n")
+        .expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "lib.rs", "oldString": "let totl = compute(2);", "newString": "x" }),
+        )
+        .expect("edit");
+    assert!(result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("Did you mean this block at line 2"), "got: {text}");
+    assert!(text.ends_with("    let total = compute(1);"), "got: {text}");
+}
+
+#[test]
+fn test_edit_verbose_layers() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.rs"), "    foo();\n    foo();\nbar();\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let edit = |old: &str, verbose: bool| {
+        router
+            .call_tool(
+                "edit",
+                json!({ "filePath": "a.rs", "oldString": old, "newString": "baz();", "verbose": verbose }),
+            )
+            .expect("edit")
+    };
+
+    let result = edit("foo();", false);
+    assert!(result.is_error);
+    assert!(!result.content[0].text.contains("Layers:"));
+    let result = edit("foo();", true);
+    assert!(result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("Layers: SimpleReplacer found 1 ambiguous candidate;"), "got: {text}");
+    assert!(text.contains("LineTrimmedReplacer found 2 ambiguous candidates"), "got: {text}");
+
+    let result = edit("bar();", true);
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.ends_with("Matched by SimpleReplacer"));
+}
+
+#[test]
+fn test_edit_rejects_bad_context_match_ratio() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.rs"), "a\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let args = json!({ "filePath": "a.rs", "oldString": "a", "newString": "b", "contextMatchRatio": 1.5 });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("contextMatchRatio"));
+}
+
+#[test]
+fn test_edit_return_content() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("a.txt"), "one\ntwo\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let args = json!({ "filePath": "a.txt", "oldString": "two", "newString": "2" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert_eq!(result.content.len(), 2);
+
+    let args = json!({ "filePath": "a.txt", "oldString": "one", "newString": "1", "returnContent": true });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert_eq!(result.content.len(), 3);
+    assert_eq!(result.content[2].text, "1\n2\n");
+
+    let big = "x".repeat(oa_coder::tools::edit::MAX_RETURN_CONTENT_BYTES);
+    std::fs::write(dir.path().join("big.txt"), format!("head\n{big}")).expect("write");
+    let args = json!({ "filePath": "big.txt", "oldString": "head", "newString": "top", "returnContent": true });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.content[2].text.starts_with("Warning: new content not returned"));
+}
+
+#[test]
+fn test_edit_reject_whitespace_only() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let file = dir.path().join("a.rs");
+    let original = "fn main() {\n    run();\n}\n";
+    std::fs::write(&file, original).expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let args = json!({
+        "filePath": "a.rs", "oldString": "    run();", "newString": "\trun();",
+        "rejectWhitespaceOnly": true
+    });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("changes only whitespace"), "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), original);
+
+    let args = json!({
+        "filePath": "a.rs", "oldString": "    run();", "newString": "    run(1);",
+        "rejectWhitespaceOnly": true
+    });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+
+    let args = json!({ "filePath": "a.rs", "oldString": "    run(1);", "newString": "\trun(1);" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "fn main() {\n\trun(1);\n}\n");
+}
+
+#[test]
+fn test_edit_normalize_unicode() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let file = dir.path().join("a.py");
+    let original = "msg = \"don't panic\"\nrange = 1 - 2\n";
+    std::fs::write(&file, original).expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    // Curly quotes against the straight-quoted source: no match by default.
+    let curly = "msg = \u{201C}don\u{2019}t panic\u{201D}";
+    let args = json!({ "filePath": "a.py", "oldString": curly, "newString": "msg = \"keep calm\"" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.is_error);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), original);
+
+    let args = json!({
+        "filePath": "a.py", "oldString": curly, "newString": "msg = \"keep calm\"",
+        "normalizeUnicode": true
+    });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert_eq!(
+        std::fs::read_to_string(&file).expect("read"),
+        "msg = \"keep calm\"\nrange = 1 - 2\n"
+    );
+
+    // An en dash and a non-breaking space stand in for "1 - 2".
+    let args = json!({
+        "filePath": "a.py", "oldString": "1\u{00A0}\u{2013} 2", "newString": "3",
+        "normalizeUnicode": true, "verbose": true
+    });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.content[0].text.contains("UnicodeNormalizedReplacer"), "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(&file).expect("read"), "msg = \"keep calm\"\nrange = 3\n");
+
+    let args = json!({ "filePath": "a.py", "startLine": 1, "newString": "x", "normalizeUnicode": true });
+    assert!(router.call_tool("edit", args).expect("edit").is_error);
+}
+
+#[test]
+fn test_edit_diff_algorithm_and_context() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let mut body = String::new();
+    for i in 1..=10 {
+        let _ = writeln!(body, "line{i}");
+    }
+    std::fs::write(dir.path().join("a.txt"), &body).expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let args = json!({
+        "filePath": "a.txt", "oldString": "line5", "newString": "five",
+        "diffAlgorithm": "myers", "contextLines": 0
+    });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("@@ -5 +5 @@"));
+    assert!(!result.content[0].text.contains(" line4"));
+
+    let args = json!({ "filePath": "a.txt", "oldString": "five", "newString": "5", "diffAlgorithm": "lcs" });
+    assert!(router.call_tool("edit", args).is_err());
+}
+
+#[test]
+fn test_max_file_size_guard() {
+    use oa_coder::tools::{DEFAULT_MAX_FILE_BYTES, ToolRouter, Workspace};
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    // Sparse: one byte over the limit without writing 20 MiB.
+    let big = std::fs::File::create(dir.path().join("big.txt")).expect("create");
+    big.set_len(DEFAULT_MAX_FILE_BYTES + 1).expect("set_len");
+    let router = ToolRouter::new(dir.path().to_path_buf(), false);
+    let err = router
+        .call_tool("edit", json!({ "filePath": "big.txt", "oldString": "a", "newString": "b" }))
+        .expect_err("too large");
+    assert!(matches!(
+        err.downcast_ref::<oa_coder::CoderError>(),
+        Some(oa_coder::CoderError::FileTooLarge { size, .. }) if *size == DEFAULT_MAX_FILE_BYTES + 1
+    ));
+
+    // A configured limit: read asks for a window, which then works.
+    std::fs::write(dir.path().join("small.txt"), "0123456789\nabcdef\n").expect("write");
+    let workspace = Workspace::new(dir.path().to_path_buf()).with_max_file_bytes(16);
+    let router = ToolRouter::with_workspace(workspace, false);
+    let result = router.call_tool("read", json!({ "filePath": "small.txt" })).expect("read");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("file too large"), "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("offset and limit"));
+    // Only a window bounded in bytes (limit × maxLineLength) gets past it.
+    let args = json!({ "filePath": "small.txt", "offset": 2, "limit": 1 });
+    let result = router.call_tool("read", args).expect("read");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("limit × maxLineLength"), "{}", result.content[0].text);
+    let args = json!({ "filePath": "small.txt", "offset": 2, "limit": 1, "maxLineLength": 16 });
+    let result = router.call_tool("read", args).expect("read");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("abcdef"));
+    let args = json!({ "filePath": "small.txt", "oldString": "abc", "newString": "x" });
+    assert!(router.call_tool("edit", args).is_err());
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("small.txt")).expect("read"),
+        "0123456789\nabcdef\n"
+    );
+}
+
+#[test]
+fn test_edit_full_rewrite() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("f.txt");
+    std::fs::write(&path, "one\ntwo\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "", "newString": "one\nthree\n", "fullRewrite": true }),
+        )
+        .expect("rewrite");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("-two"));
+    assert!(result.content[0].text.contains("+three"));
+    assert_eq!(std::fs::read_to_string(&path).expect("read"), "one\nthree\n");
+
+    // A missing file is only created with an empty oldString.
+    let err = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "new.txt", "oldString": "x", "newString": "y", "fullRewrite": true }),
+        )
+        .expect_err("fullRewrite must not create with a non-empty oldString");
+    assert!(err.to_string().contains("not found"), "{err}");
+    assert!(!dir.path().join("new.txt").exists());
+}
+
+#[test]
+fn test_edit_and_write_backup() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("f.txt");
+    std::fs::write(&path, "old\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "old", "newString": "new", "backup": true }),
+        )
+        .expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("Backup: "), "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("f.txt.bak"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("f.txt.bak")).expect("bak"), "old\n");
+    assert_eq!(std::fs::read_to_string(&path).expect("read"), "new\n");
+
+    let result = router
+        .call_tool("write", json!({ "filePath": "f.txt", "content": "newer\n", "backup": true }))
+        .expect("write");
+    assert!(result.content[0].text.contains("backup: "), "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(dir.path().join("f.txt.bak")).expect("bak"), "new\n");
+
+    // A failed edit leaves no backup behind.
+    std::fs::remove_file(dir.path().join("f.txt.bak")).expect("rm");
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "absent", "newString": "x", "backup": true }),
+        )
+        .expect("edit");
+    assert!(result.is_error);
+    assert!(!dir.path().join("f.txt.bak").exists());
+}
+
+#[test]
+fn test_tool_call_tree() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src/deep/deeper")).expect("mkdir");
+    std::fs::create_dir_all(root.join("node_modules/pkg")).expect("mkdir");
+    std::fs::create_dir_all(root.join("build")).expect("mkdir");
+    std::fs::write(root.join(".gitignore"), "build/\n*.log\n").expect("write");
+    std::fs::write(root.join("src/debug.log"), "").expect("write");
+    std::fs::write(root.join("src/main.rs"), "").expect("write");
+    std::fs::write(root.join("src/deep/deeper/x.rs"), "").expect("write");
+    std::fs::write(root.join("a.txt"), "").expect("write");
+    std::fs::write(root.join(".hidden"), "").expect("write");
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("a.txt", root.join("link")).expect("symlink");
+
+    let router = oa_coder::tools::ToolRouter::new(root.to_path_buf(), false);
+    let result = router.call_tool("tree", json!({ "maxDepth": 2 })).expect("tree");
+    assert!(!result.is_error);
+    let text = &result.content[0].text;
+
+    let mut expected = String::from("./\n├── src/\n│   ├── deep/\n│   └── main.rs\n");
+    #[cfg(unix)]
+    expected.push_str("├── a.txt\n└── link -> a.txt\n");
+    #[cfg(not(unix))]
+    expected.push_str("└── a.txt\n");
+    assert!(text.starts_with(&expected), "got:\n{text}");
+    assert!(!text.contains("node_modules"));
+    assert!(!text.contains(".hidden"));
+    assert!(!text.contains("deeper"));
+    assert!(!text.contains("build") && !text.contains("debug.log"), "gitignored:\n{text}");
+
+    // showAll bypasses every filter.
+    let text = router.call_tool("tree", json!({ "showAll": true })).expect("tree").content[0].text.clone();
+    assert!(text.contains("build/") && text.contains("debug.log") && text.contains(".hidden"), "got:\n{text}");
+
+    let result = router
+        .call_tool("tree", json!({ "showAll": true, "maxEntries": 3 }))
+        .expect("tree");
+    assert!(result.content[0].text.contains("(output truncated at 3 entries)"));
+}
+
+#[test]
+fn test_edit_reports_match_ranges() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    std::fs::write(dir.path().join("f.txt"), "ab ab ab\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "edit",
+            json!({ "filePath": "f.txt", "oldString": "ab", "newString": "xyz", "replaceAll": true }),
+        )
+        .expect("edit");
+    assert!(!result.is_error);
+    assert_eq!(result.content.len(), 2);
+    let ranges: serde_json::Value =
+        serde_json::from_str(&result.content[1].text).expect("json ranges");
+    assert_eq!(
+        ranges,
+        json!({ "ranges": [
+            { "start": 0, "end": 2 },
+            { "start": 3, "end": 5 },
+            { "start": 6, "end": 8 }
+        ]})
+    );
+}
+
+#[test]
+fn test_additional_workspace_roots() {
+    let primary = tempfile::tempdir().expect("tempdir");
+    let extra = tempfile::tempdir().expect("tempdir");
+    let outside = tempfile::tempdir().expect("tempdir");
+    std::fs::write(extra.path().join("dep.txt"), "from extra root\n").expect("write");
+    std::fs::write(outside.path().join("secret.txt"), "nope\n").expect("write");
+
+    let workspace = oa_coder::tools::Workspace::with_roots(
+        primary.path().canonicalize().expect("canonicalize"),
+        [extra.path().canonicalize().expect("canonicalize")],
+    );
+    let router = oa_coder::tools::ToolRouter::with_workspace(workspace, false);
+
+    let path = extra.path().join("dep.txt");
+    let result = router
+        .call_tool("read", json!({ "filePath": path.to_string_lossy() }))
+        .expect("read");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.contains("from extra root"));
+
+    let path = outside.path().join("secret.txt");
+    let result = router
+        .call_tool("read", json!({ "filePath": path.to_string_lossy() }))
+        .expect("read");
+    assert!(result.is_error);
+
+    // Relative paths still resolve against the primary root.
+    let result = router
+        .call_tool("write", json!({ "filePath": "new.txt", "content": "x" }))
+        .expect("write");
+    assert!(!result.is_error);
+    assert!(primary.path().join("new.txt").exists());
+}
+
+#[test]
+fn test_tool_filter_hides_denied_tools() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let filter = oa_coder::tools::ToolFilter {
+        allow: Some(vec!["read".to_owned(), "grep".to_owned(), "bash".to_owned()]),
+        deny: vec!["bash".to_owned()],
+    };
+    let router =
+        oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false).with_filter(filter);
+
+    let names: Vec<String> = router.list_tools().into_iter().map(|t| t.name).collect();
+    assert_eq!(names, ["read", "grep"]);
+
+    // Deny overrides allow, and the failure maps to method-not-found.
+    let err = router
+        .call_tool("bash", json!({ "command": "echo hi" }))
+        .expect_err("bash is denied");
+    let coder_err = err.downcast_ref::<oa_coder::CoderError>().expect("CoderError");
+    assert_eq!(coder_err.rpc_code(), -32601);
+
+    let err = router
+        .call_tool("write", json!({ "filePath": "a.txt", "content": "x" }))
+        .expect_err("write is not allowed");
+    assert!(err.to_string().contains("tool not available: write"));
+    assert!(!dir.path().join("a.txt").exists());
+
+    // `format` runs an arbitrary command, so it goes with `bash`.
+    let filter = oa_coder::tools::ToolFilter {
+        allow: None,
+        deny: vec!["bash".to_owned()],
+    };
+    let router =
+        oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false).with_filter(filter);
+    assert!(!router.list_tools().iter().any(|t| t.name == "format"));
+    assert!(router.call_tool("format", json!({ "filePath": "a.rs" })).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_through_symlinks() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let outside = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::write(root.join("real.txt"), "old\n").expect("write");
+    std::os::unix::fs::symlink("real.txt", root.join("link.txt")).expect("symlink");
+    std::os::unix::fs::symlink("missing.txt", root.join("dangling.txt")).expect("symlink");
+    std::os::unix::fs::symlink(outside.path().join("new.txt"), root.join("escape.txt"))
+        .expect("symlink");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+
+    // Writes and edits go through the link to its target; the link stays.
+    let result = router
+        .call_tool("write", json!({ "filePath": "link.txt", "content": "new\n" }))
+        .expect("write");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let result = router
+        .call_tool("edit", json!({ "filePath": "link.txt", "oldString": "new", "newString": "edited" }))
+        .expect("edit");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(root.join("link.txt").is_symlink());
+    assert_eq!(std::fs::read_to_string(root.join("real.txt")).expect("read"), "edited\n");
+
+    // A dangling link creates its (in-workspace) target.
+    let result = router
+        .call_tool("write", json!({ "filePath": "dangling.txt", "content": "x" }))
+        .expect("write");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(root.join("dangling.txt").is_symlink());
+    assert_eq!(std::fs::read_to_string(root.join("missing.txt")).expect("read"), "x");
+
+    // A dangling link pointing outside is refused, for every writer.
+    for (tool, args) in [
+        ("write", json!({ "filePath": "escape.txt", "content": "x" })),
+        ("copy", json!({ "source": "real.txt", "destination": "escape.txt" })),
+    ] {
+        let err = router
+            .call_tool(tool, args)
+            .map_or_else(|e| e.to_string(), |r| r.content[0].text.clone());
+        assert!(err.contains("escapes workspace boundary"), "{tool}: {err}");
+    }
+    assert!(!outside.path().join("new.txt").exists());
+    assert!(root.join("escape.txt").is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cached_path_swapped_for_symlink() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let outside = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::write(root.join("a.txt"), "inside\n").expect("write");
+    std::fs::write(outside.path().join("secret.txt"), "secret\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+
+    let result = router.call_tool("read", json!({ "filePath": "a.txt" })).expect("read");
+    assert!(result.content[0].text.contains("inside"));
+
+    // The cached resolution of a.txt must not survive it becoming a link out.
+    std::fs::remove_file(root.join("a.txt")).expect("remove");
+    std::os::unix::fs::symlink(outside.path().join("secret.txt"), root.join("a.txt"))
+        .expect("symlink");
+    let err = router
+        .call_tool("read", json!({ "filePath": "a.txt" }))
+        .map_or_else(|e| e.to_string(), |r| r.content[0].text.clone());
+    assert!(err.contains("escapes workspace boundary"), "{err}");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cached_path_parent_swapped_for_symlink() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let outside = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir(root.join("sub")).expect("mkdir");
+    std::fs::write(root.join("sub/a.txt"), "inside\n").expect("write");
+    std::fs::write(outside.path().join("a.txt"), "secret\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+
+    let result = router.call_tool("read", json!({ "filePath": "sub/a.txt" })).expect("read");
+    assert!(result.content[0].text.contains("inside"));
+
+    // Swapping a directory above the file, not the file itself, must also
+    // invalidate the cached resolution.
+    std::fs::remove_dir_all(root.join("sub")).expect("remove");
+    std::os::unix::fs::symlink(outside.path(), root.join("sub")).expect("symlink");
+    let err = router
+        .call_tool("read", json!({ "filePath": "sub/a.txt" }))
+        .map_or_else(|e| e.to_string(), |r| r.content[0].text.clone());
+    assert!(err.contains("escapes workspace boundary"), "{err}");
+}
+
+#[test]
+fn test_case_insensitive_workspace_boundary() {
+    use oa_coder::tools::{Workspace, validate_path};
+    use oa_coder::util::case_fold::is_case_insensitive;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let base = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir_all(base.join("Proj/src")).expect("mkdir");
+    std::fs::write(base.join("Proj/src/main.rs"), "fn main() {}\n").expect("write");
+    std::fs::create_dir(base.join("Proj2")).expect("mkdir");
+    let file = base.join("Proj/src/main.rs");
+    let file = file.to_str().expect("utf-8");
+
+    // The root as the user typed it, differing in case from the directory.
+    let workspace = Workspace::new(base.join("PROJ")).with_case_insensitive(true);
+    assert!(workspace.is_case_insensitive());
+    let resolved = validate_path(&workspace, file).expect("inside, ignoring case");
+    assert!(resolved.ends_with("src/main.rs"));
+    // Still a component-wise check: a sibling sharing the prefix is outside.
+    let sibling = base.join("Proj2/x.rs");
+    assert!(validate_path(&workspace, sibling.to_str().expect("utf-8")).is_err());
+
+    // On a case-sensitive filesystem PROJ is simply another directory.
+    if !is_case_insensitive(&base) {
+        let exact = Workspace::new(base.join("PROJ"));
+        assert!(validate_path(&exact, file).is_err());
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_follow_symlinks_policy() {
+    use oa_coder::tools::{ToolRouter, Workspace};
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let outside = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir(root.join("real")).expect("mkdir");
+    std::fs::write(root.join("real/a.txt"), "needle\n").expect("write");
+    std::fs::write(outside.path().join("secret.txt"), "needle\n").expect("write");
+    std::os::unix::fs::symlink(root.join("real"), root.join("link")).expect("symlink");
+    std::os::unix::fs::symlink(outside.path(), root.join("out")).expect("symlink");
+    std::os::unix::fs::symlink(&root, root.join("real/loop")).expect("symlink");
+
+    let glob = |router: &ToolRouter| {
+        let result = router
+            .call_tool("glob", json!({ "pattern": "**/*.txt" }))
+            .expect("glob");
+        result.content[0].text.clone()
+    };
+
+    // Default: symlinks are skipped.
+    let router = ToolRouter::new(root.clone(), false);
+    assert_eq!(glob(&router), "real/a.txt");
+
+    // Following: links inside the workspace are traversed, links leaving it
+    // are not, and the self-referencing link does not loop.
+    let router =
+        ToolRouter::with_workspace(Workspace::new(root).with_follow_symlinks(true), false);
+    assert_eq!(glob(&router), "link/a.txt\nreal/a.txt");
+
+    let result = router
+        .call_tool("grep", json!({ "pattern": "needle" }))
+        .expect("grep");
+    let text = &result.content[0].text;
+    assert!(text.contains("a.txt:1:needle"), "got:\n{text}");
+    assert!(!text.contains("secret"), "got:\n{text}");
+}
+
+#[test]
+fn test_glob_skip_names() {
+    use oa_coder::tools::{ToolRouter, Workspace};
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    for sub in ["src", "node_modules/pkg", ".venv/lib", ".github"] {
+        std::fs::create_dir_all(root.join(sub)).expect("mkdir");
+        std::fs::write(root.join(sub).join("a.py"), "").expect("write");
+    }
+    let glob = |router: &ToolRouter| {
+        let result = router
+            .call_tool("glob", json!({ "pattern": "**/*.py" }))
+            .expect("glob");
+        let mut lines: Vec<String> = result.content[0].text.lines().map(str::to_owned).collect();
+        lines.sort();
+        lines.join("\n")
+    };
+
+    // Defaults skip node_modules but not other dot directories.
+    let router = ToolRouter::new(root.clone(), false);
+    assert_eq!(glob(&router), ".github/a.py\n.venv/lib/a.py\nsrc/a.py");
+
+    let workspace = Workspace::new(root)
+        .with_skip_names([".venv".to_owned()])
+        .without_skip_names(&["node_modules".to_owned()]);
+    let router = ToolRouter::with_workspace(workspace, false);
+    assert_eq!(glob(&router), ".github/a.py\nnode_modules/pkg/a.py\nsrc/a.py");
+}
+
+#[test]
+fn test_hidden_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".github/workflows")).expect("mkdir");
+    std::fs::create_dir_all(root.join(".git")).expect("mkdir");
+    std::fs::write(root.join(".github/workflows/ci.yml"), "needle\n").expect("write");
+    std::fs::write(root.join(".env.example"), "needle\n").expect("write");
+    std::fs::write(root.join(".git/config"), "needle\n").expect("write");
+    std::fs::write(root.join("main.rs"), "needle\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.to_path_buf(), false);
+
+    let glob = |hidden: bool| {
+        let result = router
+            .call_tool("glob", json!({ "pattern": "**/*", "hidden": hidden }))
+            .expect("glob");
+        let mut lines: Vec<String> = result.content[0].text.lines().map(str::to_owned).collect();
+        lines.sort();
+        lines.join("\n")
+    };
+    assert_eq!(glob(true), ".env.example\n.github/workflows/ci.yml\nmain.rs");
+    assert_eq!(glob(false), "main.rs");
+
+    // grep searches hidden files by default too, like glob; without `rg`
+    // the fallback search agrees (see its unit tests).
+    let grep = |args: serde_json::Value| {
+        router.call_tool("grep", args).expect("grep").content[0].text.clone()
+    };
+    let text = grep(json!({ "pattern": "needle", "hidden": false }));
+    assert!(text.contains("main.rs") && !text.contains(".env.example"), "got: {text}");
+    let text = grep(json!({ "pattern": "needle" }));
+    assert!(text.contains(".env.example"), "got: {text}");
+    assert!(text.contains(".github/workflows/ci.yml"), "got: {text}");
+    assert!(!text.contains(".git/config"), "got: {text}");
+}
+
+#[test]
+fn test_tool_call_search() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir_all(root.join("src/nested")).expect("mkdir");
+    std::fs::write(root.join("src/a.rs"), "fn needle() {}\nfn other() {}\n").expect("write");
+    std::fs::write(root.join("src/nested/b.rs"), "// needle\n").expect("write");
+    std::fs::write(root.join("src/c.txt"), "needle\n").expect("write");
+    std::fs::write(root.join("top.rs"), "needle\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root, false);
+
+    let result = router
+        .call_tool("search", json!({ "glob": "src/**/*.rs", "pattern": "needle" }))
+        .expect("search");
+    assert!(!result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("a.rs:1:fn needle() {}"), "got:\n{text}");
+    assert!(text.contains("b.rs:1:// needle"), "got:\n{text}");
+    assert!(!text.contains("c.txt"), "got:\n{text}");
+    assert!(!text.contains("top.rs"), "got:\n{text}");
+
+    // maxResults applies to the combined matches.
+    let result = router
+        .call_tool(
+            "search",
+            json!({ "glob": ["**/*.rs", "**/*.txt"], "pattern": "needle", "maxResults": 2 }),
+        )
+        .expect("search");
+    let text = &result.content[0].text;
+    assert_eq!(text.lines().filter(|l| l.contains(":1:")).count(), 2, "got:\n{text}");
+
+    let result = router
+        .call_tool("search", json!({ "glob": "*.md", "pattern": "needle" }))
+        .expect("search");
+    assert!(!result.is_error);
+    assert!(result.content[0].text.contains("No files matching glob"));
+}
+
+#[test]
+fn test_write_normalizes_newlines() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let result = router
+        .call_tool(
+            "write",
+            json!({
+                "filePath": "crlf.txt",
+                "content": "a\r\nb\r\nc",
+                "normalizeNewlines": true,
+                "ensureTrailingNewline": true
+            }),
+        )
+        .expect("write");
+    assert!(!result.is_error);
+    let text = &result.content[0].text;
+    assert!(text.contains("(normalized: 2 CRLF -> LF, added final newline)"), "got: {text}");
+    let written = std::fs::read_to_string(dir.path().join("crlf.txt")).expect("read");
+    assert_eq!(written, "a\nb\nc\n");
+
+    let result = router
+        .call_tool(
+            "write",
+            json!({ "filePath": "tail.txt", "content": "x\n\n\n", "ensureTrailingNewline": true }),
+        )
+        .expect("write");
+    assert!(result.content[0].text.contains("collapsed trailing newlines"));
+    let written = std::fs::read_to_string(dir.path().join("tail.txt")).expect("read");
+    assert_eq!(written, "x\n");
+
+    // Off by default: content is written verbatim.
+    let result = router
+        .call_tool("write", json!({ "filePath": "raw.txt", "content": "a\r\nb" }))
+        .expect("write");
+    assert!(!result.content[0].text.contains("normalized"));
+    let written = std::fs::read_to_string(dir.path().join("raw.txt")).expect("read");
+    assert_eq!(written, "a\r\nb");
+}
+
+#[test]
+fn test_write_expected_sha256() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("a.txt");
+    std::fs::write(&path, "v1\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    let args = json!({ "filePath": "a.txt", "output": "json", "sha256": true });
+    let result = router.call_tool("read", args).expect("read");
+    let report: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    let hash = report["sha256"].as_str().expect("sha256").to_owned();
+    assert_eq!(hash, oa_coder::util::digest::sha256_hex(b"v1\n"));
+
+    let args = json!({ "filePath": "a.txt", "content": "v2\n", "expectedSha256": hash });
+    let result = router.call_tool("write", args).expect("write");
+    assert!(!result.is_error, "{}", result.content[0].text);
+
+    // The file moved on since `hash` was read: refused, content kept.
+    let args = json!({ "filePath": "a.txt", "content": "v3\n", "expectedSha256": hash });
+    let err = router.call_tool("write", args).expect_err("conflict");
+    assert!(matches!(
+        err.downcast_ref::<oa_coder::CoderError>(),
+        Some(oa_coder::CoderError::ContentChanged { .. })
+    ));
+    assert_eq!(std::fs::read_to_string(&path).expect("read"), "v2\n");
+
+    let result = router.call_tool("read", json!({ "filePath": "a.txt", "sha256": true })).expect("read");
+    assert!(result.content[0].text.contains(&oa_coder::util::digest::sha256_hex(b"v2\n")));
+}
+
+#[test]
+fn test_tidy_only_when_set() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+    let messy = "a  \n\n\n\n\nb\t\n";
+
+    router
+        .call_tool("write", json!({ "filePath": "raw.txt", "content": messy }))
+        .expect("write");
+    assert_eq!(std::fs::read_to_string(dir.path().join("raw.txt")).expect("read"), messy);
+
+    let result = router
+        .call_tool("write", json!({ "filePath": "tidy.txt", "content": messy, "tidy": true }))
+        .expect("write");
+    let text = &result.content[0].text;
+    assert!(text.contains("trailing whitespace stripped from 2 lines, 2 blank lines removed"), "got: {text}");
+    assert_eq!(std::fs::read_to_string(dir.path().join("tidy.txt")).expect("read"), "a\n\n\nb\n");
+
+    let edit = |tidy: bool| {
+        std::fs::write(dir.path().join("e.txt"), "x\ny\n").expect("write");
+        let args = json!({ "filePath": "e.txt", "oldString": "y", "newString": "z  ", "tidy": tidy });
+        let result = router.call_tool("edit", args).expect("edit");
+        assert!(!result.is_error, "{}", result.content[0].text);
+        (result.content[0].text.clone(), std::fs::read_to_string(dir.path().join("e.txt")).expect("read"))
+    };
+    let (diff, content) = edit(false);
+    assert_eq!(content, "x\nz  \n");
+    assert!(diff.contains("+z  \n"), "got: {diff}");
+    let (diff, content) = edit(true);
+    assert_eq!(content, "x\nz\n");
+    assert!(diff.contains("+z\n"), "got: {diff}");
+}
+
+#[test]
+fn test_write_many_all_or_nothing() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::write(root.join("existing.txt"), "old\n").expect("write");
+    std::fs::write(root.join("blocker"), "a file, not a directory\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+
+    let result = router
+        .call_tool(
+            "write_many",
+            json!({ "files": [
+                { "filePath": "existing.txt", "content": "new\n" },
+                { "filePath": "src/feature/mod.rs", "content": "pub mod a;\n" },
+                { "filePath": "blocker/x.txt", "content": "x\n" }
+            ]}),
+        )
+        .expect("write_many");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("blocker/x.txt"), "got: {}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(root.join("existing.txt")).expect("read"), "old\n");
+    assert!(!root.join("src").exists());
+    let leftovers = std::fs::read_dir(&root).expect("read_dir").count();
+    assert_eq!(leftovers, 2, "temp files left behind");
+
+    let result = router
+        .call_tool(
+            "write_many",
+            json!({ "files": [
+                { "filePath": "existing.txt", "content": "new\n" },
+                { "filePath": "src/feature/mod.rs", "content": "pub mod a;\n" }
+            ]}),
+        )
+        .expect("write_many");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    assert!(result.content[0].text.starts_with("Wrote 2 files:"));
+    assert_eq!(std::fs::read_to_string(root.join("existing.txt")).expect("read"), "new\n");
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/feature/mod.rs")).expect("read"),
+        "pub mod a;\n"
+    );
+}
+
+#[test]
+fn test_eof_newline_policies() {
+    use oa_coder::util::eof_newline::EofNewline;
+
+    let router_for = |root: &std::path::Path, policy| {
+        let workspace = oa_coder::tools::Workspace::new(root.to_path_buf()).with_eof_newline(policy);
+        oa_coder::tools::ToolRouter::with_workspace(workspace, false)
+    };
+    let read = |path: std::path::PathBuf| std::fs::read_to_string(path).expect("read");
+
+    // preserve (the default): content is written exactly as given.
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = router_for(dir.path(), EofNewline::default());
+    let result = router
+        .call_tool("write", json!({ "filePath": "a.txt", "content": "a" }))
+        .expect("write");
+    assert!(!result.is_error);
+    assert!(!result.content[0].text.contains("newline"), "{}", result.content[0].text);
+    let args = json!({ "filePath": "a.txt", "oldString": "a", "newString": "b\n\n" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(!result.content[0].text.contains("End of file"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("a.txt")), "b\n\n");
+
+    // ensure_trailing_newline: added by every writer, and reported.
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = router_for(dir.path(), EofNewline::EnsureTrailingNewline);
+    let result = router
+        .call_tool("write", json!({ "filePath": "a.txt", "content": "a" }))
+        .expect("write");
+    assert!(result.content[0].text.contains("added final newline"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("a.txt")), "a\n");
+    std::fs::write(dir.path().join("b.txt"), "one\ntwo").expect("write");
+    let args = json!({ "filePath": "b.txt", "oldString": "one", "newString": "1" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.content[0].text.contains("End of file: added final newline"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("b.txt")), "1\ntwo\n");
+    let args = json!({ "filePath": "c.txt", "oldString": "", "newString": "new" });
+    let result = router.call_tool("edit", args).expect("edit");
+    assert!(result.content[0].text.contains("(added final newline)"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("c.txt")), "new\n");
+    let args = json!({ "files": [{ "filePath": "d.txt", "content": "d" }] });
+    let result = router.call_tool("write_many", args).expect("write_many");
+    assert!(result.content[0].text.contains("(added final newline)"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("d.txt")), "d\n");
+    std::fs::write(dir.path().join("e.txt"), "old").expect("write");
+    let args = json!({ "glob": "e.txt", "oldString": "old", "newString": "new" });
+    router.call_tool("replace_in_files", args).expect("replace_in_files");
+    assert_eq!(read(dir.path().join("e.txt")), "new\n");
+    std::fs::write(dir.path().join("f.txt"), "old_name").expect("write");
+    let args = json!({ "oldName": "old_name", "newName": "new_name", "glob": "f.txt" });
+    router.call_tool("rename_symbol", args).expect("rename_symbol");
+    assert_eq!(read(dir.path().join("f.txt")), "new_name\n");
+    std::fs::write(dir.path().join("g.txt"), "g").expect("write");
+    let args = json!({ "filePath": "g.txt", "formatter": "cat" });
+    router.call_tool("format", args).expect("format");
+    assert_eq!(read(dir.path().join("g.txt")), "g\n");
+    std::fs::write(dir.path().join("h.txt"), "h").expect("write");
+    let args = json!({ "source": "h.txt", "destination": "h2.txt" });
+    let result = router.call_tool("copy", args).expect("copy");
+    assert!(result.content[0].text.contains("(2 bytes; added final newline)"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("h2.txt")), "h\n");
+
+    // strip_trailing_newlines: removed, including in the JSON diff report.
+    let dir = tempfile::tempdir().expect("tempdir");
+    let router = router_for(dir.path(), EofNewline::StripTrailingNewlines);
+    let result = router
+        .call_tool("write", json!({ "filePath": "a.txt", "content": "a\n\n" }))
+        .expect("write");
+    assert!(result.content[0].text.contains("removed 2 final newlines"), "{}", result.content[0].text);
+    assert_eq!(read(dir.path().join("a.txt")), "a");
+    std::fs::write(dir.path().join("b.txt"), "one\n").expect("write");
+    let args = json!({ "filePath": "b.txt", "oldString": "one", "newString": "1", "diffFormat": "json" });
+    let result = router.call_tool("edit", args).expect("edit");
+    let report: serde_json::Value = serde_json::from_str(&result.content[0].text).expect("json");
+    assert_eq!(report["eofNewline"], "removed final newline");
+    assert_eq!(read(dir.path().join("b.txt")), "1");
+}
+
+#[test]
+fn test_rename_symbol() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir(root.join("src")).expect("mkdir");
+    let lib = "fn parse() {}\nfn parse_all() { parse(); parse(); }\n";
+    std::fs::write(root.join("src/lib.rs"), lib).expect("write");
+    std::fs::write(root.join("src/main.rs"), "fn main() { reparse(); }\n").expect("write");
+    std::fs::write(root.join("notes.md"), "call parse first\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+
+    let args = json!({ "oldName": "parse", "newName": "parse_one", "glob": "src/**/*.rs", "dryRun": true });
+    let result = router.call_tool("rename_symbol", args).expect("rename_symbol");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let text = &result.content[0].text;
+    assert!(text.starts_with("Would rename parse -> parse_one: 3 occurrences in 1 file"), "got: {text}");
+    assert!(text.contains("+fn parse_all() { parse_one(); parse_one(); }"), "got: {text}");
+    assert_eq!(std::fs::read_to_string(root.join("src/lib.rs")).expect("read"), lib);
+
+    let args = json!({ "oldName": "parse", "newName": "parse_one", "glob": "src/**/*.rs" });
+    let result = router.call_tool("rename_symbol", args).expect("rename_symbol");
+    assert!(result.content[0].text.starts_with("Renamed parse -> parse_one: 3 occurrences"));
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/lib.rs")).expect("read"),
+        "fn parse_one() {}\nfn parse_all() { parse_one(); parse_one(); }\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/main.rs")).expect("read"),
+        "fn main() { reparse(); }\n"
+    );
+    assert_eq!(std::fs::read_to_string(root.join("notes.md")).expect("read"), "call parse first\n");
+
+    // A file over the size limit fails the call; earlier files stay as they were.
+    std::fs::write(root.join("src/zbig.rs"), format!("fn parse_one() {{}}\n{}", "x".repeat(200))).expect("write");
+    let workspace = oa_coder::tools::Workspace::new(root.clone()).with_max_file_bytes(100);
+    let limited = oa_coder::tools::ToolRouter::with_workspace(workspace, false);
+    let args = json!({ "oldName": "parse_one", "newName": "parse", "glob": "src/**/*.rs" });
+    let result = limited.call_tool("rename_symbol", args).expect("rename_symbol");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("zbig.rs"), "{}", result.content[0].text);
+    assert!(std::fs::read_to_string(root.join("src/lib.rs")).expect("read").contains("parse_one"));
+
+    let result = router
+        .call_tool("rename_symbol", json!({ "oldName": "a.b", "newName": "c" }))
+        .expect("rename_symbol");
+    assert!(result.is_error);
+}
+
+#[test]
+fn test_rename_symbol_refuses_too_many_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    for i in 0..=10_000 {
+        std::fs::write(dir.path().join(format!("f{i}.rs")), "").expect("write");
+    }
+    std::fs::write(dir.path().join("f0.rs"), "fn parse() {}\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(dir.path().to_path_buf(), false);
+
+    // A rename over part of the tree would leave the rest stale.
+    let result = router
+        .call_tool("rename_symbol", json!({ "oldName": "parse", "newName": "parse_one" }))
+        .expect("rename_symbol");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("more than 10000 files"), "{}", result.content[0].text);
+    assert_eq!(std::fs::read_to_string(dir.path().join("f0.rs")).expect("read"), "fn parse() {}\n");
+}
+
+#[test]
+fn test_replace_in_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir(root.join("src")).expect("mkdir");
+    std::fs::write(root.join("src/a.rs"), "// Copyright 2024 Acme\nfn a() {}\n").expect("write");
+    std::fs::write(root.join("src/b.rs"), "// Copyright 2024 Acme\nfn b() {}\n").expect("write");
+    std::fs::write(root.join("src/c.rs"), "fn c() {}\n").expect("write");
+    let router = oa_coder::tools::ToolRouter::new(root.clone(), false);
+    let args = |strict: bool| {
+        json!({
+            "glob": "src/*.rs",
+            "oldString": "Copyright 2024",
+            "newString": "Copyright 2025",
+            "strict": strict
+        })
+    };
+
+    // Strict: c.rs has no match, so nothing is written.
+    let result = router.call_tool("replace_in_files", args(true)).expect("replace_in_files");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("c.rs"), "got: {}", result.content[0].text);
+    let a = std::fs::read_to_string(root.join("src/a.rs")).expect("read");
+    assert!(a.contains("2024"));
+
+    let result = router.call_tool("replace_in_files", args(false)).expect("replace_in_files");
+    assert!(!result.is_error, "{}", result.content[0].text);
+    let text = &result.content[0].text;
+    assert!(text.starts_with("Replaced in 2 files (1 without a match skipped)"), "got: {text}");
+    assert!(text.contains("+// Copyright 2025 Acme"), "got: {text}");
+    for name in ["a", "b"] {
+        let content = std::fs::read_to_string(root.join(format!("src/{name}.rs"))).expect("read");
+        assert_eq!(content, format!("// Copyright 2025 Acme\nfn {name}() {{}}\n"));
+    }
+    assert_eq!(std::fs::read_to_string(root.join("src/c.rs")).expect("read"), "fn c() {}\n");
+
+    // Only exact occurrences match: edit's fuzzy layers would accept this.
+    let result = router
+        .call_tool(
+            "replace_in_files",
+            json!({ "glob": "src/*.rs", "oldString": "fn   c()  {}", "newString": "fn d() {}" }),
+        )
+        .expect("replace_in_files");
+    assert_eq!(result.content[0].text, "No matches found for oldString");
+
+    // A file over the size limit fails the call before anything is written.
+    let workspace = oa_coder::tools::Workspace::new(root.clone()).with_max_file_bytes(40);
+    let router = oa_coder::tools::ToolRouter::with_workspace(workspace, false);
+    std::fs::write(root.join("src/big.rs"), format!("// Copyright 2025\n{}", "x".repeat(64))).expect("write");
+    let args = json!({ "glob": "src/*.rs", "oldString": "Copyright 2025", "newString": "Copyright 2026" });
+    let result = router.call_tool("replace_in_files", args).expect("replace_in_files");
+    assert!(result.is_error);
+    assert!(result.content[0].text.contains("big.rs"), "got: {}", result.content[0].text);
+    assert!(std::fs::read_to_string(root.join("src/a.rs")).expect("read").contains("2025"));
+}
+
+#[test]
+fn test_server_resources() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::create_dir(root.join("src")).expect("mkdir");
+    std::fs::write(root.join("src/main.rs"), "fn main() {}\n").expect("write");
+    std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 1, 2]).expect("write");
+    std::fs::write(root.join(".gitignore"), "ignored.txt\n").expect("write");
+    std::fs::write(root.join("ignored.txt"), "x\n").expect("write");
+
+    let main_uri = format!("file://{}/src/main.rs", root.display());
+    let png_uri = format!("file://{}/logo.png", root.display());
+    let input = [
+        json!({"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}),
+        json!({"jsonrpc":"2.0","id":2,"method":"resources/list"}),
+        json!({"jsonrpc":"2.0","id":3,"method":"resources/read","params":{"uri": main_uri}}),
+        json!({"jsonrpc":"2.0","id":4,"method":"resources/read","params":{"uri": png_uri}}),
+        json!({"jsonrpc":"2.0","id":5,"method":"resources/read","params":{"uri":"file:///etc/passwd"}}),
+        json!({"jsonrpc":"2.0","id":6,"method":"resources/read",
+               "params":{"uri": format!("file://{}/missing.rs", root.display())}}),
+    ]
+    .iter()
+    .map(|v| v.to_string() + "\n")
+    .collect::<String>();
+
+    let lines = run_server(&root, &input);
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0]["result"]["capabilities"]["resources"].is_object());
+
+    let resources = lines[1]["result"]["resources"].as_array().expect("resources");
+    let names: Vec<&str> = resources.iter().filter_map(|r| r["name"].as_str()).collect();
+    assert_eq!(names, [".gitignore", "logo.png", "src/main.rs"]);
+    assert_eq!(resources[2]["uri"], json!(main_uri));
+    assert_eq!(resources[2]["mimeType"], json!("text/x-rust"));
+    assert!(lines[1]["result"].get("nextCursor").is_none());
+
+    let contents = &lines[2]["result"]["contents"][0];
+    assert_eq!(contents["text"], json!("fn main() {}\n"));
+    assert_eq!(contents["uri"], json!(main_uri));
+
+    let contents = &lines[3]["result"]["contents"][0];
+    assert_eq!(contents["mimeType"], json!("image/png"));
+    assert_eq!(contents["blob"], json!("iVBORwABAg=="));
+    assert!(contents.get("text").is_none());
+
+    assert_eq!(lines[4]["error"]["code"], json!(-32602));
+    assert!(lines[4]["error"]["message"].as_str().expect("message").contains("escapes workspace"));
+    assert_eq!(lines[5]["error"]["code"], json!(-32002));
+}
+
+#[test]
+fn test_server_resources_subscribe_without_watch() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let uri = format!("file://{}/a.txt", dir.path().display());
+    let input = [
+        json!({"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}),
+        json!({"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri": uri}}),
+    ]
+    .iter()
+    .map(|v| v.to_string() + "\n")
+    .collect::<String>();
+
+    let lines = run_server(dir.path(), &input);
+    assert_eq!(lines[0]["result"]["capabilities"]["resources"]["subscribe"], json!(false));
+    assert_eq!(lines[1]["error"]["code"], json!(-32601));
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_server_watch_notifies_subscribers() {
+    use std::io::{BufRead as _, Write as _};
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().expect("tempdir");
+    let root = dir.path().canonicalize().expect("canonicalize");
+    std::fs::write(root.join("a.txt"), "one\n").expect("write");
+    std::fs::write(root.join("0.txt"), "one\n").expect("write");
+    let uri = format!("file://{}/a.txt", root.display());
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_oa-coder"))
+        .arg("--workspace")
+        .arg(&root)
+        .arg("--watch")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn oa-coder");
+    let mut stdin = child.stdin.take().expect("stdin");
+    let stdout = std::io::BufReader::new(child.stdout.take().expect("stdout"));
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in stdout.lines().map_while(Result::ok) {
+            let _ = tx.send(serde_json::from_str::<serde_json::Value>(&line).expect("json line"));
+        }
+    });
+    let next = || rx.recv_timeout(Duration::from_secs(10)).expect("message from server");
+
+    for request in [
+        json!({"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}),
+        json!({"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri": uri}}),
+    ] {
+        writeln!(stdin, "{request}").expect("write stdin");
+    }
+    assert_eq!(next()["result"]["capabilities"]["resources"]["subscribe"], json!(true));
+    assert_eq!(next()["id"], json!(2));
+
+    // The watcher cannot follow a workspace switch, so none is allowed.
+    let set_workspace = json!({"jsonrpc":"2.0","id":3,"method":"oa-coder/setWorkspace","params":{"path":"."}});
+    writeln!(stdin, "{set_workspace}").expect("write stdin");
+    assert_eq!(next()["error"]["code"], json!(-32601));
+
+    // Only the subscribed file is reported.
+    std::fs::write(root.join("0.txt"), "two\n").expect("write");
+    std::fs::write(root.join("a.txt"), "two\n").expect("write");
+    let notification = next();
+    assert_eq!(notification["method"], json!("notifications/resources/updated"));
+    assert_eq!(notification["params"]["uri"], json!(uri));
+
+    drop(stdin);
+    child.wait().expect("wait");
+}
+
+#[test]
+fn test_server_prompts() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let input = concat!(
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#, "\n",
+        r#"{"jsonrpc":"2.0","id":2,"method":"prompts/list"}"#, "\n",
+        r#"{"jsonrpc":"2.0","id":3,"method":"prompts/get","params":{"name":"explain_file","arguments":{"path":"src/lib.rs"}}}"#, "\n",
+        r#"{"jsonrpc":"2.0","id":4,"method":"prompts/get","params":{"name":"write_tests","arguments":{}}}"#, "\n",
+    );
+
+    let lines = run_server(dir.path(), input);
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0]["result"]["capabilities"]["prompts"].is_object());
+
+    let prompts = lines[1]["result"]["prompts"].as_array().expect("prompts");
+    let names: Vec<&str> = prompts.iter().filter_map(|p| p["name"].as_str()).collect();
+    assert_eq!(names, ["explain_file", "write_tests", "refactor_selection"]);
+    assert_eq!(prompts[0]["arguments"][0], json!({
+        "name": "path",
+        "description": "File to explain (relative to the workspace)",
+        "required": true
+    }));
+
+    let message = &lines[2]["result"]["messages"][0];
+    assert_eq!(message["role"], json!("user"));
+    assert_eq!(message["content"]["type"], json!("text"));
+    assert!(message["content"]["text"].as_str().expect("text").contains("`src/lib.rs`"));
+
+    assert_eq!(lines[3]["error"]["code"], json!(-32602));
+}